@@ -65,6 +65,8 @@ const platform: Platform = {
 			.join('/')}.webp${queryParams}`,
 	getFileUrl: (libraryId, locationLocalId, filePathId) =>
 		`${customUriServerUrl}file/${libraryId}/${locationLocalId}/${filePathId}${queryParams}`,
+	getArchiveMemberUrl: (libraryId, path) =>
+		`${customUriServerUrl}file/archive/${libraryId}/${encodeURIComponent(path)}${queryParams}`,
 	openLink: shell.open,
 	getOs,
 	openDirectoryPickerDialog: () => dialog.open({ directory: true }),
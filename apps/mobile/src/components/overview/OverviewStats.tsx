@@ -1,11 +1,11 @@
 import { FC, useEffect, useState } from 'react';
 import { ScrollView, Text, View } from 'react-native';
 import RNFS from 'react-native-fs';
-import { byteSize, Statistics, useLibraryQuery } from '@sd/client';
+import { byteSize, StatisticsSummary, useLibraryQuery } from '@sd/client';
 import useCounter from '~/hooks/useCounter';
 import { tw, twStyle } from '~/lib/tailwind';
 
-const StatItemNames: Partial<Record<keyof Statistics, string>> = {
+const StatItemNames: Partial<Record<keyof StatisticsSummary, string>> = {
 	total_bytes_capacity: 'Total capacity',
 	preview_media_bytes: 'Preview media',
 	library_db_size: 'Index size',
@@ -79,7 +79,7 @@ const OverviewStats = () => {
 				return (
 					<StatItem
 						key={key}
-						title={StatItemNames[key as keyof Statistics]!}
+						title={StatItemNames[key as keyof StatisticsSummary]!}
 						bytes={bytes}
 					/>
 				);
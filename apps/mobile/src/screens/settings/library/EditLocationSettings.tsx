@@ -56,6 +56,7 @@ const EditLocationSettingsScreen = ({
 			sync_preview_media: data.syncPreviewMedia,
 			generate_preview_media: data.generatePreviewMedia,
 			hidden: data.hidden,
+			index_content: null,
 			indexer_rules_ids: []
 		})
 	);
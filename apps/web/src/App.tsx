@@ -41,6 +41,10 @@ const platform: Platform = {
 		`${spacedriveURL}/file/${encodeURIComponent(libraryId)}/${encodeURIComponent(
 			locationLocalId
 		)}/${encodeURIComponent(filePathId)}`,
+	getArchiveMemberUrl: (libraryId, path) =>
+		`${spacedriveURL}/file/archive/${encodeURIComponent(libraryId)}/${encodeURIComponent(
+			path
+		)}`,
 	openLink: (url) => window.open(url, '_blank')?.focus(),
 	confirm: (message, cb) => cb(window.confirm(message)),
 	auth: {
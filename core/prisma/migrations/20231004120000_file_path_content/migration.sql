@@ -0,0 +1,14 @@
+-- AlterTable
+ALTER TABLE "location" ADD COLUMN "index_content" BOOLEAN;
+
+-- CreateTable
+CREATE TABLE "file_path_content" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "content" TEXT NOT NULL,
+    "date_indexed" DATETIME,
+    "file_path_id" INTEGER NOT NULL,
+    CONSTRAINT "file_path_content_file_path_id_fkey" FOREIGN KEY ("file_path_id") REFERENCES "file_path" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "file_path_content_file_path_id_key" ON "file_path_content"("file_path_id");
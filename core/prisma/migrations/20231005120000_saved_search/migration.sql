@@ -0,0 +1,15 @@
+-- CreateTable
+CREATE TABLE "saved_search" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "icon" TEXT,
+    "target" TEXT,
+    "filters" TEXT,
+    "order" TEXT,
+    "date_created" DATETIME,
+    "date_modified" DATETIME
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "saved_search_pub_id_key" ON "saved_search"("pub_id");
@@ -0,0 +1,22 @@
+-- CreateTable
+CREATE TABLE "key" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "uuid" TEXT NOT NULL,
+    "version" TEXT,
+    "key_type" TEXT,
+    "name" TEXT,
+    "default" BOOLEAN NOT NULL DEFAULT false,
+    "date_created" DATETIME DEFAULT CURRENT_TIMESTAMP,
+    "algorithm" TEXT,
+    "hashing_algorithm" TEXT,
+    "content_salt" BLOB,
+    "master_key" BLOB,
+    "master_key_nonce" BLOB,
+    "key_nonce" BLOB,
+    "key" BLOB,
+    "salt" BLOB,
+    "automount" BOOLEAN NOT NULL DEFAULT false
+);
+
+-- CreateIndex
+CREATE UNIQUE INDEX "key_uuid_key" ON "key"("uuid");
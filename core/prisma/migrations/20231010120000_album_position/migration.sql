@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "object_in_album" ADD COLUMN "position" INTEGER;
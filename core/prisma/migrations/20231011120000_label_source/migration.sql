@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "label_on_object" ADD COLUMN "source" TEXT;
+
+-- CreateIndex
+CREATE UNIQUE INDEX "label_name_key" ON "label"("name");
@@ -0,0 +1,5 @@
+-- AlterTable
+ALTER TABLE "object_in_space" ADD COLUMN "height" INTEGER;
+ALTER TABLE "object_in_space" ADD COLUMN "width" INTEGER;
+ALTER TABLE "object_in_space" ADD COLUMN "x" INTEGER;
+ALTER TABLE "object_in_space" ADD COLUMN "y" INTEGER;
@@ -0,0 +1,8 @@
+-- AlterTable
+ALTER TABLE "media_data" ADD COLUMN "codecs" TEXT;
+ALTER TABLE "media_data" ADD COLUMN "duration" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "fps" REAL;
+ALTER TABLE "media_data" ADD COLUMN "height" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "streams" INTEGER;
+ALTER TABLE "media_data" ADD COLUMN "video_data" BLOB;
+ALTER TABLE "media_data" ADD COLUMN "width" INTEGER;
@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "statistics" ADD COLUMN "breakdown" BLOB;
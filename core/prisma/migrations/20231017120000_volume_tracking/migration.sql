@@ -0,0 +1,58 @@
+/*
+  Warnings:
+
+  - You are about to drop the `volume` table, it was never written to so no data will be lost.
+  - You are about to alter the column `total_capacity` on the `location` table. The data in that column will be cast from `Int` to `Binary`. This cast may fail. Please make sure the data in the column can be cast.
+  - You are about to alter the column `available_capacity` on the `location` table. The data in that column will be cast from `Int` to `Binary`. This cast may fail. Please make sure the data in the column can be cast.
+
+*/
+-- DropTable
+DROP TABLE "volume";
+
+-- CreateTable
+CREATE TABLE "volume" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "identifier" TEXT NOT NULL,
+    "name" TEXT NOT NULL,
+    "mount_point" TEXT NOT NULL,
+    "total_bytes_capacity" TEXT NOT NULL DEFAULT '0',
+    "total_bytes_available" TEXT NOT NULL DEFAULT '0',
+    "disk_type" TEXT,
+    "filesystem" TEXT,
+    "is_system" BOOLEAN NOT NULL DEFAULT false,
+    "is_mounted" BOOLEAN NOT NULL DEFAULT true,
+    "date_modified" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "instance_id" INTEGER NOT NULL,
+    CONSTRAINT "volume_instance_id_fkey" FOREIGN KEY ("instance_id") REFERENCES "instance" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
+
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_location" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "path" TEXT,
+    "total_capacity" BLOB,
+    "available_capacity" BLOB,
+    "size_in_bytes" BLOB,
+    "is_archived" BOOLEAN,
+    "generate_preview_media" BOOLEAN,
+    "sync_preview_media" BOOLEAN,
+    "hidden" BOOLEAN,
+    "index_content" BOOLEAN,
+    "date_created" DATETIME,
+    "instance_id" INTEGER,
+    "volume_id" INTEGER,
+    CONSTRAINT "location_instance_id_fkey" FOREIGN KEY ("instance_id") REFERENCES "instance" ("id") ON DELETE SET NULL ON UPDATE CASCADE,
+    CONSTRAINT "location_volume_id_fkey" FOREIGN KEY ("volume_id") REFERENCES "volume" ("id") ON DELETE SET NULL ON UPDATE CASCADE
+);
+INSERT INTO "new_location" ("date_created", "generate_preview_media", "hidden", "id", "index_content", "instance_id", "is_archived", "name", "path", "pub_id", "size_in_bytes", "sync_preview_media") SELECT "date_created", "generate_preview_media", "hidden", "id", "index_content", "instance_id", "is_archived", "name", "path", "pub_id", "size_in_bytes", "sync_preview_media" FROM "location";
+DROP TABLE "location";
+ALTER TABLE "new_location" RENAME TO "location";
+CREATE UNIQUE INDEX "location_pub_id_key" ON "location"("pub_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
+
+-- CreateIndex
+CREATE UNIQUE INDEX "volume_instance_id_identifier_key" ON "volume"("instance_id", "identifier");
@@ -0,0 +1,4 @@
+-- AlterTable
+ALTER TABLE "file_path" ADD COLUMN "trashed_at" DATETIME;
+ALTER TABLE "file_path" ADD COLUMN "trash_path" TEXT;
+ALTER TABLE "file_path" ADD COLUMN "trash_location_id" INTEGER;
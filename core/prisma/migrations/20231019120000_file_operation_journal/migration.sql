@@ -0,0 +1,8 @@
+-- CreateTable
+CREATE TABLE "file_operation" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "inverse" TEXT NOT NULL,
+    "file_path_pub_ids" BLOB NOT NULL,
+    "date_performed" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "date_undone" DATETIME
+);
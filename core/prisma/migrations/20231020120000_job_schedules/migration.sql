@@ -0,0 +1,14 @@
+-- CreateTable
+CREATE TABLE "job_schedule" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "kind" INTEGER NOT NULL,
+    "location_id" INTEGER,
+    "interval" INTEGER,
+    "cron" TEXT,
+    "enabled" BOOLEAN NOT NULL DEFAULT true,
+    "catch_up" BOOLEAN NOT NULL DEFAULT true,
+    "date_created" DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    "last_run" DATETIME,
+    "next_run" DATETIME NOT NULL,
+    CONSTRAINT "job_schedule_location_id_fkey" FOREIGN KEY ("location_id") REFERENCES "location" ("id") ON DELETE CASCADE ON UPDATE CASCADE
+);
@@ -0,0 +1,26 @@
+-- Sizes are big endian u64 blobs, which Prisma can't compare. SQLite decodes them here so
+-- searches compare sizes in the query itself. Anything past 2^63 - 1 bytes doesn't fit.
+-- AlterTable
+ALTER TABLE "file_path" ADD COLUMN "size_in_bytes_int" BIGINT GENERATED ALWAYS AS (
+    CASE WHEN length("size_in_bytes_bytes") = 8 AND unicode(hex("size_in_bytes_bytes")) < 56 THEN
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 1, 1)) - 1) * 1152921504606846976 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 2, 1)) - 1) * 72057594037927936 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 3, 1)) - 1) * 4503599627370496 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 4, 1)) - 1) * 281474976710656 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 5, 1)) - 1) * 17592186044416 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 6, 1)) - 1) * 1099511627776 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 7, 1)) - 1) * 68719476736 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 8, 1)) - 1) * 4294967296 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 9, 1)) - 1) * 268435456 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 10, 1)) - 1) * 16777216 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 11, 1)) - 1) * 1048576 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 12, 1)) - 1) * 65536 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 13, 1)) - 1) * 4096 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 14, 1)) - 1) * 256 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 15, 1)) - 1) * 16 +
+        (instr('0123456789ABCDEF', substr(hex("size_in_bytes_bytes"), 16, 1)) - 1) * 1
+    END
+) VIRTUAL;
+
+-- CreateIndex
+CREATE INDEX "file_path_size_in_bytes_int_idx" ON "file_path"("size_in_bytes_int");
@@ -0,0 +1,17 @@
+-- Albums are now created by the API, which relies on the database assigning their ids
+-- RedefineTables
+PRAGMA foreign_keys=OFF;
+CREATE TABLE "new_album" (
+    "id" INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    "pub_id" BLOB NOT NULL,
+    "name" TEXT,
+    "is_hidden" BOOLEAN,
+    "date_created" DATETIME,
+    "date_modified" DATETIME
+);
+INSERT INTO "new_album" ("date_created", "date_modified", "id", "is_hidden", "name", "pub_id") SELECT "date_created", "date_modified", "id", "is_hidden", "name", "pub_id" FROM "album";
+DROP TABLE "album";
+ALTER TABLE "new_album" RENAME TO "album";
+CREATE UNIQUE INDEX "album_pub_id_key" ON "album"("pub_id");
+PRAGMA foreign_key_check;
+PRAGMA foreign_keys=ON;
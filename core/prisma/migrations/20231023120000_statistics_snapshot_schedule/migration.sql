@@ -0,0 +1,3 @@
+-- Statistics snapshots are only taken by the scheduler, every library starts with an hourly one.
+-- The first snapshot is taken as soon as the library is loaded.
+INSERT INTO "job_schedule" ("kind", "interval", "next_run") VALUES (3, 3600, CURRENT_TIMESTAMP);
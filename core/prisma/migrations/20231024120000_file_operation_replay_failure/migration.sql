@@ -0,0 +1,2 @@
+-- AlterTable
+ALTER TABLE "file_operation" ADD COLUMN "replay_failure" TEXT;
@@ -0,0 +1,501 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	object::{album::AlbumCreateArgs, media::thumbnail::get_thumb_key},
+	prisma::{album, file_path, object, object_in_album, PrismaClient, SortOrder},
+};
+
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use prisma_client_rust::or;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+
+use super::{utils::library, Ctx, R};
+
+#[derive(Serialize, Type, Debug)]
+pub struct AlbumWithCover {
+	pub album: album::Data,
+	/// Thumbnail of the first photo or video in the album
+	pub thumbnail_key: Option<Vec<String>>,
+}
+
+#[derive(Type, Deserialize)]
+pub struct AlbumObjectsArgs {
+	pub album_id: album::id::Type,
+	pub object_ids: Vec<object::id::Type>,
+}
+
+fn not_found() -> rspc::Error {
+	rspc::Error::new(ErrorCode::NotFound, "Album not found".to_string())
+}
+
+async fn album_pub_id(db: &PrismaClient, id: album::id::Type) -> Result<Vec<u8>, rspc::Error> {
+	Ok(db
+		.album()
+		.find_unique(album::id::equals(id))
+		.select(album::select!({ pub_id }))
+		.exec()
+		.await?
+		.ok_or_else(not_found)?
+		.pub_id)
+}
+
+/// Sets a field of an album, bumping its `date_modified`
+async fn update_album(
+	Library { db, sync, .. }: &Library,
+	id: album::id::Type,
+	(field, value): (&'static str, serde_json::Value),
+	param: album::SetParam,
+) -> Result<(), rspc::Error> {
+	let pub_id = album_pub_id(db, id).await?;
+	let date_modified: DateTime<FixedOffset> = Utc::now().into();
+
+	sync.write_ops(
+		db,
+		(
+			[
+				(field, value),
+				(
+					album::date_modified::NAME,
+					json!(&date_modified.to_rfc3339()),
+				),
+			]
+			.into_iter()
+			.map(|(k, v)| {
+				sync.shared_update(
+					prisma_sync::album::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					k,
+					v,
+				)
+			})
+			.collect(),
+			db.album().update(
+				album::id::equals(id),
+				vec![param, album::date_modified::set(Some(date_modified))],
+			),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			#[derive(Type, Deserialize)]
+			#[serde(rename_all = "camelCase")]
+			pub struct AlbumListArgs {
+				#[serde(default)]
+				pub include_hidden: bool,
+			}
+
+			R.with2(library())
+				.query(|(_, library), args: AlbumListArgs| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let albums = db
+						.album()
+						.find_many(if args.include_hidden {
+							vec![]
+						} else {
+							vec![or![
+								album::is_hidden::equals(None),
+								album::is_hidden::not(Some(true))
+							]]
+						})
+						.order_by(album::date_created::order(SortOrder::Asc))
+						.exec()
+						.await?;
+
+					// Thumbnails only exist for photos and videos
+					let thumbnail_kinds = vec![ObjectKind::Image as i32, ObjectKind::Video as i32];
+
+					let covers = db
+						._batch(
+							albums
+								.iter()
+								.map(|album| {
+									db.object_in_album()
+										.find_first(vec![
+											object_in_album::album_id::equals(album.id),
+											object_in_album::object::is(vec![
+												object::kind::in_vec(thumbnail_kinds.clone()),
+												object::file_paths::some(vec![
+													file_path::cas_id::not(None),
+												]),
+											]),
+										])
+										.order_by(object_in_album::position::order(SortOrder::Asc))
+										.select(object_in_album::select!({
+											object: select {
+												file_paths(vec![file_path::cas_id::not(None)]): select {
+													cas_id
+												}
+											}
+										}))
+								})
+								.collect::<Vec<_>>(),
+						)
+						.await?;
+
+					Ok(albums
+						.into_iter()
+						.zip(covers)
+						.map(|(album, cover)| AlbumWithCover {
+							album,
+							thumbnail_key: cover.and_then(|cover| {
+								cover
+									.object
+									.file_paths
+									.into_iter()
+									.find_map(|file_path| file_path.cas_id)
+									.map(|cas_id| get_thumb_key(&cas_id))
+							}),
+						})
+						.collect::<Vec<_>>())
+				})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), album_id: album::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_unique(album::id::equals(album_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getForObject", {
+			R.with2(library())
+				.query(|(_, library), object_id: object::id::Type| async move {
+					Ok(library
+						.db
+						.album()
+						.find_many(vec![album::objects::some(vec![
+							object_in_album::object_id::equals(object_id),
+						])])
+						.exec()
+						.await?)
+				})
+		})
+		// Ids of the album's objects, in the order they were arranged in
+		.procedure("getObjectIds", {
+			R.with2(library())
+				.query(|(_, library), album_id: album::id::Type| async move {
+					Ok(library
+						.db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(album_id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.select(object_in_album::select!({ object_id }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|rel| rel.object_id)
+						.collect::<Vec<_>>())
+				})
+		})
+		.procedure("create", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumCreateArgs| async move {
+					let created_album = args.exec(&library).await?;
+
+					invalidate_query!(library, "albums.list");
+
+					Ok(created_album)
+				})
+		})
+		.procedure("rename", {
+			#[derive(Type, Deserialize)]
+			pub struct AlbumRenameArgs {
+				pub id: album::id::Type,
+				pub name: String,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumRenameArgs| async move {
+					update_album(
+						&library,
+						args.id,
+						(album::name::NAME, json!(&args.name)),
+						album::name::set(Some(args.name)),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.get");
+
+					Ok(())
+				})
+		})
+		.procedure("hide", {
+			#[derive(Type, Deserialize)]
+			pub struct AlbumHideArgs {
+				pub id: album::id::Type,
+				pub hidden: bool,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumHideArgs| async move {
+					update_album(
+						&library,
+						args.id,
+						(album::is_hidden::NAME, json!(args.hidden)),
+						album::is_hidden::set(Some(args.hidden)),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.get");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = album_pub_id(db, args.album_id).await?;
+
+					let (mut objects, last) = db
+						._batch((
+							db.object()
+								.find_many(vec![
+									object::id::in_vec(args.object_ids.clone()),
+									object::albums::none(vec![object_in_album::album_id::equals(
+										args.album_id,
+									)]),
+								])
+								.select(object::select!({ id pub_id })),
+							db.object_in_album()
+								.find_first(vec![object_in_album::album_id::equals(args.album_id)])
+								.order_by(object_in_album::position::order(SortOrder::Desc))
+								.select(object_in_album::select!({ position })),
+						))
+						.await?;
+
+					// New objects go to the end of the album, in the order they were given
+					objects.sort_by_key(|object| {
+						args.object_ids.iter().position(|id| *id == object.id)
+					});
+					let first_position = last.and_then(|rel| rel.position).map_or(0, |p| p + 1);
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+					let (sync_ops, db_creates) = objects.into_iter().zip(first_position..).fold(
+						(vec![], vec![]),
+						|(mut sync_ops, mut db_creates), (object, position)| {
+							db_creates.push(object_in_album::CreateUnchecked {
+								album_id: args.album_id,
+								object_id: object.id,
+								_params: vec![
+									object_in_album::position::set(Some(position)),
+									object_in_album::date_created::set(Some(date_created)),
+								],
+							});
+
+							sync_ops.extend(sync.relation_create(
+								prisma_sync::object_in_album::SyncId {
+									album: prisma_sync::album::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									object: prisma_sync::object::SyncId {
+										pub_id: object.pub_id,
+									},
+								},
+								[
+									(object_in_album::position::NAME, json!(position)),
+									(
+										object_in_album::date_created::NAME,
+										json!(&date_created.to_rfc3339()),
+									),
+								],
+							));
+
+							(sync_ops, db_creates)
+						},
+					);
+
+					sync.write_ops(db, (sync_ops, db.object_in_album().create_many(db_creates)))
+						.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "albums.getObjectIds");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		.procedure("removeObjects", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = album_pub_id(db, args.album_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![object::id::in_vec(args.object_ids)])
+						.select(object::select!({ id pub_id }))
+						.exec()
+						.await?;
+
+					let query = db.object_in_album().delete_many(vec![
+						object_in_album::album_id::equals(args.album_id),
+						object_in_album::object_id::in_vec(objects.iter().map(|o| o.id).collect()),
+					]);
+
+					sync.write_ops(
+						db,
+						(
+							objects
+								.into_iter()
+								.map(|object| {
+									sync.relation_delete(prisma_sync::object_in_album::SyncId {
+										album: prisma_sync::album::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object.pub_id,
+										},
+									})
+								})
+								.collect(),
+							query,
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "albums.getObjectIds");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+		// `object_ids` are the album's objects in their new order, any missing from it follow them in their previous order
+		.procedure("reorder", {
+			R.with2(library())
+				.mutation(|(_, library), args: AlbumObjectsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = album_pub_id(db, args.album_id).await?;
+
+					let members = db
+						.object_in_album()
+						.find_many(vec![object_in_album::album_id::equals(args.album_id)])
+						.order_by(object_in_album::position::order(SortOrder::Asc))
+						.select(object_in_album::select!({ object: select { id pub_id } }))
+						.exec()
+						.await?;
+
+					// Every member is renumbered, so positions stay unique whatever subset was given
+					let (mut ordered, rest): (Vec<_>, Vec<_>) = members
+						.into_iter()
+						.map(|rel| rel.object)
+						.partition(|object| args.object_ids.contains(&object.id));
+					ordered.sort_by_key(|object| {
+						args.object_ids.iter().position(|id| *id == object.id)
+					});
+
+					let (sync_ops, db_updates): (Vec<_>, Vec<_>) = ordered
+						.iter()
+						.chain(&rest)
+						.zip(0..)
+						.map(|(object, position)| {
+							(
+								sync.relation_update(
+									prisma_sync::object_in_album::SyncId {
+										album: prisma_sync::album::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object.pub_id.clone(),
+										},
+									},
+									object_in_album::position::NAME,
+									json!(position),
+								),
+								db.object_in_album().update(
+									object_in_album::album_id_object_id(args.album_id, object.id),
+									vec![object_in_album::position::set(Some(position))],
+								),
+							)
+						})
+						.unzip();
+
+					sync.write_ops(db, (sync_ops, db_updates)).await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getObjectIds");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), album_id: album::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = album_pub_id(db, album_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![object::albums::some(vec![
+							object_in_album::album_id::equals(album_id),
+						])])
+						.select(object::select!({ pub_id }))
+						.exec()
+						.await?;
+
+					// The objects have to leave the album before it can be deleted
+					sync.write_ops(
+						db,
+						(
+							objects
+								.into_iter()
+								.map(|object| {
+									sync.relation_delete(prisma_sync::object_in_album::SyncId {
+										album: prisma_sync::album::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object.pub_id,
+										},
+									})
+								})
+								.collect(),
+							db.object_in_album()
+								.delete_many(vec![object_in_album::album_id::equals(album_id)]),
+						),
+					)
+					.await?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::album::SyncId { pub_id }),
+						db.album().delete(album::id::equals(album_id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "albums.list");
+					invalidate_query!(library, "albums.getForObject");
+					invalidate_query!(library, "search.objects");
+
+					Ok(())
+				})
+		})
+}
@@ -1,31 +1,47 @@
 use crate::{
-	api::utils::library,
+	api::{utils::library, CoreEvent},
 	invalidate_query,
 	job::Job,
 	library::Library,
 	location::{
 		file_path_helper::{
-			file_path_to_isolate, file_path_to_isolate_with_id, FilePathError, IsolatedFilePathData,
+			file_path_to_isolate, file_path_walker, FilePathError, IsolatedFilePathData,
 		},
 		find_location, LocationError,
 	},
 	object::{
 		fs::{
-			copy::FileCopierJobInit, cut::FileCutterJobInit, delete::FileDeleterJobInit,
+			archive::{FileArchiverJobInit, FileExtractorJobInit},
+			conflict::ConflictDecision,
+			convert::FileConverterJobInit,
+			copy::FileCopierJobInit,
+			cut::FileCutterJobInit,
+			decrypt::FileDecryptorJobInit,
+			dedup::DeduplicatorJobInit,
+			delete::FileDeleterJobInit,
+			encrypt::FileEncryptorJobInit,
 			erase::FileEraserJobInit,
+			error::FileSystemJobsError,
+			journal::{self, journal, InverseAction, JournalEntry},
+			trash::{restore_trashed, trashed_file_path, EmptyTrashJobInit},
 		},
 		media::{
+			media_data_audio_from_prisma_data,
 			media_data_extractor::{
-				can_extract_media_data_for_image, extract_media_data, MediaDataError,
+				can_extract_media_data_for_audio, can_extract_media_data_for_image,
+				extract_audio_media_data, extract_media_data, MediaDataError,
 			},
-			media_data_image_from_prisma_data,
+			media_data_image_from_prisma_data, media_data_video_from_prisma_data,
 		},
 	},
 	prisma::{file_path, location, object},
 	util::{db::maybe_missing, error::FileIOError},
 };
 
-use sd_file_ext::{extensions::ImageExtension, kind::ObjectKind};
+use sd_file_ext::{
+	extensions::{AudioExtension, ImageExtension},
+	kind::ObjectKind,
+};
 use sd_media_metadata::MediaMetadata;
 
 use std::{
@@ -41,9 +57,17 @@ use serde::Deserialize;
 use specta::Type;
 use tokio::{fs, io};
 use tracing::{error, warn};
+use uuid::Uuid;
 
 use super::{Ctx, R};
 
+#[cfg(feature = "ffmpeg")]
+use crate::object::media::media_data_extractor::{
+	can_extract_media_data_for_video, extract_video_media_data,
+};
+#[cfg(feature = "ffmpeg")]
+use sd_file_ext::extensions::VideoExtension;
+
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
 	R.router()
 		.procedure("get", {
@@ -79,7 +103,13 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 										media_data_image_from_prisma_data(obj.media_data?).ok()?,
 									))
 								}
-								_ => return None, // TODO(brxken128): audio and video
+								Some(v) if v == ObjectKind::Audio as i32 => MediaMetadata::Audio(
+									Box::new(media_data_audio_from_prisma_data(obj.media_data?)?),
+								),
+								Some(v) if v == ObjectKind::Video as i32 => MediaMetadata::Video(
+									Box::new(media_data_video_from_prisma_data(obj.media_data?)?),
+								),
+								_ => return None,
 							})
 						})
 						.ok_or_else(|| {
@@ -93,8 +123,48 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					return Ok(None);
 				};
 
-				// TODO(fogodev): change this when we have media data for audio and videos
-				let image_extension = ImageExtension::from_str(extension).map_err(|e| {
+				let extension = extension.to_lowercase();
+
+				if let Ok(audio_extension) = AudioExtension::from_str(&extension) {
+					if !can_extract_media_data_for_audio(&audio_extension) {
+						return Ok(None);
+					}
+
+					return extract_audio_media_data(full_path)
+						.await
+						.map(|audio_media_data| {
+							Some(MediaMetadata::Audio(Box::new(audio_media_data)))
+						})
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to extract media data".to_string(),
+								e,
+							)
+						});
+				}
+
+				#[cfg(feature = "ffmpeg")]
+				if let Ok(video_extension) = VideoExtension::from_str(&extension) {
+					if !can_extract_media_data_for_video(&video_extension) {
+						return Ok(None);
+					}
+
+					return extract_video_media_data(full_path)
+						.await
+						.map(|video_media_data| {
+							Some(MediaMetadata::Video(Box::new(video_media_data)))
+						})
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to extract media data".to_string(),
+								e,
+							)
+						});
+				}
+
+				let image_extension = ImageExtension::from_str(&extension).map_err(|e| {
 					error!("Failed to parse image extension: {e:#?}");
 					rspc::Error::new(ErrorCode::BadRequest, "Invalid image extension".to_string())
 				})?;
@@ -230,24 +300,30 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					Ok(())
 				})
 		})
-		// .procedure("encryptFiles", {
-		// 	R.with2(library())
-		// 		.mutation(|(node, library), args: FileEncryptorJobInit| async move {
-		// 			Job::new(args).spawn(&node, &library).await.map_err(Into::into)
-		// 		})
-		// })
-		// .procedure("decryptFiles", {
-		// 	R.with2(library())
-		// 		.mutation(|(node, library), args: FileDecryptorJobInit| async move {
-		// 			Job::new(args).spawn(&node, &library).await.map_err(Into::into)
-		// 		})
-		// })
+		.procedure("encryptFiles", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileEncryptorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
+		.procedure("decryptFiles", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileDecryptorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
 		.procedure("deleteFiles", {
 			R.with2(library())
 				.mutation(|(node, library), args: FileDeleterJobInit| async move {
 					match args.file_path_ids.len() {
 						0 => Ok(()),
-						1 => {
+						1 if !args.to_trash => {
 							let (maybe_location, maybe_file_path) = library
 								.db
 								._batch((
@@ -317,6 +393,64 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}
 				})
 		})
+		.procedure("restoreFromTrash", {
+			R.with2(library()).mutation(
+				|(_, library), file_path_ids: Vec<file_path::id::Type>| async move {
+					let trashed = library
+						.db
+						.file_path()
+						.find_many(vec![
+							file_path::id::in_vec(file_path_ids),
+							file_path::trashed_at::not(None),
+						])
+						.select(trashed_file_path::select())
+						.exec()
+						.await?;
+
+					let mut result = Ok(());
+					for trashed in trashed {
+						if let Err(e) = restore_trashed(&library, trashed).await {
+							error!("Failed to restore from the trash: {e:#?}");
+							result = Err(match e {
+								FileSystemJobsError::WouldOverwrite(_) => rspc::Error::with_cause(
+									ErrorCode::Conflict,
+									"Something else took the original place of the file"
+										.to_string(),
+									e,
+								),
+								FileSystemJobsError::NotTrashedItself(_) => {
+									rspc::Error::with_cause(
+										ErrorCode::BadRequest,
+										"Files inside a trashed directory are restored with it"
+											.to_string(),
+										e,
+									)
+								}
+								_ => rspc::Error::with_cause(
+									ErrorCode::InternalServerError,
+									"Failed to restore the file from the trash".to_string(),
+									e,
+								),
+							});
+						}
+					}
+
+					invalidate_query!(library, "search.paths");
+					invalidate_query!(library, "search.objects");
+
+					result
+				},
+			)
+		})
+		.procedure("emptyTrash", {
+			R.with2(library())
+				.mutation(|(node, library), args: EmptyTrashJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
 		.procedure("eraseFiles", {
 			R.with2(library())
 				.mutation(|(node, library), args: FileEraserJobInit| async move {
@@ -344,6 +478,15 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.map_err(Into::into)
 				})
 		})
+		.procedure("deduplicateFiles", {
+			R.with2(library())
+				.mutation(|(node, library), args: DeduplicatorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
 		.procedure("cutFiles", {
 			R.with2(library())
 				.mutation(|(node, library), args: FileCutterJobInit| async move {
@@ -353,6 +496,75 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.map_err(Into::into)
 				})
 		})
+		.procedure("pendingConflicts", {
+			R.with2(library())
+				.query(|(_, library), _: ()| async move { Ok(library.file_conflicts.pending()) })
+		})
+		.procedure("conflicts", {
+			R.with2(library())
+				.subscription(|(node, library), _: ()| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					async_stream::stream! {
+						while let Ok(event) = event_bus_rx.recv().await {
+							match event {
+								CoreEvent::FileConflict(event) if event.library_id == library.id => yield event,
+								_ => {}
+							}
+						}
+					}
+				})
+		})
+		.procedure("resolveConflict", {
+			#[derive(Type, Deserialize)]
+			pub struct ResolveConflictArgs {
+				pub id: Uuid,
+				pub decision: ConflictDecision,
+			}
+
+			R.with2(library())
+				.mutation(|(node, library), args: ResolveConflictArgs| async move {
+					// The job is paused while it waits, so it's resumed right away
+					let job_id = library
+						.file_conflicts
+						.decide(args.id, args.decision)
+						.ok_or_else(|| {
+							rspc::Error::new(
+								ErrorCode::NotFound,
+								"No job is waiting on this conflict anymore".to_string(),
+							)
+						})?;
+
+					node.jobs.resume(job_id).await.map_err(Into::into)
+				})
+		})
+		.procedure("archiveFiles", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileArchiverJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
+		.procedure("extractFiles", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileExtractorJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
+		.procedure("convertImages", {
+			R.with2(library())
+				.mutation(|(node, library), args: FileConverterJobInit| async move {
+					Job::new(args)
+						.spawn(&node, &library)
+						.await
+						.map_err(Into::into)
+				})
+		})
 		.procedure("renameFile", {
 			#[derive(Type, Deserialize)]
 			pub struct FromPattern {
@@ -385,6 +597,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 				pub kind: RenameKind,
 			}
 
+			/// Both kinds record what they actually renamed in `renamed`, even when they fail midway,
+			/// so those renames can still be undone
 			impl RenameFileArgs {
 				pub async fn rename_one(
 					RenameOne {
@@ -393,21 +607,22 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}: RenameOne,
 					location_path: impl AsRef<Path>,
 					library: &Library,
+					renamed: &mut Vec<JournalEntry>,
 				) -> Result<(), rspc::Error> {
 					let location_path = location_path.as_ref();
-					let iso_file_path = IsolatedFilePathData::try_from(
-						library
-							.db
-							.file_path()
-							.find_unique(file_path::id::equals(from_file_path_id))
-							.select(file_path_to_isolate::select())
-							.exec()
-							.await?
-							.ok_or(LocationError::FilePath(FilePathError::IdNotFound(
-								from_file_path_id,
-							)))?,
-					)
-					.map_err(LocationError::MissingField)?;
+					let file_path = library
+						.db
+						.file_path()
+						.find_unique(file_path::id::equals(from_file_path_id))
+						.select(file_path_walker::select())
+						.exec()
+						.await?
+						.ok_or(LocationError::FilePath(FilePathError::IdNotFound(
+							from_file_path_id,
+						)))?;
+					let pub_id = file_path.pub_id.clone();
+					let iso_file_path = IsolatedFilePathData::try_from(file_path)
+						.map_err(LocationError::MissingField)?;
 
 					if iso_file_path.full_name() == to {
 						return Ok(());
@@ -442,7 +657,8 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						}
 					}
 
-					fs::rename(location_path.join(&iso_file_path), new_file_full_path)
+					let old_file_full_path = location_path.join(&iso_file_path);
+					fs::rename(&old_file_full_path, &new_file_full_path)
 						.await
 						.map_err(|e| {
 							rspc::Error::with_cause(
@@ -452,6 +668,12 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 							)
 						})?;
 
+					renamed.push(JournalEntry::new(
+						pub_id,
+						old_file_full_path,
+						new_file_full_path,
+					));
+
 					Ok(())
 				}
 
@@ -463,6 +685,7 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}: RenameMany,
 					location_path: impl AsRef<Path>,
 					library: &Library,
+					renamed: &mut Vec<JournalEntry>,
 				) -> Result<(), rspc::Error> {
 					let location_path = location_path.as_ref();
 
@@ -473,17 +696,21 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						));
 					};
 
-					let errors = join_all(
+					let (succeeded, errors): (Vec<_>, Vec<_>) = join_all(
 						library
 							.db
 							.file_path()
 							.find_many(vec![file_path::id::in_vec(from_file_path_ids)])
-							.select(file_path_to_isolate_with_id::select())
+							.select(file_path_walker::select())
 							.exec()
 							.await?
 							.into_iter()
-							.flat_map(IsolatedFilePathData::try_from)
-							.map(|iso_file_path| {
+							.flat_map(|file_path| {
+								let pub_id = file_path.pub_id.clone();
+								IsolatedFilePathData::try_from(file_path)
+									.map(|iso_file_path| (pub_id, iso_file_path))
+							})
+							.map(|(pub_id, iso_file_path)| {
 								let from = location_path.join(&iso_file_path);
 								let mut to = location_path.join(iso_file_path.parent());
 								let full_name = iso_file_path.full_name();
@@ -515,21 +742,25 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 												"Failed to rename file".to_string(),
 												e,
 											)
-										})
+										})?;
+
+										Ok(JournalEntry::new(pub_id, from, to))
 									}
 								}
 							}),
 					)
 					.await
 					.into_iter()
-					.filter_map(Result::err)
-					.collect::<Vec<_>>();
+					.partition(Result::is_ok);
+
+					renamed.extend(succeeded.into_iter().filter_map(Result::ok));
 
 					if !errors.is_empty() {
 						return Err(rspc::Error::new(
 							rspc::ErrorCode::Conflict,
 							errors
 								.into_iter()
+								.filter_map(Result::err)
 								.map(|e| e.to_string())
 								.collect::<Vec<_>>()
 								.join("\n"),
@@ -550,18 +781,48 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 						.path
 						.ok_or(LocationError::MissingPath(args.location_id))?;
 
+					let mut renamed = vec![];
 					let res = match args.kind {
 						RenameKind::One(one) => {
-							RenameFileArgs::rename_one(one, location_path, &library).await
+							RenameFileArgs::rename_one(one, location_path, &library, &mut renamed)
+								.await
 						}
 						RenameKind::Many(many) => {
-							RenameFileArgs::rename_many(many, location_path, &library).await
+							RenameFileArgs::rename_many(many, location_path, &library, &mut renamed)
+								.await
 						}
 					};
 
 					invalidate_query!(library, "search.objects");
 
+					if !renamed.is_empty() {
+						if let Err(e) = journal(
+							&library,
+							None,
+							InverseAction::RenameBack {
+								location_id: args.location_id,
+								entries: renamed,
+							},
+						)
+						.await
+						{
+							error!("Failed to journal renamed files: {e:#?}");
+						}
+					}
+
 					res
 				})
 		})
+		.procedure("undo", {
+			R.with2(library())
+				.mutation(|(node, library), _: ()| async move {
+					journal::undo(&node, &library).await.map_err(Into::into)
+				})
+		})
+		.procedure("redo", {
+			R.with2(library())
+				.mutation(|(node, library), _: ()| async move {
+					journal::redo(&node, &library).await.map_err(Into::into)
+				})
+		})
 }
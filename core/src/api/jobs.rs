@@ -1,12 +1,15 @@
 use crate::{
 	invalidate_query,
-	job::{job_without_data, Job, JobReport, JobStatus, Jobs},
+	job::{
+		job_without_data, Job, JobReport, JobSchedule, JobScheduleCreateArgs,
+		JobScheduleUpdateArgs, JobStatus, Jobs,
+	},
 	location::{find_location, LocationError},
 	object::{
 		file_identifier::file_identifier_job::FileIdentifierJobInit, media::MediaProcessorJobInit,
 		validation::validator_job::ObjectValidatorJobInit,
 	},
-	prisma::{job, location, SortOrder},
+	prisma::{job, job_schedule, location, SortOrder},
 };
 
 use std::{
@@ -21,7 +24,7 @@ use rspc::alpha::AlphaRouter;
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use tokio::time::Duration;
-use tracing::{info, trace};
+use tracing::{error, info, trace};
 use uuid::Uuid;
 
 use super::{utils::library, CoreEvent, Ctx, R};
@@ -314,4 +317,62 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 					}
 				})
 		})
+		.merge("schedules.", mount_schedules())
+}
+
+fn mount_schedules() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.job_schedule()
+					.find_many(vec![])
+					.order_by(job_schedule::next_run::order(SortOrder::Asc))
+					.exec()
+					.await?
+					.into_iter()
+					.filter_map(|data| {
+						let id = data.id;
+						JobSchedule::try_from(data)
+							.map_err(|e| {
+								error!("Skipping invalid job schedule <id='{id}'>: {e:#?}")
+							})
+							.ok()
+					})
+					.collect::<Vec<_>>())
+			})
+		})
+		.procedure("create", {
+			R.with2(library())
+				.mutation(|(_, library), args: JobScheduleCreateArgs| async move {
+					let schedule = args.create(&library).await?;
+
+					invalidate_query!(library, "jobs.schedules.list");
+					Ok(schedule)
+				})
+		})
+		.procedure("update", {
+			R.with2(library())
+				.mutation(|(_, library), args: JobScheduleUpdateArgs| async move {
+					let schedule = args.update(&library).await?;
+
+					invalidate_query!(library, "jobs.schedules.list");
+					Ok(schedule)
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), id: job_schedule::id::Type| async move {
+					library
+						.db
+						.job_schedule()
+						.delete(job_schedule::id::equals(id))
+						.exec()
+						.await?;
+
+					invalidate_query!(library, "jobs.schedules.list");
+					Ok(())
+				})
+		})
 }
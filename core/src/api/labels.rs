@@ -0,0 +1,244 @@
+use crate::{
+	invalidate_query,
+	library::Library,
+	object::label::{assign_labels, LabelSource},
+	prisma::{label, label_on_object, object},
+};
+
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use std::{collections::BTreeMap, str::FromStr};
+
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+use super::{utils::library, Ctx, R};
+
+#[derive(Serialize, Type, Debug)]
+pub struct LabelForObject {
+	pub label: label::Data,
+	/// `None` if the source is unknown to this version of Spacedrive
+	pub source: Option<LabelSource>,
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library.db.label().find_many(vec![]).exec().await?)
+			})
+		})
+		// How many objects each label is assigned to
+		.procedure("count", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				let Library { db, .. } = library.as_ref();
+
+				let label_ids = db
+					.label()
+					.find_many(vec![])
+					.select(label::select!({ id }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|label| label.id)
+					.collect::<Vec<_>>();
+
+				let counts = db
+					._batch(
+						label_ids
+							.iter()
+							.map(|id| {
+								db.label_on_object()
+									.count(vec![label_on_object::label_id::equals(*id)])
+							})
+							.collect::<Vec<_>>(),
+					)
+					.await?;
+
+				Ok(label_ids
+					.into_iter()
+					.zip(counts)
+					// TODO(@Oscar): rspc bigint support
+					.map(|(id, count)| {
+						i32::try_from(count).map(|count| (id, count)).map_err(|_| {
+							rspc::Error::new(
+								ErrorCode::InternalServerError,
+								"label object count overflowed 'i32'!".into(),
+							)
+						})
+					})
+					.collect::<Result<BTreeMap<_, _>, _>>()?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), label_id: label::id::Type| async move {
+					Ok(library
+						.db
+						.label()
+						.find_unique(label::id::equals(label_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("getForObject", {
+			R.with2(library())
+				.query(|(_, library), object_id: object::id::Type| async move {
+					Ok(library
+						.db
+						.label_on_object()
+						.find_many(vec![label_on_object::object_id::equals(object_id)])
+						.include(label_on_object::include!({ label }))
+						.exec()
+						.await?
+						.into_iter()
+						.map(|rel| LabelForObject {
+							label: rel.label,
+							source: rel
+								.source
+								.as_deref()
+								.and_then(|source| LabelSource::from_str(source).ok()),
+						})
+						.collect::<Vec<_>>())
+				})
+		})
+		.procedure("assign", {
+			#[derive(Debug, Type, Deserialize)]
+			pub struct LabelAssignArgs {
+				pub object_ids: Vec<object::id::Type>,
+				pub labels: Vec<String>,
+				pub source: LabelSource,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: LabelAssignArgs| async move {
+					assign_labels(&library, args.object_ids, args.labels, args.source).await?;
+
+					invalidate_query!(library, "labels.list");
+					invalidate_query!(library, "labels.count");
+					invalidate_query!(library, "labels.getForObject");
+
+					Ok(())
+				})
+		})
+		.procedure("unassign", {
+			#[derive(Debug, Type, Deserialize)]
+			pub struct LabelUnassignArgs {
+				pub object_ids: Vec<object::id::Type>,
+				pub label_id: label::id::Type,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: LabelUnassignArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let (label, objects) = db
+						._batch((
+							db.label()
+								.find_unique(label::id::equals(args.label_id))
+								.select(label::select!({ pub_id })),
+							db.object()
+								.find_many(vec![object::id::in_vec(args.object_ids)])
+								.select(object::select!({ id pub_id })),
+						))
+						.await?;
+
+					let label = label.ok_or_else(|| {
+						rspc::Error::new(ErrorCode::NotFound, "Label not found".to_string())
+					})?;
+
+					let query = db.label_on_object().delete_many(vec![
+						label_on_object::label_id::equals(args.label_id),
+						label_on_object::object_id::in_vec(objects.iter().map(|o| o.id).collect()),
+					]);
+
+					sync.write_ops(
+						db,
+						(
+							objects
+								.into_iter()
+								.map(|object| {
+									sync.relation_delete(prisma_sync::label_on_object::SyncId {
+										label: prisma_sync::label::SyncId {
+											pub_id: label.pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: object.pub_id,
+										},
+									})
+								})
+								.collect(),
+							query,
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "labels.count");
+					invalidate_query!(library, "labels.getForObject");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), label_id: label::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let label = db
+						.label()
+						.find_unique(label::id::equals(label_id))
+						.select(label::select!({
+							pub_id
+							label_objects: select {
+								object: select { pub_id }
+							}
+						}))
+						.exec()
+						.await?
+						.ok_or_else(|| {
+							rspc::Error::new(ErrorCode::NotFound, "Label not found".to_string())
+						})?;
+
+					// The label has to be removed from its objects before it can be deleted
+					sync.write_ops(
+						db,
+						(
+							label
+								.label_objects
+								.into_iter()
+								.map(|rel| {
+									sync.relation_delete(prisma_sync::label_on_object::SyncId {
+										label: prisma_sync::label::SyncId {
+											pub_id: label.pub_id.clone(),
+										},
+										object: prisma_sync::object::SyncId {
+											pub_id: rel.object.pub_id,
+										},
+									})
+								})
+								.collect(),
+							db.label_on_object()
+								.delete_many(vec![label_on_object::label_id::equals(label_id)]),
+						),
+					)
+					.await?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::label::SyncId {
+							pub_id: label.pub_id,
+						}),
+						db.label().delete(label::id::equals(label_id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "labels.list");
+					invalidate_query!(library, "labels.count");
+					invalidate_query!(library, "labels.getForObject");
+
+					Ok(())
+				})
+		})
+}
@@ -1,22 +1,20 @@
 use crate::{
-	library::{LibraryConfig, LibraryName},
+	library::{
+		library_statistics, statistics_summary, LibraryConfig, LibraryName, StatisticsBreakdown,
+	},
 	util::MaybeUndefined,
-	volume::get_volumes,
 };
 
-use chrono::Utc;
-use rspc::alpha::AlphaRouter;
+use chrono::{DateTime, Utc};
+use rspc::{alpha::AlphaRouter, ErrorCode};
 use sd_p2p::spacetunnel::RemoteIdentity;
-use sd_prisma::prisma::statistics;
+use sd_prisma::prisma::{statistics, SortOrder};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 use tracing::debug;
 use uuid::Uuid;
 
-use super::{
-	utils::{get_size, library},
-	Ctx, R,
-};
+use super::{utils::library, Ctx, R};
 
 // TODO(@Oscar): Replace with `specta::json`
 #[derive(Serialize, Type)]
@@ -47,63 +45,65 @@ pub(crate) fn mount() -> AlphaRouter<Ctx> {
 		.procedure("statistics", {
 			R.with2(library())
 				.query(|(node, library), _: ()| async move {
-					// TODO: get from database if library is offline
-					// let _statistics = library
-					// 	.db
-					// 	.statistics()
-					// 	.find_unique(statistics::id::equals(library.node_local_id))
-					// 	.exec()
-					// 	.await?;
-
-					let volumes = get_volumes().await;
-					// save_volume(&library).await?;
-
-					let mut total_capacity: u64 = 0;
-					let mut available_capacity: u64 = 0;
-					for volume in volumes {
-						total_capacity += volume.total_capacity;
-						available_capacity += volume.available_capacity;
-					}
-
-					let library_db_size = get_size(
-						node.config
-							.data_directory()
-							.join("libraries")
-							.join(&format!("{}.db", library.id)),
-					)
-					.await
-					.unwrap_or(0);
-
-					let thumbnail_folder_size =
-						get_size(node.config.data_directory().join("thumbnails"))
-							.await
-							.unwrap_or(0);
+					Ok(library_statistics(&node, &library).await?)
+				})
+		})
+		.procedure("statisticsHistory", {
+			#[derive(Deserialize, Type)]
+			pub struct StatisticsHistoryArgs {
+				#[specta(optional)]
+				from: Option<DateTime<Utc>>,
+				#[specta(optional)]
+				to: Option<DateTime<Utc>>,
+			}
 
+			R.with2(library())
+				.query(|(_, library), args: StatisticsHistoryArgs| async move {
 					use statistics::*;
-					let params = vec![
-						id::set(1), // Each library is a database so only one of these ever exists
-						date_captured::set(Utc::now().into()),
-						total_object_count::set(0),
-						library_db_size::set(library_db_size.to_string()),
-						total_bytes_used::set(0.to_string()),
-						total_bytes_capacity::set(total_capacity.to_string()),
-						total_unique_bytes::set(0.to_string()),
-						total_bytes_free::set(available_capacity.to_string()),
-						preview_media_bytes::set(thumbnail_folder_size.to_string()),
-					];
 
 					Ok(library
 						.db
 						.statistics()
-						.upsert(
-							statistics::id::equals(1), // Each library is a database so only one of these ever exists
-							statistics::create(params.clone()),
-							params,
-						)
+						.find_many(sd_utils::chain_optional_iter(
+							[],
+							[
+								args.from.map(|from| date_captured::gte(from.into())),
+								args.to.map(|to| date_captured::lte(to.into())),
+							],
+						))
+						.order_by(date_captured::order(SortOrder::Asc))
+						.select(statistics_summary::select())
 						.exec()
 						.await?)
 				})
 		})
+		.procedure("statisticsBreakdown", {
+			R.with2(library()).query(
+				|(_, library), id: Option<statistics::id::Type>| async move {
+					// Defaults to the latest snapshot
+					let breakdown = library
+						.db
+						.statistics()
+						.find_first(id.map(statistics::id::equals).into_iter().collect())
+						.order_by(statistics::date_captured::order(SortOrder::Desc))
+						.select(statistics::select!({ breakdown }))
+						.exec()
+						.await?
+						.and_then(|statistics| statistics.breakdown);
+
+					breakdown
+						.map(|breakdown| serde_json::from_slice::<StatisticsBreakdown>(&breakdown))
+						.transpose()
+						.map_err(|e| {
+							rspc::Error::with_cause(
+								ErrorCode::InternalServerError,
+								"Failed to deserialize statistics breakdown".to_string(),
+								e,
+							)
+						})
+				},
+			)
+		})
 		.procedure("create", {
 			#[derive(Deserialize, Type)]
 			pub struct CreateLibraryArgs {
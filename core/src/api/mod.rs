@@ -1,4 +1,11 @@
-use crate::{invalidate_query, job::JobProgressEvent, node::config::NodeConfig, Node};
+use crate::{
+	invalidate_query,
+	job::JobProgressEvent,
+	node::config::NodeConfig,
+	object::fs::{conflict::FileConflictEvent, trash::TRASH_AVAILABLE},
+	volume::VolumeEvent,
+	Node,
+};
 use itertools::Itertools;
 use rspc::{alpha::Rspc, Config, ErrorCode};
 use serde::{Deserialize, Serialize};
@@ -20,6 +27,8 @@ pub enum CoreEvent {
 	NewThumbnail { thumb_key: Vec<String> },
 	JobProgress(JobProgressEvent),
 	InvalidateOperation(InvalidateOperationEvent),
+	Volume(VolumeEvent),
+	FileConflict(FileConflictEvent),
 }
 
 /// All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
@@ -47,12 +56,14 @@ impl BackendFeature {
 	}
 }
 
+mod albums;
 mod auth;
 mod backups;
 mod categories;
 mod files;
 mod jobs;
 mod keys;
+mod labels;
 mod libraries;
 pub mod locations;
 mod nodes;
@@ -60,6 +71,7 @@ pub mod notifications;
 mod p2p;
 mod preferences;
 pub(crate) mod search;
+mod spaces;
 mod sync;
 mod tags;
 pub mod utils;
@@ -98,6 +110,8 @@ struct NodeState {
 	#[serde(flatten)]
 	config: SanitisedNodeConfig,
 	data_path: String,
+	/// Whether deleted files can be moved to the trash, they're always deleted permanently otherwise
+	trash_available: bool,
 }
 
 pub(crate) fn mount() -> Arc<Router> {
@@ -126,6 +140,7 @@ pub(crate) fn mount() -> Arc<Router> {
 						.to_str()
 						.expect("Found non-UTF-8 path")
 						.to_string(),
+					trash_available: TRASH_AVAILABLE,
 				})
 			})
 		})
@@ -171,8 +186,11 @@ pub(crate) fn mount() -> Arc<Router> {
 		.merge("library.", libraries::mount())
 		.merge("volumes.", volumes::mount())
 		.merge("tags.", tags::mount())
+		.merge("albums.", albums::mount())
+		.merge("labels.", labels::mount())
+		.merge("spaces.", spaces::mount())
 		.merge("categories.", categories::mount())
-		// .merge("keys.", keys::mount())
+		.merge("keys.", keys::mount())
 		.merge("locations.", locations::mount())
 		.merge("files.", files::mount())
 		.merge("jobs.", jobs::mount())
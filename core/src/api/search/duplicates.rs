@@ -0,0 +1,202 @@
+use crate::{
+	api::locations::{file_path_with_object, ExplorerItem},
+	library::Library,
+	location::LocationError,
+	object::media::thumbnail::get_thumb_key,
+	prisma::{file_path, object},
+	Node,
+};
+
+use std::collections::BTreeMap;
+
+use prisma_client_rust::raw;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// Copies of the same file, found in one or more locations
+#[derive(Serialize, Type, Debug)]
+pub struct DuplicateGroup {
+	pub object_id: object::id::Type,
+	/// Whether every copy has a full checksum and they all match
+	pub verified: bool,
+	/// Bytes that would be freed by keeping a single copy
+	pub wasted_bytes: String,
+	pub items: Vec<ExplorerItem>,
+}
+
+#[derive(Serialize, Type, Debug)]
+pub struct DuplicatesData {
+	pub groups: Vec<DuplicateGroup>,
+	pub total_wasted_bytes: String,
+}
+
+/// Splits the paths of an object by their full checksum.
+///
+/// Objects are keyed by a sampled `cas_id`, so paths whose checksums differ aren't really copies of each other.
+/// Paths without a checksum are only grouped with the others when there is no more than one checksum among them.
+fn split_by_checksum<T>(
+	items: Vec<T>,
+	checksum: impl Fn(&T) -> Option<&str>,
+) -> Vec<(bool, Vec<T>)> {
+	let mut by_checksum = BTreeMap::<String, Vec<T>>::new();
+	let mut unknown = vec![];
+
+	for item in items {
+		match checksum(&item).map(str::to_string) {
+			Some(checksum) => by_checksum.entry(checksum).or_default().push(item),
+			None => unknown.push(item),
+		}
+	}
+
+	if by_checksum.len() <= 1 {
+		let verified = unknown.is_empty() && !by_checksum.is_empty();
+		let mut items = by_checksum.into_values().next().unwrap_or_default();
+		items.extend(unknown);
+
+		return vec![(verified, items)];
+	}
+
+	by_checksum
+		.into_values()
+		.map(|items| (true, items))
+		.chain([(false, unknown)])
+		.filter(|(_, items)| items.len() > 1)
+		.collect()
+}
+
+fn size_in_bytes(file_path: &file_path_with_object::Data) -> u64 {
+	file_path
+		.size_in_bytes_bytes
+		.as_deref()
+		.and_then(|bytes| bytes.try_into().ok())
+		.map(u64::from_be_bytes)
+		.unwrap_or(0)
+}
+
+/// Finds files that are indexed more than once, across every location of the library
+pub async fn search_duplicates(
+	node: &Node,
+	library: &Library,
+) -> Result<DuplicatesData, rspc::Error> {
+	let Library { db, .. } = library;
+
+	#[derive(Deserialize)]
+	struct ObjectId {
+		object_id: object::id::Type,
+	}
+
+	let object_ids = db
+		._query_raw::<ObjectId>(raw!(
+			"SELECT object_id FROM file_path
+			WHERE object_id IS NOT NULL AND is_dir = 0
+			GROUP BY object_id
+			HAVING COUNT(*) > 1"
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object| object.object_id)
+		.collect::<Vec<_>>();
+
+	let mut by_object = BTreeMap::<_, Vec<_>>::new();
+	for file_path in db
+		.file_path()
+		.find_many(vec![
+			file_path::object_id::in_vec(object_ids),
+			file_path::is_dir::equals(Some(false)),
+		])
+		.include(file_path_with_object::include())
+		.exec()
+		.await?
+	{
+		if let Some(object_id) = file_path.object_id {
+			by_object.entry(object_id).or_default().push(file_path);
+		}
+	}
+
+	let mut groups = vec![];
+	let mut total_wasted_bytes = 0u64;
+
+	for (object_id, file_paths) in by_object {
+		for (verified, file_paths) in split_by_checksum(file_paths, |file_path| {
+			file_path.integrity_checksum.as_deref()
+		}) {
+			if file_paths.len() < 2 {
+				continue;
+			}
+
+			let wasted_bytes =
+				size_in_bytes(&file_paths[0]).saturating_mul(file_paths.len() as u64 - 1);
+			total_wasted_bytes = total_wasted_bytes.saturating_add(wasted_bytes);
+
+			let mut items = Vec::with_capacity(file_paths.len());
+			for file_path in file_paths {
+				let has_local_thumbnail = if let Some(cas_id) = &file_path.cas_id {
+					library
+						.thumbnail_exists(node, cas_id)
+						.await
+						.map_err(LocationError::from)?
+				} else {
+					false
+				};
+
+				items.push(ExplorerItem::Path {
+					has_local_thumbnail,
+					thumbnail_key: file_path.cas_id.as_ref().map(|i| get_thumb_key(i)),
+					item: file_path,
+				});
+			}
+
+			groups.push((
+				wasted_bytes,
+				DuplicateGroup {
+					object_id,
+					verified,
+					wasted_bytes: wasted_bytes.to_string(),
+					items,
+				},
+			));
+		}
+	}
+
+	// The groups wasting the most space come first
+	groups.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+	Ok(DuplicatesData {
+		groups: groups.into_iter().map(|(_, group)| group).collect(),
+		total_wasted_bytes: total_wasted_bytes.to_string(),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn split(items: &[(u8, Option<&'static str>)]) -> Vec<(bool, Vec<u8>)> {
+		split_by_checksum(items.to_vec(), |(_, checksum)| *checksum)
+			.into_iter()
+			.map(|(verified, items)| (verified, items.into_iter().map(|(id, _)| id).collect()))
+			.collect()
+	}
+
+	#[test]
+	fn test_split_by_checksum() {
+		assert_eq!(
+			split(&[(1, Some("a")), (2, Some("a"))]),
+			vec![(true, vec![1, 2])]
+		);
+		assert_eq!(
+			split(&[(1, Some("a")), (2, None)]),
+			vec![(false, vec![1, 2])]
+		);
+		assert_eq!(split(&[(1, None), (2, None)]), vec![(false, vec![1, 2])]);
+		assert_eq!(
+			split(&[(1, Some("a")), (2, Some("b")), (3, Some("a")), (4, None)]),
+			vec![(true, vec![1, 3])]
+		);
+		assert_eq!(
+			split(&[(1, Some("a")), (2, Some("b")), (3, None), (4, None)]),
+			vec![(false, vec![3, 4])]
+		);
+	}
+}
@@ -0,0 +1,929 @@
+//! A small query language for `search.paths` and `search.objects`.
+//!
+//! Terms are ANDed together when separated by whitespace, `OR` has lower precedence than
+//! the implicit `AND` and parentheses can be used for grouping. A term can be negated with
+//! a leading `-` or with `NOT`.
+//!
+//! ```text
+//! ext:rs size:>10MB tag:work -hidden modified:<2023-01-01 "exact phrase" (a OR b)
+//! duration:>10m res:>=4k
+//! ```
+//!
+//! Bare words and quoted phrases match against the file name, `hidden`, `favorite` and `dir`
+//! are flags (quote them to search for them in names) and everything else must be written
+//! as `field:value`, where comparable fields accept a `<`, `<=`, `>`, `>=` or `=` prefix.
+
+use crate::prisma::{file_path, media_data, object, tag, tag_on_object};
+
+use std::{fmt, iter::Peekable, str::CharIndices};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, TimeZone, Utc};
+use prisma_client_rust::{operator, or};
+use rspc::ErrorCode;
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum QueryParseError {
+	#[error("unexpected end of search query")]
+	UnexpectedEnd,
+	#[error("unexpected '{token}' at position {position} in search query")]
+	UnexpectedToken { token: String, position: usize },
+	#[error("unclosed quote starting at position {0} in search query")]
+	UnclosedQuote(usize),
+	#[error("unclosed parenthesis starting at position {0} in search query")]
+	UnclosedParenthesis(usize),
+	#[error("unknown search field '{0}'")]
+	UnknownField(String),
+	#[error("invalid value '{value}' for search field '{field}'")]
+	InvalidValue { field: String, value: String },
+}
+
+impl From<QueryParseError> for rspc::Error {
+	fn from(e: QueryParseError) -> Self {
+		rspc::Error::with_cause(ErrorCode::BadRequest, e.to_string(), e)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Comparison {
+	Lt,
+	Lte,
+	Gt,
+	Gte,
+	Eq,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Flag {
+	Hidden,
+	Favorite,
+	Dir,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateField {
+	Created,
+	Modified,
+	Indexed,
+	Accessed,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchTerm {
+	Name(String),
+	Extension(String),
+	Size(Comparison, u64),
+	Tag(String),
+	Flag(Flag),
+	Date(DateField, Comparison, NaiveDate),
+	/// In seconds, for videos
+	Duration(Comparison, u32),
+	/// Height in pixels, for videos
+	Resolution(Comparison, u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SearchQuery {
+	And(Vec<SearchQuery>),
+	Or(Vec<SearchQuery>),
+	Not(Box<SearchQuery>),
+	Term(SearchTerm),
+}
+
+impl SearchQuery {
+	pub fn parse(query: &str) -> Result<Self, QueryParseError> {
+		let mut parser = Parser {
+			tokens: tokenize(query)?.into_iter().peekable(),
+		};
+
+		let query = parser.parse_or()?;
+
+		match parser.tokens.next() {
+			None => Ok(query),
+			Some((token, position)) => Err(QueryParseError::UnexpectedToken {
+				token: token.to_string(),
+				position,
+			}),
+		}
+	}
+
+	pub fn to_file_path_param(&self) -> file_path::WhereParam {
+		use file_path::*;
+
+		match self {
+			Self::And(queries) => operator::and(
+				queries
+					.iter()
+					.map(|query| query.to_file_path_param())
+					.collect(),
+			),
+			Self::Or(queries) => operator::or(
+				queries
+					.iter()
+					.map(|query| query.to_file_path_param())
+					.collect(),
+			),
+			// Flags are nullable, so a plain NOT would also drop rows where they were never set
+			Self::Not(query) => match query.as_ref() {
+				Self::Term(SearchTerm::Flag(Flag::Hidden)) => {
+					or![hidden::equals(None), hidden::not(Some(true))]
+				}
+				Self::Term(SearchTerm::Flag(Flag::Dir)) => {
+					or![is_dir::equals(None), is_dir::not(Some(true))]
+				}
+				query => operator::not(vec![query.to_file_path_param()]),
+			},
+			Self::Term(term) => match term {
+				// These describe the object of a path
+				SearchTerm::Flag(Flag::Favorite)
+				| SearchTerm::Tag(_)
+				| SearchTerm::Date(DateField::Accessed, ..)
+				| SearchTerm::Duration(..)
+				| SearchTerm::Resolution(..) => file_path::object::is(vec![self.to_object_param()]),
+				SearchTerm::Name(value) => name::contains(value.clone()),
+				SearchTerm::Extension(value) => extension::equals(Some(value.clone())),
+				SearchTerm::Size(comparison, size) => size_param(*comparison, *size),
+				SearchTerm::Flag(Flag::Hidden) => hidden::equals(Some(true)),
+				SearchTerm::Flag(Flag::Dir) => is_dir::equals(Some(true)),
+				SearchTerm::Date(DateField::Created, comparison, date) => {
+					date_param(*comparison, *date, date_created::gte, date_created::lt)
+				}
+				SearchTerm::Date(DateField::Modified, comparison, date) => {
+					date_param(*comparison, *date, date_modified::gte, date_modified::lt)
+				}
+				SearchTerm::Date(DateField::Indexed, comparison, date) => {
+					date_param(*comparison, *date, date_indexed::gte, date_indexed::lt)
+				}
+			},
+		}
+	}
+
+	pub fn to_object_param(&self) -> object::WhereParam {
+		use object::*;
+
+		match self {
+			Self::And(queries) => operator::and(
+				queries
+					.iter()
+					.map(|query| query.to_object_param())
+					.collect(),
+			),
+			Self::Or(queries) => operator::or(
+				queries
+					.iter()
+					.map(|query| query.to_object_param())
+					.collect(),
+			),
+			Self::Not(query) => match query.as_ref() {
+				Self::Term(SearchTerm::Flag(Flag::Hidden)) => {
+					or![hidden::equals(None), hidden::not(Some(true))]
+				}
+				Self::Term(SearchTerm::Flag(Flag::Favorite)) => {
+					or![favorite::equals(None), favorite::not(Some(true))]
+				}
+				query => operator::not(vec![query.to_object_param()]),
+			},
+			Self::Term(term) => match term {
+				SearchTerm::Tag(name) => {
+					tags::some(vec![tag_on_object::tag::is(vec![tag::name::equals(Some(
+						name.clone(),
+					))])])
+				}
+				SearchTerm::Flag(Flag::Hidden) => hidden::equals(Some(true)),
+				SearchTerm::Flag(Flag::Favorite) => favorite::equals(Some(true)),
+				SearchTerm::Date(DateField::Created, comparison, date) => {
+					date_param(*comparison, *date, date_created::gte, date_created::lt)
+				}
+				SearchTerm::Date(DateField::Accessed, comparison, date) => {
+					date_param(*comparison, *date, date_accessed::gte, date_accessed::lt)
+				}
+				SearchTerm::Duration(comparison, seconds) => {
+					object::media_data::is(vec![duration_param(*comparison, *seconds)])
+				}
+				SearchTerm::Resolution(comparison, height) => {
+					object::media_data::is(vec![resolution_param(*comparison, *height)])
+				}
+				// Everything else describes the paths of an object
+				_ => file_paths::some(vec![self.to_file_path_param()]),
+			},
+		}
+	}
+}
+
+fn date_param<T: From<prisma_client_rust::Operator<T>>>(
+	comparison: Comparison,
+	date: NaiveDate,
+	gte: fn(DateTime<FixedOffset>) -> T,
+	lt: fn(DateTime<FixedOffset>) -> T,
+) -> T {
+	let start_of_day = |date: NaiveDate| -> DateTime<FixedOffset> {
+		Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).expect("midnight is always valid"))
+			.into()
+	};
+
+	range_param(
+		comparison,
+		start_of_day(date),
+		start_of_day(date.succ_opt().unwrap_or(NaiveDate::MAX)),
+		gte,
+		lt,
+	)
+}
+
+/// Sizes are compared through `size_in_bytes_int`, which SQLite decodes from the big endian
+/// `size_in_bytes_bytes` as Prisma can't compare `Bytes` columns
+fn size_param(comparison: Comparison, size: u64) -> file_path::WhereParam {
+	use file_path::size_in_bytes_int;
+
+	// Nothing is large enough to not fit, the column leaves such sizes out anyway
+	let size = i64::try_from(size).unwrap_or(i64::MAX);
+
+	match comparison {
+		Comparison::Lt => size_in_bytes_int::lt(size),
+		Comparison::Lte => size_in_bytes_int::lte(size),
+		Comparison::Gt => size_in_bytes_int::gt(size),
+		Comparison::Gte => size_in_bytes_int::gte(size),
+		Comparison::Eq => size_in_bytes_int::equals(Some(size)),
+	}
+}
+
+fn duration_param(comparison: Comparison, seconds: u32) -> media_data::WhereParam {
+	// Durations are stored in milliseconds, so a second spans a range of them
+	let start = i32::try_from(seconds.saturating_mul(1000)).unwrap_or(i32::MAX);
+
+	range_param(
+		comparison,
+		start,
+		start.saturating_add(1000),
+		media_data::duration::gte,
+		media_data::duration::lt,
+	)
+}
+
+fn resolution_param(comparison: Comparison, height: u32) -> media_data::WhereParam {
+	let height = i32::try_from(height).unwrap_or(i32::MAX);
+
+	range_param(
+		comparison,
+		height,
+		height.saturating_add(1),
+		media_data::height::gte,
+		media_data::height::lt,
+	)
+}
+
+/// Compares against the `[start, end)` range covered by a value, like a whole day for a date
+fn range_param<V, T: From<prisma_client_rust::Operator<T>>>(
+	comparison: Comparison,
+	start: V,
+	end: V,
+	gte: fn(V) -> T,
+	lt: fn(V) -> T,
+) -> T {
+	match comparison {
+		Comparison::Lt => lt(start),
+		Comparison::Lte => lt(end),
+		Comparison::Gt => gte(end),
+		Comparison::Gte => gte(start),
+		Comparison::Eq => operator::and(vec![gte(start), lt(end)]),
+	}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+	LParen,
+	RParen,
+	Minus,
+	Word(String),
+	Phrase(String),
+	Field(String, String),
+}
+
+impl fmt::Display for Token {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::LParen => write!(f, "("),
+			Self::RParen => write!(f, ")"),
+			Self::Minus => write!(f, "-"),
+			Self::Word(word) => write!(f, "{word}"),
+			Self::Phrase(phrase) => write!(f, "\"{phrase}\""),
+			Self::Field(field, value) => write!(f, "{field}:{value}"),
+		}
+	}
+}
+
+fn read_quoted(
+	chars: &mut Peekable<CharIndices<'_>>,
+	start: usize,
+) -> Result<String, QueryParseError> {
+	let mut value = String::new();
+
+	loop {
+		match chars.next() {
+			Some((_, '"')) => return Ok(value),
+			Some((_, '\\')) => match chars.next() {
+				Some((_, c)) => value.push(c),
+				None => return Err(QueryParseError::UnclosedQuote(start)),
+			},
+			Some((_, c)) => value.push(c),
+			None => return Err(QueryParseError::UnclosedQuote(start)),
+		}
+	}
+}
+
+fn tokenize(query: &str) -> Result<Vec<(Token, usize)>, QueryParseError> {
+	let mut tokens = vec![];
+	let mut chars = query.char_indices().peekable();
+
+	while let Some(&(position, c)) = chars.peek() {
+		match c {
+			c if c.is_whitespace() => {
+				chars.next();
+			}
+			'(' => {
+				chars.next();
+				tokens.push((Token::LParen, position));
+			}
+			')' => {
+				chars.next();
+				tokens.push((Token::RParen, position));
+			}
+			'"' => {
+				chars.next();
+				tokens.push((Token::Phrase(read_quoted(&mut chars, position)?), position));
+			}
+			'-' => {
+				chars.next();
+				match chars.peek() {
+					Some(&(_, next)) if !next.is_whitespace() && next != ')' => {
+						tokens.push((Token::Minus, position));
+					}
+					_ => tokens.push((Token::Word("-".to_string()), position)),
+				}
+			}
+			_ => {
+				let mut word = String::new();
+				let mut field_value = None;
+
+				while let Some(&(_, c)) = chars.peek() {
+					if c.is_whitespace() || c == '(' || c == ')' {
+						break;
+					}
+
+					chars.next();
+
+					if c == ':' && field_value.is_none() && !word.is_empty() {
+						if let Some(&(quote_position, '"')) = chars.peek() {
+							chars.next();
+							field_value = Some(read_quoted(&mut chars, quote_position)?);
+							break;
+						}
+
+						field_value = Some(String::new());
+						continue;
+					}
+
+					match &mut field_value {
+						Some(value) => value.push(c),
+						None => word.push(c),
+					}
+				}
+
+				tokens.push((
+					match field_value {
+						Some(value) => Token::Field(word, value),
+						None => Token::Word(word),
+					},
+					position,
+				));
+			}
+		}
+	}
+
+	Ok(tokens)
+}
+
+struct Parser {
+	tokens: Peekable<std::vec::IntoIter<(Token, usize)>>,
+}
+
+impl Parser {
+	fn parse_or(&mut self) -> Result<SearchQuery, QueryParseError> {
+		let mut queries = vec![self.parse_and()?];
+
+		while matches!(self.tokens.peek(), Some((Token::Word(word), _)) if word == "OR") {
+			self.tokens.next();
+			queries.push(self.parse_and()?);
+		}
+
+		Ok(if queries.len() == 1 {
+			queries.remove(0)
+		} else {
+			SearchQuery::Or(queries)
+		})
+	}
+
+	fn parse_and(&mut self) -> Result<SearchQuery, QueryParseError> {
+		let mut queries = vec![];
+
+		loop {
+			match self.tokens.peek() {
+				None | Some((Token::RParen, _)) => break,
+				Some((Token::Word(word), _)) if word == "OR" => break,
+				Some((Token::Word(word), _)) if word == "AND" && !queries.is_empty() => {
+					self.tokens.next();
+					queries.push(self.parse_unary()?);
+				}
+				_ => queries.push(self.parse_unary()?),
+			}
+		}
+
+		match queries.len() {
+			0 => Err(match self.tokens.peek() {
+				Some((token, position)) => QueryParseError::UnexpectedToken {
+					token: token.to_string(),
+					position: *position,
+				},
+				None => QueryParseError::UnexpectedEnd,
+			}),
+			1 => Ok(queries.remove(0)),
+			_ => Ok(SearchQuery::And(queries)),
+		}
+	}
+
+	fn parse_unary(&mut self) -> Result<SearchQuery, QueryParseError> {
+		match self.tokens.peek() {
+			Some((Token::Minus, _)) => {
+				self.tokens.next();
+				Ok(SearchQuery::Not(Box::new(self.parse_unary()?)))
+			}
+			Some((Token::Word(word), _)) if word == "NOT" => {
+				self.tokens.next();
+				Ok(SearchQuery::Not(Box::new(self.parse_unary()?)))
+			}
+			_ => self.parse_primary(),
+		}
+	}
+
+	fn parse_primary(&mut self) -> Result<SearchQuery, QueryParseError> {
+		let Some((token, position)) = self.tokens.next() else {
+			return Err(QueryParseError::UnexpectedEnd);
+		};
+
+		match token {
+			Token::LParen => {
+				let query = self.parse_or()?;
+
+				match self.tokens.next() {
+					Some((Token::RParen, _)) => Ok(query),
+					_ => Err(QueryParseError::UnclosedParenthesis(position)),
+				}
+			}
+			Token::Phrase(phrase) => Ok(SearchQuery::Term(SearchTerm::Name(phrase))),
+			Token::Word(word) => Ok(SearchQuery::Term(
+				parse_flag(&word).map_or(SearchTerm::Name(word), SearchTerm::Flag),
+			)),
+			Token::Field(field, value) => parse_field(field, value).map(SearchQuery::Term),
+			token @ (Token::RParen | Token::Minus) => Err(QueryParseError::UnexpectedToken {
+				token: token.to_string(),
+				position,
+			}),
+		}
+	}
+}
+
+fn parse_flag(word: &str) -> Option<Flag> {
+	match word {
+		"hidden" => Some(Flag::Hidden),
+		"favorite" => Some(Flag::Favorite),
+		"dir" => Some(Flag::Dir),
+		_ => None,
+	}
+}
+
+fn split_comparison(value: &str) -> (Comparison, &str) {
+	if let Some(value) = value.strip_prefix("<=") {
+		(Comparison::Lte, value)
+	} else if let Some(value) = value.strip_prefix(">=") {
+		(Comparison::Gte, value)
+	} else if let Some(value) = value.strip_prefix('<') {
+		(Comparison::Lt, value)
+	} else if let Some(value) = value.strip_prefix('>') {
+		(Comparison::Gt, value)
+	} else {
+		(Comparison::Eq, value.strip_prefix('=').unwrap_or(value))
+	}
+}
+
+fn parse_size(value: &str) -> Option<u64> {
+	let unit_start = value
+		.find(|c: char| !(c.is_ascii_digit() || c == '.'))
+		.unwrap_or(value.len());
+
+	let (number, unit) = value.split_at(unit_start);
+
+	let multiplier = match unit.to_ascii_lowercase().as_str() {
+		"" | "b" => 1,
+		"kb" => 1000,
+		"mb" => 1000u64.pow(2),
+		"gb" => 1000u64.pow(3),
+		"tb" => 1000u64.pow(4),
+		"kib" => 1024,
+		"mib" => 1024u64.pow(2),
+		"gib" => 1024u64.pow(3),
+		"tib" => 1024u64.pow(4),
+		_ => return None,
+	};
+
+	if let Ok(number) = number.parse::<u64>() {
+		number.checked_mul(multiplier)
+	} else {
+		let number = number.parse::<f64>().ok()?;
+		(number.is_finite() && number >= 0.0).then(|| (number * multiplier as f64).round() as u64)
+	}
+}
+
+/// Seconds, either bare or with `h`, `m` and `s` units, like `90`, `10m` or `1h30m`
+fn parse_duration(value: &str) -> Option<u32> {
+	if let Ok(seconds) = value.parse() {
+		return Some(seconds);
+	}
+
+	let mut seconds = 0u32;
+	let mut number_start = 0;
+	for (i, c) in value.char_indices() {
+		if c.is_ascii_digit() {
+			continue;
+		}
+
+		let multiplier = match c.to_ascii_lowercase() {
+			'h' => 60 * 60,
+			'm' => 60,
+			's' => 1,
+			_ => return None,
+		};
+
+		let number = value[number_start..i].parse::<u32>().ok()?;
+		seconds = seconds.checked_add(number.checked_mul(multiplier)?)?;
+		number_start = i + 1;
+	}
+
+	(!value.is_empty() && number_start == value.len()).then_some(seconds)
+}
+
+/// The height of a video, like `1080`, `720p` or `4k`
+fn parse_resolution(value: &str) -> Option<u32> {
+	match value.to_ascii_lowercase().as_str() {
+		"4k" => Some(2160),
+		"8k" => Some(4320),
+		value => value.strip_suffix('p').unwrap_or(value).parse().ok(),
+	}
+}
+
+fn parse_field(field: String, value: String) -> Result<SearchTerm, QueryParseError> {
+	let invalid_value = || QueryParseError::InvalidValue {
+		field: field.clone(),
+		value: value.clone(),
+	};
+
+	let date_field = match field.as_str() {
+		"created" => Some(DateField::Created),
+		"modified" => Some(DateField::Modified),
+		"indexed" => Some(DateField::Indexed),
+		"accessed" => Some(DateField::Accessed),
+		_ => None,
+	};
+
+	if let Some(date_field) = date_field {
+		let (comparison, date) = split_comparison(&value);
+
+		return NaiveDate::parse_from_str(date, "%Y-%m-%d")
+			.map(|date| SearchTerm::Date(date_field, comparison, date))
+			.map_err(|_| invalid_value());
+	}
+
+	if value.is_empty() {
+		return Err(invalid_value());
+	}
+
+	match field.as_str() {
+		"name" => Ok(SearchTerm::Name(value)),
+		"ext" | "extension" => Ok(SearchTerm::Extension(
+			value.trim_start_matches('.').to_string(),
+		)),
+		"tag" => Ok(SearchTerm::Tag(value)),
+		"is" => parse_flag(&value)
+			.map(SearchTerm::Flag)
+			.ok_or_else(invalid_value),
+		"size" => {
+			let (comparison, size) = split_comparison(&value);
+
+			parse_size(size)
+				.map(|size| SearchTerm::Size(comparison, size))
+				.ok_or_else(invalid_value)
+		}
+		"duration" => {
+			let (comparison, duration) = split_comparison(&value);
+
+			parse_duration(duration)
+				.map(|seconds| SearchTerm::Duration(comparison, seconds))
+				.ok_or_else(invalid_value)
+		}
+		"res" | "resolution" => {
+			let (comparison, resolution) = split_comparison(&value);
+
+			parse_resolution(resolution)
+				.map(|height| SearchTerm::Resolution(comparison, height))
+				.ok_or_else(invalid_value)
+		}
+		_ => Err(QueryParseError::UnknownField(field)),
+	}
+}
+
+impl fmt::Display for Comparison {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Lt => "<",
+			Self::Lte => "<=",
+			Self::Gt => ">",
+			Self::Gte => ">=",
+			Self::Eq => "",
+		})
+	}
+}
+
+impl fmt::Display for Flag {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Hidden => "hidden",
+			Self::Favorite => "favorite",
+			Self::Dir => "dir",
+		})
+	}
+}
+
+impl fmt::Display for DateField {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(match self {
+			Self::Created => "created",
+			Self::Modified => "modified",
+			Self::Indexed => "indexed",
+			Self::Accessed => "accessed",
+		})
+	}
+}
+
+/// Writes a value bare when it would be read back as the same word, quoted otherwise
+fn write_value(f: &mut fmt::Formatter<'_>, value: &str, is_field_value: bool) -> fmt::Result {
+	let needs_quotes = value.is_empty()
+		|| value.starts_with('-')
+		|| value
+			.chars()
+			.any(|c| c.is_whitespace() || matches!(c, '"' | '(' | ')' | '\\' | ':'))
+		|| (!is_field_value
+			&& (matches!(value, "OR" | "AND" | "NOT") || parse_flag(value).is_some()));
+
+	if needs_quotes {
+		write!(
+			f,
+			"\"{}\"",
+			value.replace('\\', "\\\\").replace('"', "\\\"")
+		)
+	} else {
+		f.write_str(value)
+	}
+}
+
+impl fmt::Display for SearchTerm {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Name(name) => write_value(f, name, false),
+			Self::Extension(extension) => {
+				f.write_str("ext:")?;
+				write_value(f, extension, true)
+			}
+			Self::Size(comparison, size) => write!(f, "size:{comparison}{size}"),
+			Self::Tag(tag) => {
+				f.write_str("tag:")?;
+				write_value(f, tag, true)
+			}
+			Self::Flag(flag) => write!(f, "{flag}"),
+			Self::Date(field, comparison, date) => {
+				write!(f, "{field}:{comparison}{}", date.format("%Y-%m-%d"))
+			}
+			Self::Duration(comparison, seconds) => write!(f, "duration:{comparison}{seconds}"),
+			Self::Resolution(comparison, height) => write!(f, "res:{comparison}{height}"),
+		}
+	}
+}
+
+impl fmt::Display for SearchQuery {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let write_all = |f: &mut fmt::Formatter<'_>, queries: &[SearchQuery], separator| {
+			for (i, query) in queries.iter().enumerate() {
+				if i > 0 {
+					f.write_str(separator)?;
+				}
+
+				// AND binds tighter than OR, so only nested groups of the same kind need parentheses
+				match (self, query) {
+					(Self::And(_), Self::And(_) | Self::Or(_)) | (Self::Or(_), Self::Or(_)) => {
+						write!(f, "({query})")?
+					}
+					_ => write!(f, "{query}")?,
+				}
+			}
+
+			Ok(())
+		};
+
+		match self {
+			Self::And(queries) => write_all(f, queries, " "),
+			Self::Or(queries) => write_all(f, queries, " OR "),
+			Self::Not(query) => match query.as_ref() {
+				Self::And(_) | Self::Or(_) => write!(f, "-({query})"),
+				_ => write!(f, "-{query}"),
+			},
+			Self::Term(term) => write!(f, "{term}"),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn name(name: &str) -> SearchQuery {
+		SearchQuery::Term(SearchTerm::Name(name.to_string()))
+	}
+
+	#[test]
+	fn parse_full_example() {
+		let query = SearchQuery::parse(
+			r#"ext:rs size:>10MB tag:work -hidden modified:<2023-01-01 "exact phrase" (a OR b)"#,
+		)
+		.unwrap();
+
+		assert_eq!(
+			query,
+			SearchQuery::And(vec![
+				SearchQuery::Term(SearchTerm::Extension("rs".to_string())),
+				SearchQuery::Term(SearchTerm::Size(Comparison::Gt, 10_000_000)),
+				SearchQuery::Term(SearchTerm::Tag("work".to_string())),
+				SearchQuery::Not(Box::new(SearchQuery::Term(SearchTerm::Flag(Flag::Hidden)))),
+				SearchQuery::Term(SearchTerm::Date(
+					DateField::Modified,
+					Comparison::Lt,
+					NaiveDate::from_ymd_opt(2023, 1, 1).unwrap()
+				)),
+				name("exact phrase"),
+				SearchQuery::Or(vec![name("a"), name("b")]),
+			])
+		);
+	}
+
+	#[test]
+	fn or_has_lower_precedence_than_and() {
+		assert_eq!(
+			SearchQuery::parse("a b OR c").unwrap(),
+			SearchQuery::Or(vec![
+				SearchQuery::And(vec![name("a"), name("b")]),
+				name("c")
+			])
+		);
+		assert_eq!(
+			SearchQuery::parse("a AND b").unwrap(),
+			SearchQuery::And(vec![name("a"), name("b")])
+		);
+		assert_eq!(
+			SearchQuery::parse("NOT (a OR b)").unwrap(),
+			SearchQuery::Not(Box::new(SearchQuery::Or(vec![name("a"), name("b")])))
+		);
+	}
+
+	#[test]
+	fn flags() {
+		assert_eq!(
+			SearchQuery::parse("is:hidden dir").unwrap(),
+			SearchQuery::And(vec![
+				SearchQuery::Term(SearchTerm::Flag(Flag::Hidden)),
+				SearchQuery::Term(SearchTerm::Flag(Flag::Dir)),
+			])
+		);
+		assert_eq!(SearchQuery::parse("\"dir\"").unwrap(), name("dir"));
+	}
+
+	#[test]
+	fn sizes_and_units() {
+		assert_eq!(parse_size("10"), Some(10));
+		assert_eq!(parse_size("1.5KB"), Some(1500));
+		assert_eq!(parse_size("2mib"), Some(2 * 1024 * 1024));
+		assert_eq!(parse_size("10XB"), None);
+		assert_eq!(parse_size("MB"), None);
+	}
+
+	#[test]
+	fn durations_and_resolutions() {
+		assert_eq!(parse_duration("90"), Some(90));
+		assert_eq!(parse_duration("10m"), Some(600));
+		assert_eq!(parse_duration("1h30m"), Some(5400));
+		assert_eq!(parse_duration("1h30"), None);
+		assert_eq!(parse_duration("m"), None);
+		assert_eq!(parse_duration(""), None);
+		assert_eq!(parse_resolution("4K"), Some(2160));
+		assert_eq!(parse_resolution("720p"), Some(720));
+		assert_eq!(parse_resolution("hd"), None);
+
+		assert_eq!(
+			SearchQuery::parse("duration:>10m res:>=4k").unwrap(),
+			SearchQuery::And(vec![
+				SearchQuery::Term(SearchTerm::Duration(Comparison::Gt, 600)),
+				SearchQuery::Term(SearchTerm::Resolution(Comparison::Gte, 2160)),
+			])
+		);
+	}
+
+	#[test]
+	fn quoted_field_values() {
+		assert_eq!(
+			SearchQuery::parse(r#"tag:"my work" name:"a \"b\"""#).unwrap(),
+			SearchQuery::And(vec![
+				SearchQuery::Term(SearchTerm::Tag("my work".to_string())),
+				name(r#"a "b""#),
+			])
+		);
+	}
+
+	#[test]
+	fn parse_errors() {
+		assert_eq!(SearchQuery::parse(""), Err(QueryParseError::UnexpectedEnd));
+		assert_eq!(
+			SearchQuery::parse("\"open"),
+			Err(QueryParseError::UnclosedQuote(0))
+		);
+		assert_eq!(
+			SearchQuery::parse("a (b"),
+			Err(QueryParseError::UnclosedParenthesis(2))
+		);
+		assert_eq!(
+			SearchQuery::parse("a )"),
+			Err(QueryParseError::UnexpectedToken {
+				token: ")".to_string(),
+				position: 2
+			})
+		);
+		assert_eq!(
+			SearchQuery::parse("color:red"),
+			Err(QueryParseError::UnknownField("color".to_string()))
+		);
+		assert_eq!(
+			SearchQuery::parse("size:>big"),
+			Err(QueryParseError::InvalidValue {
+				field: "size".to_string(),
+				value: ">big".to_string()
+			})
+		);
+		assert_eq!(
+			SearchQuery::parse("modified:yesterday"),
+			Err(QueryParseError::InvalidValue {
+				field: "modified".to_string(),
+				value: "yesterday".to_string()
+			})
+		);
+	}
+
+	#[test]
+	fn round_trip() {
+		for query in [
+			"a",
+			"a b c",
+			"a OR b",
+			"a b OR c d",
+			"(a OR b) c",
+			"(a OR b) OR c",
+			"-a",
+			"-(a b)",
+			"-(a OR b)",
+			"--a",
+			"ext:rs",
+			"ext:\"tar gz\"",
+			"size:>10485760",
+			"size:<=1",
+			"size:42",
+			"tag:work",
+			"tag:\"my work\"",
+			"hidden -favorite dir",
+			"created:2023-01-01 modified:>=2022-12-31 indexed:<2020-02-29 accessed:>2021-06-01",
+			"duration:>600 res:>=2160 duration:90",
+			"\"exact phrase\"",
+			"\"OR\" \"hidden\" \"-dash\" \"a:b\" \"quote\\\"d\"",
+			r#"ext:rs size:>10000000 tag:work -hidden modified:<2023-01-01 "exact phrase" (a OR b)"#,
+		] {
+			let parsed = SearchQuery::parse(query).unwrap();
+			let printed = parsed.to_string();
+
+			assert_eq!(printed, query, "printing {parsed:?}");
+			assert_eq!(SearchQuery::parse(&printed).unwrap(), parsed);
+		}
+	}
+}
@@ -0,0 +1,623 @@
+use crate::{
+	api::{locations::ExplorerItem, utils::library, Ctx, R},
+	invalidate_query,
+	library::Library,
+	prisma::{album, label, location, saved_search, tag, PrismaClient},
+};
+
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use std::mem;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use futures::future::try_join_all;
+use prisma_client_rust::QueryError;
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::{
+	search_objects, search_paths, FilePathFilterArgs, FilePathOrder, FilePathSearchArgs,
+	ObjectFilterArgs, ObjectOrder, ObjectSearchArgs, OrderAndPagination, MAX_TAKE,
+};
+
+const PATHS_TARGET: &str = "paths";
+const OBJECTS_TARGET: &str = "objects";
+
+/// What a saved search looks for and how its results are ordered
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+#[serde(rename_all = "camelCase", tag = "target")]
+enum SavedSearchQuery {
+	Paths {
+		filter: FilePathFilterArgs,
+		#[specta(optional)]
+		order: Option<FilePathOrder>,
+	},
+	Objects {
+		filter: ObjectFilterArgs,
+		#[specta(optional)]
+		order: Option<ObjectOrder>,
+	},
+}
+
+/// The locations, tags, albums and labels a filter refers to
+#[derive(Serialize, Deserialize, Default, Debug)]
+#[serde(rename_all = "camelCase")]
+struct FilterIds<T> {
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	location: Option<T>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	tags: Vec<T>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	albums: Vec<T>,
+	#[serde(default, skip_serializing_if = "Vec::is_empty")]
+	labels: Vec<T>,
+}
+
+/// Swaps every `$from` of `$model` in `$values` for its `$to`, `None` if one of them doesn't exist
+macro_rules! swap_ids {
+	($db:expr, $model:ident, $from:ident => $to:ident, $values:expr) => {{
+		let mut values: Vec<_> = $values;
+		values.sort();
+		values.dedup();
+
+		if values.is_empty() {
+			Some(vec![])
+		} else {
+			let found = $db
+				.$model()
+				.find_many(vec![$model::$from::in_vec(values.clone())])
+				.select($model::select!({ $to }))
+				.exec()
+				.await?;
+
+			(found.len() == values.len()).then(|| found.into_iter().map(|d| d.$to).collect())
+		}
+	}};
+}
+
+impl FilterIds<i32> {
+	async fn to_pub_ids(self, db: &PrismaClient) -> Result<Option<FilterIds<Vec<u8>>>, QueryError> {
+		let (Some(location), Some(tags), Some(albums), Some(labels)) = (
+			swap_ids!(db, location, id => pub_id, self.location.into_iter().collect()),
+			swap_ids!(db, tag, id => pub_id, self.tags),
+			swap_ids!(db, album, id => pub_id, self.albums),
+			swap_ids!(db, label, id => pub_id, self.labels),
+		) else {
+			return Ok(None);
+		};
+
+		Ok(Some(FilterIds {
+			location: location.into_iter().next(),
+			tags,
+			albums,
+			labels,
+		}))
+	}
+}
+
+impl FilterIds<Vec<u8>> {
+	async fn to_local_ids(self, db: &PrismaClient) -> Result<Option<FilterIds<i32>>, QueryError> {
+		let (Some(location), Some(tags), Some(albums), Some(labels)) = (
+			swap_ids!(db, location, pub_id => id, self.location.into_iter().collect()),
+			swap_ids!(db, tag, pub_id => id, self.tags),
+			swap_ids!(db, album, pub_id => id, self.albums),
+			swap_ids!(db, label, pub_id => id, self.labels),
+		) else {
+			return Ok(None);
+		};
+
+		Ok(Some(FilterIds {
+			location: location.into_iter().next(),
+			tags,
+			albums,
+			labels,
+		}))
+	}
+}
+
+/// A filter as stored in a [`saved_search`]. Saved searches are synced, so the ids
+/// local to this instance are taken out of the filter and stored as pub ids instead.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StoredFilter<F> {
+	filter: F,
+	#[serde(default)]
+	pub_ids: FilterIds<Vec<u8>>,
+}
+
+trait SavedFilter: Serialize + DeserializeOwned {
+	fn take_ids(&mut self) -> FilterIds<i32>;
+
+	fn put_ids(&mut self, ids: FilterIds<i32>);
+}
+
+impl SavedFilter for ObjectFilterArgs {
+	fn take_ids(&mut self) -> FilterIds<i32> {
+		FilterIds {
+			location: None,
+			tags: mem::take(&mut self.tags),
+			albums: mem::take(&mut self.albums),
+			labels: mem::take(&mut self.labels),
+		}
+	}
+
+	fn put_ids(&mut self, ids: FilterIds<i32>) {
+		self.tags = ids.tags;
+		self.albums = ids.albums;
+		self.labels = ids.labels;
+	}
+}
+
+impl SavedFilter for FilePathFilterArgs {
+	fn take_ids(&mut self) -> FilterIds<i32> {
+		let mut ids = self
+			.object
+			.as_mut()
+			.map(SavedFilter::take_ids)
+			.unwrap_or_default();
+		ids.location = self.location_id.take();
+
+		ids
+	}
+
+	fn put_ids(&mut self, mut ids: FilterIds<i32>) {
+		self.location_id = ids.location.take();
+
+		if !ids.tags.is_empty() || !ids.albums.is_empty() || !ids.labels.is_empty() {
+			self.object
+				.get_or_insert_with(Default::default)
+				.put_ids(ids);
+		}
+	}
+}
+
+async fn store_filter(
+	mut filter: impl SavedFilter,
+	db: &PrismaClient,
+) -> Result<String, rspc::Error> {
+	let pub_ids = filter.take_ids().to_pub_ids(db).await?.ok_or_else(|| {
+		rspc::Error::new(
+			ErrorCode::BadRequest,
+			"Search refers to a location, tag, album or label that doesn't exist".to_string(),
+		)
+	})?;
+
+	serde_json::to_string(&StoredFilter { filter, pub_ids }).map_err(serialize_error)
+}
+
+/// `None` if the filter is malformed or refers to something that isn't synced here yet
+async fn load_filter<F: SavedFilter>(
+	id: saved_search::id::Type,
+	filters: &str,
+	db: &PrismaClient,
+) -> Result<Option<F>, QueryError> {
+	let StoredFilter::<F> {
+		mut filter,
+		pub_ids,
+	} = match serde_json::from_str(filters) {
+		Ok(stored) => stored,
+		Err(e) => {
+			warn!("Failed to deserialize saved search {id}: {e:#?}");
+			return Ok(None);
+		}
+	};
+
+	Ok(pub_ids.to_local_ids(db).await?.map(|ids| {
+		filter.put_ids(ids);
+		filter
+	}))
+}
+
+/// `Some(None)` without an order, `None` if it's malformed
+fn load_order<O: DeserializeOwned>(
+	id: saved_search::id::Type,
+	order: Option<&str>,
+) -> Option<Option<O>> {
+	order
+		.map(serde_json::from_str)
+		.transpose()
+		.map_err(|e| warn!("Failed to deserialize saved search {id}: {e:#?}"))
+		.ok()
+}
+
+impl SavedSearchQuery {
+	/// Splits the query into the `target`, `filters` and `order` columns of a [`saved_search`]
+	async fn to_columns(
+		&self,
+		db: &PrismaClient,
+	) -> Result<(&'static str, String, Option<String>), rspc::Error> {
+		fn order_json(order: Option<&impl Serialize>) -> Result<Option<String>, rspc::Error> {
+			order
+				.map(serde_json::to_string)
+				.transpose()
+				.map_err(serialize_error)
+		}
+
+		Ok(match self {
+			Self::Paths { filter, order } => (
+				PATHS_TARGET,
+				store_filter(filter.clone(), db).await?,
+				order_json(order.as_ref())?,
+			),
+			Self::Objects { filter, order } => (
+				OBJECTS_TARGET,
+				store_filter(filter.clone(), db).await?,
+				order_json(order.as_ref())?,
+			),
+		})
+	}
+
+	/// Rebuilds the query from its columns, `None` if they are missing or malformed.
+	/// Columns are synced one by one, so a search created on another instance
+	/// may be incomplete for a little while.
+	async fn from_columns(
+		data: &saved_search::Data,
+		db: &PrismaClient,
+	) -> Result<Option<Self>, QueryError> {
+		let (Some(target), Some(filters)) = (data.target.as_deref(), data.filters.as_deref())
+		else {
+			return Ok(None);
+		};
+		let order = data.order.as_deref();
+
+		Ok(match target {
+			PATHS_TARGET => load_filter(data.id, filters, db).await?.and_then(|filter| {
+				Some(Self::Paths {
+					filter,
+					order: load_order(data.id, order)?,
+				})
+			}),
+			OBJECTS_TARGET => load_filter(data.id, filters, db).await?.and_then(|filter| {
+				Some(Self::Objects {
+					filter,
+					order: load_order(data.id, order)?,
+				})
+			}),
+			_ => {
+				warn!("Saved search {} has an unknown target: '{target}'", data.id);
+				None
+			}
+		})
+	}
+}
+
+#[derive(Serialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SavedSearch {
+	id: saved_search::id::Type,
+	pub_id: Vec<u8>,
+	name: Option<String>,
+	icon: Option<String>,
+	search: Option<SavedSearchQuery>,
+	date_created: Option<DateTime<FixedOffset>>,
+	date_modified: Option<DateTime<FixedOffset>>,
+}
+
+impl SavedSearch {
+	async fn from_data(data: saved_search::Data, db: &PrismaClient) -> Result<Self, QueryError> {
+		Ok(Self {
+			search: SavedSearchQuery::from_columns(&data, db).await?,
+			id: data.id,
+			pub_id: data.pub_id,
+			name: data.name,
+			icon: data.icon,
+			date_created: data.date_created,
+			date_modified: data.date_modified,
+		})
+	}
+}
+
+fn serialize_error(e: serde_json::Error) -> rspc::Error {
+	rspc::Error::with_cause(
+		ErrorCode::InternalServerError,
+		"Failed to serialize saved search".to_string(),
+		e,
+	)
+}
+
+fn not_found() -> rspc::Error {
+	rspc::Error::new(ErrorCode::NotFound, "Saved search not found".to_string())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				let db = &library.db;
+
+				Ok(try_join_all(
+					db.saved_search()
+						.find_many(vec![])
+						.exec()
+						.await?
+						.into_iter()
+						.map(|data| SavedSearch::from_data(data, db)),
+				)
+				.await?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), id: saved_search::id::Type| async move {
+					let db = &library.db;
+
+					Ok(
+						match db
+							.saved_search()
+							.find_unique(saved_search::id::equals(id))
+							.exec()
+							.await?
+						{
+							Some(data) => Some(SavedSearch::from_data(data, db).await?),
+							None => None,
+						},
+					)
+				})
+		})
+		.procedure("create", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SavedSearchCreateArgs {
+				name: String,
+				#[specta(optional)]
+				icon: Option<String>,
+				search: SavedSearchQuery,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SavedSearchCreateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = Uuid::new_v4().as_bytes().to_vec();
+					let date_created: DateTime<FixedOffset> = Utc::now().into();
+					let (target, filters, order) = args.search.to_columns(db).await?;
+
+					let created = sync
+						.write_ops(
+							db,
+							(
+								sync.shared_create(
+									prisma_sync::saved_search::SyncId {
+										pub_id: pub_id.clone(),
+									},
+									[
+										(saved_search::name::NAME, json!(&args.name)),
+										(saved_search::icon::NAME, json!(&args.icon)),
+										(saved_search::target::NAME, json!(target)),
+										(saved_search::filters::NAME, json!(&filters)),
+										(saved_search::order::NAME, json!(&order)),
+										(
+											saved_search::date_created::NAME,
+											json!(&date_created.to_rfc3339()),
+										),
+									],
+								),
+								db.saved_search().create(
+									pub_id,
+									vec![
+										saved_search::name::set(Some(args.name)),
+										saved_search::icon::set(args.icon),
+										saved_search::target::set(Some(target.to_string())),
+										saved_search::filters::set(Some(filters)),
+										saved_search::order::set(order),
+										saved_search::date_created::set(Some(date_created)),
+									],
+								),
+							),
+						)
+						.await?;
+
+					invalidate_query!(library, "search.saved.list");
+
+					Ok(SavedSearch::from(created))
+				})
+		})
+		.procedure("update", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SavedSearchUpdateArgs {
+				id: saved_search::id::Type,
+				#[specta(optional)]
+				name: Option<String>,
+				#[specta(optional)]
+				icon: Option<String>,
+				#[specta(optional)]
+				search: Option<SavedSearchQuery>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SavedSearchUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let saved_search = db
+						.saved_search()
+						.find_unique(saved_search::id::equals(args.id))
+						.select(saved_search::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or_else(not_found)?;
+
+					let (target, filters, order) = match &args.search {
+						Some(search) => {
+							let (target, filters, order) = search.to_columns(db).await?;
+
+							(Some(target.to_string()), Some(filters), Some(order))
+						}
+						None => (None, None, None),
+					};
+
+					let date_modified: DateTime<FixedOffset> = Utc::now().into();
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						args.name.map(|name| {
+							(
+								(saved_search::name::NAME, json!(&name)),
+								saved_search::name::set(Some(name)),
+							)
+						}),
+						args.icon.map(|icon| {
+							(
+								(saved_search::icon::NAME, json!(&icon)),
+								saved_search::icon::set(Some(icon)),
+							)
+						}),
+						target.map(|target| {
+							(
+								(saved_search::target::NAME, json!(&target)),
+								saved_search::target::set(Some(target)),
+							)
+						}),
+						filters.map(|filters| {
+							(
+								(saved_search::filters::NAME, json!(&filters)),
+								saved_search::filters::set(Some(filters)),
+							)
+						}),
+						order.map(|order| {
+							(
+								(saved_search::order::NAME, json!(&order)),
+								saved_search::order::set(order),
+							)
+						}),
+						Some((
+							(
+								saved_search::date_modified::NAME,
+								json!(&date_modified.to_rfc3339()),
+							),
+							saved_search::date_modified::set(Some(date_modified)),
+						)),
+					]
+					.into_iter()
+					.flatten()
+					.unzip();
+
+					sync.write_ops(
+						db,
+						(
+							sync_params
+								.into_iter()
+								.map(|(k, v)| {
+									sync.shared_update(
+										prisma_sync::saved_search::SyncId {
+											pub_id: saved_search.pub_id.clone(),
+										},
+										k,
+										v,
+									)
+								})
+								.collect(),
+							db.saved_search()
+								.update(saved_search::id::equals(args.id), db_params),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "search.saved.list");
+					invalidate_query!(library, "search.saved.get");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), id: saved_search::id::Type| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let saved_search = db
+						.saved_search()
+						.find_unique(saved_search::id::equals(id))
+						.select(saved_search::select!({ pub_id }))
+						.exec()
+						.await?
+						.ok_or_else(not_found)?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::saved_search::SyncId {
+							pub_id: saved_search.pub_id,
+						}),
+						db.saved_search().delete(saved_search::id::equals(id)),
+					)
+					.await?;
+
+					invalidate_query!(library, "search.saved.list");
+					invalidate_query!(library, "search.saved.get");
+
+					Ok(())
+				})
+		})
+		.procedure("run", {
+			#[derive(Deserialize, Type, Debug)]
+			#[serde(rename_all = "camelCase")]
+			struct SavedSearchRunArgs {
+				id: saved_search::id::Type,
+				#[specta(optional)]
+				take: Option<u8>,
+				#[specta(optional)]
+				offset: Option<i32>,
+			}
+
+			R.with2(library()).query(
+				|(node, library), SavedSearchRunArgs { id, take, offset }| async move {
+					let db = &library.db;
+
+					let data = db
+						.saved_search()
+						.find_unique(saved_search::id::equals(id))
+						.exec()
+						.await?
+						.ok_or_else(not_found)?;
+
+					let search =
+						SavedSearch::from_data(data, db)
+							.await?
+							.search
+							.ok_or_else(|| {
+								rspc::Error::new(
+									ErrorCode::Conflict,
+									"Saved search is incomplete or malformed".to_string(),
+								)
+							})?;
+
+					match search {
+						SavedSearchQuery::Paths { filter, order } => {
+							search_paths(
+								&node,
+								&library,
+								FilePathSearchArgs {
+									take,
+									order_and_pagination: Some(OrderAndPagination::Offset {
+										offset: offset.unwrap_or_default(),
+										order,
+									}),
+									filter,
+									group_directories: true,
+								},
+							)
+							.await
+						}
+						SavedSearchQuery::Objects { filter, order } => {
+							search_objects(
+								&node,
+								&library,
+								ObjectSearchArgs {
+									take: take.unwrap_or(MAX_TAKE),
+									order_and_pagination: Some(OrderAndPagination::Offset {
+										offset: offset.unwrap_or_default(),
+										order,
+									}),
+									filter,
+								},
+							)
+							.await
+						}
+					}
+				},
+			)
+		})
+}
@@ -0,0 +1,276 @@
+use crate::{
+	api::locations::{object_with_file_paths, ExplorerItem},
+	library::Library,
+	location::LocationError,
+	object::media::thumbnail::get_thumb_key,
+	prisma::{media_data, object},
+	Node,
+};
+
+use sd_images::hamming_distance;
+
+use std::collections::HashMap;
+
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+
+/// How many bits two perceptual hashes can differ by for the images to be considered the same photo
+const DEFAULT_THRESHOLD: u32 = 8;
+/// Past this, unrelated images start to match
+const MAX_THRESHOLD: u32 = 16;
+/// Grouping splits hashes into `threshold + 1` chunks, past this they are too narrow to keep
+/// the buckets sparse and comparing images sharing a chunk approaches comparing every pair
+const MAX_GROUP_THRESHOLD: u32 = DEFAULT_THRESHOLD;
+
+#[derive(Deserialize, Type, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarImagesArgs {
+	pub object_id: object::id::Type,
+	#[specta(optional)]
+	pub threshold: Option<u32>,
+}
+
+#[derive(Serialize, Type, Debug)]
+pub struct SimilarImage {
+	/// Bits that differ between the perceptual hashes, `0` being the closest match
+	pub distance: u32,
+	pub item: ExplorerItem,
+}
+
+fn threshold(threshold: Option<u32>, max: u32) -> u32 {
+	threshold.unwrap_or(DEFAULT_THRESHOLD).min(max)
+}
+
+fn decode_hash(bytes: &[u8]) -> Option<u64> {
+	bytes.try_into().ok().map(u64::from_be_bytes)
+}
+
+async fn hashes(library: &Library) -> Result<Vec<(object::id::Type, u64)>, rspc::Error> {
+	Ok(library
+		.db
+		.media_data()
+		.find_many(vec![media_data::perceptual_hash::not(None)])
+		.select(media_data::select!({ object_id perceptual_hash }))
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|media_data| {
+			media_data
+				.perceptual_hash
+				.as_deref()
+				.and_then(decode_hash)
+				.map(|hash| (media_data.object_id, hash))
+		})
+		.collect())
+}
+
+/// Fetches the objects as explorer items, keyed by their id
+async fn explorer_items(
+	node: &Node,
+	library: &Library,
+	object_ids: Vec<object::id::Type>,
+) -> Result<HashMap<object::id::Type, ExplorerItem>, rspc::Error> {
+	let objects = library
+		.db
+		.object()
+		.find_many(vec![object::id::in_vec(object_ids)])
+		.include(object_with_file_paths::include())
+		.exec()
+		.await?;
+
+	let mut items = HashMap::with_capacity(objects.len());
+
+	for object in objects {
+		let cas_id = object
+			.file_paths
+			.iter()
+			.find_map(|file_path| file_path.cas_id.as_ref());
+
+		let has_local_thumbnail = if let Some(cas_id) = cas_id {
+			library
+				.thumbnail_exists(node, cas_id)
+				.await
+				.map_err(LocationError::from)?
+		} else {
+			false
+		};
+
+		items.insert(
+			object.id,
+			ExplorerItem::Object {
+				has_local_thumbnail,
+				thumbnail_key: cas_id.map(|cas_id| get_thumb_key(cas_id)),
+				item: object,
+			},
+		);
+	}
+
+	Ok(items)
+}
+
+/// Finds images that look like the given one, closest first
+pub async fn similar_images(
+	node: &Node,
+	library: &Library,
+	args: SimilarImagesArgs,
+) -> Result<Vec<SimilarImage>, rspc::Error> {
+	let threshold = threshold(args.threshold, MAX_THRESHOLD);
+
+	let hash = library
+		.db
+		.media_data()
+		.find_unique(media_data::object_id::equals(args.object_id))
+		.select(media_data::select!({ perceptual_hash }))
+		.exec()
+		.await?
+		.and_then(|media_data| media_data.perceptual_hash)
+		.as_deref()
+		.and_then(decode_hash)
+		.ok_or_else(|| {
+			rspc::Error::new(
+				ErrorCode::NotFound,
+				"Object doesn't have a perceptual hash".to_string(),
+			)
+		})?;
+
+	let mut matches = hashes(library)
+		.await?
+		.into_iter()
+		.filter(|(object_id, _)| *object_id != args.object_id)
+		.map(|(object_id, other)| (object_id, hamming_distance(hash, other)))
+		.filter(|(_, distance)| *distance <= threshold)
+		.collect::<Vec<_>>();
+
+	matches.sort_by_key(|(_, distance)| *distance);
+
+	let mut items = explorer_items(
+		node,
+		library,
+		matches.iter().map(|(object_id, _)| *object_id).collect(),
+	)
+	.await?;
+
+	Ok(matches
+		.into_iter()
+		.filter_map(|(object_id, distance)| {
+			items
+				.remove(&object_id)
+				.map(|item| SimilarImage { distance, item })
+		})
+		.collect())
+}
+
+/// Groups images that are probably the same photo, largest groups first
+pub async fn similar_image_groups(
+	node: &Node,
+	library: &Library,
+	threshold: Option<u32>,
+) -> Result<Vec<Vec<ExplorerItem>>, rspc::Error> {
+	let hashes = hashes(library).await?;
+	let clusters = cluster(
+		&hashes.iter().map(|(_, hash)| *hash).collect::<Vec<_>>(),
+		self::threshold(threshold, MAX_GROUP_THRESHOLD),
+	);
+
+	let mut items = explorer_items(
+		node,
+		library,
+		clusters
+			.iter()
+			.flatten()
+			.map(|index| hashes[*index].0)
+			.collect(),
+	)
+	.await?;
+
+	Ok(clusters
+		.into_iter()
+		.map(|cluster| {
+			cluster
+				.into_iter()
+				.filter_map(|index| items.remove(&hashes[index].0))
+				.collect::<Vec<_>>()
+		})
+		.filter(|group| group.len() > 1)
+		.collect())
+}
+
+/// Clusters hashes within `threshold` bits of each other, returning the indices of each cluster with more than one hash.
+///
+/// Hashes are split into `threshold + 1` chunks, two hashes within the threshold must share at least one of them,
+/// so only hashes sharing a chunk are compared.
+fn cluster(hashes: &[u64], threshold: u32) -> Vec<Vec<usize>> {
+	fn find(parents: &mut [usize], mut i: usize) -> usize {
+		while parents[i] != i {
+			parents[i] = parents[parents[i]];
+			i = parents[i];
+		}
+		i
+	}
+
+	let chunks = threshold + 1;
+	let mut buckets = HashMap::<_, Vec<_>>::new();
+
+	for (index, hash) in hashes.iter().enumerate() {
+		for chunk in 0..chunks {
+			let start = 64 * chunk / chunks;
+			let end = 64 * (chunk + 1) / chunks;
+			let mask = u64::MAX >> (64 - (end - start));
+
+			buckets
+				.entry((chunk, (hash >> start) & mask))
+				.or_default()
+				.push(index);
+		}
+	}
+
+	let mut parents = (0..hashes.len()).collect::<Vec<_>>();
+
+	for bucket in buckets.values() {
+		for (i, a) in bucket.iter().enumerate() {
+			for b in &bucket[i + 1..] {
+				if hamming_distance(hashes[*a], hashes[*b]) <= threshold {
+					let (root_a, root_b) = (find(&mut parents, *a), find(&mut parents, *b));
+					parents[root_a] = root_b;
+				}
+			}
+		}
+	}
+
+	let mut clusters = HashMap::<_, Vec<_>>::new();
+	for index in 0..hashes.len() {
+		let root = find(&mut parents, index);
+		clusters.entry(root).or_default().push(index);
+	}
+
+	let mut clusters = clusters
+		.into_values()
+		.filter(|cluster| cluster.len() > 1)
+		.collect::<Vec<_>>();
+	clusters.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+
+	clusters
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_cluster() {
+		let hashes = [
+			0,
+			0b111,
+			u64::MAX,
+			u64::MAX ^ 0b1,
+			0b1111_1111_1111,
+			0xF0F0_F0F0_F0F0_F0F0,
+		];
+
+		assert_eq!(cluster(&hashes, 3), vec![vec![0, 1], vec![2, 3]]);
+		// Hashes are linked through their neighbours
+		assert_eq!(cluster(&hashes, 9), vec![vec![0, 1, 4], vec![2, 3]]);
+		assert_eq!(cluster(&hashes, 0), Vec::<Vec<usize>>::new());
+	}
+}
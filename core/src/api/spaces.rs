@@ -0,0 +1,470 @@
+use crate::{
+	api::locations::{object_with_file_paths, ExplorerItem},
+	invalidate_query,
+	library::Library,
+	object::{
+		media::thumbnail::get_thumb_key,
+		space::{object_in_space_sync_id, SpaceContent, SpaceCreateArgs, SpaceLayout},
+	},
+	prisma::{object, object_in_space, space, PrismaClient},
+};
+
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use rspc::{alpha::AlphaRouter, ErrorCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+
+use super::{utils::library, Ctx, R};
+
+#[derive(Serialize, Type, Debug)]
+pub struct SpaceItem {
+	pub layout: SpaceLayout,
+	pub item: ExplorerItem,
+}
+
+#[derive(Type, Deserialize)]
+pub struct SpaceObject {
+	pub object_id: object::id::Type,
+	pub layout: SpaceLayout,
+}
+
+fn not_found() -> rspc::Error {
+	rspc::Error::new(ErrorCode::NotFound, "Space not found".to_string())
+}
+
+async fn space_pub_id(db: &PrismaClient, id: space::id::Type) -> Result<Vec<u8>, rspc::Error> {
+	Ok(db
+		.space()
+		.find_unique(space::id::equals(id))
+		.select(space::select!({ pub_id }))
+		.exec()
+		.await?
+		.ok_or_else(not_found)?
+		.pub_id)
+}
+
+/// Adds objects to a space, skipping any that are already in it
+async fn add_objects(
+	Library { db, sync, .. }: &Library,
+	space_id: space::id::Type,
+	items: Vec<SpaceObject>,
+) -> Result<(), rspc::Error> {
+	let pub_id = space_pub_id(db, space_id).await?;
+
+	let objects = db
+		.object()
+		.find_many(vec![
+			object::id::in_vec(items.iter().map(|item| item.object_id).collect()),
+			object::spaces::none(vec![object_in_space::space_id::equals(space_id)]),
+		])
+		.select(object::select!({ id pub_id }))
+		.exec()
+		.await?;
+
+	let (sync_ops, db_creates) = items
+		.into_iter()
+		.filter_map(|item| {
+			objects
+				.iter()
+				.find(|object| object.id == item.object_id)
+				.map(|object| (object, item.layout))
+		})
+		.fold(
+			(vec![], vec![]),
+			|(mut sync_ops, mut db_creates), (object, layout)| {
+				db_creates.push(object_in_space::CreateUnchecked {
+					space_id,
+					object_id: object.id,
+					_params: layout.to_params(),
+				});
+
+				sync_ops.extend(sync.relation_create(
+					object_in_space_sync_id(&pub_id, object.pub_id.clone()),
+					layout.to_sync_values(),
+				));
+
+				(sync_ops, db_creates)
+			},
+		);
+
+	sync.write_ops(db, (sync_ops, db.object_in_space().create_many(db_creates)))
+		.await?;
+
+	Ok(())
+}
+
+pub(crate) fn mount() -> AlphaRouter<Ctx> {
+	R.router()
+		.procedure("list", {
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library.db.space().find_many(vec![]).exec().await?)
+			})
+		})
+		.procedure("get", {
+			R.with2(library())
+				.query(|(_, library), space_id: space::id::Type| async move {
+					Ok(library
+						.db
+						.space()
+						.find_unique(space::id::equals(space_id))
+						.exec()
+						.await?)
+				})
+		})
+		.procedure("content", {
+			R.with2(library())
+				.query(|(node, library), space_id: space::id::Type| async move {
+					let Library { db, .. } = library.as_ref();
+
+					let rels = db
+						.object_in_space()
+						.find_many(vec![object_in_space::space_id::equals(space_id)])
+						.exec()
+						.await?;
+
+					let mut objects = db
+						.object()
+						.find_many(vec![object::id::in_vec(
+							rels.iter().map(|rel| rel.object_id).collect(),
+						)])
+						.include(object_with_file_paths::include())
+						.exec()
+						.await?;
+
+					let mut items = Vec::with_capacity(rels.len());
+
+					for rel in rels {
+						let Some(index) = objects.iter().position(|o| o.id == rel.object_id) else {
+							continue;
+						};
+						let object = objects.swap_remove(index);
+
+						let cas_id = object.file_paths.iter().find_map(|fp| fp.cas_id.as_ref());
+
+						let has_local_thumbnail = if let Some(cas_id) = cas_id {
+							library.thumbnail_exists(&node, cas_id).await.map_err(|e| {
+								rspc::Error::with_cause(
+									ErrorCode::InternalServerError,
+									"Failed to check that thumbnail exists".to_string(),
+									e,
+								)
+							})?
+						} else {
+							false
+						};
+
+						items.push(SpaceItem {
+							layout: SpaceLayout::from_data(&rel),
+							item: ExplorerItem::Object {
+								has_local_thumbnail,
+								thumbnail_key: cas_id.map(|cas_id| get_thumb_key(cas_id)),
+								item: object,
+							},
+						});
+					}
+
+					Ok(items)
+				})
+		})
+		.procedure("create", {
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceCreateArgs| async move {
+					let created_space = args.exec(&library).await?;
+
+					invalidate_query!(library, "spaces.list");
+
+					Ok(created_space)
+				})
+		})
+		.procedure("update", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceUpdateArgs {
+				pub id: space::id::Type,
+				#[specta(optional)]
+				pub name: Option<String>,
+				#[specta(optional)]
+				pub description: Option<String>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceUpdateArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = space_pub_id(db, args.id).await?;
+					let date_modified: DateTime<FixedOffset> = Utc::now().into();
+
+					let (sync_params, db_params): (Vec<_>, Vec<_>) = [
+						args.name.map(|name| {
+							(
+								(space::name::NAME, json!(&name)),
+								space::name::set(Some(name)),
+							)
+						}),
+						args.description.map(|description| {
+							(
+								(space::description::NAME, json!(&description)),
+								space::description::set(Some(description)),
+							)
+						}),
+					]
+					.into_iter()
+					.flatten()
+					.chain([(
+						(
+							space::date_modified::NAME,
+							json!(&date_modified.to_rfc3339()),
+						),
+						space::date_modified::set(Some(date_modified)),
+					)])
+					.unzip();
+
+					sync.write_ops(
+						db,
+						(
+							sync_params
+								.into_iter()
+								.map(|(k, v)| {
+									sync.shared_update(
+										prisma_sync::space::SyncId {
+											pub_id: pub_id.clone(),
+										},
+										k,
+										v,
+									)
+								})
+								.collect(),
+							db.space().update(space::id::equals(args.id), db_params),
+						),
+					)
+					.await?;
+
+					invalidate_query!(library, "spaces.list");
+					invalidate_query!(library, "spaces.get");
+
+					Ok(())
+				})
+		})
+		.procedure("addObjects", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceAddObjectsArgs {
+				pub space_id: space::id::Type,
+				pub objects: Vec<SpaceObject>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceAddObjectsArgs| async move {
+					add_objects(&library, args.space_id, args.objects).await?;
+
+					invalidate_query!(library, "spaces.content");
+
+					Ok(())
+				})
+		})
+		// Links and notes are added as objects that only exist within the space
+		.procedure("addContent", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceAddContentArgs {
+				pub space_id: space::id::Type,
+				pub content: SpaceContent,
+				pub layout: SpaceLayout,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceAddContentArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let object = args
+						.content
+						.create_in_space(
+							db,
+							sync,
+							space_pub_id(db, args.space_id).await?,
+							args.layout,
+						)
+						.await?;
+
+					invalidate_query!(library, "spaces.content");
+
+					Ok(object.id)
+				})
+		})
+		.procedure("moveItems", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceMoveItemsArgs {
+				pub space_id: space::id::Type,
+				pub objects: Vec<SpaceObject>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceMoveItemsArgs| async move {
+					let Library { db, sync, .. } = library.as_ref();
+
+					let pub_id = space_pub_id(db, args.space_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![
+							object::id::in_vec(
+								args.objects.iter().map(|item| item.object_id).collect(),
+							),
+							object::spaces::some(vec![object_in_space::space_id::equals(
+								args.space_id,
+							)]),
+						])
+						.select(object::select!({ id pub_id }))
+						.exec()
+						.await?;
+
+					let (sync_ops, db_updates): (Vec<_>, Vec<_>) = args
+						.objects
+						.into_iter()
+						.filter_map(|item| {
+							objects
+								.iter()
+								.find(|object| object.id == item.object_id)
+								.map(|object| (object, item.layout))
+						})
+						.map(|(object, layout)| {
+							(
+								layout
+									.to_sync_values()
+									.into_iter()
+									.map(|(k, v)| {
+										sync.relation_update(
+											object_in_space_sync_id(&pub_id, object.pub_id.clone()),
+											k,
+											v,
+										)
+									})
+									.collect::<Vec<_>>(),
+								db.object_in_space().update(
+									object_in_space::space_id_object_id(args.space_id, object.id),
+									layout.to_params(),
+								),
+							)
+						})
+						.unzip();
+
+					sync.write_ops(db, (sync_ops.into_iter().flatten().collect(), db_updates))
+						.await?;
+
+					invalidate_query!(library, "spaces.content");
+
+					Ok(())
+				})
+		})
+		.procedure("removeItems", {
+			#[derive(Type, Deserialize)]
+			pub struct SpaceRemoveItemsArgs {
+				pub space_id: space::id::Type,
+				pub object_ids: Vec<object::id::Type>,
+			}
+
+			R.with2(library())
+				.mutation(|(_, library), args: SpaceRemoveItemsArgs| async move {
+					let Library {
+						db,
+						sync,
+						orphan_remover,
+						..
+					} = library.as_ref();
+
+					let pub_id = space_pub_id(db, args.space_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![object::id::in_vec(args.object_ids)])
+						.select(object::select!({ id pub_id }))
+						.exec()
+						.await?;
+
+					let query = db.object_in_space().delete_many(vec![
+						object_in_space::space_id::equals(args.space_id),
+						object_in_space::object_id::in_vec(objects.iter().map(|o| o.id).collect()),
+					]);
+
+					sync.write_ops(
+						db,
+						(
+							objects
+								.into_iter()
+								.map(|object| {
+									sync.relation_delete(object_in_space_sync_id(
+										&pub_id,
+										object.pub_id,
+									))
+								})
+								.collect(),
+							query,
+						),
+					)
+					.await?;
+
+					// Links and notes that are no longer in any space are cleaned up
+					orphan_remover.invoke().await;
+
+					invalidate_query!(library, "spaces.content");
+
+					Ok(())
+				})
+		})
+		.procedure("delete", {
+			R.with2(library())
+				.mutation(|(_, library), space_id: space::id::Type| async move {
+					let Library {
+						db,
+						sync,
+						orphan_remover,
+						..
+					} = library.as_ref();
+
+					let pub_id = space_pub_id(db, space_id).await?;
+
+					let objects = db
+						.object()
+						.find_many(vec![object::spaces::some(vec![
+							object_in_space::space_id::equals(space_id),
+						])])
+						.select(object::select!({ pub_id }))
+						.exec()
+						.await?;
+
+					// The space has to be emptied before it can be deleted
+					sync.write_ops(
+						db,
+						(
+							objects
+								.into_iter()
+								.map(|object| {
+									sync.relation_delete(object_in_space_sync_id(
+										&pub_id,
+										object.pub_id,
+									))
+								})
+								.collect(),
+							db.object_in_space()
+								.delete_many(vec![object_in_space::space_id::equals(space_id)]),
+						),
+					)
+					.await?;
+
+					sync.write_op(
+						db,
+						sync.shared_delete(prisma_sync::space::SyncId { pub_id }),
+						db.space().delete(space::id::equals(space_id)),
+					)
+					.await?;
+
+					orphan_remover.invoke().await;
+
+					invalidate_query!(library, "spaces.list");
+					invalidate_query!(library, "spaces.content");
+
+					Ok(())
+				})
+		})
+}
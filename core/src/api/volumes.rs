@@ -1,11 +1,45 @@
-use rspc::alpha::AlphaRouter;
+use crate::{
+	api::CoreEvent,
+	prisma::volume,
+	volume::{get_volumes, tracked_volume},
+};
 
-use crate::volume::get_volumes;
+use rspc::alpha::AlphaRouter;
 
-use super::{Ctx, R};
+use super::{utils::library, Ctx, R};
 
 pub(crate) fn mount() -> AlphaRouter<Ctx> {
-	R.router().procedure("list", {
-		R.query(|_, _: ()| async move { Ok(get_volumes().await) })
-	})
+	R.router()
+		.procedure("list", {
+			R.query(|_, _: ()| async move { Ok(get_volumes().await) })
+		})
+		.procedure("tracked", {
+			// Every volume this instance has seen, including the ones that aren't mounted right now
+			R.with2(library()).query(|(_, library), _: ()| async move {
+				Ok(library
+					.db
+					.volume()
+					.find_many(vec![volume::instance_id::equals(
+						library.config().instance_id,
+					)])
+					.select(tracked_volume::select())
+					.exec()
+					.await?)
+			})
+		})
+		.procedure("events", {
+			R.with2(library())
+				.subscription(|(node, library), _: ()| async move {
+					let mut event_bus_rx = node.event_bus.0.subscribe();
+
+					async_stream::stream! {
+						while let Ok(event) = event_bus_rx.recv().await {
+							match event {
+								CoreEvent::Volume(event) if event.library_id == library.id => yield event,
+								_ => {}
+							}
+						}
+					}
+				})
+		})
 }
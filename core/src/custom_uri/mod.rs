@@ -1,6 +1,9 @@
 use crate::{
 	library::Library,
-	location::file_path_helper::{file_path_to_handle_custom_uri, IsolatedFilePathData},
+	location::file_path_helper::{
+		file_path_to_handle_custom_uri, filter_existing_file_path_params, IsolatedFilePathData,
+	},
+	object::fs::archive::{read_archive_member, split_archive_path, ArchiveError},
 	p2p::{sync::InstanceState, IdentityOrRemoteIdentity},
 	prisma::{file_path, location},
 	util::{db::*, InfallibleResponse},
@@ -12,8 +15,8 @@ use std::{
 	ffi::OsStr,
 	fmt::Debug,
 	fs::Metadata,
-	io::{self, SeekFrom},
-	path::{Path, PathBuf},
+	io::{self, Read, SeekFrom},
+	path::{Component, Path, PathBuf},
 	str::FromStr,
 	sync::{atomic::Ordering, Arc},
 };
@@ -22,19 +25,22 @@ use async_stream::stream;
 use axum::{
 	body::{self, Body, BoxBody, Full, StreamBody},
 	extract::{self, State},
-	http::{HeaderValue, Request, Response, StatusCode},
+	http::{header, HeaderValue, Request, Response, StatusCode},
 	middleware,
 	routing::get,
 	Router,
 };
 use bytes::Bytes;
 
+use http_range::HttpRange;
 use mini_moka::sync::Cache;
 use sd_file_ext::text::is_text;
 use sd_p2p::{spaceblock::Range, spacetunnel::RemoteIdentity};
 use tokio::{
-	fs::File,
+	fs::{self, File},
 	io::{AsyncReadExt, AsyncSeekExt},
+	sync::{mpsc, oneshot},
+	task::spawn_blocking,
 };
 use tokio_util::sync::PollSender;
 use tracing::error;
@@ -54,10 +60,13 @@ struct CacheValue {
 	name: PathBuf,
 	ext: String,
 	file_path_pub_id: Uuid,
+	/// The size of the file as it was last indexed, required to serve `Range` requests of remote files
+	size: Option<u64>,
 	serve_from: ServeFrom,
 }
 
 const MAX_TEXT_READ_LENGTH: usize = 10 * 1024; // 10KB
+const ARCHIVE_MEMBER_CHUNK_LEN: usize = 64 * 1024; // 64KiB
 
 #[derive(Debug, Clone)]
 pub enum ServeFrom {
@@ -129,6 +138,11 @@ async fn get_or_init_lru_entry(
 			name: path,
 			ext: maybe_missing(file_path.extension, "extension").map_err(not_found)?,
 			file_path_pub_id: Uuid::from_slice(&file_path.pub_id).map_err(internal_server_error)?,
+			size: file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|size| size.try_into().ok())
+				.map(u64::from_be_bytes),
 			serve_from: if identity == library.identity.to_remote_identity() {
 				ServeFrom::Local
 			} else {
@@ -144,6 +158,109 @@ async fn get_or_init_lru_entry(
 	}
 }
 
+/// Checks the archive of a virtual path can be read. Inside the library's locations on this
+/// instance it must be indexed, so the indexer rules of the location are respected. Anywhere else
+/// it's browsed as a non-indexed path, the same as `search.ephemeralPaths` lists it.
+async fn find_local_archive(
+	library: &Library,
+	archive_path: &Path,
+) -> Result<(), Response<BoxBody>> {
+	// Locations are matched by their path alone, so `..` could reach outside of them
+	if !archive_path.is_absolute()
+		|| archive_path
+			.components()
+			.any(|component| component == Component::ParentDir)
+	{
+		return Err(bad_request(()));
+	}
+
+	let locations = library
+		.db
+		.location()
+		.find_many(vec![location::path::in_vec(
+			archive_path
+				.ancestors()
+				.skip(1)
+				.filter_map(|path| path.to_str().map(str::to_string))
+				.collect(),
+		)])
+		.select(location::select!({ id path instance: select { identity } }))
+		.exec()
+		.await
+		.map_err(internal_server_error)?;
+
+	let mut in_local_location = false;
+
+	for location in locations {
+		let (Some(location_path), Some(instance)) = (&location.path, &location.instance) else {
+			continue;
+		};
+
+		let identity = IdentityOrRemoteIdentity::from_bytes(&instance.identity)
+			.map_err(internal_server_error)?
+			.remote_identity();
+
+		if identity != library.identity.to_remote_identity() {
+			continue;
+		}
+
+		in_local_location = true;
+
+		let iso_file_path =
+			IsolatedFilePathData::new(location.id, location_path, archive_path, false)
+				.map_err(internal_server_error)?;
+
+		if library
+			.db
+			.file_path()
+			.count(filter_existing_file_path_params(&iso_file_path))
+			.exec()
+			.await
+			.map_err(internal_server_error)?
+			> 0
+		{
+			return Ok(());
+		}
+	}
+
+	if in_local_location {
+		return Err(not_found(()));
+	}
+
+	match fs::metadata(archive_path).await {
+		Ok(metadata) if metadata.is_file() => Ok(()),
+		Ok(_) => Err(not_found(())),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Err(not_found(())),
+		Err(e) => Err(internal_server_error(e)),
+	}
+}
+
+/// Sends `length` bytes of an archive member from `start` on, until the request goes away
+fn send_archive_member(
+	mut reader: impl Read,
+	start: u64,
+	length: u64,
+	tx: &mpsc::Sender<io::Result<Bytes>>,
+) -> io::Result<()> {
+	// Compressed archives can't seek, so everything before the range is read and thrown away
+	io::copy(&mut (&mut reader).take(start), &mut io::sink())?;
+
+	let mut reader = reader.take(length);
+	let mut buf = vec![0; ARCHIVE_MEMBER_CHUNK_LEN];
+
+	loop {
+		let read = reader.read(&mut buf)?;
+
+		if read == 0
+			|| tx
+				.blocking_send(Ok(Bytes::copy_from_slice(&buf[..read])))
+				.is_err()
+		{
+			return Ok(());
+		}
+	}
+}
+
 // We are using Axum on all platforms because Tauri's custom URI protocols can't be async!
 pub fn router(node: Arc<Node>) -> Router<()> {
 	Router::new()
@@ -184,6 +301,152 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 				},
 			),
 		)
+		.route(
+			"/file/archive/:lib_id/:path",
+			get(
+				|State(state): State<LocalState>,
+				 extract::Path((lib_id, path)): extract::Path<(String, String)>,
+				 request: Request<Body>| async move {
+					let library_id = Uuid::from_str(&lib_id).map_err(bad_request)?;
+					let library = state
+						.node
+						.libraries
+						.get_library(&library_id)
+						.await
+						.ok_or_else(|| internal_server_error(()))?;
+
+					// `path` is a virtual path like `/x/foo.zip!/docs/readme.md`,
+					// the same ones returned by `search.ephemeralPaths` for archive contents
+					let (format, archive_path, member) =
+						split_archive_path(&path).ok_or_else(|| bad_request(&path))?;
+
+					find_local_archive(&library, archive_path).await?;
+
+					let ext = Path::new(member)
+						.extension()
+						.and_then(OsStr::to_str)
+						.map(str::to_lowercase)
+						.unwrap_or_default();
+
+					let (member_tx, member_rx) = oneshot::channel();
+					let (range_tx, range_rx) = oneshot::channel::<(u64, u64)>();
+					let (tx, mut rx) = mpsc::channel::<io::Result<Bytes>>(16);
+
+					// Archive readers are blocking, so the member is streamed from a blocking task
+					let reader = spawn_blocking({
+						let (archive_path, member) =
+							(archive_path.to_path_buf(), member.to_string());
+						move || {
+							read_archive_member(format, &archive_path, &member, |size, reader| {
+								// The beginning of the member tells the mime type of text files
+								let mut head = vec![];
+								(&mut *reader)
+									.take(MAX_TEXT_READ_LENGTH as u64)
+									.read_to_end(&mut head)?;
+
+								if member_tx.send((size, head.clone())).is_err() {
+									return Ok(());
+								}
+
+								// Dropped when the request can't be served
+								let Ok((start, length)) = range_rx.blocking_recv() else {
+									return Ok(());
+								};
+
+								if let Err(e) = send_archive_member(
+									io::Cursor::new(head).chain(reader),
+									start,
+									length,
+									&tx,
+								) {
+									tx.blocking_send(Err(e)).ok();
+								}
+
+								Ok(())
+							})
+						}
+					});
+
+					let Ok((size, head)) = member_rx.await else {
+						return Err(match reader.await.map_err(internal_server_error)? {
+							Ok(()) => internal_server_error(()),
+							Err(err) => match err {
+								ArchiveError::EntryNotFound(..) => not_found(err),
+								ArchiveError::FileIO(ref e)
+									if e.source.kind() == io::ErrorKind::NotFound =>
+								{
+									not_found(err)
+								}
+								_ => internal_server_error(err),
+							},
+						});
+					};
+
+					let mime_type = match extension_mime_type(&ext) {
+						"text/plain" => text_mime_type(&ext, &head, (head.len() as u64) < size)
+							.unwrap_or_else(|| "application/octet-stream".to_string()),
+						mime_type => mime_type.to_string(),
+					};
+
+					let mut resp = InfallibleResponse::builder()
+						.header(
+							"Content-Type",
+							HeaderValue::from_str(&mime_type).map_err(internal_server_error)?,
+						)
+						.header(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+					let (start, length) = match request.headers().get(header::RANGE) {
+						Some(range) => {
+							let Some(range) =
+								HttpRange::parse(range.to_str().map_err(bad_request)?, size)
+									.ok()
+									// TODO: Multipart requests are not support, yet
+									.filter(|ranges| ranges.len() == 1)
+									.and_then(|ranges| ranges.first().copied())
+									.filter(|range| range.start + range.length <= size)
+							else {
+								return Ok(range_not_satisfiable(size));
+							};
+
+							resp = resp.status(StatusCode::PARTIAL_CONTENT).header(
+								header::CONTENT_RANGE,
+								HeaderValue::from_str(&format!(
+									"bytes {}-{}/{}",
+									range.start,
+									range.start + range.length - 1,
+									size
+								))
+								.map_err(internal_server_error)?,
+							);
+
+							(range.start, range.length)
+						}
+						None => {
+							resp = resp.status(StatusCode::OK);
+
+							(0, size)
+						}
+					};
+
+					range_tx
+						.send((start, length))
+						.map_err(|_| internal_server_error(()))?;
+
+					Ok::<_, Response<BoxBody>>(
+						resp.header(
+							header::CONTENT_LENGTH,
+							HeaderValue::from_str(&length.to_string())
+								.map_err(internal_server_error)?,
+						)
+						.body(body::boxed(StreamBody::new(stream! {
+							while let Some(item) = rx.recv().await {
+								yield item;
+							}
+						}))),
+					)
+				},
+			),
+		)
 		.route(
 			"/file/:lib_id/:loc_id/:path_id",
 			get(
@@ -193,6 +456,7 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 							name: file_path_full_path,
 							ext: extension,
 							file_path_pub_id,
+							size,
 							serve_from,
 							..
 						},
@@ -237,7 +501,7 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 								return Ok(not_found(()));
 							}
 
-							// TODO: Support `Range` requests and `ETag` headers
+							// TODO: Support `ETag` headers
 							#[allow(clippy::unwrap_used)]
 							match *state
 								.node
@@ -254,30 +518,107 @@ pub fn router(node: Arc<Node>) -> Router<()> {
 									Ok(not_found(()))
 								}
 								InstanceState::Connected(peer_id) => {
+									// We can only make sense of a `Range` header if we know how large the file is.
+									// The indexed size may be stale, so the response is built from the size the peer reports.
+									let http_range =
+										match (request.headers().get(header::RANGE), size) {
+											(Some(range), Some(size)) => {
+												let Some(range) = HttpRange::parse(
+													range.to_str().map_err(bad_request)?,
+													size,
+												)
+												.ok()
+												// TODO: Multipart requests are not support, yet
+												.filter(|ranges| ranges.len() == 1)
+												.and_then(|ranges| ranges.first().copied())
+												.filter(|range| range.start + range.length <= size) else {
+													return Ok(range_not_satisfiable(size));
+												};
+
+												Some(range)
+											}
+											_ => None,
+										};
+
+									let (size_tx, size_rx) = oneshot::channel();
 									let (tx, mut rx) =
 										tokio::sync::mpsc::channel::<io::Result<Bytes>>(150);
 									// TODO: We only start a thread because of stupid `ManagerStreamAction2` and libp2p's `!Send/!Sync` bounds on a stream.
 									let node = state.node.clone();
 									tokio::spawn(async move {
-										node.p2p
+										if let Err(e) = node
+											.p2p
 											.request_file(
 												peer_id,
+												&identity,
 												&library,
 												file_path_pub_id,
-												Range::Full,
+												http_range.map_or(Range::Full, |range| {
+													Range::Partial(
+														range.start..range.start + range.length,
+													)
+												}),
+												size_tx,
 												MpscToAsyncWrite::new(PollSender::new(tx)),
 											)
-											.await;
+											.await
+										{
+											error!(
+												"Error requesting file '{file_path_pub_id}' from peer '{peer_id}': {e}"
+											);
+										}
 									});
 
+									// The request failing before the peer replied was already logged above
+									let remote_size =
+										size_rx.await.map_err(internal_server_error)?;
+
+									let mut resp = InfallibleResponse::builder();
+									if size.is_some() {
+										resp = resp.header(
+											header::ACCEPT_RANGES,
+											HeaderValue::from_static("bytes"),
+										);
+									}
+
+									resp = match http_range {
+										Some(range)
+											if range.start + range.length <= remote_size =>
+										{
+											resp.status(StatusCode::PARTIAL_CONTENT)
+												.header(
+													header::CONTENT_RANGE,
+													HeaderValue::from_str(&format!(
+														"bytes {}-{}/{}",
+														range.start,
+														range.start + range.length - 1,
+														remote_size
+													))
+													.map_err(internal_server_error)?,
+												)
+												.header(
+													header::CONTENT_LENGTH,
+													HeaderValue::from_str(
+														&range.length.to_string(),
+													)
+													.map_err(internal_server_error)?,
+												)
+										}
+										// The file shrank since it was indexed, the peer won't send the range either
+										Some(_) => return Ok(range_not_satisfiable(remote_size)),
+										None => resp.status(StatusCode::OK).header(
+											header::CONTENT_LENGTH,
+											HeaderValue::from_str(&remote_size.to_string())
+												.map_err(internal_server_error)?,
+										),
+									};
+
 									// TODO: Content Type
-									Ok(InfallibleResponse::builder().status(StatusCode::OK).body(
-										body::boxed(StreamBody::new(stream! {
-											while let Some(item) = rx.recv().await {
-												yield item;
-											}
-										})),
-									))
+									Ok(resp.body(body::boxed(StreamBody::new(stream! {
+										while let Some(item) = rx.recv().await {
+											yield item;
+										}
+									}))))
 								}
 							}
 						}
@@ -298,7 +639,41 @@ async fn infer_the_mime_type(
 	file: &mut File,
 	metadata: &Metadata,
 ) -> Result<String, Response<BoxBody>> {
-	let mime_type = match ext {
+	let mime_type = extension_mime_type(ext);
+
+	Ok(if mime_type == "text/plain" {
+		let mut text_buf = vec![
+			0;
+			min(
+				metadata.len().try_into().unwrap_or(usize::MAX),
+				MAX_TEXT_READ_LENGTH
+			)
+		];
+		if !text_buf.is_empty() {
+			file.read_exact(&mut text_buf)
+				.await
+				.map_err(internal_server_error)?;
+			file.seek(SeekFrom::Start(0))
+				.await
+				.map_err(internal_server_error)?;
+		}
+
+		let Some(mime_type) =
+			text_mime_type(ext, &text_buf, text_buf.len() == (metadata.len() as usize))
+		else {
+			todo!();
+			// "TODO: This filetype is not supported because of the missing mime type!",
+		};
+
+		mime_type
+	} else {
+		mime_type.to_string()
+	})
+}
+
+/// Mime types of the formats we know by extension, `text/plain` for anything else
+fn extension_mime_type(ext: &str) -> &'static str {
+	match ext {
 		// AAC audio
 		"aac" => "audio/aac",
 		// Musical Instrument Digital Interface (MIDI)
@@ -364,59 +739,38 @@ async fn infer_the_mime_type(
 		// AVIF images
 		"avif" | "avci" | "avcs" => "image/avif",
 		_ => "text/plain",
-	};
+	}
+}
 
-	Ok(if mime_type == "text/plain" {
-		let mut text_buf = vec![
-			0;
-			min(
-				metadata.len().try_into().unwrap_or(usize::MAX),
-				MAX_TEXT_READ_LENGTH
-			)
-		];
-		if !text_buf.is_empty() {
-			file.read_exact(&mut text_buf)
-				.await
-				.map_err(internal_server_error)?;
-			file.seek(SeekFrom::Start(0))
-				.await
-				.map_err(internal_server_error)?;
-		}
+/// Mime type with charset for the beginning of a text file,
+/// `None` if it doesn't look like text and we don't know its extension
+fn text_mime_type(ext: &str, text_buf: &[u8], partial: bool) -> Option<String> {
+	let charset = is_text(text_buf, partial).unwrap_or("");
 
-		let charset = is_text(&text_buf, text_buf.len() == (metadata.len() as usize)).unwrap_or("");
-
-		// Only browser recognized types, everything else should be text/plain
-		// https://www.iana.org/assignments/media-types/media-types.xhtml#table-text
-		let mime_type = match ext {
-			// HyperText Markup Language
-			"html" | "htm" => "text/html",
-			// Cascading Style Sheets
-			"css" => "text/css",
-			// Javascript
-			"js" | "mjs" => "text/javascript",
-			// Comma-separated values
-			"csv" => "text/csv",
-			// Markdown
-			"md" | "markdown" => "text/markdown",
-			// Rich text format
-			"rtf" => "text/rtf",
-			// Web Video Text Tracks
-			"vtt" => "text/vtt",
-			// Extensible Markup Language
-			"xml" => "text/xml",
-			// Text
-			"txt" => "text/plain",
-			_ => {
-				if charset.is_empty() {
-					todo!();
-					// "TODO: This filetype is not supported because of the missing mime type!",
-				};
-				mime_type
-			}
-		};
+	// Only browser recognized types, everything else should be text/plain
+	// https://www.iana.org/assignments/media-types/media-types.xhtml#table-text
+	let mime_type = match ext {
+		// HyperText Markup Language
+		"html" | "htm" => "text/html",
+		// Cascading Style Sheets
+		"css" => "text/css",
+		// Javascript
+		"js" | "mjs" => "text/javascript",
+		// Comma-separated values
+		"csv" => "text/csv",
+		// Markdown
+		"md" | "markdown" => "text/markdown",
+		// Rich text format
+		"rtf" => "text/rtf",
+		// Web Video Text Tracks
+		"vtt" => "text/vtt",
+		// Extensible Markup Language
+		"xml" => "text/xml",
+		// Text
+		"txt" => "text/plain",
+		_ if charset.is_empty() => return None,
+		_ => "text/plain",
+	};
 
-		format!("{mime_type}; charset={charset}")
-	} else {
-		mime_type.to_string()
-	})
+	Some(format!("{mime_type}; charset={charset}"))
 }
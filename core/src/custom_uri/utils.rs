@@ -40,6 +40,20 @@ pub(crate) fn internal_server_error(err: impl Debug) -> http::Response<BoxBody>
 		.body(body::boxed(Full::from("")))
 }
 
+#[track_caller]
+pub(crate) fn range_not_satisfiable(size: u64) -> http::Response<BoxBody> {
+	debug!("416: Range Not Satisfiable at {}", Location::caller());
+
+	InfallibleResponse::builder()
+		.header(
+			http::header::CONTENT_RANGE,
+			HeaderValue::from_str(&format!("bytes */{size}"))
+				.expect("number won't fail conversion"),
+		)
+		.status(StatusCode::RANGE_NOT_SATISFIABLE)
+		.body(body::boxed(Full::from("")))
+}
+
 pub(crate) async fn cors_middleware<B>(req: Request<B>, next: Next<B>) -> Response<BoxBody> {
 	if req.method() == Method::OPTIONS {
 		return Response::builder()
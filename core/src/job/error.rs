@@ -1,8 +1,11 @@
 use crate::{
 	location::{indexer::IndexerError, LocationError},
 	object::{
-		file_identifier::FileIdentifierJobError, fs::error::FileSystemJobsError,
-		media::media_processor::MediaProcessorError, validation::ValidatorError,
+		content::ContentIndexerError,
+		file_identifier::FileIdentifierJobError,
+		fs::{archive::ArchiveError, error::FileSystemJobsError},
+		media::media_processor::MediaProcessorError,
+		validation::ValidatorError,
 	},
 	util::{db::MissingFieldError, error::FileIOError},
 };
@@ -60,8 +63,12 @@ pub enum JobError {
 	#[error(transparent)]
 	Validator(#[from] ValidatorError),
 	#[error(transparent)]
+	ContentIndexer(#[from] ContentIndexerError),
+	#[error(transparent)]
 	FileSystemJobsError(#[from] FileSystemJobsError),
 	#[error(transparent)]
+	Archive(#[from] ArchiveError),
+	#[error(transparent)]
 	CryptoError(#[from] CryptoError),
 
 	// Not errors
@@ -3,10 +3,19 @@ use crate::{
 	library::Library,
 	location::indexer::indexer_job::IndexerJobInit,
 	object::{
+		content::content_indexer_job::ContentIndexerJobInit,
 		file_identifier::file_identifier_job::FileIdentifierJobInit,
 		fs::{
-			copy::FileCopierJobInit, cut::FileCutterJobInit, delete::FileDeleterJobInit,
+			archive::{FileArchiverJobInit, FileExtractorJobInit},
+			convert::FileConverterJobInit,
+			copy::FileCopierJobInit,
+			cut::FileCutterJobInit,
+			decrypt::FileDecryptorJobInit,
+			dedup::DeduplicatorJobInit,
+			delete::FileDeleterJobInit,
+			encrypt::FileEncryptorJobInit,
 			erase::FileEraserJobInit,
+			trash::EmptyTrashJobInit,
 		},
 		media::media_processor::MediaProcessorJobInit,
 		validation::validator_job::ObjectValidatorJobInit,
@@ -392,10 +401,18 @@ fn initialize_resumable_job(
 			IndexerJobInit,
 			FileIdentifierJobInit,
 			ObjectValidatorJobInit,
+			ContentIndexerJobInit,
 			FileCutterJobInit,
 			FileCopierJobInit,
 			FileDeleterJobInit,
 			FileEraserJobInit,
+			EmptyTrashJobInit,
+			FileArchiverJobInit,
+			FileExtractorJobInit,
+			FileConverterJobInit,
+			FileEncryptorJobInit,
+			FileDecryptorJobInit,
+			DeduplicatorJobInit,
 		]
 	)
 }
@@ -17,11 +17,13 @@ use uuid::Uuid;
 mod error;
 mod manager;
 mod report;
+mod scheduler;
 mod worker;
 
 pub use error::*;
 pub use manager::*;
 pub use report::*;
+pub use scheduler::*;
 pub use worker::*;
 
 pub type JobResult = Result<JobMetadata, JobError>;
@@ -0,0 +1,567 @@
+use crate::{
+	invalidate_query,
+	library::{update_library_statistics, Library, LibraryManagerError},
+	location::{find_location, location_with_indexer_rules, scan_location, LocationError},
+	object::{media::MediaProcessorJobInit, validation::validator_job::ObjectValidatorJobInit},
+	prisma::{job_schedule, location},
+	Node,
+};
+
+use std::{
+	str::FromStr,
+	sync::{Arc, Weak},
+	time::Duration,
+};
+
+use chrono::{DateTime, Local, Utc};
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{debug, error};
+
+use super::{Job, JobManagerError};
+
+/// How often the scheduler looks for due schedules, schedules can't be due more often than this
+pub const SCHEDULER_TICK: Duration = Duration::from_secs(60);
+/// A run this late wasn't just waiting for the next tick, the node was offline when it was due
+const MISSED_AFTER: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Error, Debug)]
+pub enum SchedulerError {
+	#[error("job schedule not found <id='{0}'>")]
+	NotFound(job_schedule::id::Type),
+	#[error("invalid scheduled job kind: {0}")]
+	InvalidKind(i32),
+	#[error("{0:?} jobs must be scheduled for a location")]
+	MissingLocation(ScheduledJobKind),
+	#[error("{0:?} jobs aren't scheduled for a location")]
+	UnexpectedLocation(ScheduledJobKind),
+	#[error("location <id='{0}'> belongs to another instance, its jobs can't be scheduled here")]
+	ForeignLocation(location::id::Type),
+	#[error("schedules must be at least {}s apart, got {0}s", SCHEDULER_TICK.as_secs())]
+	IntervalTooShort(u32),
+	#[error("invalid cron expression '{expression}': {source}")]
+	InvalidCron {
+		expression: String,
+		source: cron::error::Error,
+	},
+	#[error("cron expression '{0}' never matches again")]
+	NeverDue(String),
+	#[error("job schedule has neither an interval nor a cron expression")]
+	MissingRecurrence,
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error(transparent)]
+	Location(#[from] LocationError),
+	#[error(transparent)]
+	JobManager(#[from] JobManagerError),
+	#[error(transparent)]
+	LibraryManager(#[from] LibraryManagerError),
+}
+
+impl From<SchedulerError> for rspc::Error {
+	fn from(err: SchedulerError) -> Self {
+		match err {
+			SchedulerError::NotFound(_) => {
+				rspc::Error::with_cause(ErrorCode::NotFound, err.to_string(), err)
+			}
+			SchedulerError::MissingLocation(_)
+			| SchedulerError::UnexpectedLocation(_)
+			| SchedulerError::ForeignLocation(_)
+			| SchedulerError::IntervalTooShort(_)
+			| SchedulerError::InvalidCron { .. }
+			| SchedulerError::NeverDue(_) => {
+				rspc::Error::with_cause(ErrorCode::BadRequest, err.to_string(), err)
+			}
+			SchedulerError::Location(e) => e.into(),
+			SchedulerError::JobManager(e) => e.into(),
+			SchedulerError::LibraryManager(e) => e.into(),
+			_ => rspc::Error::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
+		}
+	}
+}
+
+#[repr(i32)]
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduledJobKind {
+	/// Indexes the location again, then identifies and processes what changed
+	Rescan = 0,
+	/// Checks the integrity of the location's files against their checksums
+	ObjectValidator = 1,
+	RegenerateThumbnails = 2,
+	/// Records the library's statistics in their history
+	StatisticsSnapshot = 3,
+}
+
+impl ScheduledJobKind {
+	fn needs_location(self) -> bool {
+		!matches!(self, Self::StatisticsSnapshot)
+	}
+}
+
+impl TryFrom<i32> for ScheduledJobKind {
+	type Error = SchedulerError;
+
+	fn try_from(value: i32) -> Result<Self, Self::Error> {
+		let kind = match value {
+			0 => Self::Rescan,
+			1 => Self::ObjectValidator,
+			2 => Self::RegenerateThumbnails,
+			3 => Self::StatisticsSnapshot,
+			_ => return Err(SchedulerError::InvalidKind(value)),
+		};
+
+		Ok(kind)
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "type", content = "value")]
+pub enum Recurrence {
+	/// Every so many seconds
+	Interval(u32),
+	/// Whenever the expression matches the node's local time. Takes crontab's 5 fields, or 6 to 7
+	/// fields starting with the seconds and ending with the year, or shorthands like `@daily`.
+	Cron(String),
+}
+
+impl Recurrence {
+	fn from_columns(interval: Option<i32>, cron: Option<String>) -> Result<Self, SchedulerError> {
+		match (interval, cron) {
+			(Some(seconds), _) => Ok(Self::Interval(seconds.max(0) as u32)),
+			(None, Some(expression)) => Ok(Self::Cron(expression)),
+			(None, None) => Err(SchedulerError::MissingRecurrence),
+		}
+	}
+
+	fn columns(&self) -> (Option<i32>, Option<String>) {
+		match self {
+			Self::Interval(seconds) => (Some(i32::try_from(*seconds).unwrap_or(i32::MAX)), None),
+			Self::Cron(expression) => (None, Some(expression.clone())),
+		}
+	}
+
+	/// The first time the schedule is due after `after`. Cron expressions are rejected like short
+	/// intervals when their next two matches are closer than a scheduler tick.
+	pub fn next_after(&self, after: DateTime<Utc>) -> Result<DateTime<Utc>, SchedulerError> {
+		match self {
+			Self::Interval(seconds) if u64::from(*seconds) < SCHEDULER_TICK.as_secs() => {
+				Err(SchedulerError::IntervalTooShort(*seconds))
+			}
+			Self::Interval(seconds) => Ok(after + chrono::Duration::seconds(i64::from(*seconds))),
+			Self::Cron(expression) => {
+				let schedule = parse_cron(expression)?;
+				let mut matches = schedule.after(&after.with_timezone(&Local));
+
+				let next = matches
+					.next()
+					.ok_or_else(|| SchedulerError::NeverDue(expression.clone()))?;
+
+				if let Some(following) = matches.next() {
+					let gap = following.signed_duration_since(next);
+					if gap.to_std().unwrap_or_default() < SCHEDULER_TICK {
+						return Err(SchedulerError::IntervalTooShort(
+							gap.num_seconds().max(0) as u32
+						));
+					}
+				}
+
+				Ok(next.with_timezone(&Utc))
+			}
+		}
+	}
+
+	/// The first time the schedule is due after `now`, skipping every time it was due since `since`
+	fn upcoming(
+		&self,
+		since: DateTime<Utc>,
+		now: DateTime<Utc>,
+	) -> Result<DateTime<Utc>, SchedulerError> {
+		match self {
+			Self::Interval(seconds) => {
+				let next = self.next_after(since)?;
+				if next > now {
+					return Ok(next);
+				}
+
+				// Staying in step with the runs that were skipped
+				let seconds = i64::from(*seconds);
+				let skipped = now.signed_duration_since(since).num_seconds() / seconds;
+
+				Ok(since + chrono::Duration::seconds((skipped + 1) * seconds))
+			}
+			Self::Cron(_) => self.next_after(since.max(now)),
+		}
+	}
+}
+
+fn parse_cron(expression: &str) -> Result<cron::Schedule, SchedulerError> {
+	let expression = expression.trim();
+
+	// crontab expressions don't have the leading seconds field
+	let with_seconds = if expression.split_whitespace().count() == 5 {
+		format!("0 {expression}")
+	} else {
+		expression.to_string()
+	};
+
+	cron::Schedule::from_str(&with_seconds).map_err(|source| SchedulerError::InvalidCron {
+		expression: expression.to_string(),
+		source,
+	})
+}
+
+fn is_missed(due: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+	now.signed_duration_since(due).to_std().unwrap_or_default() > MISSED_AFTER
+}
+
+#[derive(Serialize, Type, Debug, Clone)]
+pub struct JobSchedule {
+	pub id: job_schedule::id::Type,
+	pub kind: ScheduledJobKind,
+	pub location_id: Option<location::id::Type>,
+	pub recurrence: Recurrence,
+	pub enabled: bool,
+	/// Whether a run missed while the node was offline is made up for once it's back
+	pub catch_up: bool,
+	pub last_run: Option<DateTime<Utc>>,
+	pub next_run: DateTime<Utc>,
+}
+
+impl TryFrom<job_schedule::Data> for JobSchedule {
+	type Error = SchedulerError;
+
+	fn try_from(data: job_schedule::Data) -> Result<Self, Self::Error> {
+		Ok(Self {
+			id: data.id,
+			kind: data.kind.try_into()?,
+			location_id: data.location_id,
+			recurrence: Recurrence::from_columns(data.interval, data.cron)?,
+			enabled: data.enabled,
+			catch_up: data.catch_up,
+			last_run: data.last_run.map(Into::into),
+			next_run: data.next_run.into(),
+		})
+	}
+}
+
+impl JobSchedule {
+	async fn run(&self, node: &Arc<Node>, library: &Arc<Library>) -> Result<(), SchedulerError> {
+		let location_id = self
+			.location_id
+			.ok_or(SchedulerError::MissingLocation(self.kind));
+
+		match self.kind {
+			ScheduledJobKind::Rescan => {
+				let location_id = location_id?;
+
+				scan_location(
+					node,
+					library,
+					find_location(library, location_id)
+						.include(location_with_indexer_rules::include())
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?,
+				)
+				.await?;
+			}
+			ScheduledJobKind::ObjectValidator => {
+				let location_id = location_id?;
+
+				Job::new(ObjectValidatorJobInit {
+					location: find_location(library, location_id)
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?,
+					sub_path: None,
+				})
+				.spawn(node, library)
+				.await?;
+			}
+			ScheduledJobKind::RegenerateThumbnails => {
+				let location_id = location_id?;
+
+				Job::new(MediaProcessorJobInit {
+					location: find_location(library, location_id)
+						.exec()
+						.await?
+						.ok_or(LocationError::IdNotFound(location_id))?,
+					sub_path: None,
+					regenerate_thumbnails: true,
+				})
+				.spawn(node, library)
+				.await?;
+			}
+			ScheduledJobKind::StatisticsSnapshot => {
+				update_library_statistics(node, library).await?;
+
+				invalidate_query!(library, "library.statisticsHistory");
+				invalidate_query!(library, "library.statisticsBreakdown");
+			}
+		}
+
+		Ok(())
+	}
+}
+
+#[derive(Deserialize, Type, Debug)]
+pub struct JobScheduleCreateArgs {
+	pub kind: ScheduledJobKind,
+	pub location_id: Option<location::id::Type>,
+	pub recurrence: Recurrence,
+	pub catch_up: bool,
+}
+
+impl JobScheduleCreateArgs {
+	pub async fn create(self, library: &Library) -> Result<JobSchedule, SchedulerError> {
+		let mut params = vec![job_schedule::catch_up::set(self.catch_up)];
+
+		match (self.kind.needs_location(), self.location_id) {
+			(true, None) => return Err(SchedulerError::MissingLocation(self.kind)),
+			(false, Some(_)) => return Err(SchedulerError::UnexpectedLocation(self.kind)),
+			(false, None) => {}
+			(true, Some(location_id)) => {
+				let location = find_location(library, location_id)
+					.select(location::select!({ instance_id }))
+					.exec()
+					.await?
+					.ok_or(LocationError::IdNotFound(location_id))?;
+
+				// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
+				if location.instance_id != Some(library.config().instance_id) {
+					return Err(SchedulerError::ForeignLocation(location_id));
+				}
+
+				params.push(job_schedule::location::connect(location::id::equals(
+					location_id,
+				)));
+			}
+		}
+
+		let next_run = self.recurrence.next_after(Utc::now())?;
+		let (interval, cron) = self.recurrence.columns();
+		params.extend([
+			job_schedule::interval::set(interval),
+			job_schedule::cron::set(cron),
+		]);
+
+		library
+			.db
+			.job_schedule()
+			.create(self.kind as i32, next_run.into(), params)
+			.exec()
+			.await?
+			.try_into()
+	}
+}
+
+#[derive(Deserialize, Type, Debug)]
+pub struct JobScheduleUpdateArgs {
+	pub id: job_schedule::id::Type,
+	pub recurrence: Option<Recurrence>,
+	pub enabled: Option<bool>,
+	pub catch_up: Option<bool>,
+}
+
+impl JobScheduleUpdateArgs {
+	/// Changing the recurrence or enabling the schedule again sets its next run from now, so
+	/// the time it was disabled isn't caught up on
+	pub async fn update(self, library: &Library) -> Result<JobSchedule, SchedulerError> {
+		let schedule = library
+			.db
+			.job_schedule()
+			.find_unique(job_schedule::id::equals(self.id))
+			.exec()
+			.await?
+			.ok_or(SchedulerError::NotFound(self.id))?;
+
+		let mut params = vec![];
+
+		let reschedule =
+			self.recurrence.is_some() || (self.enabled == Some(true) && !schedule.enabled);
+
+		let recurrence = match self.recurrence {
+			Some(recurrence) => {
+				let (interval, cron) = recurrence.columns();
+				params.extend([
+					job_schedule::interval::set(interval),
+					job_schedule::cron::set(cron),
+				]);
+
+				recurrence
+			}
+			None => Recurrence::from_columns(schedule.interval, schedule.cron)?,
+		};
+
+		if reschedule {
+			params.push(job_schedule::next_run::set(
+				recurrence.next_after(Utc::now())?.into(),
+			));
+		}
+
+		if let Some(enabled) = self.enabled {
+			params.push(job_schedule::enabled::set(enabled));
+		}
+
+		if let Some(catch_up) = self.catch_up {
+			params.push(job_schedule::catch_up::set(catch_up));
+		}
+
+		library
+			.db
+			.job_schedule()
+			.update(job_schedule::id::equals(self.id), params)
+			.exec()
+			.await?
+			.try_into()
+	}
+}
+
+/// Runs the due schedules of a library for as long as it's loaded. The first look happens right
+/// away, making up for what was missed while the node was offline, once per schedule.
+pub(crate) fn spawn_job_scheduler(node: Arc<Node>, library: Weak<Library>) {
+	tokio::spawn(async move {
+		let mut tick = interval(SCHEDULER_TICK);
+		tick.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			tick.tick().await;
+
+			let Some(library) = library.upgrade() else {
+				break;
+			};
+
+			if let Err(e) = run_due_schedules(&node, &library).await {
+				error!(
+					"Failed to run the job schedules of library <id='{}'>: {e:#?}",
+					library.id
+				);
+			}
+		}
+	});
+}
+
+async fn run_due_schedules(node: &Arc<Node>, library: &Arc<Library>) -> Result<(), SchedulerError> {
+	let now = Utc::now();
+
+	let due = library
+		.db
+		.job_schedule()
+		.find_many(vec![
+			job_schedule::enabled::equals(true),
+			job_schedule::next_run::lte(now.into()),
+		])
+		.exec()
+		.await?;
+
+	if due.is_empty() {
+		return Ok(());
+	}
+
+	for data in due {
+		let id = data.id;
+		let schedule = match JobSchedule::try_from(data) {
+			Ok(schedule) => schedule,
+			Err(e) => {
+				error!("Skipping invalid job schedule <id='{id}'>: {e:#?}");
+				continue;
+			}
+		};
+
+		let mut params = vec![];
+
+		if is_missed(schedule.next_run, now) && !schedule.catch_up {
+			debug!("Skipping the run of job schedule <id='{id}'> missed while offline");
+		} else {
+			// Failed runs aren't retried every tick, they wait for the next time they're due
+			if let Err(e) = schedule.run(node, library).await {
+				error!("Failed to run job schedule <id='{id}'>: {e:#?}");
+			}
+
+			params.push(job_schedule::last_run::set(Some(now.into())));
+		}
+
+		match schedule.recurrence.upcoming(schedule.next_run, now) {
+			Ok(next_run) => params.push(job_schedule::next_run::set(next_run.into())),
+			Err(e) => {
+				error!("Disabling job schedule <id='{id}'> as it can't be due again: {e:#?}");
+				params.push(job_schedule::enabled::set(false));
+			}
+		}
+
+		library
+			.db
+			.job_schedule()
+			.update(job_schedule::id::equals(id), params)
+			.exec()
+			.await?;
+	}
+
+	invalidate_query!(library, "jobs.schedules.list");
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use chrono::{TimeZone, Timelike};
+
+	#[test]
+	fn missed_runs_are_skipped_over() {
+		let since = Utc.with_ymd_and_hms(2023, 10, 20, 12, 0, 0).unwrap();
+		let now = since + chrono::Duration::minutes(330);
+
+		assert_eq!(
+			Recurrence::Interval(60 * 60).upcoming(since, now).unwrap(),
+			since + chrono::Duration::hours(6)
+		);
+		assert!(!is_missed(since, since + chrono::Duration::seconds(59)));
+		assert!(is_missed(since, now));
+	}
+
+	#[test]
+	fn crontab_expressions_are_accepted() {
+		let after = Utc.with_ymd_and_hms(2023, 10, 20, 12, 0, 0).unwrap();
+
+		let next = Recurrence::Cron("30 3 * * *".to_string())
+			.next_after(after)
+			.unwrap()
+			.with_timezone(&Local);
+		assert_eq!((next.hour(), next.minute(), next.second()), (3, 30, 0));
+
+		assert!(Recurrence::Cron("@weekly".to_string())
+			.next_after(after)
+			.is_ok());
+		assert!(matches!(
+			Recurrence::Cron("61 * * * *".to_string()).next_after(after),
+			Err(SchedulerError::InvalidCron { .. })
+		));
+		assert!(matches!(
+			Recurrence::Interval(10).next_after(after),
+			Err(SchedulerError::IntervalTooShort(10))
+		));
+		assert!(matches!(
+			Recurrence::Cron("*/10 * * * * *".to_string()).next_after(after),
+			Err(SchedulerError::IntervalTooShort(10))
+		));
+	}
+
+	#[test]
+	fn missed_cron_runs_are_skipped_over() {
+		let since = Utc.with_ymd_and_hms(2023, 10, 20, 12, 0, 0).unwrap();
+		let now = since + chrono::Duration::minutes(330);
+
+		let next = Recurrence::Cron("*/15 * * * *".to_string())
+			.upcoming(since, now)
+			.unwrap();
+		assert!(next > now);
+		assert!(next <= now + chrono::Duration::minutes(15));
+		assert_eq!(next.with_timezone(&Local).minute() % 15, 0);
+	}
+}
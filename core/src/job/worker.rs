@@ -54,6 +54,8 @@ pub enum WorkerCommand {
 pub struct WorkerContext {
 	pub library: Arc<Library>,
 	pub node: Arc<Node>,
+	/// The id the job manager knows the running job by, children jobs run under their parent's
+	pub worker_id: Uuid,
 	pub(super) events_tx: mpsc::UnboundedSender<WorkerEvent>,
 }
 
@@ -311,6 +313,7 @@ impl Worker {
 			WorkerContext {
 				library: library.clone(),
 				node: node.clone(),
+				worker_id,
 				events_tx,
 			},
 			commands_rx,
@@ -1,5 +1,5 @@
-use crate::prisma::object;
-use prisma_client_rust::not;
+use crate::prisma::{album, file_path, object, object_in_album};
+use prisma_client_rust::{and, not, or};
 use sd_file_ext::kind::ObjectKind;
 use serde::{Deserialize, Serialize};
 use specta::Type;
@@ -60,9 +60,14 @@ impl Category {
 	}
 
 	pub fn to_where_param(self) -> object::WhereParam {
-		match self {
+		let param = match self {
 			Category::Recents => not![object::date_accessed::equals(None)],
 			Category::Favorites => object::favorite::equals(Some(true)),
+			// Objects in any album that isn't hidden
+			Category::Albums => object::albums::some(vec![object_in_album::album::is(vec![or![
+				album::is_hidden::equals(None),
+				album::is_hidden::not(Some(true))
+			]])]),
 			Category::Photos
 			| Category::Videos
 			| Category::Music
@@ -71,7 +76,16 @@ impl Category {
 			| Category::Archives
 			| Category::Applications
 			| Category::Books => object::kind::equals(Some(self.to_object_kind() as i32)),
+			Category::Trash => {
+				return object::file_paths::some(vec![file_path::trashed_at::not(None)])
+			}
 			_ => object::id::equals(-1),
-		}
+		};
+
+		// Objects only left in the trash belong to it alone
+		and![
+			param,
+			object::file_paths::some(vec![file_path::trashed_at::equals(None)])
+		]
 	}
 }
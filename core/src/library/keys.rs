@@ -0,0 +1,249 @@
+use crate::prisma::{key, PrismaClient};
+
+use sd_crypto::{
+	keys::keymanager::StoredKey,
+	types::{EncryptedKey, Nonce, Salt},
+};
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use std::str::FromStr;
+
+use prisma_client_rust::QueryError;
+use serde_json::json;
+use thiserror::Error;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::Library;
+
+#[derive(Error, Debug)]
+pub enum KeyStoreError {
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error("failed to serialize stored key: {0}")]
+	Serialization(#[from] serde_json::Error),
+}
+
+impl From<KeyStoreError> for rspc::Error {
+	fn from(error: KeyStoreError) -> Self {
+		rspc::Error::with_cause(
+			rspc::ErrorCode::InternalServerError,
+			error.to_string(),
+			error,
+		)
+	}
+}
+
+/// The columns of a [`key`] row, everything but the bytes being stored as JSON
+#[derive(Debug)]
+struct KeyColumns {
+	version: String,
+	key_type: String,
+	algorithm: String,
+	hashing_algorithm: String,
+	content_salt: Vec<u8>,
+	master_key: Vec<u8>,
+	master_key_nonce: Vec<u8>,
+	key_nonce: Vec<u8>,
+	key: Vec<u8>,
+	salt: Vec<u8>,
+}
+
+impl KeyColumns {
+	fn from_stored_key(stored_key: &StoredKey) -> serde_json::Result<Self> {
+		Ok(Self {
+			version: serde_json::to_string(&stored_key.version)?,
+			key_type: serde_json::to_string(&stored_key.key_type)?,
+			algorithm: serde_json::to_string(&stored_key.algorithm)?,
+			hashing_algorithm: serde_json::to_string(&stored_key.hashing_algorithm)?,
+			content_salt: stored_key.content_salt.to_vec(),
+			master_key: stored_key.master_key.to_vec(),
+			master_key_nonce: stored_key.master_key_nonce.as_ref().to_vec(),
+			key_nonce: stored_key.key_nonce.as_ref().to_vec(),
+			key: stored_key.key.clone(),
+			salt: stored_key.salt.to_vec(),
+		})
+	}
+
+	/// Columns are synced one by one, so a key created on another instance
+	/// may be incomplete for a little while.
+	fn from_data(data: key::Data) -> Option<Self> {
+		Some(Self {
+			version: data.version?,
+			key_type: data.key_type?,
+			algorithm: data.algorithm?,
+			hashing_algorithm: data.hashing_algorithm?,
+			content_salt: data.content_salt?,
+			master_key: data.master_key?,
+			master_key_nonce: data.master_key_nonce?,
+			key_nonce: data.key_nonce?,
+			key: data.key?,
+			salt: data.salt?,
+		})
+	}
+
+	/// `None` if any of the columns is malformed
+	fn into_stored_key(self, uuid: Uuid, automount: bool) -> Option<StoredKey> {
+		Some(StoredKey {
+			uuid,
+			version: serde_json::from_str(&self.version).ok()?,
+			key_type: serde_json::from_str(&self.key_type).ok()?,
+			algorithm: serde_json::from_str(&self.algorithm).ok()?,
+			hashing_algorithm: serde_json::from_str(&self.hashing_algorithm).ok()?,
+			content_salt: Salt::try_from(self.content_salt).ok()?,
+			master_key: EncryptedKey::try_from(self.master_key).ok()?,
+			master_key_nonce: Nonce::try_from(self.master_key_nonce).ok()?,
+			key_nonce: Nonce::try_from(self.key_nonce).ok()?,
+			key: self.key,
+			salt: Salt::try_from(self.salt).ok()?,
+			// Only keys synced with the library are ever written to the database
+			memory_only: false,
+			automount,
+		})
+	}
+}
+
+/// Loads every stored key of the library, to populate its key manager with.
+///
+/// Rows that are incomplete or malformed are skipped.
+pub async fn load_stored_keys(db: &PrismaClient) -> Result<Vec<StoredKey>, QueryError> {
+	Ok(db
+		.key()
+		.find_many(vec![])
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|data| {
+			let stored_key = Uuid::from_str(&data.uuid).ok().and_then(|uuid| {
+				let automount = data.automount;
+				KeyColumns::from_data(data)
+					.and_then(|columns| columns.into_stored_key(uuid, automount))
+			});
+
+			if stored_key.is_none() {
+				warn!("Skipping incomplete or malformed stored key");
+			}
+
+			stored_key
+		})
+		.collect())
+}
+
+/// Writes a stored key to the library's database, so it's synced to other instances
+pub async fn write_stored_key(
+	library: &Library,
+	stored_key: &StoredKey,
+) -> Result<(), KeyStoreError> {
+	let Library { db, sync, .. } = library;
+
+	let uuid = stored_key.uuid.to_string();
+	let KeyColumns {
+		version,
+		key_type,
+		algorithm,
+		hashing_algorithm,
+		content_salt,
+		master_key,
+		master_key_nonce,
+		key_nonce,
+		key,
+		salt,
+	} = KeyColumns::from_stored_key(stored_key)?;
+
+	sync.write_ops(
+		db,
+		(
+			sync.shared_create(
+				prisma_sync::key::SyncId { uuid: uuid.clone() },
+				[
+					(key::version::NAME, json!(&version)),
+					(key::key_type::NAME, json!(&key_type)),
+					(key::algorithm::NAME, json!(&algorithm)),
+					(key::hashing_algorithm::NAME, json!(&hashing_algorithm)),
+					(key::content_salt::NAME, json!(&content_salt)),
+					(key::master_key::NAME, json!(&master_key)),
+					(key::master_key_nonce::NAME, json!(&master_key_nonce)),
+					(key::key_nonce::NAME, json!(&key_nonce)),
+					(key::key::NAME, json!(&key)),
+					(key::salt::NAME, json!(&salt)),
+					(key::automount::NAME, json!(stored_key.automount)),
+				],
+			),
+			db.key().create(
+				uuid,
+				vec![
+					key::version::set(Some(version)),
+					key::key_type::set(Some(key_type)),
+					key::algorithm::set(Some(algorithm)),
+					key::hashing_algorithm::set(Some(hashing_algorithm)),
+					key::content_salt::set(Some(content_salt)),
+					key::master_key::set(Some(master_key)),
+					key::master_key_nonce::set(Some(master_key_nonce)),
+					key::key_nonce::set(Some(key_nonce)),
+					key::key::set(Some(key)),
+					key::salt::set(Some(salt)),
+					key::automount::set(stored_key.automount),
+				],
+			),
+		),
+	)
+	.await?;
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use sd_crypto::{
+		keys::keymanager::{StoredKeyType, StoredKeyVersion},
+		primitives::ENCRYPTED_KEY_LEN,
+		types::{Algorithm, HashingAlgorithm, Params},
+	};
+
+	fn stored_key(algorithm: Algorithm, key_type: StoredKeyType) -> StoredKey {
+		StoredKey {
+			uuid: Uuid::new_v4(),
+			version: StoredKeyVersion::V1,
+			key_type,
+			algorithm,
+			hashing_algorithm: HashingAlgorithm::Argon2id(Params::Standard),
+			content_salt: Salt::generate(),
+			master_key: EncryptedKey::try_from(vec![7; ENCRYPTED_KEY_LEN]).unwrap(),
+			master_key_nonce: Nonce::generate(algorithm).unwrap(),
+			key_nonce: Nonce::generate(algorithm).unwrap(),
+			key: vec![1, 2, 3, 4],
+			salt: Salt::generate(),
+			memory_only: false,
+			automount: true,
+		}
+	}
+
+	#[test]
+	fn stored_key_columns_round_trip() {
+		for (algorithm, key_type) in [
+			(Algorithm::XChaCha20Poly1305, StoredKeyType::User),
+			(Algorithm::Aes256Gcm, StoredKeyType::Root),
+		] {
+			let stored_key = stored_key(algorithm, key_type);
+			let columns = KeyColumns::from_stored_key(&stored_key).unwrap();
+
+			assert!(
+				columns.into_stored_key(stored_key.uuid, true).unwrap() == stored_key,
+				"stored key changed after a round trip through its columns"
+			);
+		}
+	}
+
+	#[test]
+	fn malformed_columns_are_skipped() {
+		let stored_key = stored_key(Algorithm::XChaCha20Poly1305, StoredKeyType::User);
+
+		let mut columns = KeyColumns::from_stored_key(&stored_key).unwrap();
+		columns.master_key_nonce.pop();
+
+		assert!(columns.into_stored_key(stored_key.uuid, true).is_none());
+	}
+}
@@ -5,7 +5,10 @@ use crate::{
 	},
 	location::file_path_helper::{file_path_to_full_path, IsolatedFilePathData},
 	notifications,
-	object::{media::thumbnail::get_thumbnail_path, orphan_remover::OrphanRemoverActor},
+	object::{
+		fs::conflict::FileConflicts, media::thumbnail::get_thumbnail_path,
+		orphan_remover::OrphanRemoverActor,
+	},
 	prisma::{file_path, location, PrismaClient},
 	sync,
 	util::{db::maybe_missing, error::FileIOError},
@@ -20,6 +23,7 @@ use std::{
 };
 
 use chrono::{DateTime, Utc};
+use sd_crypto::keys::keymanager::KeyManager;
 use sd_p2p::spacetunnel::Identity;
 use sd_prisma::prisma::notification;
 use tokio::{fs, io, sync::broadcast};
@@ -46,10 +50,12 @@ pub struct Library {
 	pub db: Arc<PrismaClient>,
 	pub sync: Arc<sync::Manager>,
 	/// key manager that provides encryption keys to functions that require them
-	// pub key_manager: Arc<KeyManager>,
+	pub key_manager: Arc<KeyManager>,
 	/// p2p identity
 	pub identity: Arc<Identity>,
 	pub orphan_remover: OrphanRemoverActor,
+	/// Conflicts the fs jobs of this library are waiting on the client to settle
+	pub file_conflicts: FileConflicts,
 	// The UUID which matches `config.instance_id`'s primary key.
 	pub instance_uuid: Uuid,
 
@@ -82,15 +88,17 @@ impl Library {
 		db: Arc<PrismaClient>,
 		node: &Arc<Node>,
 		sync: Arc<sync::Manager>,
+		key_manager: Arc<KeyManager>,
 	) -> Arc<Self> {
 		Arc::new(Self {
 			id,
 			config: RwLock::new(config),
 			sync,
 			db: db.clone(),
-			// key_manager,
+			key_manager,
 			identity,
 			orphan_remover: OrphanRemoverActor::spawn(db),
+			file_conflicts: FileConflicts::default(),
 			notifications: node.notifications.clone(),
 			instance_uuid,
 			event_bus_tx: node.event_bus.0.clone(),
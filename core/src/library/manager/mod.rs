@@ -1,6 +1,7 @@
 use crate::{
 	api::{utils::InvalidateOperationEvent, CoreEvent},
 	invalidate_query,
+	job::spawn_job_scheduler,
 	location::indexer,
 	node::Platform,
 	object::tag,
@@ -13,6 +14,7 @@ use crate::{
 		migrator::Migrate,
 		mpscrr, MaybeUndefined,
 	},
+	volume::spawn_volume_tracker,
 	Node,
 };
 
@@ -25,13 +27,14 @@ use std::{
 
 use chrono::Utc;
 use sd_core_sync::SyncMessage;
+use sd_crypto::keys::keymanager::KeyManager;
 use sd_p2p::spacetunnel::Identity;
 use sd_prisma::prisma::instance;
 use tokio::{fs, io, sync::RwLock, try_join};
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use super::{Library, LibraryConfig, LibraryName};
+use super::{load_stored_keys, Library, LibraryConfig, LibraryName};
 
 mod error;
 
@@ -382,8 +385,7 @@ impl Libraries {
 
 		// TODO: Move this reconciliation into P2P and do reconciliation of both local and remote nodes.
 
-		// let key_manager = Arc::new(KeyManager::new(vec![]).await?);
-		// seed_keymanager(&db, &key_manager).await?;
+		let key_manager = Arc::new(KeyManager::new(load_stored_keys(&db).await?).await?);
 
 		let mut sync = sync::Manager::new(&db, instance_id, &self.emit_messages_flag);
 
@@ -392,10 +394,10 @@ impl Libraries {
 			config,
 			instance_id,
 			identity,
-			// key_manager,
 			db,
 			node,
 			Arc::new(sync.manager),
+			key_manager,
 		)
 		.await;
 
@@ -416,13 +418,22 @@ impl Libraries {
 							InvalidateOperationEvent::all(),
 						)),
 						SyncMessage::Created => {
-							p2p::sync::originator(id, &library.sync, &node.nlm, &node.p2p).await
+							p2p::sync::originator(
+								id,
+								&library.sync,
+								&library.identity,
+								&node.nlm,
+								&node.p2p,
+							)
+							.await
 						}
 					}
 				}
 			}
 		});
 
+		spawn_volume_tracker(node.clone(), Arc::downgrade(&library));
+
 		self.tx
 			.emit(LibraryManagerEvent::Load(library.clone()))
 			.await;
@@ -456,6 +467,9 @@ impl Libraries {
 			error!("Failed to resume jobs for library. {:#?}", e);
 		}
 
+		// After resuming, so jobs interrupted by the shutdown run before the ones catching up
+		spawn_job_scheduler(node.clone(), Arc::downgrade(&library));
+
 		Ok(library)
 	}
 
@@ -1,12 +1,16 @@
 pub(crate) mod cat;
 mod config;
+mod keys;
 #[allow(clippy::module_inception)]
 mod library;
 mod manager;
 mod name;
+mod statistics;
 
 pub use cat::*;
 pub use config::*;
+pub use keys::*;
 pub use library::*;
 pub use manager::*;
 pub use name::*;
+pub use statistics::*;
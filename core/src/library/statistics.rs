@@ -0,0 +1,365 @@
+use crate::{
+	api::utils::get_size,
+	prisma::{file_path, location, object, statistics, PrismaClient, SortOrder},
+	volume::{get_volumes, volume_of, Volume},
+	Node,
+};
+
+use sd_file_ext::kind::ObjectKind;
+
+use std::{
+	collections::{BTreeMap, HashMap, HashSet},
+	path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use prisma_client_rust::raw;
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use specta::Type;
+
+use super::{Library, LibraryManagerError};
+
+const FILE_PATHS_BATCH_SIZE: i64 = 10_000;
+
+statistics::select!(statistics_summary {
+	id
+	date_captured
+	total_object_count
+	library_db_size
+	total_bytes_used
+	total_bytes_capacity
+	total_unique_bytes
+	total_bytes_free
+	preview_media_bytes
+});
+
+/// The statistics of a library as they are right now, their history is recorded by
+/// [`update_library_statistics`]
+#[serde_as]
+#[derive(Serialize, Type, Debug, Clone)]
+pub struct LibraryStatistics {
+	pub total_object_count: i32,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub library_db_size: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_bytes_used: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_bytes_capacity: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_unique_bytes: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_bytes_free: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub preview_media_bytes: u64,
+}
+
+/// Size of a group of files, where `unique_bytes` counts each object only once and
+/// `duplicated_bytes` are taken by the extra copies
+#[serde_as]
+#[derive(Serialize, Deserialize, Type, Debug, Default, Clone)]
+pub struct StorageStatistics {
+	pub files: u32,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_bytes: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub unique_bytes: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub duplicated_bytes: u64,
+}
+
+impl StorageStatistics {
+	fn add(&mut self, size: u64, is_first_copy: bool) {
+		self.files += 1;
+		self.total_bytes += size;
+		if is_first_copy {
+			self.unique_bytes += size;
+		} else {
+			self.duplicated_bytes += size;
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct LocationStatistics {
+	pub location_id: location::id::Type,
+	pub name: Option<String>,
+	pub storage: StorageStatistics,
+}
+
+#[serde_as]
+#[derive(Serialize, Deserialize, Type, Debug, Clone)]
+pub struct VolumeStatistics {
+	pub name: String,
+	pub mount_point: PathBuf,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub total_capacity: u64,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub available_capacity: u64,
+	pub storage: StorageStatistics,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Default, Clone)]
+pub struct StatisticsBreakdown {
+	/// Keyed by `ObjectKind`, files that weren't identified yet count as `Unknown`
+	pub kinds: BTreeMap<i32, StorageStatistics>,
+	pub locations: Vec<LocationStatistics>,
+	/// Only the volumes holding a location of this library
+	pub volumes: Vec<VolumeStatistics>,
+}
+
+/// Walks every file of the library to break their sizes down by kind, location and volume
+async fn compute_breakdown(
+	db: &PrismaClient,
+	volumes: &[Volume],
+) -> Result<StatisticsBreakdown, LibraryManagerError> {
+	let locations = db
+		.location()
+		.find_many(vec![])
+		.select(location::select!({ id name path }))
+		.exec()
+		.await?;
+
+	let mut location_volumes = HashMap::with_capacity(locations.len());
+	let mut used_volumes = BTreeMap::new();
+	for location in &locations {
+		if let Some((idx, mount_point)) = location
+			.path
+			.as_ref()
+			.and_then(|path| volume_of(Path::new(path), volumes))
+		{
+			location_volumes.insert(location.id, idx);
+			used_volumes.entry(idx).or_insert(mount_point);
+		}
+	}
+
+	let objects_kinds = db
+		.object()
+		.find_many(vec![])
+		.select(object::select!({ id kind }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|object| (object.id, object.kind.unwrap_or(ObjectKind::Unknown as i32)))
+		.collect::<HashMap<_, _>>();
+
+	let mut kinds = BTreeMap::<i32, StorageStatistics>::new();
+	let mut by_location = HashMap::<location::id::Type, StorageStatistics>::new();
+	let mut by_volume = HashMap::<usize, StorageStatistics>::new();
+
+	let mut seen_objects = HashSet::new();
+	let mut seen_in_location = HashSet::new();
+	let mut seen_in_volume = HashSet::new();
+
+	let mut last_id = 0;
+	loop {
+		let file_paths = db
+			.file_path()
+			.find_many(vec![
+				file_path::id::gt(last_id),
+				file_path::is_dir::equals(Some(false)),
+			])
+			.order_by(file_path::id::order(SortOrder::Asc))
+			.take(FILE_PATHS_BATCH_SIZE)
+			.select(file_path::select!({ id location_id object_id size_in_bytes_bytes }))
+			.exec()
+			.await?;
+
+		let Some(last) = file_paths.last() else {
+			break;
+		};
+		last_id = last.id;
+		let fetched = file_paths.len();
+
+		for file_path in file_paths {
+			let size = file_path
+				.size_in_bytes_bytes
+				.as_deref()
+				.and_then(|bytes| bytes.try_into().ok())
+				.map(u64::from_be_bytes)
+				.unwrap_or(0);
+
+			// Files without an object can't be told apart from their copies, so they're all unique
+			let (kind, is_first_copy) = match file_path.object_id {
+				Some(object_id) => (
+					objects_kinds
+						.get(&object_id)
+						.copied()
+						.unwrap_or(ObjectKind::Unknown as i32),
+					seen_objects.insert(object_id),
+				),
+				None => (ObjectKind::Unknown as i32, true),
+			};
+
+			kinds.entry(kind).or_default().add(size, is_first_copy);
+
+			if let Some(location_id) = file_path.location_id {
+				let is_first_copy = file_path.object_id.map_or(true, |object_id| {
+					seen_in_location.insert((location_id, object_id))
+				});
+				by_location
+					.entry(location_id)
+					.or_default()
+					.add(size, is_first_copy);
+
+				if let Some(&idx) = location_volumes.get(&location_id) {
+					let is_first_copy = file_path
+						.object_id
+						.map_or(true, |object_id| seen_in_volume.insert((idx, object_id)));
+					by_volume.entry(idx).or_default().add(size, is_first_copy);
+				}
+			}
+		}
+
+		if fetched < FILE_PATHS_BATCH_SIZE as usize {
+			break;
+		}
+	}
+
+	Ok(StatisticsBreakdown {
+		kinds,
+		locations: locations
+			.into_iter()
+			.map(|location| LocationStatistics {
+				storage: by_location.remove(&location.id).unwrap_or_default(),
+				location_id: location.id,
+				name: location.name,
+			})
+			.collect(),
+		volumes: used_volumes
+			.into_iter()
+			.map(|(idx, mount_point)| VolumeStatistics {
+				name: volumes[idx].name.clone(),
+				mount_point,
+				total_capacity: volumes[idx].total_capacity,
+				available_capacity: volumes[idx].available_capacity,
+				storage: by_volume.remove(&idx).unwrap_or_default(),
+			})
+			.collect(),
+	})
+}
+
+async fn compute_statistics(
+	node: &Node,
+	library: &Library,
+	volumes: &[Volume],
+) -> Result<LibraryStatistics, LibraryManagerError> {
+	#[derive(Deserialize)]
+	struct Sizes {
+		total_bytes: i64,
+		unique_bytes: i64,
+	}
+
+	let (total_bytes_capacity, total_bytes_free) =
+		volumes
+			.iter()
+			.fold((0u64, 0u64), |(total, available), volume| {
+				(
+					total + volume.total_capacity,
+					available + volume.available_capacity,
+				)
+			});
+
+	let library_db_size = get_size(
+		node.config
+			.data_directory()
+			.join("libraries")
+			.join(&format!("{}.db", library.id)),
+	)
+	.await
+	.unwrap_or(0);
+
+	let preview_media_bytes = get_size(node.config.data_directory().join("thumbnails"))
+		.await
+		.unwrap_or(0);
+
+	let total_object_count = library.db.object().count(vec![]).exec().await?;
+
+	// Copies of an object only count once towards the unique bytes,
+	// files without an object can't be told apart from their copies so they're all unique
+	let sizes = library
+		.db
+		._query_raw::<Sizes>(raw!(
+			"SELECT COALESCE(SUM(total), 0) AS total_bytes, COALESCE(SUM(size), 0) AS unique_bytes
+			FROM (
+				SELECT SUM(size_in_bytes_int) AS total, MAX(size_in_bytes_int) AS size
+				FROM file_path
+				WHERE is_dir = 0
+				GROUP BY COALESCE(object_id, -id)
+			)"
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.next();
+
+	Ok(LibraryStatistics {
+		total_object_count: total_object_count as i32,
+		library_db_size,
+		total_bytes_used: sizes
+			.as_ref()
+			.map_or(0, |sizes| sizes.total_bytes.max(0) as u64),
+		total_bytes_capacity,
+		total_unique_bytes: sizes.map_or(0, |sizes| sizes.unique_bytes.max(0) as u64),
+		total_bytes_free,
+		preview_media_bytes,
+	})
+}
+
+/// Computes the statistics of a library, these aren't recorded
+pub async fn library_statistics(
+	node: &Node,
+	library: &Library,
+) -> Result<LibraryStatistics, LibraryManagerError> {
+	compute_statistics(node, library, &get_volumes().await).await
+}
+
+/// Records the statistics of a library and their breakdown in its history.
+///
+/// Snapshots are taken by the `StatisticsSnapshot` job schedule, which every library starts with.
+pub async fn update_library_statistics(
+	node: &Node,
+	library: &Library,
+) -> Result<statistics::Data, LibraryManagerError> {
+	let volumes = get_volumes().await;
+
+	let LibraryStatistics {
+		total_object_count,
+		library_db_size,
+		total_bytes_used,
+		total_bytes_capacity,
+		total_unique_bytes,
+		total_bytes_free,
+		preview_media_bytes,
+	} = compute_statistics(node, library, &volumes).await?;
+	let breakdown = compute_breakdown(&library.db, &volumes).await?;
+
+	use statistics::*;
+	Ok(library
+		.db
+		.statistics()
+		.create(vec![
+			date_captured::set(Utc::now().into()),
+			total_object_count::set(total_object_count),
+			library_db_size::set(library_db_size.to_string()),
+			total_bytes_used::set(total_bytes_used.to_string()),
+			total_bytes_capacity::set(total_bytes_capacity.to_string()),
+			total_unique_bytes::set(total_unique_bytes.to_string()),
+			total_bytes_free::set(total_bytes_free.to_string()),
+			preview_media_bytes::set(preview_media_bytes.to_string()),
+			breakdown::set(Some(serde_json::to_vec(&breakdown)?)),
+		])
+		.exec()
+		.await?)
+}
@@ -1,4 +1,5 @@
 use crate::{
+	job::JobManagerError,
 	prisma::location,
 	util::{db::MissingFieldError, error::FileIOError},
 };
@@ -66,6 +67,8 @@ pub enum LocationError {
 	#[error(transparent)]
 	LocationManager(#[from] LocationManagerError),
 	#[error(transparent)]
+	JobManager(#[from] JobManagerError),
+	#[error(transparent)]
 	FilePath(#[from] FilePathError),
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
@@ -45,6 +45,15 @@ file_path::select!(file_path_for_object_validator {
 	extension
 	integrity_checksum
 });
+file_path::select!(file_path_for_content_indexer {
+	id
+	materialized_path
+	is_dir
+	name
+	extension
+	date_modified
+	content: select { date_indexed }
+});
 file_path::select!(file_path_for_media_processor {
 	id
 	materialized_path
@@ -86,6 +95,7 @@ file_path::select!(file_path_to_handle_custom_uri {
 	is_dir
 	name
 	extension
+	size_in_bytes_bytes
 	location: select {
 		id
 		path
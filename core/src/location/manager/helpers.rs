@@ -1,4 +1,9 @@
-use crate::{library::Library, prisma::location, util::db::maybe_missing, Node};
+use crate::{
+	library::Library,
+	prisma::{location, volume},
+	util::db::maybe_missing,
+	Node,
+};
 
 use std::{
 	collections::{HashMap, HashSet},
@@ -29,6 +34,22 @@ pub(super) async fn check_online(
 
 	// TODO(N): This isn't gonna work with removable media and this will likely permanently break if the DB is restored from a backup.
 	if location.instance_id == Some(library.config().instance_id) {
+		// The mount point of a removable drive can outlive it, so its volume has the final say
+		if let Some(volume_id) = location.volume_id {
+			let volume = library
+				.db
+				.volume()
+				.find_unique(volume::id::equals(volume_id))
+				.select(volume::select!({ is_mounted }))
+				.exec()
+				.await?;
+
+			if volume.map_or(false, |volume| !volume.is_mounted) {
+				node.locations.remove_online(&pub_id).await;
+				return Ok(false);
+			}
+		}
+
 		match fs::metadata(&location_path).await {
 			Ok(_) => {
 				node.locations.add_online(pub_id).await;
@@ -17,6 +17,7 @@ use crate::{
 		scan_location_sub_path, update_location_size,
 	},
 	object::{
+		content::{can_index_content, index_file_path_content},
 		file_identifier::FileMetadata,
 		media::{
 			media_data_extractor::{can_extract_media_data_for_image, extract_media_data},
@@ -310,6 +311,8 @@ async fn inner_create_file(
 		}
 	}
 
+	update_content_index(location_id, created_file.id, kind, path, library).await?;
+
 	invalidate_query!(library, "search.paths");
 
 	Ok(())
@@ -557,12 +560,48 @@ async fn inner_update_file(
 			}
 		}
 
+		if let Some(location_id) = file_path.location_id {
+			update_content_index(location_id, file_path.id, kind, full_path, library).await?;
+		}
+
 		invalidate_query!(library, "search.paths");
 	}
 
 	Ok(())
 }
 
+/// Re-extracts the text of a created or modified file if its location has content indexing enabled
+async fn update_content_index(
+	location_id: location::id::Type,
+	file_path_id: file_path::id::Type,
+	kind: ObjectKind,
+	full_path: impl AsRef<Path>,
+	library: &Library,
+) -> Result<(), LocationManagerError> {
+	if !can_index_content(kind) {
+		return Ok(());
+	}
+
+	let index_content = library
+		.db
+		.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ index_content }))
+		.exec()
+		.await?
+		.and_then(|location| location.index_content)
+		.unwrap_or_default();
+
+	if index_content {
+		match index_file_path_content(&library.db, file_path_id, full_path).await {
+			Ok(_) => invalidate_query!(library, "search.content"),
+			Err(e) => error!("Failed to index file content: {e:#?}"),
+		}
+	}
+
+	Ok(())
+}
+
 pub(super) async fn rename(
 	location_id: location::id::Type,
 	new_path: impl AsRef<Path>,
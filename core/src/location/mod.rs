@@ -1,10 +1,11 @@
 use crate::{
 	api::CoreEvent,
 	invalidate_query,
-	job::{JobBuilder, JobError, JobManagerError},
+	job::{Job, JobBuilder, JobError, JobManagerError},
 	library::Library,
 	location::file_path_helper::filter_existing_file_path_params,
 	object::{
+		content::ContentIndexerJobInit,
 		file_identifier::{self, file_identifier_job::FileIdentifierJobInit},
 		media::{
 			media_processor,
@@ -15,8 +16,9 @@ use crate::{
 			MediaProcessorJobInit,
 		},
 	},
-	prisma::{file_path, indexer_rules_in_location, location, PrismaClient},
+	prisma::{file_path, file_path_content, indexer_rules_in_location, location, PrismaClient},
 	util::{db::maybe_missing, error::FileIOError},
+	volume::save_volumes,
 	Node,
 };
 
@@ -252,6 +254,9 @@ impl LocationCreateArgs {
 /// It contains the id of the location to be updated, possible a name to change the current location's name
 /// and a vector of indexer rules ids to add or remove from the location.
 ///
+/// Enabling `index_content` spawns a content indexer job for the location, disabling it drops
+/// all the content that was already extracted from its files.
+///
 /// It is important to note that only the indexer rule ids in this vector will be used from now on.
 /// Old rules that aren't in this vector will be purged.
 #[derive(Type, Deserialize)]
@@ -261,12 +266,17 @@ pub struct LocationUpdateArgs {
 	generate_preview_media: Option<bool>,
 	sync_preview_media: Option<bool>,
 	hidden: Option<bool>,
+	index_content: Option<bool>,
 	indexer_rules_ids: Vec<i32>,
 	path: Option<String>,
 }
 
 impl LocationUpdateArgs {
-	pub async fn update(self, node: &Node, library: &Arc<Library>) -> Result<(), LocationError> {
+	pub async fn update(
+		self,
+		node: &Arc<Node>,
+		library: &Arc<Library>,
+	) -> Result<(), LocationError> {
 		let Library { sync, db, .. } = &**library;
 
 		let location = find_location(library, self.id)
@@ -304,6 +314,12 @@ impl LocationUpdateArgs {
 					location::hidden::set(Some(v)),
 				)
 			}),
+			self.index_content.map(|v| {
+				(
+					(location::index_content::NAME, json!(v)),
+					location::index_content::set(Some(v)),
+				)
+			}),
 			self.path.clone().map(|v| {
 				(
 					(location::path::NAME, json!(v)),
@@ -354,6 +370,34 @@ impl LocationUpdateArgs {
 				node.locations.remove(self.id, library.clone()).await?;
 				node.locations.add(self.id, library.clone()).await?;
 			}
+
+			match self.index_content {
+				Some(true)
+					if location.index_content != Some(true)
+						&& location.instance_id == Some(library.config().instance_id) =>
+				{
+					let mut location_data = location::Data::from(&location);
+					location_data.index_content = Some(true);
+
+					Job::new(ContentIndexerJobInit {
+						location: location_data,
+						sub_path: None,
+					})
+					.spawn(node, library)
+					.await?;
+				}
+				Some(false) => {
+					db.file_path_content()
+						.delete_many(vec![file_path_content::file_path::is(vec![
+							file_path::location_id::equals(Some(self.id)),
+						])])
+						.exec()
+						.await?;
+
+					invalidate_query!(library, "search.content");
+				}
+				_ => {}
+			}
 		}
 
 		let current_rules_ids = location
@@ -437,7 +481,7 @@ pub async fn scan_location(
 
 	let location_base_data = location::Data::from(&location);
 
-	JobBuilder::new(IndexerJobInit {
+	let mut job = JobBuilder::new(IndexerJobInit {
 		location,
 		sub_path: None,
 	})
@@ -449,13 +493,19 @@ pub async fn scan_location(
 		sub_path: None,
 	})
 	.queue_next(MediaProcessorJobInit {
-		location: location_base_data,
+		location: location_base_data.clone(),
 		sub_path: None,
 		regenerate_thumbnails: false,
-	})
-	.spawn(node, library)
-	.await
-	.map_err(Into::into)
+	});
+
+	if location_base_data.index_content == Some(true) {
+		job = job.queue_next(ContentIndexerJobInit {
+			location: location_base_data,
+			sub_path: None,
+		});
+	}
+
+	job.spawn(node, library).await.map_err(Into::into)
 }
 
 pub async fn scan_location_sub_path(
@@ -473,7 +523,7 @@ pub async fn scan_location_sub_path(
 
 	let location_base_data = location::Data::from(&location);
 
-	JobBuilder::new(IndexerJobInit {
+	let mut job = JobBuilder::new(IndexerJobInit {
 		location,
 		sub_path: Some(sub_path.clone()),
 	})
@@ -488,13 +538,19 @@ pub async fn scan_location_sub_path(
 		sub_path: Some(sub_path.clone()),
 	})
 	.queue_next(MediaProcessorJobInit {
-		location: location_base_data,
-		sub_path: Some(sub_path),
+		location: location_base_data.clone(),
+		sub_path: Some(sub_path.clone()),
 		regenerate_thumbnails: false,
-	})
-	.spawn(node, library)
-	.await
-	.map_err(Into::into)
+	});
+
+	if location_base_data.index_content == Some(true) {
+		job = job.queue_next(ContentIndexerJobInit {
+			location: location_base_data,
+			sub_path: Some(sub_path),
+		});
+	}
+
+	job.spawn(node, library).await.map_err(Into::into)
 }
 
 pub async fn light_scan_location(
@@ -631,7 +687,8 @@ async fn create_location(
 		.location()
 		.count(vec![location::path::equals(Some(path.clone()))])
 		.exec()
-		.await? > 0
+		.await?
+		> 0
 	{
 		return Err(LocationError::LocationAlreadyExists(
 			location_path.as_ref().to_path_buf(),
@@ -694,6 +751,14 @@ async fn create_location(
 		link_location_and_indexer_rules(library, location.id, indexer_rules_ids).await?;
 	}
 
+	// Binds the new location to its volume right away instead of waiting for the volume tracker
+	if let Err(e) = save_volumes(library).await {
+		error!(
+			"Failed to bind location <id='{}'> to its volume: {e:#?}",
+			location.id
+		);
+	}
+
 	// Updating our location variable to include information about the indexer rules
 	let location = find_location(library, location.id)
 		.include(location_with_indexer_rules::include())
@@ -811,10 +876,13 @@ impl From<location_with_indexer_rules::Data> for location::Data {
 			generate_preview_media: data.generate_preview_media,
 			sync_preview_media: data.sync_preview_media,
 			hidden: data.hidden,
+			index_content: data.index_content,
 			date_created: data.date_created,
+			volume_id: data.volume_id,
 			file_paths: None,
 			indexer_rules: None,
 			instance: None,
+			volume: None,
 		}
 	}
 }
@@ -827,17 +895,20 @@ impl From<&location_with_indexer_rules::Data> for location::Data {
 			path: data.path.clone(),
 			instance_id: data.instance_id,
 			name: data.name.clone(),
-			total_capacity: data.total_capacity,
-			available_capacity: data.available_capacity,
+			total_capacity: data.total_capacity.clone(),
+			available_capacity: data.available_capacity.clone(),
 			size_in_bytes: data.size_in_bytes.clone(),
 			is_archived: data.is_archived,
 			generate_preview_media: data.generate_preview_media,
 			sync_preview_media: data.sync_preview_media,
 			hidden: data.hidden,
+			index_content: data.index_content,
 			date_created: data.date_created,
+			volume_id: data.volume_id,
 			file_paths: None,
 			indexer_rules: None,
 			instance: None,
+			volume: None,
 		}
 	}
 }
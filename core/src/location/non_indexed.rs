@@ -1,7 +1,14 @@
 use crate::{
 	api::locations::ExplorerItem,
 	library::Library,
-	object::{cas::generate_cas_id, media::thumbnail::get_thumb_key},
+	object::{
+		cas::generate_cas_id,
+		fs::archive::{
+			list_archive_entries, split_archive_path, ArchiveError, ArchiveFormat,
+			ARCHIVE_PATH_SEPARATOR,
+		},
+		media::thumbnail::get_thumb_key,
+	},
 	prisma::location,
 	util::error::FileIOError,
 	Node,
@@ -13,7 +20,7 @@ use std::{
 	sync::Arc,
 };
 
-use sd_file_ext::{extensions::Extension, kind::ObjectKind};
+use sd_file_ext::{extensions::Extension, kind::ObjectKind, magic::ExtensionPossibility};
 
 use chrono::{DateTime, Utc};
 use rspc::ErrorCode;
@@ -21,7 +28,7 @@ use sd_utils::chain_optional_iter;
 use serde::Serialize;
 use specta::Type;
 use thiserror::Error;
-use tokio::{fs, io};
+use tokio::{fs, io, task::spawn_blocking};
 use tracing::{error, warn};
 
 use super::{
@@ -42,14 +49,20 @@ pub enum NonIndexedLocationError {
 	#[error(transparent)]
 	FileIO(#[from] FileIOError),
 
+	#[error(transparent)]
+	Archive(#[from] ArchiveError),
+
 	#[error("database error: {0}")]
 	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	JoinTask(#[from] tokio::task::JoinError),
 }
 
 impl From<NonIndexedLocationError> for rspc::Error {
 	fn from(err: NonIndexedLocationError) -> Self {
 		match err {
-			NonIndexedLocationError::NotFound(_) => {
+			NonIndexedLocationError::NotFound(_)
+			| NonIndexedLocationError::Archive(ArchiveError::EntryNotFound(..)) => {
 				rspc::Error::with_cause(ErrorCode::NotFound, err.to_string(), err)
 			}
 			_ => rspc::Error::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
@@ -93,6 +106,11 @@ pub async fn walk(
 	library: Arc<Library>,
 ) -> Result<NonIndexedFileSystemEntries, NonIndexedLocationError> {
 	let path = full_path.as_ref();
+
+	if let Some((format, archive_path, directory)) = path.to_str().and_then(split_archive_path) {
+		return walk_archive(format, archive_path, directory, with_hidden_files).await;
+	}
+
 	let mut read_dir = fs::read_dir(path).await.map_err(|e| (path, e))?;
 
 	let mut directories = vec![];
@@ -250,3 +268,98 @@ pub async fn walk(
 
 	Ok(NonIndexedFileSystemEntries { entries, errors })
 }
+
+/// Lists a directory inside of an archive, as if it was a regular directory.
+///
+/// Entries get virtual paths like `/x/foo.zip!/docs/readme.md`, which can be walked again
+/// or served through the custom URI `/file/archive` route.
+async fn walk_archive(
+	format: ArchiveFormat,
+	archive_path: &Path,
+	directory: &str,
+	with_hidden_files: bool,
+) -> Result<NonIndexedFileSystemEntries, NonIndexedLocationError> {
+	let metadata = fs::metadata(archive_path)
+		.await
+		.map_err(|e| (archive_path, e))?;
+
+	let archive_entries = spawn_blocking({
+		let (archive_path, directory) = (archive_path.to_path_buf(), directory.to_string());
+		move || list_archive_entries(format, &archive_path, &directory)
+	})
+	.await??;
+
+	let entries = archive_entries
+		.into_iter()
+		.filter_map(|entry| {
+			let file_name = entry
+				.path
+				.rsplit_once('/')
+				.map_or(entry.path.as_str(), |(_, file_name)| file_name);
+
+			let hidden = file_name.starts_with('.');
+			if hidden && !with_hidden_files {
+				return None;
+			}
+
+			let (name, extension, kind) = if entry.is_dir {
+				(file_name.to_string(), String::new(), ObjectKind::Folder)
+			} else {
+				let file_name = Path::new(file_name);
+				let extension = file_name
+					.extension()
+					.and_then(|s| s.to_str().map(str::to_string))
+					.unwrap_or_default();
+
+				// We can't check magic bytes without extracting, so conflicts are best effort
+				let kind = match Extension::from_str(&extension) {
+					Some(ExtensionPossibility::Known(ext)) => ext.into(),
+					Some(ExtensionPossibility::Conflicts(exts)) => exts
+						.into_iter()
+						.next()
+						.map_or(ObjectKind::Unknown, Into::into),
+					None => ObjectKind::Unknown,
+				};
+
+				(
+					file_name
+						.file_stem()
+						.and_then(|s| s.to_str().map(str::to_string))
+						.unwrap_or_default(),
+					extension,
+					kind,
+				)
+			};
+
+			// Members without a date of their own get the archive's dates
+			let date_modified = entry
+				.date_modified
+				.unwrap_or_else(|| metadata.modified_or_now().into());
+
+			Some(ExplorerItem::NonIndexedPath {
+				has_local_thumbnail: false,
+				thumbnail_key: None,
+				item: NonIndexedPathItem {
+					path: format!(
+						"{}{ARCHIVE_PATH_SEPARATOR}{}",
+						archive_path.display(),
+						entry.path
+					),
+					name,
+					extension,
+					kind: kind as i32,
+					is_dir: entry.is_dir,
+					date_created: date_modified,
+					date_modified,
+					size_in_bytes_bytes: entry.size.to_be_bytes().to_vec(),
+					hidden,
+				},
+			})
+		})
+		.collect();
+
+	Ok(NonIndexedFileSystemEntries {
+		entries,
+		errors: vec![],
+	})
+}
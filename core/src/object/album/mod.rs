@@ -0,0 +1,50 @@
+use chrono::{DateTime, FixedOffset, Utc};
+use sd_prisma::prisma_sync;
+use sd_sync::*;
+use serde::Deserialize;
+use serde_json::json;
+use specta::Type;
+
+use uuid::Uuid;
+
+use crate::{library::Library, prisma::album};
+
+#[derive(Type, Deserialize, Clone)]
+pub struct AlbumCreateArgs {
+	pub name: String,
+}
+
+impl AlbumCreateArgs {
+	pub async fn exec(
+		self,
+		Library { db, sync, .. }: &Library,
+	) -> prisma_client_rust::Result<album::Data> {
+		let pub_id = Uuid::new_v4().as_bytes().to_vec();
+		let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+		sync.write_ops(
+			db,
+			(
+				sync.shared_create(
+					prisma_sync::album::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					[
+						(album::name::NAME, json!(&self.name)),
+						(album::is_hidden::NAME, json!(false)),
+						(album::date_created::NAME, json!(&date_created.to_rfc3339())),
+					],
+				),
+				db.album().create(
+					pub_id,
+					vec![
+						album::name::set(Some(self.name)),
+						album::is_hidden::set(Some(false)),
+						album::date_created::set(Some(date_created)),
+					],
+				),
+			),
+		)
+		.await
+	}
+}
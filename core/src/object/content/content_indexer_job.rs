@@ -0,0 +1,209 @@
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobReportUpdate, JobResult, JobStepOutput,
+		StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::file_path_helper::{
+		ensure_file_path_exists, ensure_sub_path_is_directory, ensure_sub_path_is_in_location,
+		file_path_for_content_indexer, IsolatedFilePathData,
+	},
+	prisma::{file_path, location, object},
+	util::db::maybe_missing,
+};
+
+use std::{
+	hash::{Hash, Hasher},
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tracing::info;
+
+use super::{
+	index_file_path_content, ContentIndexerError, ContentIndexerMetadata, CONTENT_INDEXABLE_KINDS,
+};
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContentIndexerJobData {
+	location_path: PathBuf,
+	task_count: usize,
+}
+
+/// Extracts the text of every text-like file in a location, so it can be found by `search.content`
+#[derive(Serialize, Deserialize, Debug)]
+pub struct ContentIndexerJobInit {
+	pub location: location::Data,
+	pub sub_path: Option<PathBuf>,
+}
+
+impl Hash for ContentIndexerJobInit {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		self.location.id.hash(state);
+		if let Some(ref sub_path) = self.sub_path {
+			sub_path.hash(state);
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for ContentIndexerJobInit {
+	type Data = ContentIndexerJobData;
+	type Step = file_path_for_content_indexer::Data;
+	type RunMetadata = ContentIndexerMetadata;
+
+	const NAME: &'static str = "content_indexer";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		if init.location.index_content != Some(true) {
+			return Err(JobError::EarlyFinish {
+				name: <Self as StatefulJob>::NAME.to_string(),
+				reason: "content indexing is disabled for this location".to_string(),
+			});
+		}
+
+		let location_id = init.location.id;
+
+		let location_path =
+			maybe_missing(&init.location.path, "location.path").map(PathBuf::from)?;
+
+		let maybe_sub_iso_file_path = match &init.sub_path {
+			Some(sub_path) if sub_path != Path::new("") => {
+				let full_path = ensure_sub_path_is_in_location(&location_path, sub_path)
+					.await
+					.map_err(ContentIndexerError::from)?;
+				ensure_sub_path_is_directory(&location_path, sub_path)
+					.await
+					.map_err(ContentIndexerError::from)?;
+
+				let sub_iso_file_path =
+					IsolatedFilePathData::new(location_id, &location_path, &full_path, true)
+						.map_err(ContentIndexerError::from)?;
+
+				ensure_file_path_exists(
+					sub_path,
+					&sub_iso_file_path,
+					db,
+					ContentIndexerError::SubPathNotFound,
+				)
+				.await?;
+
+				Some(sub_iso_file_path)
+			}
+			_ => None,
+		};
+
+		let steps = db
+			.file_path()
+			.find_many(sd_utils::chain_optional_iter(
+				[
+					file_path::location_id::equals(Some(location_id)),
+					file_path::is_dir::equals(Some(false)),
+					file_path::object::is(vec![object::kind::in_vec(
+						CONTENT_INDEXABLE_KINDS
+							.into_iter()
+							.map(|kind| kind as i32)
+							.collect(),
+					)]),
+				],
+				[maybe_sub_iso_file_path.and_then(|iso_sub_path| {
+					iso_sub_path
+						.materialized_path_for_children()
+						.map(file_path::materialized_path::starts_with)
+				})],
+			))
+			.select(file_path_for_content_indexer::select())
+			.exec()
+			.await?;
+
+		ctx.progress(vec![JobReportUpdate::TaskCount(steps.len())]);
+
+		*data = Some(ContentIndexerJobData {
+			location_path,
+			task_count: steps.len(),
+		});
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: file_path,
+			step_number,
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		// Skipping files that weren't modified since we last extracted their content
+		let up_to_date = matches!(
+			(
+				file_path.content.as_ref().and_then(|c| c.date_indexed),
+				file_path.date_modified
+			),
+			(Some(date_indexed), Some(date_modified)) if date_indexed >= date_modified
+		);
+
+		let mut run_metadata = ContentIndexerMetadata::default();
+
+		if up_to_date {
+			run_metadata.skipped += 1;
+		} else {
+			let full_path = data.location_path.join(IsolatedFilePathData::try_from((
+				init.location.id,
+				file_path,
+			))?);
+
+			if index_file_path_content(&ctx.library.db, file_path.id, &full_path).await? {
+				run_metadata.indexed += 1;
+			} else {
+				run_metadata.skipped += 1;
+			}
+		}
+
+		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(step_number + 1)]);
+
+		Ok(run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		info!(
+			"finalizing content indexer job at {}{}: {} tasks, {} indexed",
+			data.location_path.display(),
+			init.sub_path
+				.as_ref()
+				.map(|p| format!("{}", p.display()))
+				.unwrap_or_default(),
+			data.task_count,
+			run_metadata.indexed,
+		);
+
+		if run_metadata.indexed > 0 {
+			invalidate_query!(ctx.library, "search.content");
+		}
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
@@ -0,0 +1,398 @@
+use crate::{
+	job::JobRunMetadata,
+	location::file_path_helper::FilePathError,
+	prisma::{file_path, file_path_content, PrismaClient},
+	util::error::FileIOError,
+};
+
+use sd_file_ext::{kind::ObjectKind, text::is_text};
+
+use std::{collections::HashMap, path::Path};
+
+use chrono::Utc;
+use prisma_client_rust::{PrismaValue, QueryError, Raw};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tokio::{fs::File, io::AsyncReadExt};
+
+pub mod content_indexer_job;
+
+pub use content_indexer_job::ContentIndexerJobInit;
+
+/// Only the first MiB of a file is indexed, anything after it won't be searchable
+pub const MAX_INDEXED_CONTENT_BYTES: u64 = 1024 * 1024;
+
+/// How many bytes of context we keep on each side of the first match in a snippet
+const SNIPPET_RADIUS: usize = 80;
+/// Characters read from the database on each side of the first match to build a snippet. One
+/// more than the radius, so [`snippet`] can tell whether it cut anything off.
+const SNIPPET_WINDOW: usize = SNIPPET_RADIUS + 1;
+
+/// Object kinds whose contents are worth checking with [`is_text`]
+pub const CONTENT_INDEXABLE_KINDS: [ObjectKind; 4] = [
+	ObjectKind::Text,
+	ObjectKind::Code,
+	ObjectKind::Config,
+	ObjectKind::Document,
+];
+
+#[derive(Error, Debug)]
+pub enum ContentIndexerError {
+	#[error("sub path not found: <path='{}'>", .0.display())]
+	SubPathNotFound(Box<Path>),
+
+	// Internal errors
+	#[error("database error: {0}")]
+	Database(#[from] prisma_client_rust::QueryError),
+	#[error(transparent)]
+	FilePath(#[from] FilePathError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct ContentIndexerMetadata {
+	indexed: u32,
+	skipped: u32,
+}
+
+impl JobRunMetadata for ContentIndexerMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.indexed += new_data.indexed;
+		self.skipped += new_data.skipped;
+	}
+}
+
+pub fn can_index_content(kind: ObjectKind) -> bool {
+	CONTENT_INDEXABLE_KINDS.contains(&kind)
+}
+
+/// Reads the beginning of a file and decodes it as text, returning `None` for binary files
+pub async fn extract_text(path: impl AsRef<Path>) -> Result<Option<String>, FileIOError> {
+	let path = path.as_ref();
+
+	let file = File::open(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	let mut buf = Vec::new();
+	file.take(MAX_INDEXED_CONTENT_BYTES)
+		.read_to_end(&mut buf)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?;
+
+	let partial = buf.len() as u64 == MAX_INDEXED_CONTENT_BYTES;
+
+	Ok(is_text(&buf, partial).map(|encoding| decode_text(&buf, encoding)))
+}
+
+fn decode_text(buf: &[u8], encoding: &str) -> String {
+	let text = match encoding {
+		"utf-16le" | "utf-16be" => char::decode_utf16(buf.chunks_exact(2).map(|bytes| {
+			let bytes = [bytes[0], bytes[1]];
+			if encoding == "utf-16le" {
+				u16::from_le_bytes(bytes)
+			} else {
+				u16::from_be_bytes(bytes)
+			}
+		}))
+		.map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+		.collect(),
+		"utf-32le" | "utf-32be" => buf
+			.chunks_exact(4)
+			.map(|bytes| {
+				let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+				char::from_u32(if encoding == "utf-32le" {
+					u32::from_le_bytes(bytes)
+				} else {
+					u32::from_be_bytes(bytes)
+				})
+				.unwrap_or(char::REPLACEMENT_CHARACTER)
+			})
+			.collect(),
+		"iso-8859-1" => buf.iter().copied().map(char::from).collect(),
+		_ => String::from_utf8_lossy(buf).into_owned(),
+	};
+
+	text.trim_start_matches('\u{feff}').to_string()
+}
+
+/// Extracts the text of a file and stores it for content search.
+/// Returns `false` and drops any stale content if the file turned out not to be text.
+pub async fn index_file_path_content(
+	db: &PrismaClient,
+	file_path_id: file_path::id::Type,
+	full_path: impl AsRef<Path>,
+) -> Result<bool, ContentIndexerError> {
+	let Some(content) = extract_text(full_path).await? else {
+		db.file_path_content()
+			.delete_many(vec![file_path_content::file_path_id::equals(file_path_id)])
+			.exec()
+			.await?;
+
+		return Ok(false);
+	};
+
+	let params = vec![file_path_content::date_indexed::set(Some(
+		Utc::now().into(),
+	))];
+
+	db.file_path_content()
+		.upsert(
+			file_path_content::file_path_id::equals(file_path_id),
+			file_path_content::create(
+				content.clone(),
+				file_path::id::equals(file_path_id),
+				params.clone(),
+			),
+			[vec![file_path_content::content::set(content)], params].concat(),
+		)
+		.exec()
+		.await?;
+
+	Ok(true)
+}
+
+/// Splits a search query into the lowercased terms used for matching and ranking.
+///
+/// We only fold ASCII case, the same as SQLite's `LIKE`, so byte offsets stay valid
+/// between the original content and its lowercased copy.
+pub fn search_terms(query: &str) -> Vec<String> {
+	let mut terms = Vec::new();
+
+	for term in query.split_whitespace().map(str::to_ascii_lowercase) {
+		if !terms.contains(&term) {
+			terms.push(term);
+		}
+	}
+
+	terms
+}
+
+/// Scores how relevant a content is from how many times each term occurs in it, `None` if any
+/// term is missing.
+///
+/// Each term contributes `1 + ln(occurrences)`, so repeating a word has diminishing returns.
+pub fn rank(occurrences: impl IntoIterator<Item = u32>) -> Option<f64> {
+	occurrences.into_iter().try_fold(0.0, |score, count| {
+		(count > 0).then(|| score + 1.0 + f64::from(count).ln())
+	})
+}
+
+/// Ranks every indexed content containing all the terms, best first.
+///
+/// SQLite does the matching and counts the occurrences of each term, so contents never leave
+/// the database. As there's a count per term, they come back space separated in a single column.
+pub async fn rank_contents(
+	db: &PrismaClient,
+	terms: &[String],
+) -> Result<Vec<(file_path::id::Type, f64)>, QueryError> {
+	#[derive(Deserialize)]
+	struct Occurrences {
+		file_path_id: file_path::id::Type,
+		occurrences: String,
+	}
+
+	if terms.is_empty() {
+		return Ok(vec![]);
+	}
+
+	let occurrences = vec![
+		"((length(content) - length(replace(lower(content), {}, ''))) / length({}))";
+		terms.len()
+	]
+	.join(" || ' ' || ");
+	let contains = vec!["content LIKE {} ESCAPE '\\'"; terms.len()].join(" AND ");
+
+	let params = terms
+		.iter()
+		.flat_map(|term| [term.clone(), term.clone()])
+		.chain(terms.iter().map(|term| format!("%{}%", escape_like(term))))
+		.map(PrismaValue::String)
+		.collect();
+
+	let mut ranked = db
+		._query_raw::<Occurrences>(Raw::new(
+			&format!(
+				"SELECT file_path_id, {occurrences} AS occurrences
+				FROM file_path_content
+				WHERE {contains}"
+			),
+			params,
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.filter_map(|row| {
+			rank(
+				row.occurrences
+					.split(' ')
+					.map(|count| count.parse().unwrap_or_default()),
+			)
+			.map(|score| (row.file_path_id, score))
+		})
+		.collect::<Vec<_>>();
+
+	ranked.sort_by(|(_, score1), (_, score2)| score2.total_cmp(score1));
+
+	Ok(ranked)
+}
+
+/// Builds the snippets of the given contents, reading only a few characters around their
+/// first match from the database
+pub async fn snippets(
+	db: &PrismaClient,
+	terms: &[String],
+	file_path_ids: &[file_path::id::Type],
+) -> Result<HashMap<file_path::id::Type, String>, QueryError> {
+	#[derive(Deserialize)]
+	struct Excerpt {
+		file_path_id: file_path::id::Type,
+		excerpt: String,
+	}
+
+	if terms.is_empty() || file_path_ids.is_empty() {
+		return Ok(HashMap::new());
+	}
+
+	// SQLite's `min` is an aggregate function when given a single argument
+	let first_match = match terms.len() {
+		1 => "instr(lower(content), {})".to_string(),
+		len => format!("min({})", vec!["instr(lower(content), {})"; len].join(", ")),
+	};
+	let longest_term = terms
+		.iter()
+		.map(|term| term.chars().count())
+		.max()
+		.unwrap_or_default();
+
+	// We only have ids here, so this is sql injection safe
+	Ok(db
+		._query_raw::<Excerpt>(Raw::new(
+			&format!(
+				"SELECT file_path_id,
+					substr(content, max(1, first_match - {SNIPPET_WINDOW}), {}) AS excerpt
+				FROM (
+					SELECT file_path_id, content, {first_match} AS first_match
+					FROM file_path_content
+					WHERE file_path_id IN ({})
+				)",
+				2 * SNIPPET_WINDOW + longest_term,
+				file_path_ids
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join(",")
+			),
+			terms.iter().cloned().map(PrismaValue::String).collect(),
+		))
+		.exec()
+		.await?
+		.into_iter()
+		.map(
+			|Excerpt {
+			     file_path_id,
+			     excerpt,
+			 }| (file_path_id, snippet(&excerpt, terms)),
+		)
+		.collect())
+}
+
+fn escape_like(term: &str) -> String {
+	term.replace('\\', "\\\\")
+		.replace('%', "\\%")
+		.replace('_', "\\_")
+}
+
+/// Builds a single line excerpt around the first match of any of the terms
+pub fn snippet(content: &str, terms: &[String]) -> String {
+	let lowercase = content.to_ascii_lowercase();
+
+	let first_match = terms
+		.iter()
+		.filter_map(|term| lowercase.find(term.as_str()))
+		.min()
+		.unwrap_or(0);
+
+	let mut start = first_match.saturating_sub(SNIPPET_RADIUS);
+	while !content.is_char_boundary(start) {
+		start -= 1;
+	}
+
+	let mut end = (first_match + SNIPPET_RADIUS).min(content.len());
+	while !content.is_char_boundary(end) {
+		end += 1;
+	}
+
+	let mut snippet = content[start..end]
+		.split_whitespace()
+		.collect::<Vec<_>>()
+		.join(" ");
+
+	if start > 0 {
+		snippet.insert(0, '…');
+	}
+	if end < content.len() {
+		snippet.push('…');
+	}
+
+	snippet
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn terms_are_lowercased_and_deduplicated() {
+		assert_eq!(
+			search_terms("  Hello world HELLO\tthere "),
+			vec!["hello", "world", "there"]
+		);
+		assert!(search_terms("   ").is_empty());
+	}
+
+	#[test]
+	fn rank_requires_every_term() {
+		assert!(rank([1, 2]).is_some());
+		assert!(rank([1, 0]).is_none());
+	}
+
+	#[test]
+	fn rank_prefers_more_occurrences() {
+		assert!(rank([3]).unwrap() > rank([1]).unwrap());
+	}
+
+	#[test]
+	fn like_wildcards_are_escaped() {
+		assert_eq!(escape_like(r"100%_a\b"), r"100\%\_a\\b");
+	}
+
+	#[test]
+	fn snippet_is_centered_on_first_match() {
+		let content = format!("{}needle{}", "a ".repeat(100), " b".repeat(100));
+		let snippet = snippet(&content, &search_terms("NEEDLE"));
+
+		assert!(snippet.contains("needle"));
+		assert!(snippet.starts_with('…'));
+		assert!(snippet.ends_with('…'));
+		assert!(snippet.len() < content.len());
+	}
+
+	#[test]
+	fn snippet_respects_char_boundaries() {
+		let content = format!("{}needle{}", "é".repeat(100), "ü".repeat(100));
+
+		assert!(snippet(&content, &search_terms("needle")).contains("needle"));
+	}
+
+	#[test]
+	fn decode_utf16_with_bom() {
+		let buf = "\u{feff}hello"
+			.encode_utf16()
+			.flat_map(u16::to_le_bytes)
+			.collect::<Vec<_>>();
+
+		assert_eq!(decode_text(&buf, "utf-16le"), "hello");
+	}
+}
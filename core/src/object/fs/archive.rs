@@ -0,0 +1,1141 @@
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::file_path_helper::join_location_relative_path,
+	prisma::{file_path, location},
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use std::{
+	collections::BTreeMap,
+	fs::{File, OpenOptions},
+	hash::Hash,
+	io::{self, Read, Seek, SeekFrom},
+	path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use thiserror::Error;
+use tokio::{fs, sync::mpsc, task::spawn_blocking};
+use tracing::{trace, warn};
+use zip::{result::ZipError, write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+use super::{
+	conflict::{partial_path_of, settle_conflict, ConflictPolicy, ConflictResolution, Settlement},
+	construct_target_filename,
+	error::FileSystemJobsError,
+	fetch_source_and_target_location_paths, get_many_files_datas,
+};
+
+/// A tar archive ends with two empty 512 bytes blocks
+const TAR_END_OF_ARCHIVE_LEN: u64 = 1024;
+
+/// Separates the path of an archive from the path of an entry inside of it,
+/// e.g. `/x/foo.zip!/docs/readme.md`
+pub const ARCHIVE_PATH_SEPARATOR: &str = "!/";
+
+#[derive(Error, Debug)]
+pub enum ArchiveError {
+	#[error("unsupported archive format: <path='{}'>", .0.display())]
+	UnsupportedFormat(Box<Path>),
+	#[error("archive entry name isn't valid UTF-8: <path='{}'>", .0.display())]
+	NonUtf8EntryName(Box<Path>),
+	#[error("archive entry not found: <archive='{}', entry='{1}'>", .0.display())]
+	EntryNotFound(Box<Path>, String),
+	#[error("zip error: {0}")]
+	Zip(#[from] ZipError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+	Zip,
+	Tar,
+	TarGz,
+	TarZst,
+}
+
+impl ArchiveFormat {
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Zip => "zip",
+			Self::Tar => "tar",
+			Self::TarGz => "tar.gz",
+			Self::TarZst => "tar.zst",
+		}
+	}
+
+	/// Guesses the format of an archive from its file name, returning it alongside the name
+	/// without the archive extension
+	pub fn from_file_name(file_name: &str) -> Option<(Self, &str)> {
+		const SUFFIXES: [(&str, ArchiveFormat); 6] = [
+			(".tar.gz", ArchiveFormat::TarGz),
+			(".tgz", ArchiveFormat::TarGz),
+			(".tar.zst", ArchiveFormat::TarZst),
+			(".tzst", ArchiveFormat::TarZst),
+			(".tar", ArchiveFormat::Tar),
+			(".zip", ArchiveFormat::Zip),
+		];
+
+		SUFFIXES.into_iter().find_map(|(suffix, format)| {
+			let stem_len = file_name.len().checked_sub(suffix.len())?;
+
+			(stem_len > 0
+				&& file_name.is_char_boundary(stem_len)
+				&& file_name[stem_len..].eq_ignore_ascii_case(suffix))
+			.then(|| (format, &file_name[..stem_len]))
+		})
+	}
+}
+
+/// Splits a virtual path like `/x/foo.zip!/docs/` into the archive format, the archive path
+/// and the path of the entry inside of it, without leading or trailing slashes.
+///
+/// Returns `None` if the path doesn't point inside a supported archive.
+pub fn split_archive_path(path: &str) -> Option<(ArchiveFormat, &Path, &str)> {
+	path.match_indices(ARCHIVE_PATH_SEPARATOR)
+		.find_map(|(idx, _)| {
+			let archive_path = Path::new(&path[..idx]);
+
+			archive_path
+				.file_name()
+				.and_then(|name| name.to_str())
+				.and_then(ArchiveFormat::from_file_name)
+				.map(|(format, _)| {
+					(
+						format,
+						archive_path,
+						path[idx + ARCHIVE_PATH_SEPARATOR.len()..].trim_matches('/'),
+					)
+				})
+		})
+}
+
+/// An entry listed from inside an archive, see [`list_archive_entries`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveEntry {
+	/// Full path of the entry inside the archive, without a trailing slash
+	pub path: String,
+	pub is_dir: bool,
+	pub size: u64,
+	pub date_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileArchiverJobInit {
+	pub source_location_id: location::id::Type,
+	pub sources_file_path_ids: Vec<file_path::id::Type>,
+	pub target_location_id: location::id::Type,
+	pub target_location_relative_directory_path: PathBuf,
+	/// The archive file name, without extension
+	pub archive_name: String,
+	pub format: ArchiveFormat,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileArchiverJobData {
+	archive_path: PathBuf,
+	/// Entries are appended to an uncompressed archive at this path, which is then
+	/// compressed or moved to `archive_path` at the last step
+	staging_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum FileArchiverJobStep {
+	Append {
+		full_path: PathBuf,
+		entry_name: String,
+		is_dir: bool,
+	},
+	Finish,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileArchiverJobRunMetadata {
+	archived_entries: u64,
+	/// Size of the staging archive after the last completed step. Anything past it was
+	/// written by a step that got interrupted, so it's truncated before appending again.
+	committed_len: u64,
+}
+
+impl JobRunMetadata for FileArchiverJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.archived_entries += new_data.archived_entries;
+		self.committed_len = self.committed_len.max(new_data.committed_len);
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for FileArchiverJobInit {
+	type Data = FileArchiverJobData;
+	type Step = FileArchiverJobStep;
+	type RunMetadata = FileArchiverJobRunMetadata;
+
+	const NAME: &'static str = "file_archiver";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let (sources_location_path, target_location_path) = fetch_source_and_target_location_paths(
+			db,
+			init.source_location_id,
+			init.target_location_id,
+		)
+		.await?;
+
+		let archive_path = join_location_relative_path(
+			&target_location_path,
+			&init.target_location_relative_directory_path,
+		)
+		.join(format!("{}.{}", init.archive_name, init.format.extension()));
+
+		match fs::metadata(&archive_path).await {
+			Ok(_) => {
+				return Err(
+					FileSystemJobsError::WouldOverwrite(archive_path.into_boxed_path()).into(),
+				)
+			}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+			Err(e) => return Err(FileIOError::from((archive_path, e)).into()),
+		}
+
+		let mut steps = vec![];
+		let mut errors = vec![];
+
+		// Walking directories upfront, so we know the total amount of entries for progress
+		// and the last step is only reached after every entry was appended
+		for file_data in
+			get_many_files_datas(db, &sources_location_path, &init.sources_file_path_ids).await?
+		{
+			let entry_name = construct_target_filename(&file_data, &None)?;
+
+			if !maybe_missing(file_data.file_path.is_dir, "file_path.is_dir")? {
+				steps.push(FileArchiverJobStep::Append {
+					full_path: file_data.full_path,
+					entry_name,
+					is_dir: false,
+				});
+				continue;
+			}
+
+			let mut dirs_to_walk = vec![(file_data.full_path, entry_name)];
+
+			while let Some((dir_path, dir_entry_name)) = dirs_to_walk.pop() {
+				let mut read_dir = fs::read_dir(&dir_path)
+					.await
+					.map_err(|e| FileIOError::from((&dir_path, e)))?;
+
+				while let Some(children_entry) = read_dir
+					.next_entry()
+					.await
+					.map_err(|e| FileIOError::from((&dir_path, e)))?
+				{
+					let children_path = children_entry.path();
+					let file_type = children_entry
+						.file_type()
+						.await
+						.map_err(|e| FileIOError::from((&children_path, e)))?;
+
+					let Some(children_name) = children_entry
+						.file_name()
+						.to_str()
+						.map(|name| format!("{dir_entry_name}/{name}"))
+					else {
+						errors.push(
+							ArchiveError::NonUtf8EntryName(children_path.into_boxed_path())
+								.to_string(),
+						);
+						continue;
+					};
+
+					if file_type.is_dir() {
+						dirs_to_walk.push((children_path, children_name));
+					} else if file_type.is_file() {
+						steps.push(FileArchiverJobStep::Append {
+							full_path: children_path,
+							entry_name: children_name,
+							is_dir: false,
+						});
+					} else {
+						warn!(
+							"Skipping archiving {} as it isn't a regular file or directory",
+							children_path.display()
+						);
+					}
+				}
+
+				steps.push(FileArchiverJobStep::Append {
+					full_path: dir_path,
+					entry_name: dir_entry_name,
+					is_dir: true,
+				});
+			}
+		}
+
+		steps.push(FileArchiverJobStep::Finish);
+
+		let mut staging_file_name = archive_path
+			.file_name()
+			.expect("we just joined a file name to this path")
+			.to_os_string();
+		staging_file_name.push(".part");
+
+		*data = Some(FileArchiverJobData {
+			staging_path: archive_path.with_file_name(staging_file_name),
+			archive_path,
+		});
+
+		Ok((Default::default(), steps, JobRunErrors(errors)).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+		let format = init.format;
+		let staging_path = data.staging_path.clone();
+
+		match step {
+			FileArchiverJobStep::Append {
+				full_path,
+				entry_name,
+				is_dir,
+			} => {
+				ctx.progress_msg(format!("Archiving {entry_name}"));
+
+				let committed_len = run_metadata.committed_len;
+				let (full_path, entry_name, is_dir) =
+					(full_path.clone(), entry_name.clone(), *is_dir);
+
+				let committed_len = spawn_blocking(move || {
+					append_entry(
+						format,
+						&staging_path,
+						committed_len,
+						&full_path,
+						&entry_name,
+						is_dir,
+					)
+				})
+				.await??;
+
+				Ok(FileArchiverJobRunMetadata {
+					archived_entries: 1,
+					committed_len,
+				}
+				.into())
+			}
+			FileArchiverJobStep::Finish => {
+				ctx.progress_msg(format!(
+					"Writing {}",
+					data.archive_path
+						.file_name()
+						.unwrap_or_default()
+						.to_string_lossy()
+				));
+
+				let archive_path = data.archive_path.clone();
+
+				spawn_blocking(move || finish_archive(format, &staging_path, &archive_path))
+					.await??;
+
+				Ok(None.into())
+			}
+		}
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileExtractorJobInit {
+	pub source_location_id: location::id::Type,
+	pub sources_file_path_ids: Vec<file_path::id::Type>,
+	pub target_location_id: location::id::Type,
+	pub target_location_relative_directory_path: PathBuf,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+}
+
+/// Each archive is extracted into a new directory named after it, which is only put in place
+/// once the whole archive was extracted.
+/// If the job is interrupted mid archive, that archive is extracted again from the start.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileExtractorJobStep {
+	archive_path: PathBuf,
+	format: ArchiveFormat,
+	target_directory_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileExtractorJobRunMetadata {
+	extracted_entries: u64,
+	/// The client's decision for every remaining conflict, once it made one
+	#[serde(default)]
+	conflict_decision: Option<ConflictResolution>,
+}
+
+impl JobRunMetadata for FileExtractorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.extracted_entries += new_data.extracted_entries;
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for FileExtractorJobInit {
+	type Data = ();
+	type Step = FileExtractorJobStep;
+	type RunMetadata = FileExtractorJobRunMetadata;
+
+	const NAME: &'static str = "file_extractor";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let (sources_location_path, target_location_path) = fetch_source_and_target_location_paths(
+			db,
+			init.source_location_id,
+			init.target_location_id,
+		)
+		.await?;
+
+		let target_directory_path = join_location_relative_path(
+			target_location_path,
+			&init.target_location_relative_directory_path,
+		);
+
+		let mut steps = vec![];
+
+		for file_data in
+			get_many_files_datas(db, &sources_location_path, &init.sources_file_path_ids).await?
+		{
+			let file_name = construct_target_filename(&file_data, &None)?;
+
+			let (format, stem) = ArchiveFormat::from_file_name(&file_name).ok_or_else(|| {
+				ArchiveError::UnsupportedFormat(file_data.full_path.clone().into_boxed_path())
+			})?;
+
+			steps.push(FileExtractorJobStep {
+				archive_path: file_data.full_path,
+				format,
+				target_directory_path: target_directory_path.join(stem),
+			});
+		}
+
+		// Must fill in the data, otherwise the job will not run
+		*data = Some(());
+
+		Ok((Default::default(), steps).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step:
+				FileExtractorJobStep {
+					archive_path,
+					format,
+					target_directory_path,
+				},
+			..
+		}: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let Settlement {
+			target,
+			decided_for_all,
+			errors,
+		} = settle_conflict(
+			ctx,
+			init.conflict_policy,
+			run_metadata.conflict_decision,
+			archive_path,
+			target_directory_path,
+		)
+		.await?;
+
+		let mut new_run_metadata = FileExtractorJobRunMetadata {
+			conflict_decision: decided_for_all,
+			..Default::default()
+		};
+
+		let Some(target_directory_path) = target else {
+			return Ok((new_run_metadata, JobRunErrors(errors)).into());
+		};
+
+		trace!(
+			"Extracting {} to {}",
+			archive_path.display(),
+			target_directory_path.display()
+		);
+
+		let (entries_tx, mut entries_rx) = mpsc::unbounded_channel();
+		let partial_path = partial_path_of(&target_directory_path);
+
+		let extraction = spawn_blocking({
+			let (format, archive_path, partial_path) =
+				(*format, archive_path.clone(), partial_path.clone());
+
+			move || {
+				extract_archive(format, &archive_path, &partial_path, |entry| {
+					// The receiver only goes away if the step was aborted
+					entries_tx.send(entry.to_string()).ok();
+				})
+			}
+		});
+
+		// Reporting every extracted entry, until the extraction drops the sender
+		while let Some(entry) = entries_rx.recv().await {
+			ctx.progress_msg(format!("Extracting {entry}"));
+		}
+
+		new_run_metadata.extracted_entries = extraction.await??;
+
+		fs::rename(&partial_path, &target_directory_path)
+			.await
+			.map_err(|e| FileIOError::from((&partial_path, e)))?;
+
+		Ok(new_run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+// These functions are intended to be called in a `spawn_blocking` task,
+// as neither the `zip` nor the `tar` crates are async.
+
+/// Appends a single file or directory to the staging archive, returning its new size
+fn append_entry(
+	format: ArchiveFormat,
+	staging_path: &Path,
+	committed_len: u64,
+	full_path: &Path,
+	entry_name: &str,
+	is_dir: bool,
+) -> Result<u64, ArchiveError> {
+	let staging_io_err = |e| FileIOError::from((staging_path, e));
+	let source_io_err = |e| FileIOError::from((full_path, e));
+
+	let mut staging_file = OpenOptions::new()
+		.read(true)
+		.write(true)
+		.create(true)
+		.truncate(false)
+		.open(staging_path)
+		.map_err(staging_io_err)?;
+
+	staging_file
+		.set_len(committed_len)
+		.map_err(staging_io_err)?;
+
+	let mut staging_file = match format {
+		ArchiveFormat::Zip => {
+			let mut zip = if committed_len == 0 {
+				ZipWriter::new(staging_file)
+			} else {
+				ZipWriter::new_append(staging_file)?
+			};
+
+			let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+			if is_dir {
+				zip.add_directory(entry_name, options)?;
+			} else {
+				let mut source_file = File::open(full_path).map_err(source_io_err)?;
+				let size = source_file.metadata().map_err(source_io_err)?.len();
+
+				zip.start_file(entry_name, options.large_file(size > u32::MAX as u64))?;
+				io::copy(&mut source_file, &mut zip).map_err(source_io_err)?;
+			}
+
+			zip.finish()?
+		}
+		ArchiveFormat::Tar | ArchiveFormat::TarGz | ArchiveFormat::TarZst => {
+			// Overwriting the end of archive marker written by the previous step
+			staging_file
+				.seek(SeekFrom::Start(
+					committed_len.saturating_sub(TAR_END_OF_ARCHIVE_LEN),
+				))
+				.map_err(staging_io_err)?;
+
+			let mut tar = tar::Builder::new(staging_file);
+			tar.follow_symlinks(false);
+
+			if is_dir {
+				tar.append_dir(entry_name, full_path)
+			} else {
+				tar.append_path_with_name(full_path, entry_name)
+			}
+			.map_err(source_io_err)?;
+
+			// Also writes the end of archive marker back
+			tar.into_inner().map_err(staging_io_err)?
+		}
+	};
+
+	Ok(staging_file.stream_position().map_err(staging_io_err)?)
+}
+
+/// Compresses the staging archive into its final path, or just moves it there
+fn finish_archive(
+	format: ArchiveFormat,
+	staging_path: &Path,
+	archive_path: &Path,
+) -> Result<(), ArchiveError> {
+	let staging_io_err = |e| FileIOError::from((staging_path, e));
+	let archive_io_err = |e| FileIOError::from((archive_path, e));
+
+	match format {
+		ArchiveFormat::Zip | ArchiveFormat::Tar => {
+			std::fs::rename(staging_path, archive_path).map_err(archive_io_err)?;
+			return Ok(());
+		}
+		ArchiveFormat::TarGz => {
+			let mut staging_file = File::open(staging_path).map_err(staging_io_err)?;
+			let mut encoder = GzEncoder::new(
+				File::create(archive_path).map_err(archive_io_err)?,
+				Compression::default(),
+			);
+
+			io::copy(&mut staging_file, &mut encoder).map_err(archive_io_err)?;
+			encoder.finish().map_err(archive_io_err)?;
+		}
+		ArchiveFormat::TarZst => {
+			zstd::stream::copy_encode(
+				File::open(staging_path).map_err(staging_io_err)?,
+				File::create(archive_path).map_err(archive_io_err)?,
+				zstd::DEFAULT_COMPRESSION_LEVEL,
+			)
+			.map_err(archive_io_err)?;
+		}
+	}
+
+	Ok(std::fs::remove_file(staging_path).map_err(staging_io_err)?)
+}
+
+/// Extracts every entry of an archive into `target_directory_path`, calling `on_entry`
+/// with the name of each one, and returns how many entries were extracted.
+///
+/// Entries that would end up outside of `target_directory_path` are skipped.
+fn extract_archive(
+	format: ArchiveFormat,
+	archive_path: &Path,
+	target_directory_path: &Path,
+	mut on_entry: impl FnMut(&str),
+) -> Result<u64, ArchiveError> {
+	let archive_io_err = |e| FileIOError::from((archive_path, e));
+	let target_io_err = |e| FileIOError::from((target_directory_path, e));
+
+	std::fs::create_dir_all(target_directory_path).map_err(target_io_err)?;
+
+	let archive_file = File::open(archive_path).map_err(archive_io_err)?;
+
+	let unpack_tar = |reader: Box<dyn Read>, on_entry: &mut dyn FnMut(&str)| {
+		let mut tar = tar::Archive::new(reader);
+		let mut extracted_entries = 0;
+
+		for entry in tar.entries().map_err(archive_io_err)? {
+			let mut entry = entry.map_err(archive_io_err)?;
+			let entry_name = entry
+				.path()
+				.map_err(archive_io_err)?
+				.to_string_lossy()
+				.to_string();
+
+			if entry
+				.unpack_in(target_directory_path)
+				.map_err(target_io_err)?
+			{
+				extracted_entries += 1;
+				on_entry(&entry_name);
+			} else {
+				warn!("Skipping extracting unsafe archive entry: {entry_name}");
+			}
+		}
+
+		Ok::<_, ArchiveError>(extracted_entries)
+	};
+
+	match format {
+		ArchiveFormat::Zip => {
+			let mut zip = ZipArchive::new(archive_file)?;
+			let mut extracted_entries = 0;
+
+			for i in 0..zip.len() {
+				let mut entry = zip.by_index(i)?;
+
+				let Some(entry_path) = entry
+					.enclosed_name()
+					.map(|name| target_directory_path.join(name))
+				else {
+					warn!("Skipping extracting unsafe archive entry: {}", entry.name());
+					continue;
+				};
+
+				let entry_io_err = |e| FileIOError::from((&entry_path, e));
+
+				if entry.is_dir() {
+					std::fs::create_dir_all(&entry_path).map_err(entry_io_err)?;
+				} else {
+					if let Some(parent) = entry_path.parent() {
+						std::fs::create_dir_all(parent).map_err(entry_io_err)?;
+					}
+
+					io::copy(
+						&mut entry,
+						&mut File::create(&entry_path).map_err(entry_io_err)?,
+					)
+					.map_err(entry_io_err)?;
+				}
+
+				extracted_entries += 1;
+				on_entry(entry.name());
+			}
+
+			Ok(extracted_entries)
+		}
+		ArchiveFormat::Tar => unpack_tar(Box::new(archive_file), &mut on_entry),
+		ArchiveFormat::TarGz => unpack_tar(Box::new(GzDecoder::new(archive_file)), &mut on_entry),
+		ArchiveFormat::TarZst => unpack_tar(
+			Box::new(zstd::stream::read::Decoder::new(archive_file).map_err(archive_io_err)?),
+			&mut on_entry,
+		),
+	}
+}
+
+/// Opens a tar archive of any of the supported compressions
+fn open_tar(
+	format: ArchiveFormat,
+	archive_path: &Path,
+) -> Result<tar::Archive<Box<dyn Read>>, ArchiveError> {
+	let archive_io_err = |e| FileIOError::from((archive_path, e));
+	let archive_file = File::open(archive_path).map_err(archive_io_err)?;
+
+	Ok(tar::Archive::new(match format {
+		ArchiveFormat::TarGz => Box::new(GzDecoder::new(archive_file)),
+		ArchiveFormat::TarZst => {
+			Box::new(zstd::stream::read::Decoder::new(archive_file).map_err(archive_io_err)?)
+		}
+		ArchiveFormat::Tar | ArchiveFormat::Zip => Box::new(archive_file),
+	}))
+}
+
+/// Normalizes an archive entry path, rejecting anything that could escape the archive root
+fn normalize_entry_path(path: &Path) -> Option<String> {
+	use std::path::Component;
+
+	let mut normalized = Vec::new();
+
+	for component in path.components() {
+		match component {
+			Component::Normal(part) => normalized.push(part.to_str()?),
+			Component::CurDir => {}
+			_ => return None,
+		}
+	}
+
+	(!normalized.is_empty()).then(|| normalized.join("/"))
+}
+
+fn zip_date_to_utc(date: zip::DateTime) -> Option<DateTime<Utc>> {
+	// Zip dates don't carry a timezone, so we just assume UTC
+	NaiveDate::from_ymd_opt(date.year().into(), date.month().into(), date.day().into())
+		.and_then(|day| {
+			day.and_hms_opt(
+				date.hour().into(),
+				date.minute().into(),
+				date.second().into(),
+			)
+		})
+		.map(|date_time| Utc.from_utc_datetime(&date_time))
+}
+
+/// Lists the direct children of `directory` inside an archive, where an empty `directory`
+/// is the archive root.
+///
+/// Archives don't always have entries for their directories, so those are synthesized
+/// from the paths of the files inside of them.
+pub fn list_archive_entries(
+	format: ArchiveFormat,
+	archive_path: &Path,
+	directory: &str,
+) -> Result<Vec<ArchiveEntry>, ArchiveError> {
+	let archive_io_err = |e| FileIOError::from((archive_path, e));
+
+	let mut children = BTreeMap::new();
+	let mut directory_found = directory.is_empty();
+
+	let mut add_entry = |path: String, is_dir, size, date_modified| {
+		let relative = if directory.is_empty() {
+			path.as_str()
+		} else if path == directory {
+			directory_found = true;
+			return;
+		} else if let Some(relative) = path
+			.strip_prefix(directory)
+			.and_then(|relative| relative.strip_prefix('/'))
+		{
+			relative
+		} else {
+			return;
+		};
+
+		directory_found = true;
+
+		match relative.split_once('/') {
+			Some((child, _)) => {
+				let child_path = if directory.is_empty() {
+					child.to_string()
+				} else {
+					format!("{directory}/{child}")
+				};
+
+				children
+					.entry(child.to_string())
+					.or_insert_with(|| ArchiveEntry {
+						path: child_path,
+						is_dir: true,
+						size: 0,
+						date_modified: None,
+					});
+			}
+			None => {
+				children.insert(
+					relative.to_string(),
+					ArchiveEntry {
+						path,
+						is_dir,
+						size,
+						date_modified,
+					},
+				);
+			}
+		}
+	};
+
+	if format == ArchiveFormat::Zip {
+		let mut zip = ZipArchive::new(File::open(archive_path).map_err(archive_io_err)?)?;
+
+		for i in 0..zip.len() {
+			let entry = zip.by_index(i)?;
+
+			let Some(path) = entry.enclosed_name().and_then(normalize_entry_path) else {
+				warn!("Skipping listing unsafe archive entry: {}", entry.name());
+				continue;
+			};
+
+			add_entry(
+				path,
+				entry.is_dir(),
+				entry.size(),
+				zip_date_to_utc(entry.last_modified()),
+			);
+		}
+	} else {
+		for entry in open_tar(format, archive_path)?
+			.entries()
+			.map_err(archive_io_err)?
+		{
+			let entry = entry.map_err(archive_io_err)?;
+			let header = entry.header();
+
+			let Some(path) = entry.path().ok().as_deref().and_then(normalize_entry_path) else {
+				warn!(
+					"Skipping listing unsafe archive entry: {:?}",
+					header.path_bytes()
+				);
+				continue;
+			};
+
+			add_entry(
+				path,
+				header.entry_type().is_dir(),
+				entry.size(),
+				header
+					.mtime()
+					.ok()
+					.and_then(|mtime| Utc.timestamp_opt(mtime as i64, 0).single()),
+			);
+		}
+	}
+
+	if !directory_found {
+		return Err(ArchiveError::EntryNotFound(
+			archive_path.into(),
+			directory.to_string(),
+		));
+	}
+
+	Ok(children.into_values().collect())
+}
+
+/// Finds a single file inside an archive and hands its size and a reader of its content to `read`.
+/// Compressed archives can't seek, so the reader has to be consumed from the start.
+pub fn read_archive_member<T>(
+	format: ArchiveFormat,
+	archive_path: &Path,
+	member: &str,
+	read: impl FnOnce(u64, &mut dyn Read) -> io::Result<T>,
+) -> Result<T, ArchiveError> {
+	let archive_io_err = |e| FileIOError::from((archive_path, e));
+	let not_found = || ArchiveError::EntryNotFound(archive_path.into(), member.to_string());
+
+	if format == ArchiveFormat::Zip {
+		let mut zip = ZipArchive::new(File::open(archive_path).map_err(archive_io_err)?)?;
+
+		for i in 0..zip.len() {
+			let mut entry = zip.by_index(i)?;
+
+			if !entry.is_dir()
+				&& entry
+					.enclosed_name()
+					.and_then(normalize_entry_path)
+					.as_deref() == Some(member)
+			{
+				return read(entry.size(), &mut entry).map_err(|e| archive_io_err(e).into());
+			}
+		}
+	} else {
+		for entry in open_tar(format, archive_path)?
+			.entries()
+			.map_err(archive_io_err)?
+		{
+			let mut entry = entry.map_err(archive_io_err)?;
+
+			if entry.header().entry_type().is_file()
+				&& entry
+					.path()
+					.ok()
+					.as_deref()
+					.and_then(normalize_entry_path)
+					.as_deref() == Some(member)
+			{
+				return read(entry.size(), &mut entry).map_err(|e| archive_io_err(e).into());
+			}
+		}
+	}
+
+	Err(not_found())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use tempfile::tempdir;
+
+	#[test]
+	fn format_from_file_name() {
+		assert_eq!(
+			ArchiveFormat::from_file_name("photos.TAR.GZ"),
+			Some((ArchiveFormat::TarGz, "photos"))
+		);
+		assert_eq!(
+			ArchiveFormat::from_file_name("backup.2023.tzst"),
+			Some((ArchiveFormat::TarZst, "backup.2023"))
+		);
+		assert_eq!(
+			ArchiveFormat::from_file_name("docs.zip"),
+			Some((ArchiveFormat::Zip, "docs"))
+		);
+		assert_eq!(ArchiveFormat::from_file_name(".zip"), None);
+		assert_eq!(ArchiveFormat::from_file_name("notes.txt"), None);
+	}
+
+	#[test]
+	fn split_virtual_archive_path() {
+		assert_eq!(
+			split_archive_path("/x/foo.zip!/docs/"),
+			Some((ArchiveFormat::Zip, Path::new("/x/foo.zip"), "docs"))
+		);
+		assert_eq!(
+			split_archive_path("/x/wow!/bar.tar.gz!/"),
+			Some((ArchiveFormat::TarGz, Path::new("/x/wow!/bar.tar.gz"), ""))
+		);
+		assert_eq!(split_archive_path("/x/foo.zip"), None);
+		assert_eq!(split_archive_path("/x/wow!/docs"), None);
+	}
+
+	#[test]
+	fn list_and_read_archive_members() {
+		for format in [ArchiveFormat::Zip, ArchiveFormat::TarZst] {
+			let dir = tempdir().unwrap();
+			std::fs::write(dir.path().join("a.txt"), "hello").unwrap();
+
+			let staging_path = dir.path().join("archive.part");
+			let archive_path = dir.path().join(format!("archive.{}", format.extension()));
+
+			// No entries for the `docs` and `docs/nested` directories
+			let mut committed_len = 0;
+			for entry_name in ["a.txt", "docs/b.txt", "docs/nested/c.txt"] {
+				committed_len = append_entry(
+					format,
+					&staging_path,
+					committed_len,
+					&dir.path().join("a.txt"),
+					entry_name,
+					false,
+				)
+				.unwrap();
+			}
+			finish_archive(format, &staging_path, &archive_path).unwrap();
+
+			let root = list_archive_entries(format, &archive_path, "").unwrap();
+			assert_eq!(
+				root.iter()
+					.map(|e| (e.path.as_str(), e.is_dir))
+					.collect::<Vec<_>>(),
+				vec![("a.txt", false), ("docs", true)],
+				"{format:?}"
+			);
+			assert_eq!(root[0].size, 5);
+			assert!(root[0].date_modified.is_some());
+
+			let docs = list_archive_entries(format, &archive_path, "docs").unwrap();
+			assert_eq!(
+				docs.iter().map(|e| e.path.as_str()).collect::<Vec<_>>(),
+				vec!["docs/b.txt", "docs/nested"]
+			);
+
+			assert!(matches!(
+				list_archive_entries(format, &archive_path, "doc"),
+				Err(ArchiveError::EntryNotFound(..))
+			));
+
+			let read_to_end = |size: u64, reader: &mut dyn Read| -> io::Result<_> {
+				let mut buf = vec![];
+				reader.read_to_end(&mut buf)?;
+				Ok((size, buf))
+			};
+
+			assert_eq!(
+				read_archive_member(format, &archive_path, "docs/nested/c.txt", read_to_end)
+					.unwrap(),
+				(5, b"hello".to_vec())
+			);
+			assert!(matches!(
+				read_archive_member(format, &archive_path, "docs", read_to_end),
+				Err(ArchiveError::EntryNotFound(..))
+			));
+		}
+	}
+
+	#[test]
+	fn archive_and_extract_round_trip() {
+		for format in [
+			ArchiveFormat::Zip,
+			ArchiveFormat::Tar,
+			ArchiveFormat::TarGz,
+			ArchiveFormat::TarZst,
+		] {
+			let dir = tempdir().unwrap();
+			let source = dir.path().join("source");
+			std::fs::create_dir_all(source.join("nested")).unwrap();
+			std::fs::write(source.join("a.txt"), "hello").unwrap();
+			std::fs::write(source.join("nested/b.txt"), "world").unwrap();
+
+			let staging_path = dir.path().join("archive.part");
+			let archive_path = dir.path().join(format!("archive.{}", format.extension()));
+
+			let mut committed_len = 0;
+			for (full_path, entry_name, is_dir) in [
+				(source.join("a.txt"), "source/a.txt", false),
+				(source.join("nested/b.txt"), "source/nested/b.txt", false),
+				(source.join("nested"), "source/nested", true),
+				(source.clone(), "source", true),
+			] {
+				// Simulating a step that got interrupted after writing some garbage
+				std::fs::OpenOptions::new()
+					.append(true)
+					.create(true)
+					.open(&staging_path)
+					.and_then(|mut file| io::Write::write_all(&mut file, b"garbage"))
+					.unwrap();
+
+				committed_len = append_entry(
+					format,
+					&staging_path,
+					committed_len,
+					&full_path,
+					entry_name,
+					is_dir,
+				)
+				.unwrap();
+			}
+
+			finish_archive(format, &staging_path, &archive_path).unwrap();
+			assert!(!staging_path.exists());
+
+			let target = dir.path().join("target");
+			let mut entries = vec![];
+			let extracted = extract_archive(format, &archive_path, &target, |entry| {
+				entries.push(entry.trim_end_matches('/').to_string())
+			})
+			.unwrap();
+
+			assert_eq!(extracted, 4, "{format:?}");
+			assert!(entries.contains(&"source/nested/b.txt".to_string()));
+			assert_eq!(
+				std::fs::read_to_string(target.join("source/a.txt")).unwrap(),
+				"hello"
+			);
+			assert_eq!(
+				std::fs::read_to_string(target.join("source/nested/b.txt")).unwrap(),
+				"world"
+			);
+		}
+	}
+}
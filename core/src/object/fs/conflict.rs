@@ -0,0 +1,441 @@
+use crate::{
+	api::{utils::get_size, CoreEvent},
+	job::WorkerContext,
+	util::error::FileIOError,
+};
+
+use std::{
+	collections::{HashMap, HashSet},
+	fs::Metadata,
+	io,
+	path::{Path, PathBuf},
+	sync::{Mutex, PoisonError},
+};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_with::{serde_as, DisplayFromStr};
+use specta::Type;
+use tokio::{fs, sync::oneshot};
+use tracing::{trace, warn};
+use uuid::Uuid;
+
+use super::{error::FileSystemJobsError, find_available_path};
+
+/// What a job does when an item would take the place of one that's already there
+#[derive(Serialize, Deserialize, Type, Hash, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+	/// Leaves the existing item alone and reports the new one as skipped
+	#[default]
+	Skip,
+	Overwrite,
+	/// Keeps the existing item, the new one gets a number appended to its name
+	KeepBoth,
+	/// Keeps whichever of the two was modified last
+	KeepNewer,
+	/// Keeps whichever of the two is larger
+	KeepLarger,
+	/// Pauses the job until the client decides, item by item
+	Ask,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+	Skip,
+	Overwrite,
+	KeepBoth,
+}
+
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy)]
+pub struct ConflictDecision {
+	pub resolution: ConflictResolution,
+	/// Settles every remaining conflict of the job the same way, without asking again
+	pub apply_to_all: bool,
+}
+
+#[serde_as]
+#[derive(Serialize, Type, Debug, Clone)]
+pub struct ConflictingItem {
+	pub path: PathBuf,
+	pub is_dir: bool,
+	#[specta(type = String)]
+	#[serde_as(as = "DisplayFromStr")]
+	pub size: u64,
+	pub date_modified: Option<DateTime<Utc>>,
+}
+
+impl ConflictingItem {
+	fn new(path: &Path, metadata: &Metadata) -> Self {
+		Self {
+			path: path.to_path_buf(),
+			is_dir: metadata.is_dir(),
+			size: metadata.len(),
+			date_modified: metadata.modified().ok().map(Into::into),
+		}
+	}
+}
+
+/// Emitted when a job with the [`ConflictPolicy::Ask`] policy paused to wait for a decision
+#[derive(Serialize, Type, Debug, Clone)]
+pub struct FileConflictEvent {
+	pub id: Uuid,
+	pub library_id: Uuid,
+	pub job_id: Uuid,
+	/// The item being copied or moved
+	pub source: ConflictingItem,
+	/// The item already in its place
+	pub target: ConflictingItem,
+}
+
+/// Conflicts waiting for a decision from the client, along with the paused jobs waiting on them
+#[derive(Default)]
+pub struct FileConflicts(
+	Mutex<HashMap<Uuid, (FileConflictEvent, oneshot::Sender<ConflictDecision>)>>,
+);
+
+impl FileConflicts {
+	pub fn pending(&self) -> Vec<FileConflictEvent> {
+		self.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.values()
+			.map(|(event, _)| event.clone())
+			.collect()
+	}
+
+	/// Hands the decision to the job waiting on the conflict, returning the id of the job to
+	/// resume or `None` if nothing was waiting on it anymore
+	pub fn decide(&self, conflict_id: Uuid, decision: ConflictDecision) -> Option<Uuid> {
+		let (event, decision_tx) = self
+			.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&conflict_id)?;
+
+		decision_tx.send(decision).ok().map(|()| event.job_id)
+	}
+
+	fn register(&self, event: FileConflictEvent) -> oneshot::Receiver<ConflictDecision> {
+		let (decision_tx, decision_rx) = oneshot::channel();
+
+		self.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(event.id, (event, decision_tx));
+
+		decision_rx
+	}
+
+	fn forget(&self, conflict_id: Uuid) {
+		self.0
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&conflict_id);
+	}
+}
+
+/// Removes a conflict from the pending ones when the job stops waiting on it, as canceled and shut
+/// down jobs drop their step halfway through
+struct PendingConflict<'a> {
+	conflicts: &'a FileConflicts,
+	id: Uuid,
+}
+
+impl Drop for PendingConflict<'_> {
+	fn drop(&mut self) {
+		self.conflicts.forget(self.id);
+	}
+}
+
+#[derive(Debug)]
+pub struct Settlement {
+	/// Where the item goes, `None` when it's skipped
+	pub target: Option<PathBuf>,
+	/// Set when the client decided every remaining conflict of the job at once
+	pub decided_for_all: Option<ConflictResolution>,
+	/// Only items skipped by the [`ConflictPolicy::Skip`] policy are reported
+	pub errors: Vec<String>,
+}
+
+/// Settles the conflict between `source` and whatever is at `target`, if anything, making room at
+/// `target` or picking a free name next to it as needed. `decided` is a previous decision of the
+/// client meant for every conflict of the job.
+pub async fn settle_conflict(
+	ctx: &WorkerContext,
+	policy: ConflictPolicy,
+	decided: Option<ConflictResolution>,
+	source: &Path,
+	target: &Path,
+) -> Result<Settlement, FileSystemJobsError> {
+	let mut settlement = Settlement {
+		target: Some(target.to_path_buf()),
+		decided_for_all: None,
+		errors: vec![],
+	};
+
+	let target_metadata = match fs::symlink_metadata(target).await {
+		Ok(metadata) => metadata,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(settlement),
+		Err(e) => return Err(FileIOError::from((target, e)).into()),
+	};
+	let source_metadata = fs::symlink_metadata(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	// Making room at the target would remove the source itself, whatever the policy
+	if is_same_file(source, target).await? {
+		warn!("Skipping {} as it would replace itself", source.display());
+		settlement.target = None;
+		settlement
+			.errors
+			.push(FileSystemJobsError::WouldReplaceItself(source.into()).to_string());
+
+		return Ok(settlement);
+	}
+
+	let resolution = match (policy, decided) {
+		(ConflictPolicy::Skip, _) => {
+			warn!("Skipping {} as it would be overwritten", target.display());
+			settlement
+				.errors
+				.push(FileSystemJobsError::WouldOverwrite(target.into()).to_string());
+
+			ConflictResolution::Skip
+		}
+		(ConflictPolicy::Overwrite, _) => ConflictResolution::Overwrite,
+		(ConflictPolicy::KeepBoth, _) => ConflictResolution::KeepBoth,
+		(ConflictPolicy::KeepNewer, _) => {
+			keep_source_if(source_metadata.modified().ok() > target_metadata.modified().ok())
+		}
+		(ConflictPolicy::KeepLarger, _) => keep_source_if(
+			size_of(source, &source_metadata).await? > size_of(target, &target_metadata).await?,
+		),
+		(ConflictPolicy::Ask, Some(resolution)) => resolution,
+		(ConflictPolicy::Ask, None) => {
+			let decision = ask(
+				ctx,
+				ConflictingItem::new(source, &source_metadata),
+				ConflictingItem::new(target, &target_metadata),
+			)
+			.await;
+
+			if decision.apply_to_all {
+				settlement.decided_for_all = Some(decision.resolution);
+			}
+
+			decision.resolution
+		}
+	};
+
+	trace!(
+		"Settled the conflict between {} and {} with {resolution:?}",
+		source.display(),
+		target.display()
+	);
+
+	settlement.target = match resolution {
+		ConflictResolution::Skip => None,
+		ConflictResolution::Overwrite => {
+			if target_metadata.is_dir() {
+				fs::remove_dir_all(target).await
+			} else {
+				fs::remove_file(target).await
+			}
+			.map_err(|e| FileIOError::from((target, e)))?;
+
+			Some(target.to_path_buf())
+		}
+		ConflictResolution::KeepBoth => {
+			Some(numbered_path(target, source_metadata.is_dir()).await?)
+		}
+	};
+
+	Ok(settlement)
+}
+
+/// Whether `target` is `source` itself under another name, like a differently cased name on a
+/// case-insensitive file system. A target that doesn't exist can't be.
+pub async fn is_same_file(source: &Path, target: &Path) -> Result<bool, FileIOError> {
+	let target = match fs::canonicalize(target).await {
+		Ok(target) => target,
+		Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+		Err(e) => return Err(FileIOError::from((target, e))),
+	};
+
+	fs::canonicalize(source)
+		.await
+		.map(|source| source == target)
+		.map_err(|e| FileIOError::from((source, e)))
+}
+
+/// Where a job writes an item before moving it to the `target` it settled on, so an item left
+/// halfway by an interrupted step isn't taken for a conflicting one once the job resumes
+pub fn partial_path_of(target: &Path) -> PathBuf {
+	let mut file_name = target.file_name().unwrap_or_default().to_os_string();
+	file_name.push(".part");
+
+	target.with_file_name(file_name)
+}
+
+fn keep_source_if(source_wins: bool) -> ConflictResolution {
+	if source_wins {
+		ConflictResolution::Overwrite
+	} else {
+		ConflictResolution::Skip
+	}
+}
+
+async fn size_of(path: &Path, metadata: &Metadata) -> Result<u64, FileIOError> {
+	if metadata.is_dir() {
+		get_size(path)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))
+	} else {
+		Ok(metadata.len())
+	}
+}
+
+/// Picks a free `{stem} ({n}).{extension}` name next to `target`, directories keep their whole name
+/// as the stem
+async fn numbered_path(target: &Path, is_dir: bool) -> Result<PathBuf, FileIOError> {
+	let directory = target.parent().unwrap_or(Path::new(""));
+
+	let (stem, extension) = if is_dir {
+		(target.file_name(), None)
+	} else {
+		(target.file_stem(), target.extension())
+	};
+
+	find_available_path(
+		directory,
+		&stem.unwrap_or_default().to_string_lossy(),
+		extension
+			.map(|extension| extension.to_string_lossy())
+			.as_deref(),
+		&mut HashSet::new(),
+	)
+	.await
+}
+
+/// Pauses the job and waits for the client to decide. A job that's resumed without a decision
+/// keeps waiting, while canceling or shutting it down drops the conflict, which is asked again
+/// once the job is resumed after a shutdown.
+async fn ask(
+	ctx: &WorkerContext,
+	source: ConflictingItem,
+	target: ConflictingItem,
+) -> ConflictDecision {
+	let event = FileConflictEvent {
+		id: Uuid::new_v4(),
+		library_id: ctx.library.id,
+		job_id: ctx.worker_id,
+		source,
+		target,
+	};
+
+	ctx.progress_msg(format!(
+		"Waiting for a decision on {}",
+		event.target.path.display()
+	));
+
+	let pending = PendingConflict {
+		conflicts: &ctx.library.file_conflicts,
+		id: event.id,
+	};
+	let decision_rx = pending.conflicts.register(event.clone());
+
+	ctx.library.emit(CoreEvent::FileConflict(event));
+
+	if let Err(e) = ctx.node.jobs.pause(ctx.worker_id).await {
+		warn!("Failed to pause the job waiting for a conflict to be settled: {e:#?}");
+	}
+
+	let decision = decision_rx.await.unwrap_or(ConflictDecision {
+		resolution: ConflictResolution::Skip,
+		apply_to_all: false,
+	});
+
+	drop(pending);
+
+	decision
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn kept_copies_are_numbered() {
+		let dir = tempdir().unwrap();
+
+		fs::write(dir.path().join("photo.jpg"), b"").await.unwrap();
+		fs::write(dir.path().join("photo (1).jpg"), b"")
+			.await
+			.unwrap();
+		fs::create_dir(dir.path().join("album.2023")).await.unwrap();
+
+		assert_eq!(
+			numbered_path(&dir.path().join("photo.jpg"), false)
+				.await
+				.unwrap(),
+			dir.path().join("photo (2).jpg")
+		);
+		assert_eq!(
+			numbered_path(&dir.path().join("album.2023"), true)
+				.await
+				.unwrap(),
+			dir.path().join("album.2023 (1)")
+		);
+	}
+
+	#[tokio::test]
+	async fn same_file_under_another_path() {
+		let dir = tempdir().unwrap();
+		let photo = dir.path().join("photo.png");
+		fs::write(&photo, b"").await.unwrap();
+
+		assert!(
+			is_same_file(&photo, &dir.path().join(".").join("photo.png"))
+				.await
+				.unwrap()
+		);
+		assert!(!is_same_file(&photo, &dir.path().join("photo.webp"))
+			.await
+			.unwrap());
+	}
+
+	#[test]
+	fn decisions_reach_the_waiting_job() {
+		let conflicts = FileConflicts::default();
+		let item = ConflictingItem {
+			path: PathBuf::from("/a"),
+			is_dir: false,
+			size: 0,
+			date_modified: None,
+		};
+		let event = FileConflictEvent {
+			id: Uuid::new_v4(),
+			library_id: Uuid::new_v4(),
+			job_id: Uuid::new_v4(),
+			source: item.clone(),
+			target: item,
+		};
+		let decision = ConflictDecision {
+			resolution: ConflictResolution::KeepBoth,
+			apply_to_all: true,
+		};
+
+		let mut decision_rx = conflicts.register(event.clone());
+		assert_eq!(conflicts.pending().len(), 1);
+
+		assert_eq!(conflicts.decide(event.id, decision), Some(event.job_id));
+		assert_eq!(
+			decision_rx.try_recv().unwrap().resolution,
+			ConflictResolution::KeepBoth
+		);
+		assert!(conflicts.pending().is_empty());
+		assert_eq!(conflicts.decide(event.id, decision), None);
+	}
+}
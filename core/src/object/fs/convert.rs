@@ -0,0 +1,468 @@
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::{
+		file_path_helper::join_location_relative_path, light_scan_location,
+		location_with_indexer_rules,
+	},
+	prisma::{file_path, location},
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use sd_images::format_image;
+use sd_media_metadata::image::Orientation;
+
+use std::{
+	hash::Hash,
+	io::Cursor,
+	path::{Path, PathBuf},
+};
+
+use image::{codecs::jpeg::JpegEncoder, imageops::FilterType, DynamicImage, ImageOutputFormat};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use thiserror::Error;
+use tokio::{fs, task::spawn_blocking};
+use tracing::error;
+
+use super::{
+	conflict::{
+		is_same_file, partial_path_of, settle_conflict, ConflictPolicy, ConflictResolution,
+		Settlement,
+	},
+	error::FileSystemJobsError,
+	get_location_path_from_location_id, get_many_files_datas,
+};
+
+/// Quality used for lossy formats when none is requested
+const DEFAULT_QUALITY: u8 = 90;
+
+#[derive(Error, Debug)]
+pub enum FileConverterError {
+	#[error("failed to load image: <path='{}'>: {1}", .0.display())]
+	Load(Box<Path>, sd_images::Error),
+	#[error("failed to encode image: <path='{}'>: {1}", .0.display())]
+	Encode(Box<Path>, String),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+}
+
+/// Formats that images can be converted to. Anything [`format_image`] can load
+/// (including HEIF and SVG) can be converted into one of these.
+#[derive(Serialize, Deserialize, Hash, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConvertImageFormat {
+	Png,
+	Jpeg,
+	Webp,
+}
+
+impl ConvertImageFormat {
+	pub const fn extension(self) -> &'static str {
+		match self {
+			Self::Png => "png",
+			Self::Jpeg => "jpg",
+			Self::Webp => "webp",
+		}
+	}
+}
+
+/// Images larger than these dimensions are downscaled to fit them, keeping their aspect ratio
+#[derive(Serialize, Deserialize, Hash, Type, Debug, Clone, Copy)]
+pub struct ImageResize {
+	pub max_width: u32,
+	pub max_height: u32,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileConverterTarget {
+	pub location_id: location::id::Type,
+	pub location_relative_directory_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileConverterJobInit {
+	pub source_location_id: location::id::Type,
+	pub sources_file_path_ids: Vec<file_path::id::Type>,
+	pub format: ConvertImageFormat,
+	#[specta(optional)]
+	pub resize: Option<ImageResize>,
+	/// From 1 to 100, ignored for PNG as it's lossless
+	#[specta(optional)]
+	pub quality: Option<u8>,
+	/// Converted images are written next to their sources if no target is chosen
+	#[specta(optional)]
+	pub target: Option<FileConverterTarget>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileConverterJobData {
+	/// Directories that receive converted images, indexed once the job finishes
+	target_directories: Vec<(location::id::Type, PathBuf)>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileConverterJobStep {
+	source_path: PathBuf,
+	target_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileConverterJobRunMetadata {
+	converted_images: u32,
+	/// The client's decision for every remaining conflict, once it made one
+	#[serde(default)]
+	conflict_decision: Option<ConflictResolution>,
+}
+
+impl JobRunMetadata for FileConverterJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.converted_images += new_data.converted_images;
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for FileConverterJobInit {
+	type Data = FileConverterJobData;
+	type Step = FileConverterJobStep;
+	type RunMetadata = FileConverterJobRunMetadata;
+
+	const NAME: &'static str = "file_converter";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let sources_location_path =
+			get_location_path_from_location_id(db, init.source_location_id).await?;
+
+		let target = match &init.target {
+			Some(FileConverterTarget {
+				location_id,
+				location_relative_directory_path,
+			}) => Some((
+				*location_id,
+				join_location_relative_path(
+					get_location_path_from_location_id(db, *location_id).await?,
+					location_relative_directory_path,
+				),
+			)),
+			None => None,
+		};
+
+		let mut steps = vec![];
+		let mut errors = vec![];
+		let mut target_directories = vec![];
+
+		for file_data in
+			get_many_files_datas(db, &sources_location_path, &init.sources_file_path_ids).await?
+		{
+			if maybe_missing(file_data.file_path.is_dir, "file_path.is_dir")? {
+				errors.push(format!(
+					"Skipping converting {} as it's a directory",
+					file_data.full_path.display()
+				));
+				continue;
+			}
+
+			let (target_location_id, target_directory_path) = match &target {
+				Some((location_id, directory_path)) => (*location_id, directory_path.clone()),
+				None => (
+					init.source_location_id,
+					file_data
+						.full_path
+						.parent()
+						.map(Path::to_path_buf)
+						.unwrap_or_else(|| sources_location_path.clone()),
+				),
+			};
+
+			let target_path = target_directory_path.join(format!(
+				"{}.{}",
+				maybe_missing(&file_data.file_path.name, "file_path.name")?,
+				init.format.extension()
+			));
+
+			// Converting an image to its own format would have it take its own place
+			if is_same_file(&file_data.full_path, &target_path).await? {
+				errors.push(format!(
+					"Skipping converting {} as it would replace itself",
+					file_data.full_path.display()
+				));
+				continue;
+			}
+
+			let target_directory = (target_location_id, target_directory_path);
+			if !target_directories.contains(&target_directory) {
+				target_directories.push(target_directory);
+			}
+
+			steps.push(FileConverterJobStep {
+				source_path: file_data.full_path,
+				target_path,
+			});
+		}
+
+		*data = Some(FileConverterJobData { target_directories });
+
+		Ok((Default::default(), steps, JobRunErrors(errors)).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: FileConverterJobStep {
+				source_path,
+				target_path,
+			},
+			..
+		}: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		if is_same_file(source_path, target_path).await? {
+			return Ok(JobRunErrors(vec![FileSystemJobsError::WouldReplaceItself(
+				source_path.clone().into_boxed_path(),
+			)
+			.to_string()])
+			.into());
+		}
+
+		let Settlement {
+			target,
+			decided_for_all,
+			errors,
+		} = settle_conflict(
+			ctx,
+			init.conflict_policy,
+			run_metadata.conflict_decision,
+			source_path,
+			target_path,
+		)
+		.await?;
+
+		let mut new_run_metadata = FileConverterJobRunMetadata {
+			conflict_decision: decided_for_all,
+			..Default::default()
+		};
+
+		let Some(target_path) = target else {
+			return Ok((new_run_metadata, JobRunErrors(errors)).into());
+		};
+
+		ctx.progress_msg(format!(
+			"Converting {}",
+			source_path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+		));
+
+		let conversion = spawn_blocking({
+			let (source_path, format, resize) = (source_path.clone(), init.format, init.resize);
+			let quality = init.quality.unwrap_or(DEFAULT_QUALITY).clamp(1, 100);
+
+			move || convert_image(&source_path, format, resize, quality)
+		})
+		.await?;
+
+		// A single image failing to convert shouldn't stop the others
+		let image = match conversion {
+			Ok(image) => image,
+			Err(e) => return Ok((new_run_metadata, JobRunErrors(vec![e.to_string()])).into()),
+		};
+
+		// If we were interrupted while writing, the same partial path is written again on resume
+		let partial_path = partial_path_of(&target_path);
+		fs::write(&partial_path, image)
+			.await
+			.map_err(|e| FileIOError::from((&partial_path, e)))?;
+		fs::rename(&partial_path, &target_path)
+			.await
+			.map_err(|e| FileIOError::from((&partial_path, e)))?;
+
+		new_run_metadata.converted_images = 1;
+
+		Ok(new_run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		// Indexing the converted images right away, instead of waiting for the watcher or a full scan
+		for (location_id, directory_path) in &data.target_directories {
+			let Some(location) = ctx
+				.library
+				.db
+				.location()
+				.find_unique(location::id::equals(*location_id))
+				.include(location_with_indexer_rules::include())
+				.exec()
+				.await?
+			else {
+				error!(
+					"Location <id='{location_id}'> vanished before we could index converted images"
+				);
+				continue;
+			};
+
+			if let Err(e) = light_scan_location(
+				ctx.node.clone(),
+				ctx.library.clone(),
+				location,
+				directory_path,
+			)
+			.await
+			{
+				error!(
+					"Failed to index converted images at {}: {e:#?}",
+					directory_path.display()
+				);
+			}
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({ "init": init, "run_metadata": run_metadata })))
+	}
+}
+
+/// Loads an image with [`format_image`], applying its EXIF orientation and the optional resize,
+/// then encodes it into `format`.
+///
+/// This function is intended to be called in a `spawn_blocking` task, as image decoding and
+/// encoding are CPU bound.
+pub fn convert_image(
+	source_path: &Path,
+	format: ConvertImageFormat,
+	resize: Option<ImageResize>,
+	quality: u8,
+) -> Result<Vec<u8>, FileConverterError> {
+	let encode_err =
+		|e: &dyn ToString| FileConverterError::Encode(source_path.into(), e.to_string());
+
+	let mut img = format_image(source_path).map_err(|e| match e {
+		sd_images::Error::Io(e) => FileIOError::from((source_path, e)).into(),
+		e => FileConverterError::Load(source_path.into(), e),
+	})?;
+
+	// Orientation isn't carried over to the converted image, so we apply it to the pixels instead
+	if let Some(orientation) = Orientation::from_path(source_path) {
+		img = orientation.correct_thumbnail(img);
+	}
+
+	if let Some(ImageResize {
+		max_width,
+		max_height,
+	}) = resize
+	{
+		if img.width() > max_width || img.height() > max_height {
+			img = img.resize(max_width.max(1), max_height.max(1), FilterType::Lanczos3);
+		}
+	}
+
+	let mut buf = vec![];
+
+	match format {
+		ConvertImageFormat::Png => img
+			.write_to(&mut Cursor::new(&mut buf), ImageOutputFormat::Png)
+			.map_err(|e| encode_err(&e))?,
+		// JPEG has no alpha channel
+		ConvertImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut buf, quality)
+			.encode_image(&DynamicImage::ImageRgb8(img.to_rgb8()))
+			.map_err(|e| encode_err(&e))?,
+		ConvertImageFormat::Webp => {
+			// The webp encoder only accepts 8 bits RGB(A) images
+			let img = match img {
+				DynamicImage::ImageRgb8(_) | DynamicImage::ImageRgba8(_) => img,
+				img => DynamicImage::ImageRgba8(img.to_rgba8()),
+			};
+
+			buf.extend_from_slice(
+				&webp::Encoder::from_image(&img)
+					.map_err(|e| encode_err(&e))?
+					.encode(f32::from(quality)),
+			);
+		}
+	}
+
+	Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use image::{GenericImageView, ImageFormat, Rgba, RgbaImage};
+	use tempfile::tempdir;
+
+	#[test]
+	fn convert_and_downscale() {
+		let dir = tempdir().unwrap();
+		let source_path = dir.path().join("source.png");
+		RgbaImage::from_pixel(400, 200, Rgba([255, 0, 0, 128]))
+			.save(&source_path)
+			.unwrap();
+
+		for (format, image_format) in [
+			(ConvertImageFormat::Png, ImageFormat::Png),
+			(ConvertImageFormat::Jpeg, ImageFormat::Jpeg),
+			(ConvertImageFormat::Webp, ImageFormat::WebP),
+		] {
+			let converted = convert_image(
+				&source_path,
+				format,
+				Some(ImageResize {
+					max_width: 100,
+					max_height: 100,
+				}),
+				DEFAULT_QUALITY,
+			)
+			.unwrap();
+
+			assert_eq!(image::guess_format(&converted).unwrap(), image_format);
+			assert_eq!(
+				image::load_from_memory(&converted).unwrap().dimensions(),
+				(100, 50),
+				"{format:?}"
+			);
+		}
+	}
+
+	#[test]
+	fn fails_on_non_images() {
+		let dir = tempdir().unwrap();
+		let source_path = dir.path().join("notes.png");
+		std::fs::write(&source_path, "not an image").unwrap();
+
+		assert!(matches!(
+			convert_image(&source_path, ConvertImageFormat::Png, None, DEFAULT_QUALITY),
+			Err(FileConverterError::Load(..))
+		));
+	}
+}
@@ -1,11 +1,14 @@
 use crate::{
 	invalidate_query,
 	job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobStepOutput, StatefulJob,
-		WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
 	},
 	library::Library,
-	location::file_path_helper::{join_location_relative_path, IsolatedFilePathData},
+	location::{
+		file_path_helper::{join_location_relative_path, IsolatedFilePathData},
+		find_location, light_scan_location, location_with_indexer_rules, LocationError,
+	},
 	prisma::{file_path, location},
 	util::{
 		db::{maybe_missing, MissingFieldError},
@@ -13,22 +16,33 @@ use crate::{
 	},
 };
 
-use std::{hash::Hash, path::PathBuf};
+use std::{collections::BTreeSet, hash::Hash, io, path::PathBuf};
 
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
-use tokio::{fs, io};
-use tracing::{trace, warn};
+use tokio::fs;
+use tracing::{error, trace, warn};
 
 use super::{
-	construct_target_filename, error::FileSystemJobsError, fetch_source_and_target_location_paths,
-	get_file_data_from_isolated_file_path, get_many_files_datas, FileData,
+	conflict::{settle_conflict, ConflictPolicy, ConflictResolution, Settlement},
+	construct_target_filename,
+	error::FileSystemJobsError,
+	fetch_source_and_target_location_paths, get_file_data_from_isolated_file_path,
+	get_many_files_datas,
+	journal::{journal, InverseAction, JournalEntry, JournalReplay},
+	transfer::{copy_file, finish_copy, partial_path, preserve_metadata},
+	FileData,
 };
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FileCopierJobData {
 	sources_location_path: PathBuf,
+	/// The copies about to be made, as long as their targets were free
+	#[serde(default)]
+	journal: Vec<JournalEntry>,
 }
 
 #[derive(Serialize, Deserialize, Hash, Type, Debug)]
@@ -38,6 +52,18 @@ pub struct FileCopierJobInit {
 	pub sources_file_path_ids: Vec<file_path::id::Type>,
 	pub target_location_relative_directory_path: PathBuf,
 	pub target_file_name_suffix: Option<String>,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+	/// Checks every copied file against its source with a BLAKE3 checksum, removing the copies
+	/// that don't match
+	#[serde(default)]
+	#[specta(optional)]
+	pub verify: bool,
+	/// Set on jobs undoing or redoing a journaled operation, which aren't journaled by themselves
+	#[serde(default)]
+	#[specta(optional)]
+	pub journal_replay: Option<JournalReplay>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -46,11 +72,37 @@ pub struct FileCopierJobStep {
 	pub target_full_path: PathBuf,
 }
 
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileCopierJobRunMetadata {
+	/// The client's decision for every remaining conflict, once it made one
+	conflict_decision: Option<ConflictResolution>,
+	/// Copies kept next to an existing item under a new name
+	renamed_copies: Vec<JournalEntry>,
+	/// Directories copied as `(source, target)`, their metadata is carried over once all of
+	/// their contents were copied
+	#[serde(default)]
+	copied_directories: Vec<(PathBuf, PathBuf)>,
+	/// Checksums of the copied files, as `(target, checksum)`, to set on their new file_paths
+	#[serde(default)]
+	checksums: Vec<(PathBuf, String)>,
+}
+
+impl JobRunMetadata for FileCopierJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+		self.renamed_copies.extend(new_data.renamed_copies);
+		self.copied_directories.extend(new_data.copied_directories);
+		self.checksums.extend(new_data.checksums);
+	}
+}
+
 #[async_trait::async_trait]
 impl StatefulJob for FileCopierJobInit {
 	type Data = FileCopierJobData;
 	type Step = FileCopierJobStep;
-	type RunMetadata = ();
+	type RunMetadata = FileCopierJobRunMetadata;
 
 	const NAME: &'static str = "file_copier";
 
@@ -92,8 +144,20 @@ impl StatefulJob for FileCopierJobInit {
 			})
 			.collect::<Vec<_>>();
 
+		let mut journal = Vec::with_capacity(steps.len());
+		for step in &steps {
+			if fs::symlink_metadata(&step.target_full_path).await.is_err() {
+				journal.push(JournalEntry::new(
+					step.source_file_data.file_path.pub_id.clone(),
+					step.source_file_data.full_path.clone(),
+					step.target_full_path.clone(),
+				));
+			}
+		}
+
 		*data = Some(FileCopierJobData {
 			sources_location_path,
+			journal,
 		});
 
 		Ok(steps.into())
@@ -103,23 +167,74 @@ impl StatefulJob for FileCopierJobInit {
 		&self,
 		ctx: &WorkerContext,
 		CurrentStep {
-			step: FileCopierJobStep {
-				source_file_data,
-				target_full_path,
-			},
+			step:
+				FileCopierJobStep {
+					source_file_data,
+					target_full_path: step_target_full_path,
+				},
 			..
 		}: CurrentStep<'_, Self::Step>,
 		data: &Self::Data,
-		_: &Self::RunMetadata,
+		run_metadata: &Self::RunMetadata,
 	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
 		let init = self;
 
-		if maybe_missing(source_file_data.file_path.is_dir, "file_path.is_dir")? {
+		if &source_file_data.full_path == step_target_full_path {
+			// File is already here, do nothing
+			return Ok(None.into());
+		}
+
+		let is_dir = maybe_missing(source_file_data.file_path.is_dir, "file_path.is_dir")?;
+
+		// Directories are merged into the ones already there, only their contents can conflict
+		let Settlement {
+			target,
+			decided_for_all,
+			errors,
+		} = if is_dir
+			&& fs::metadata(step_target_full_path)
+				.await
+				.map_or(false, |metadata| metadata.is_dir())
+		{
+			Settlement {
+				target: Some(step_target_full_path.clone()),
+				decided_for_all: None,
+				errors: vec![],
+			}
+		} else {
+			settle_conflict(
+				ctx,
+				init.conflict_policy,
+				run_metadata.conflict_decision,
+				&source_file_data.full_path,
+				step_target_full_path,
+			)
+			.await?
+		};
+
+		let mut new_run_metadata = FileCopierJobRunMetadata {
+			conflict_decision: decided_for_all,
+			..Default::default()
+		};
+
+		let Some(target_full_path) = target else {
+			return Ok((new_run_metadata, JobRunErrors(errors)).into());
+		};
+
+		if &target_full_path != step_target_full_path {
+			new_run_metadata.renamed_copies.push(JournalEntry::new(
+				source_file_data.file_path.pub_id.clone(),
+				source_file_data.full_path.clone(),
+				target_full_path.clone(),
+			));
+		}
+
+		if is_dir {
 			let mut more_steps = Vec::new();
 
-			fs::create_dir_all(target_full_path)
+			fs::create_dir_all(&target_full_path)
 				.await
-				.map_err(|e| FileIOError::from((target_full_path, e)))?;
+				.map_err(|e| FileIOError::from((&target_full_path, e)))?;
 
 			let mut read_dir = fs::read_dir(&source_file_data.full_path)
 				.await
@@ -172,55 +287,195 @@ impl StatefulJob for FileCopierJobInit {
 				}
 			}
 
-			Ok(more_steps.into())
-		} else if &source_file_data.full_path == target_full_path {
-			// File is already here, do nothing
-			Ok(().into())
+			new_run_metadata
+				.copied_directories
+				.push((source_file_data.full_path.clone(), target_full_path));
+
+			Ok((more_steps, new_run_metadata).into())
 		} else {
-			match fs::metadata(target_full_path).await {
-				Ok(_) => {
-					// only skip as it could be half way through a huge directory copy and run into an issue
-					warn!(
-						"Skipping {} as it would be overwritten",
-						target_full_path.display()
-					);
+			let source = &source_file_data.full_path;
 
-					Ok(JobRunErrors(vec![FileSystemJobsError::WouldOverwrite(
-						target_full_path.clone().into_boxed_path(),
-					)
-					.to_string()])
-					.into())
+			trace!(
+				"Copying from {} to {}",
+				source.display(),
+				target_full_path.display()
+			);
+
+			// The copy is only moved to its target once it's complete, so an interrupted step never
+			// leaves a truncated file there. A partial copy left by one is started over.
+			let partial = partial_path(&target_full_path);
+			if let Err(e) = fs::remove_file(&partial).await {
+				if e.kind() != io::ErrorKind::NotFound {
+					return Err(FileIOError::from((&partial, e)).into());
 				}
-				Err(e) if e.kind() == io::ErrorKind::NotFound => {
-					trace!(
-						"Copying from {} to {}",
-						source_file_data.full_path.display(),
-						target_full_path.display()
-					);
+			}
 
-					fs::copy(&source_file_data.full_path, &target_full_path)
-						.await
-						// Using the ? here because we don't want to increase the completed task
-						// count in case of file system errors
-						.map_err(|e| FileIOError::from((target_full_path, e)))?;
+			// Using the ? here because we don't want to increase the completed task
+			// count in case of file system errors
+			copy_file(source, &partial).await?;
+
+			let checksum = match finish_copy(source, &partial, &target_full_path, init.verify).await
+			{
+				// The copy has the same contents, so it has the same checksum, if it was computed
+				Ok(checksum) => {
+					checksum.or_else(|| source_file_data.file_path.integrity_checksum.clone())
+				}
+				Err(e @ FileSystemJobsError::CopyMismatch(_)) => {
+					warn!(
+						"Removed the copy of {} as it doesn't match its source",
+						source.display()
+					);
 
-					Ok(().into())
+					return Ok((new_run_metadata, JobRunErrors(vec![e.to_string()])).into());
 				}
-				Err(e) => return Err(FileIOError::from((target_full_path, e)).into()),
+				Err(e) => return Err(e.into()),
+			};
+
+			if let Some(checksum) = checksum {
+				new_run_metadata
+					.checksums
+					.push((target_full_path, checksum));
 			}
+
+			Ok(new_run_metadata.into())
 		}
 	}
 
 	async fn finalize(
 		&self,
 		ctx: &WorkerContext,
-		_data: &Option<Self::Data>,
-		_run_metadata: &Self::RunMetadata,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
 	) -> JobResult {
 		let init = self;
 
+		// Deepest directories first, as copying their contents changed their parents too
+		let mut copied_directories = run_metadata.copied_directories.iter().collect::<Vec<_>>();
+		copied_directories
+			.sort_by_key(|(source, _)| std::cmp::Reverse(source.components().count()));
+
+		for (source, target) in copied_directories {
+			match fs::metadata(source).await {
+				Ok(metadata) => {
+					if let Err(e) = preserve_metadata(source, &metadata, target).await {
+						warn!(
+							"Failed to preserve metadata of {}: {e:#?}",
+							target.display()
+						);
+					}
+				}
+				Err(e) => warn!("Failed to read metadata of {}: {e:#?}", source.display()),
+			}
+		}
+
+		if !run_metadata.checksums.is_empty() {
+			if let Err(e) =
+				carry_checksums(ctx, init.target_location_id, &run_metadata.checksums).await
+			{
+				error!("Failed to set the checksums of the copied files: {e:#?}");
+			}
+		}
+
+		if let Some(data) = data {
+			let mut entries = Vec::with_capacity(data.journal.len());
+			for entry in data.journal.iter().chain(&run_metadata.renamed_copies) {
+				if fs::symlink_metadata(&entry.after).await.is_ok() {
+					entries.push(entry.clone());
+				}
+			}
+
+			if let Err(e) = journal(
+				&ctx.library,
+				init.journal_replay,
+				InverseAction::TrashCopies {
+					source_location_id: init.source_location_id,
+					target_location_id: init.target_location_id,
+					target_file_name_suffix: init.target_file_name_suffix.clone(),
+					entries,
+				},
+			)
+			.await
+			{
+				error!("Failed to journal copying files: {e:#?}");
+			}
+		}
+
 		invalidate_query!(ctx.library, "search.paths");
 
 		Ok(Some(json!({ "init": init })))
 	}
 }
+
+/// Indexes the directories holding the copies, unless the watcher already did, so the new
+/// file_paths can get the checksums of their sources
+async fn carry_checksums(
+	ctx: &WorkerContext,
+	location_id: location::id::Type,
+	checksums: &[(PathBuf, String)],
+) -> Result<(), JobError> {
+	let Library { db, sync, .. } = &*ctx.library;
+
+	let location = find_location(&ctx.library, location_id)
+		.include(location_with_indexer_rules::include())
+		.exec()
+		.await?
+		.ok_or(LocationError::IdNotFound(location_id))?;
+	let location_path = PathBuf::from(maybe_missing(&location.path, "location.path")?);
+
+	let directories = checksums
+		.iter()
+		.filter_map(|(target, _)| target.parent())
+		.collect::<BTreeSet<_>>();
+
+	for directory in directories {
+		let Ok(sub_path) = directory.strip_prefix(&location_path) else {
+			warn!("{} isn't in the target location", directory.display());
+			continue;
+		};
+
+		light_scan_location(
+			ctx.node.clone(),
+			ctx.library.clone(),
+			location.clone(),
+			sub_path,
+		)
+		.await?;
+	}
+
+	for (target, checksum) in checksums {
+		let iso_file_path = IsolatedFilePathData::new(location_id, &location_path, target, false)
+			.map_err(FileSystemJobsError::from)?;
+
+		let Some(file_path) = db
+			.file_path()
+			.find_unique((&iso_file_path).into())
+			.select(file_path::select!({ pub_id }))
+			.exec()
+			.await?
+		else {
+			warn!(
+				"{} wasn't indexed, leaving it without a checksum",
+				target.display()
+			);
+			continue;
+		};
+
+		sync.write_op(
+			db,
+			sync.shared_update(
+				prisma_sync::file_path::SyncId {
+					pub_id: file_path.pub_id.clone(),
+				},
+				file_path::integrity_checksum::NAME,
+				json!(checksum),
+			),
+			db.file_path().update(
+				file_path::pub_id::equals(file_path.pub_id),
+				vec![file_path::integrity_checksum::set(Some(checksum.clone()))],
+			),
+		)
+		.await?;
+	}
+
+	Ok(())
+}
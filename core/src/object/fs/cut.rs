@@ -1,25 +1,36 @@
 use crate::{
 	invalidate_query,
 	job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobStepOutput, StatefulJob,
-		WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
 	},
 	library::Library,
 	location::file_path_helper::push_location_relative_path,
-	object::fs::{construct_target_filename, error::FileSystemJobsError},
+	object::fs::construct_target_filename,
 	prisma::{file_path, location},
-	util::error::FileIOError,
+	util::{db::MissingFieldError, error::FileIOError},
 };
 
-use std::{hash::Hash, path::PathBuf};
+use std::{
+	hash::Hash,
+	path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
-use tokio::{fs, io};
-use tracing::{trace, warn};
+use tokio::fs;
+use tracing::{error, trace, warn};
 
-use super::{fetch_source_and_target_location_paths, get_many_files_datas, FileData};
+use super::{
+	conflict::{settle_conflict, ConflictPolicy, ConflictResolution, Settlement},
+	fetch_source_and_target_location_paths, get_many_files_datas,
+	journal::{journal, InverseAction, JournalEntry, JournalReplay},
+	transfer::{
+		copy_chunk, finish_copy, is_cross_device_error, move_symlink, partial_path,
+		preserve_metadata,
+	},
+};
 
 #[derive(Serialize, Deserialize, Hash, Type, Debug)]
 pub struct FileCutterJobInit {
@@ -27,18 +38,62 @@ pub struct FileCutterJobInit {
 	pub target_location_id: location::id::Type,
 	pub sources_file_path_ids: Vec<file_path::id::Type>,
 	pub target_location_relative_directory_path: PathBuf,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+	/// Set on jobs undoing or redoing a journaled operation, which aren't journaled by themselves
+	#[serde(default)]
+	#[specta(optional)]
+	pub journal_replay: Option<JournalReplay>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub struct FileCutterJobData {
-	full_target_directory_path: PathBuf,
+	/// Every item being cut, only the ones that fully left their source are journaled
+	journal: Vec<JournalEntry>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum FileCutterJobStep {
+	/// Moves a file or directory with a rename, falling back to copying it when the target is on
+	/// another filesystem
+	Move { source: PathBuf, target: PathBuf },
+	/// Copies a file or directory to another filesystem and removes the source afterwards
+	CopyAcross { source: PathBuf, target: PathBuf },
+	/// Copies the next chunk of a file to another filesystem, starting at `offset`
+	CopyChunk {
+		source: PathBuf,
+		target: PathBuf,
+		offset: u64,
+	},
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileCutterJobRunMetadata {
+	/// Directories copied to another filesystem as `(source, target)`. The sources are removed,
+	/// if empty, only once all of their contents were moved.
+	copied_directories: Vec<(PathBuf, PathBuf)>,
+	/// The client's decision for every remaining conflict, once it made one
+	conflict_decision: Option<ConflictResolution>,
+	/// Items moved next to an existing one under a new name, as `(source, target)`
+	renamed_targets: Vec<(PathBuf, PathBuf)>,
+}
+
+impl JobRunMetadata for FileCutterJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.copied_directories.extend(new_data.copied_directories);
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+		self.renamed_targets.extend(new_data.renamed_targets);
+	}
 }
 
 #[async_trait::async_trait]
 impl StatefulJob for FileCutterJobInit {
 	type Data = FileCutterJobData;
-	type Step = FileData;
-	type RunMetadata = ();
+	type Step = FileCutterJobStep;
+	type RunMetadata = FileCutterJobRunMetadata;
 
 	const NAME: &'static str = "file_cutter";
 
@@ -63,74 +118,337 @@ impl StatefulJob for FileCutterJobInit {
 			&init.target_location_relative_directory_path,
 		);
 
-		*data = Some(FileCutterJobData {
-			full_target_directory_path,
-		});
+		let mut journal = vec![];
 
-		let steps =
-			get_many_files_datas(db, &sources_location_path, &init.sources_file_path_ids).await?;
+		let steps = get_many_files_datas(db, &sources_location_path, &init.sources_file_path_ids)
+			.await?
+			.into_iter()
+			.map(|file_data| {
+				let target =
+					full_target_directory_path.join(construct_target_filename(&file_data, &None)?);
+
+				journal.push(JournalEntry::new(
+					file_data.file_path.pub_id,
+					file_data.full_path.clone(),
+					target.clone(),
+				));
+
+				Ok(FileCutterJobStep::Move {
+					source: file_data.full_path,
+					target,
+				})
+			})
+			.collect::<Result<Vec<_>, MissingFieldError>>()?;
+
+		*data = Some(FileCutterJobData { journal });
 
 		Ok(steps.into())
 	}
 
 	async fn execute_step(
 		&self,
-		_: &WorkerContext,
-		CurrentStep {
-			step: file_data, ..
-		}: CurrentStep<'_, Self::Step>,
-		data: &Self::Data,
-		_: &Self::RunMetadata,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
 	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
-		let full_output = data
-			.full_target_directory_path
-			.join(construct_target_filename(file_data, &None)?);
-
-		if file_data.full_path == full_output {
-			// File is already here, do nothing
-			Ok(().into())
-		} else {
-			match fs::metadata(&full_output).await {
-				Ok(_) => {
-					warn!(
-						"Skipping {} as it would be overwritten",
-						full_output.display()
-					);
-
-					Ok(JobRunErrors(vec![FileSystemJobsError::WouldOverwrite(
-						full_output.into_boxed_path(),
-					)
-					.to_string()])
-					.into())
+		let init = self;
+
+		match step {
+			FileCutterJobStep::Move { source, target } => {
+				if source == target {
+					// File is already here, do nothing
+					return Ok(None.into());
+				}
+
+				let Settlement {
+					target: settled_target,
+					decided_for_all,
+					errors,
+				} = settle_conflict(
+					ctx,
+					init.conflict_policy,
+					run_metadata.conflict_decision,
+					source,
+					target,
+				)
+				.await?;
+
+				let mut new_run_metadata = FileCutterJobRunMetadata {
+					conflict_decision: decided_for_all,
+					..Default::default()
+				};
+
+				let Some(settled_target) = settled_target else {
+					return Ok((new_run_metadata, JobRunErrors(errors)).into());
+				};
+
+				if &settled_target != target {
+					new_run_metadata
+						.renamed_targets
+						.push((source.clone(), settled_target.clone()));
 				}
-				Err(e) if e.kind() == io::ErrorKind::NotFound => {
-					trace!(
-						"Cutting {} to {}",
-						file_data.full_path.display(),
-						full_output.display()
-					);
-
-					fs::rename(&file_data.full_path, &full_output)
-						.await
-						.map_err(|e| FileIOError::from((&file_data.full_path, e)))?;
-
-					Ok(().into())
+
+				trace!(
+					"Cutting {} to {}",
+					source.display(),
+					settled_target.display()
+				);
+
+				match fs::rename(source, &settled_target).await {
+					Ok(()) => Ok(new_run_metadata.into()),
+					Err(e) if is_cross_device_error(&e) => {
+						trace!(
+							"{} is on another filesystem than {}, copying it instead",
+							settled_target.display(),
+							source.display()
+						);
+
+						Ok((
+							vec![FileCutterJobStep::CopyAcross {
+								source: source.clone(),
+								target: settled_target,
+							}],
+							new_run_metadata,
+						)
+							.into())
+					}
+					Err(e) => Err(FileIOError::from((source, e)).into()),
 				}
+			}
 
-				Err(e) => return Err(FileIOError::from((&full_output, e)).into()),
+			FileCutterJobStep::CopyAcross { source, target } => {
+				copy_across(ctx, init.conflict_policy, run_metadata, source, target).await
 			}
+
+			FileCutterJobStep::CopyChunk {
+				source,
+				target,
+				offset,
+			} => copy_file_chunk(ctx, source, target, *offset, Default::default()).await,
 		}
 	}
 
 	async fn finalize(
 		&self,
 		ctx: &WorkerContext,
-		_data: &Option<Self::Data>,
-		_run_metadata: &Self::RunMetadata,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
 	) -> JobResult {
 		let init = self;
+
+		// Deepest directories first, as their parents can only be removed once they're gone
+		let mut copied_directories = run_metadata.copied_directories.iter().collect::<Vec<_>>();
+		copied_directories
+			.sort_by_key(|(source, _)| std::cmp::Reverse(source.components().count()));
+
+		for (source, target) in copied_directories {
+			match fs::metadata(source).await {
+				Ok(metadata) => {
+					if let Err(e) = preserve_metadata(source, &metadata, target).await {
+						warn!(
+							"Failed to preserve metadata of {}: {e:#?}",
+							target.display()
+						);
+					}
+				}
+				Err(e) => {
+					warn!("Failed to read metadata of {}: {e:#?}", source.display());
+					continue;
+				}
+			}
+
+			// Anything left behind was skipped, so the directory is kept along with it
+			if let Err(e) = fs::remove_dir(source).await {
+				warn!(
+					"Keeping {} as it couldn't be removed: {e:#?}",
+					source.display()
+				);
+			}
+		}
+
+		if let Some(data) = data {
+			let mut entries = Vec::with_capacity(data.journal.len());
+			for entry in &data.journal {
+				let mut entry = entry.clone();
+				if let Some((_, target)) = run_metadata
+					.renamed_targets
+					.iter()
+					.find(|(source, _)| source == &entry.before)
+				{
+					entry.after = target.clone();
+				}
+
+				if entry.before != entry.after
+					&& fs::symlink_metadata(&entry.before).await.is_err()
+					&& fs::symlink_metadata(&entry.after).await.is_ok()
+				{
+					entries.push(entry);
+				}
+			}
+
+			if let Err(e) = journal(
+				&ctx.library,
+				init.journal_replay,
+				InverseAction::CutBack {
+					source_location_id: init.source_location_id,
+					target_location_id: init.target_location_id,
+					entries,
+				},
+			)
+			.await
+			{
+				error!("Failed to journal cutting files: {e:#?}");
+			}
+		}
+
 		invalidate_query!(ctx.library, "search.paths");
 
 		Ok(Some(json!({ "init": init })))
 	}
 }
+
+/// Starts copying a file or directory to another filesystem. Directories are recreated at the
+/// target, merging into one already there or left by an interrupted step, and their entries are
+/// queued as new steps. Files are copied chunk by chunk, symlinks are recreated and never followed.
+async fn copy_across(
+	ctx: &WorkerContext,
+	conflict_policy: ConflictPolicy,
+	run_metadata: &FileCutterJobRunMetadata,
+	source: &Path,
+	target: &Path,
+) -> Result<JobStepOutput<FileCutterJobStep, FileCutterJobRunMetadata>, JobError> {
+	let metadata = fs::symlink_metadata(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	let target_is_dir = fs::symlink_metadata(target)
+		.await
+		.map_or(false, |target_metadata| target_metadata.is_dir());
+
+	let (target, conflict_decision) = if metadata.is_dir() && target_is_dir {
+		(target.to_path_buf(), None)
+	} else {
+		let Settlement {
+			target: settled_target,
+			decided_for_all,
+			errors,
+		} = settle_conflict(
+			ctx,
+			conflict_policy,
+			run_metadata.conflict_decision,
+			source,
+			target,
+		)
+		.await?;
+
+		let Some(settled_target) = settled_target else {
+			return Ok((
+				FileCutterJobRunMetadata {
+					conflict_decision: decided_for_all,
+					..Default::default()
+				},
+				JobRunErrors(errors),
+			)
+				.into());
+		};
+
+		(settled_target, decided_for_all)
+	};
+	let target = target.as_path();
+
+	let new_run_metadata = FileCutterJobRunMetadata {
+		conflict_decision,
+		..Default::default()
+	};
+
+	if metadata.is_symlink() {
+		move_symlink(source, target).await?;
+		return Ok(new_run_metadata.into());
+	}
+
+	if !metadata.is_dir() {
+		return copy_file_chunk(ctx, source, target, 0, new_run_metadata).await;
+	}
+
+	// Its permissions and timestamps are only carried over once everything in it was moved
+	fs::create_dir_all(target)
+		.await
+		.map_err(|e| FileIOError::from((target, e)))?;
+
+	let mut more_steps = Vec::new();
+
+	let mut read_dir = fs::read_dir(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	// Unlike copies, every entry is moved, indexed or not, as the source is removed afterwards
+	while let Some(entry) = read_dir
+		.next_entry()
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?
+	{
+		more_steps.push(FileCutterJobStep::CopyAcross {
+			target: target.join(entry.file_name()),
+			source: entry.path(),
+		});
+	}
+
+	Ok((
+		more_steps,
+		FileCutterJobRunMetadata {
+			copied_directories: vec![(source.to_path_buf(), target.to_path_buf())],
+			..new_run_metadata
+		},
+	)
+		.into())
+}
+
+/// Copies one chunk of a file to another filesystem, queueing the next one if there's more to
+/// copy. After the last chunk the copy is verified and the source is removed.
+async fn copy_file_chunk(
+	ctx: &WorkerContext,
+	source: &Path,
+	target: &Path,
+	offset: u64,
+	new_run_metadata: FileCutterJobRunMetadata,
+) -> Result<JobStepOutput<FileCutterJobStep, FileCutterJobRunMetadata>, JobError> {
+	let size = fs::metadata(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?
+		.len();
+
+	let partial = partial_path(target);
+	let copied_until = offset + copy_chunk(source, &partial, offset).await?;
+
+	if copied_until < size {
+		ctx.progress_msg(format!(
+			"Moving {} ({}%)",
+			source.display(),
+			copied_until * 100 / size
+		));
+
+		return Ok((
+			vec![FileCutterJobStep::CopyChunk {
+				source: source.to_path_buf(),
+				target: target.to_path_buf(),
+				offset: copied_until,
+			}],
+			new_run_metadata,
+		)
+			.into());
+	}
+
+	if let Err(e) = finish_copy(source, &partial, target, true).await {
+		// Keeping the source, the job can be run again to retry
+		warn!("Failed to move {}: {e:#?}", source.display());
+
+		return Ok((new_run_metadata, JobRunErrors(vec![e.to_string()])).into());
+	}
+
+	fs::remove_file(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	Ok(new_run_metadata.into())
+}
@@ -1,159 +1,314 @@
-// use crate::{
-// 	invalidate_query,
-// 	job::{
-// 		JobError, JobInitData, JobReportUpdate, JobResult, JobState, StatefulJob, WorkerContext,
-// 	},
-// 	library::Library,
-// 	location::{file_path_helper:: location::id::Type},
-// 	util::error::FileIOError,
-// };
-
-// use sd_crypto::{crypto::Decryptor, header::file::FileHeader, Protected};
-
-// use serde::{Deserialize, Serialize};
-// use specta::Type;
-// use tokio::fs::File;
-
-// use super::{get_location_path_from_location_id, get_many_files_datas, FileData, BYTES_EXT};
-// pub struct FileDecryptorJob;
-
-// // decrypt could have an option to restore metadata (and another specific option for file name? - would turn "output file" into "output path" in the UI)
-// #[derive(Serialize, Deserialize, Debug, Type, Hash)]
-// pub struct FileDecryptorJobInit {
-// 	pub location_id: location::id::Type,
-// 	pub file_path_ids: Vec<file_path::id::Type>,
-// 	pub mount_associated_key: bool,
-// 	pub password: Option<String>, // if this is set, we can assume the user chose password decryption
-// 	pub save_to_library: Option<bool>,
-// }
-
-// impl JobInitData for FileDecryptorJobInit {
-// 	type Job = FileDecryptorJob;
-// }
-
-// #[async_trait::async_trait]
-// impl StatefulJob for FileDecryptorJob {
-// 	type Init = FileDecryptorJobInit;
-// 	type Data = ();
-// 	type Step = FileData;
-
-// 	const NAME: &'static str = "file_decryptor";
-
-// 	fn new() -> Self {
-// 		Self {}
-// 	}
-
-// 	async fn init(&self, ctx: WorkerContext, state: &mut JobState<Self>) -> Result<(), JobError> {
-// 		let Library { db, .. } = &*ctx.library;
-
-// 		state.steps = get_many_files_datas(
-// 			db,
-// 			get_location_path_from_location_id(db, state.init.location_id).await?,
-// 			&state.init.file_path_ids,
-// 		)
-// 		.await?
-// 		.into();
-
-// 		ctx.progress(vec![JobReportUpdate::TaskCount(state.steps.len())]);
-
-// 		Ok(())
-// 	}
-
-// 	async fn execute_step(
-// 		&self,
-// 		ctx: WorkerContext,
-// 		state: &mut JobState<Self>,
-// 	) -> Result<(), JobError> {
-// 		let step = &state.steps[0];
-// 		let key_manager = &ctx.library.key_manager;
-
-// 		// handle overwriting checks, and making sure there's enough available space
-// 		let output_path = {
-// 			let mut path = step.full_path.clone();
-// 			let extension = path.extension().map_or("decrypted", |ext| {
-// 				if ext == BYTES_EXT {
-// 					""
-// 				} else {
-// 					"decrypted"
-// 				}
-// 			});
-// 			path.set_extension(extension);
-// 			path
-// 		};
-
-// 		let mut reader = File::open(&step.full_path)
-// 			.await
-// 			.map_err(|e| FileIOError::from((&step.full_path, e)))?;
-// 		let mut writer = File::create(&output_path)
-// 			.await
-// 			.map_err(|e| FileIOError::from((output_path, e)))?;
-
-// 		let (header, aad) = FileHeader::from_reader(&mut reader).await?;
-
-// 		let master_key = if let Some(password) = state.init.password.clone() {
-// 			if let Some(save_to_library) = state.init.save_to_library {
-// 				// we can do this first, as `find_key_index` requires a successful decryption (just like `decrypt_master_key`)
-// 				let password_bytes = Protected::new(password.as_bytes().to_vec());
-
-// 				if save_to_library {
-// 					let index = header.find_key_index(password_bytes.clone()).await?;
-
-// 					// inherit the encryption algorithm from the keyslot
-// 					key_manager
-// 						.add_to_keystore(
-// 							Protected::new(password),
-// 							header.algorithm,
-// 							header.keyslots[index].hashing_algorithm,
-// 							false,
-// 							false,
-// 							Some(header.keyslots[index].salt),
-// 						)
-// 						.await?;
-// 				}
-
-// 				header.decrypt_master_key(password_bytes).await?
-// 			} else {
-// 				return Err(JobError::JobDataNotFound(String::from(
-// 					"Password decryption selected, but save to library boolean was not included",
-// 				)));
-// 			}
-// 		} else {
-// 			if state.init.mount_associated_key {
-// 				for key in key_manager.dump_keystore().iter().filter(|x| {
-// 					header
-// 						.keyslots
-// 						.iter()
-// 						.any(|k| k.content_salt == x.content_salt)
-// 				}) {
-// 					key_manager.mount(key.uuid).await.ok();
-// 				}
-// 			}
-
-// 			let keys = key_manager.enumerate_hashed_keys();
-
-// 			header.decrypt_master_key_from_prehashed(keys).await?
-// 		};
-
-// 		let decryptor = Decryptor::new(master_key, header.nonce, header.algorithm)?;
-
-// 		decryptor
-// 			.decrypt_streams(&mut reader, &mut writer, &aad)
-// 			.await?;
-
-// 		// need to decrypt preview media/metadata, and maybe add an option in the UI so the user can chosoe to restore these values
-// 		// for now this can't easily be implemented, as we don't know what the new object id for the file will be (we know the old one, but it may differ)
-
-// 		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
-// 			state.step_number + 1,
-// 		)]);
-
-// 		Ok(())
-// 	}
-
-// 	async fn finalize(&self, ctx: WorkerContext, state: &mut JobState<Self>) -> JobResult {
-// 		invalidate_query!(ctx.library, "search.paths");
-
-// 		// mark job as successful
-// 		Ok(Some(serde_json::to_value(&state.init)?))
-// 	}
-// }
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::{light_scan_location, location_with_indexer_rules},
+	prisma::{file_path, location},
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use sd_crypto::{crypto::Decryptor, header::file::FileHeader, Protected};
+
+use std::{
+	hash::Hash,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::fs::{self, File};
+use tracing::error;
+use uuid::Uuid;
+
+use super::{
+	conflict::{partial_path_of, settle_conflict, ConflictPolicy, ConflictResolution, Settlement},
+	encrypt::{key_password, remove_partial_output, FileEncryptionError},
+	get_location_path_from_location_id, get_many_files_datas, BYTES_EXT,
+};
+
+/// Extension given to decrypted files whose original extension is unknown
+const DECRYPTED_EXT: &str = "decrypted";
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileDecryptorJobInit {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	/// The key manager key whose value is the password, so the password itself never ends up
+	/// in the job's state
+	pub key_uuid: Uuid,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileDecryptorJobData {
+	/// Directories that receive decrypted files, indexed once the job finishes
+	target_directories: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileDecryptorJobStep {
+	source_path: PathBuf,
+	target_path: PathBuf,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileDecryptorJobRunMetadata {
+	decrypted_files: u32,
+	/// The client's decision for every remaining conflict, once it made one
+	#[serde(default)]
+	conflict_decision: Option<ConflictResolution>,
+}
+
+impl JobRunMetadata for FileDecryptorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.decrypted_files += new_data.decrypted_files;
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for FileDecryptorJobInit {
+	type Data = FileDecryptorJobData;
+	type Step = FileDecryptorJobStep;
+	type RunMetadata = FileDecryptorJobRunMetadata;
+
+	const NAME: &'static str = "file_decryptor";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		// Failing early if the key manager is locked or the key is gone
+		key_password(&ctx.library, init.key_uuid).await?;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id).await?;
+
+		let mut steps = vec![];
+		let mut errors = vec![];
+		let mut target_directories = vec![];
+
+		for file_data in get_many_files_datas(db, &location_path, &init.file_path_ids).await? {
+			if maybe_missing(file_data.file_path.is_dir, "file_path.is_dir")? {
+				errors.push(format!(
+					"Skipping decrypting {} as it's a directory",
+					file_data.full_path.display()
+				));
+				continue;
+			}
+
+			let target_directory = file_data
+				.full_path
+				.parent()
+				.map(Path::to_path_buf)
+				.unwrap_or_else(|| location_path.clone());
+
+			let name = maybe_missing(&file_data.file_path.name, "file_path.name")?;
+
+			// `photo.jpg.bytes` goes back to `photo.jpg`, anything else we can't guess
+			let (stem, extension) = if file_data.file_path.extension.as_deref() == Some(BYTES_EXT) {
+				let original = Path::new(name);
+				(
+					original
+						.file_stem()
+						.unwrap_or_default()
+						.to_string_lossy()
+						.to_string(),
+					original
+						.extension()
+						.map(|extension| extension.to_string_lossy().to_string()),
+				)
+			} else {
+				(name.clone(), Some(DECRYPTED_EXT.to_string()))
+			};
+
+			let target_path = target_directory.join(match extension {
+				Some(extension) => format!("{stem}.{extension}"),
+				None => stem,
+			});
+
+			if !target_directories.contains(&target_directory) {
+				target_directories.push(target_directory);
+			}
+
+			steps.push(FileDecryptorJobStep {
+				source_path: file_data.full_path,
+				target_path,
+			});
+		}
+
+		*data = Some(FileDecryptorJobData { target_directories });
+
+		Ok((Default::default(), steps, JobRunErrors(errors)).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step: FileDecryptorJobStep {
+				source_path,
+				target_path,
+			},
+			..
+		}: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let Settlement {
+			target,
+			decided_for_all,
+			errors,
+		} = settle_conflict(
+			ctx,
+			init.conflict_policy,
+			run_metadata.conflict_decision,
+			source_path,
+			target_path,
+		)
+		.await?;
+
+		let mut new_run_metadata = FileDecryptorJobRunMetadata {
+			conflict_decision: decided_for_all,
+			..Default::default()
+		};
+
+		let Some(target_path) = target else {
+			return Ok((new_run_metadata, JobRunErrors(errors)).into());
+		};
+
+		ctx.progress_msg(format!(
+			"Decrypting {}",
+			source_path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+		));
+
+		// If we were interrupted while writing, the same partial path is written again on resume
+		let partial_path = partial_path_of(&target_path);
+		if let Err(e) = decrypt_file(
+			source_path,
+			&partial_path,
+			key_password(&ctx.library, init.key_uuid).await?,
+		)
+		.await
+		{
+			// Files may have been encrypted with different passwords, so we keep going
+			return Ok((
+				new_run_metadata,
+				JobRunErrors(vec![format!(
+					"Failed to decrypt {}: {e}",
+					source_path.display()
+				)]),
+			)
+				.into());
+		}
+
+		fs::rename(&partial_path, &target_path)
+			.await
+			.map_err(|e| FileIOError::from((&partial_path, e)))?;
+
+		new_run_metadata.decrypted_files = 1;
+
+		Ok(new_run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+
+		if let Some(location) = ctx
+			.library
+			.db
+			.location()
+			.find_unique(location::id::equals(init.location_id))
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+		{
+			// Indexing the decrypted files right away, instead of waiting for the watcher or a full scan
+			for directory_path in &data.target_directories {
+				if let Err(e) = light_scan_location(
+					ctx.node.clone(),
+					ctx.library.clone(),
+					location.clone(),
+					directory_path,
+				)
+				.await
+				{
+					error!(
+						"Failed to index decrypted files at {}: {e:#?}",
+						directory_path.display()
+					);
+				}
+			}
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({
+			"init": init,
+			"decrypted_files": run_metadata.decrypted_files,
+		})))
+	}
+}
+
+/// Decrypts a file written by [`super::encrypt::encrypt_file`] into `target_path`.
+///
+/// Nothing is written if the password doesn't unlock any of the file's keyslots.
+pub async fn decrypt_file(
+	source_path: impl AsRef<Path>,
+	target_path: impl AsRef<Path>,
+	password: Protected<Vec<u8>>,
+) -> Result<(), FileEncryptionError> {
+	let (source_path, target_path) = (source_path.as_ref(), target_path.as_ref());
+
+	let mut reader = File::open(source_path)
+		.await
+		.map_err(|e| FileIOError::from((source_path, e)))?;
+
+	let (header, aad) = FileHeader::from_reader(&mut reader).await?;
+
+	let decryptor = Decryptor::new(
+		header.decrypt_master_key(password).await?,
+		header.nonce,
+		header.algorithm,
+	)?;
+
+	let mut writer = File::create(target_path)
+		.await
+		.map_err(|e| FileIOError::from((target_path, e)))?;
+
+	if let Err(e) = decryptor
+		.decrypt_streams(&mut reader, &mut writer, &aad)
+		.await
+	{
+		drop(writer);
+		remove_partial_output(target_path).await;
+
+		return Err(e.into());
+	}
+
+	Ok(())
+}
@@ -0,0 +1,329 @@
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::{
+		file_path_helper::{
+			file_path_with_object, join_location_relative_path, IsolatedFilePathData,
+		},
+		LocationError,
+	},
+	object::validation::hash::file_checksum,
+	prisma::{file_path, location},
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use std::{
+	collections::{HashMap, HashSet},
+	hash::Hash,
+	path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::fs;
+use tracing::warn;
+
+use super::{error::FileSystemJobsError, find_available_path, FileData};
+
+/// What to do with the extra copies of a file
+#[derive(Serialize, Deserialize, Hash, Type, Debug, Clone)]
+pub enum DeduplicateAction {
+	Delete,
+	/// Replace each copy with a hard link to the kept file, so the data is only stored once
+	Hardlink,
+	/// Move the copies to a folder, so they can be reviewed before being deleted
+	Move {
+		target_location_id: location::id::Type,
+		target_location_relative_directory_path: PathBuf,
+	},
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct DuplicateSelection {
+	pub keep: file_path::id::Type,
+	pub duplicates: Vec<file_path::id::Type>,
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct DeduplicatorJobInit {
+	pub groups: Vec<DuplicateSelection>,
+	pub action: DeduplicateAction,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeduplicatorJobData {
+	/// Only set when moving the copies
+	target_directory: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DeduplicatorJobStep {
+	pub keep: FileData,
+	pub duplicates: Vec<FileData>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct DeduplicatorJobRunMetadata {
+	deduplicated: u64,
+	freed_bytes: u64,
+	/// Paths already used as move targets, so copies with the same name don't collide
+	taken_paths: HashSet<PathBuf>,
+}
+
+impl JobRunMetadata for DeduplicatorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.deduplicated += new_data.deduplicated;
+		self.freed_bytes += new_data.freed_bytes;
+		self.taken_paths.extend(new_data.taken_paths);
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for DeduplicatorJobInit {
+	type Data = DeduplicatorJobData;
+	type Step = DeduplicatorJobStep;
+	type RunMetadata = DeduplicatorJobRunMetadata;
+
+	const NAME: &'static str = "deduplicator";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let file_path_ids = init
+			.groups
+			.iter()
+			.flat_map(|group| [group.keep].into_iter().chain(group.duplicates.clone()))
+			.collect::<Vec<_>>();
+
+		// Copies can be spread across locations, so every file is joined with its own location path
+		let mut file_paths = db
+			.file_path()
+			.find_many(vec![file_path::id::in_vec(file_path_ids)])
+			.include(file_path_with_object::include())
+			.exec()
+			.await?
+			.into_iter()
+			.map(|file_path| (file_path.id, file_path))
+			.collect::<HashMap<_, _>>();
+
+		let location_ids = file_paths
+			.values()
+			.filter_map(|file_path| file_path.location_id)
+			.chain(match &init.action {
+				DeduplicateAction::Move {
+					target_location_id, ..
+				} => Some(*target_location_id),
+				_ => None,
+			})
+			.collect::<HashSet<_>>();
+
+		let location_paths = db
+			.location()
+			.find_many(vec![location::id::in_vec(
+				location_ids.into_iter().collect(),
+			)])
+			.select(location::select!({ id path }))
+			.exec()
+			.await?
+			.into_iter()
+			.filter_map(|location| location.path.map(|path| (location.id, PathBuf::from(path))))
+			.collect::<HashMap<_, _>>();
+
+		let mut file_data = |id| {
+			let file_path = file_paths
+				.remove(&id)
+				.ok_or(FileSystemJobsError::FilePathIdNotFound(id))?;
+			let location_id = maybe_missing(file_path.location_id, "file_path.location_id")?;
+			let location_path = location_paths
+				.get(&location_id)
+				.ok_or(LocationError::IdNotFound(location_id))?;
+
+			Ok::<_, JobError>(FileData {
+				full_path: location_path.join(IsolatedFilePathData::try_from(&file_path)?),
+				file_path,
+			})
+		};
+
+		let steps = init
+			.groups
+			.iter()
+			.map(|group| {
+				Ok(DeduplicatorJobStep {
+					keep: file_data(group.keep)?,
+					duplicates: group
+						.duplicates
+						.iter()
+						.filter(|id| **id != group.keep)
+						.map(|id| file_data(*id))
+						.collect::<Result<_, _>>()?,
+				})
+			})
+			.collect::<Result<Vec<_>, JobError>>()?;
+
+		let target_directory = match &init.action {
+			DeduplicateAction::Move {
+				target_location_id,
+				target_location_relative_directory_path,
+			} => {
+				let target_directory = join_location_relative_path(
+					location_paths
+						.get(target_location_id)
+						.ok_or(LocationError::IdNotFound(*target_location_id))?,
+					target_location_relative_directory_path,
+				);
+
+				fs::create_dir_all(&target_directory)
+					.await
+					.map_err(|e| FileIOError::from((&target_directory, e)))?;
+
+				Some(target_directory)
+			}
+			_ => None,
+		};
+
+		*data = Some(DeduplicatorJobData { target_directory });
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		_: &WorkerContext,
+		CurrentStep {
+			step: DeduplicatorJobStep { keep, duplicates },
+			..
+		}: CurrentStep<'_, Self::Step>,
+		data: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let mut new_metadata = DeduplicatorJobRunMetadata::default();
+		let mut errors = vec![];
+
+		// The indexed checksums may be stale, so the files on disk are hashed again before acting
+		let checksum = file_checksum(&keep.full_path)
+			.await
+			.map_err(|e| FileIOError::from((&keep.full_path, e)))?;
+
+		for duplicate in duplicates {
+			match file_checksum(&duplicate.full_path).await {
+				Ok(duplicate_checksum) if duplicate_checksum == checksum => {}
+				Ok(_) => {
+					errors.push(format!(
+						"'{}' isn't a copy of '{}', skipping it",
+						duplicate.full_path.display(),
+						keep.full_path.display()
+					));
+					continue;
+				}
+				Err(e) => {
+					errors.push(FileIOError::from((&duplicate.full_path, e)).to_string());
+					continue;
+				}
+			}
+
+			let size = fs::metadata(&duplicate.full_path)
+				.await
+				.map_err(|e| FileIOError::from((&duplicate.full_path, e)))?
+				.len();
+
+			let result = match &init.action {
+				DeduplicateAction::Delete => fs::remove_file(&duplicate.full_path)
+					.await
+					.map_err(|e| FileIOError::from((&duplicate.full_path, e))),
+				DeduplicateAction::Hardlink => {
+					replace_with_hardlink(&keep.full_path, &duplicate.full_path).await
+				}
+				DeduplicateAction::Move { .. } => {
+					let target_directory = data
+						.target_directory
+						.as_ref()
+						.expect("target directory is set when moving");
+
+					let mut taken_paths = run_metadata.taken_paths.clone();
+					taken_paths.extend(new_metadata.taken_paths.iter().cloned());
+
+					let target_path = find_available_path(
+						target_directory,
+						maybe_missing(&duplicate.file_path.name, "file_path.name")?,
+						duplicate.file_path.extension.as_deref(),
+						&mut taken_paths,
+					)
+					.await?;
+					new_metadata.taken_paths.insert(target_path.clone());
+
+					fs::rename(&duplicate.full_path, &target_path)
+						.await
+						.map_err(|e| FileIOError::from((&duplicate.full_path, e)))
+				}
+			};
+
+			match result {
+				Ok(()) => {
+					new_metadata.deduplicated += 1;
+					// Moved copies still take up space, until they're deleted
+					if !matches!(init.action, DeduplicateAction::Move { .. }) {
+						new_metadata.freed_bytes += size;
+					}
+				}
+				Err(e) => errors.push(e.to_string()),
+			}
+		}
+
+		Ok((new_metadata, JobRunErrors(errors)).into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		invalidate_query!(ctx.library, "search.paths");
+		invalidate_query!(ctx.library, "search.duplicates");
+
+		ctx.library.orphan_remover.invoke().await;
+
+		Ok(Some(json!({
+			"init": init,
+			"deduplicated": run_metadata.deduplicated,
+			"freed_bytes": run_metadata.freed_bytes,
+		})))
+	}
+}
+
+/// Links `duplicate` to `original` through a temporary file, so the copy is only gone once the link exists
+async fn replace_with_hardlink(original: &Path, duplicate: &Path) -> Result<(), FileIOError> {
+	let mut temp_name = duplicate.file_name().unwrap_or_default().to_os_string();
+	temp_name.push(".sd-dedup");
+	let temp_path = duplicate.with_file_name(temp_name);
+
+	fs::hard_link(original, &temp_path)
+		.await
+		.map_err(|e| FileIOError::from((&temp_path, e)))?;
+
+	if let Err(e) = fs::rename(&temp_path, duplicate).await {
+		if let Err(e) = fs::remove_file(&temp_path).await {
+			warn!(
+				"Failed to remove temporary hard link at {}: {e:#?}",
+				temp_path.display()
+			);
+		}
+
+		return Err(FileIOError::from((duplicate, e)));
+	}
+
+	Ok(())
+}
@@ -1,34 +1,63 @@
 use crate::{
 	invalidate_query,
 	job::{
-		CurrentStep, JobError, JobInitOutput, JobResult, JobStepOutput, StatefulJob, WorkerContext,
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
 	},
 	library::Library,
+	location::file_path_helper::IsolatedFilePathData,
 	prisma::{file_path, location},
 	util::{db::maybe_missing, error::FileIOError},
 };
 
 use std::hash::Hash;
 
+use chrono::Utc;
+use prisma_client_rust::{and, or};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use specta::Type;
 use tokio::{fs, io};
-use tracing::warn;
+use tracing::{error, warn};
 
-use super::{get_location_path_from_location_id, get_many_files_datas, FileData};
+use super::{
+	error::FileSystemJobsError,
+	get_location_path_from_location_id, get_many_files_datas,
+	journal::{journal, InverseAction, JournalEntry, JournalReplay},
+	trash::{location_ops, location_pub_id, move_to_trash},
+	FileData,
+};
 
 #[derive(Serialize, Deserialize, Hash, Type, Debug)]
 pub struct FileDeleterJobInit {
 	pub location_id: location::id::Type,
 	pub file_path_ids: Vec<file_path::id::Type>,
+	/// Moves the files to the trash instead of removing them, they can be restored from there
+	#[serde(default)]
+	pub to_trash: bool,
+	/// Set on jobs undoing or redoing a journaled operation, which aren't journaled by themselves
+	#[serde(default)]
+	#[specta(optional)]
+	pub journal_replay: Option<JournalReplay>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct FileDeleterJobRunMetadata {
+	/// Items moved to the trash, permanently deleted ones can't be brought back
+	trashed: Vec<JournalEntry>,
+}
+
+impl JobRunMetadata for FileDeleterJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.trashed.extend(new_data.trashed);
+	}
 }
 
 #[async_trait::async_trait]
 impl StatefulJob for FileDeleterJobInit {
 	type Data = ();
 	type Step = FileData;
-	type RunMetadata = ();
+	type RunMetadata = FileDeleterJobRunMetadata;
 
 	const NAME: &'static str = "file_deleter";
 
@@ -60,6 +89,10 @@ impl StatefulJob for FileDeleterJobInit {
 		_: &Self::Data,
 		_: &Self::RunMetadata,
 	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		if self.to_trash {
+			return trash_file(ctx, self.location_id, step).await;
+		}
+
 		// need to handle stuff such as querying prisma for all paths of a file, and deleting all of those if requested (with a checkbox in the ui)
 		// maybe a files.countOccurances/and or files.getPath(location_id, path_id) to show how many of these files would be deleted (and where?)
 
@@ -86,20 +119,141 @@ impl StatefulJob for FileDeleterJobInit {
 			}
 		}
 
-		Ok(().into())
+		Ok(None.into())
 	}
 
 	async fn finalize(
 		&self,
 		ctx: &WorkerContext,
 		_data: &Option<Self::Data>,
-		_run_metadata: &Self::RunMetadata,
+		run_metadata: &Self::RunMetadata,
 	) -> JobResult {
 		let init = self;
 		invalidate_query!(ctx.library, "search.paths");
 
-		ctx.library.orphan_remover.invoke().await;
+		if init.to_trash {
+			if let Err(e) = journal(
+				&ctx.library,
+				init.journal_replay,
+				InverseAction::RestoreFromTrash {
+					location_id: init.location_id,
+					entries: run_metadata.trashed.clone(),
+				},
+			)
+			.await
+			{
+				error!("Failed to journal moving files to the trash: {e:#?}");
+			}
+
+			invalidate_query!(ctx.library, "search.objects");
+		} else {
+			ctx.library.orphan_remover.invoke().await;
+		}
 
 		Ok(Some(json!({ "init": init })))
 	}
 }
+
+/// Detaches the rows of a file, and of everything inside it for directories, from their location
+/// before moving the file to the trash, so the watcher and the indexer leave them alone
+async fn trash_file(
+	ctx: &WorkerContext,
+	location_id: location::id::Type,
+	step: &FileData,
+) -> Result<JobStepOutput<FileData, FileDeleterJobRunMetadata>, JobError> {
+	let Library { db, sync, .. } = &*ctx.library;
+
+	let is_dir = maybe_missing(step.file_path.is_dir, "file_path.is_dir")?;
+	let trashed_at = Some(Utc::now().into());
+	let location_pub_id = location_pub_id(db, location_id).await?;
+
+	let mut trashed = vec![file_path::id::equals(step.file_path.id)];
+	if is_dir {
+		if let Some(children_path) = IsolatedFilePathData::try_from(&step.file_path)
+			.map_err(FileSystemJobsError::from)?
+			.materialized_path_for_children()
+		{
+			trashed = vec![or![
+				file_path::id::equals(step.file_path.id),
+				and![
+					file_path::location_id::equals(Some(location_id)),
+					file_path::materialized_path::starts_with(children_path),
+				],
+			]];
+		}
+	}
+
+	let (trashed_ids, trashed_pub_ids): (Vec<_>, Vec<_>) = db
+		.file_path()
+		.find_many(trashed)
+		.select(file_path::select!({ id pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|file_path| (file_path.id, file_path.pub_id))
+		.unzip();
+
+	sync.write_ops(
+		db,
+		(
+			location_ops(sync, &trashed_pub_ids, None),
+			db.file_path().update_many(
+				vec![file_path::id::in_vec(trashed_ids.clone())],
+				vec![
+					file_path::location_id::set(None),
+					file_path::trashed_at::set(trashed_at),
+					file_path::trash_location_id::set(Some(location_id)),
+				],
+			),
+		),
+	)
+	.await?;
+
+	match move_to_trash(&step.full_path).await {
+		Ok(trash_path) => {
+			db.file_path()
+				.update(
+					file_path::id::equals(step.file_path.id),
+					vec![file_path::trash_path::set(Some(
+						trash_path.to_string_lossy().to_string(),
+					))],
+				)
+				.exec()
+				.await?;
+
+			Ok(FileDeleterJobRunMetadata {
+				trashed: vec![JournalEntry::new(
+					step.file_path.pub_id.clone(),
+					step.full_path.clone(),
+					trash_path,
+				)],
+			}
+			.into())
+		}
+		Err(e) => {
+			warn!(
+				"Failed to move {} to the trash: {e:#?}",
+				step.full_path.display()
+			);
+
+			// The file is still where it was, so its rows go back to the location
+			sync.write_ops(
+				db,
+				(
+					location_ops(sync, &trashed_pub_ids, Some(&location_pub_id)),
+					db.file_path().update_many(
+						vec![file_path::id::in_vec(trashed_ids)],
+						vec![
+							file_path::location_id::set(Some(location_id)),
+							file_path::trashed_at::set(None),
+							file_path::trash_location_id::set(None),
+						],
+					),
+				),
+			)
+			.await?;
+
+			Ok(JobRunErrors(vec![e.to_string()]).into())
+		}
+	}
+}
@@ -1,261 +1,573 @@
-// use crate::{
-// 	invalidate_query,
-// 	job::*,
-// 	library::Library,
-// 	location::{file_path_helper:: location::id::Type},
-// 	util::error::{FileIOError, NonUtf8PathError},
-// };
-
-// use sd_crypto::{
-// 	crypto::Encryptor,
-// 	header::{file::FileHeader, keyslot::Keyslot},
-// 	primitives::{LATEST_FILE_HEADER, LATEST_KEYSLOT, LATEST_METADATA, LATEST_PREVIEW_MEDIA},
-// 	types::{Algorithm, Key},
-// };
-
-// use chrono::FixedOffset;
-// use serde::{Deserialize, Serialize};
-// use specta::Type;
-// use tokio::{
-// 	fs::{self, File},
-// 	io,
-// };
-// use tracing::{error, warn};
-// use uuid::Uuid;
-
-// use super::{
-// 	error::FileSystemJobsError, get_location_path_from_location_id, get_many_files_datas, FileData,
-// 	BYTES_EXT,
-// };
-
-// pub struct FileEncryptorJob;
-
-// #[derive(Serialize, Deserialize, Type, Hash)]
-// pub struct FileEncryptorJobInit {
-// 	pub location_id: location::id::Type,
-// 	pub file_path_ids: Vec<file_path::id::Type>,
-// 	pub key_uuid: Uuid,
-// 	pub algorithm: Algorithm,
-// 	pub metadata: bool,
-// 	pub preview_media: bool,
-// }
-
-// #[derive(Serialize, Deserialize)]
-// pub struct Metadata {
-// 	pub file_path_id: file_path::id::Type,
-// 	pub name: String,
-// 	pub hidden: bool,
-// 	pub favorite: bool,
-// 	pub important: bool,
-// 	pub note: Option<String>,
-// 	pub date_created: chrono::DateTime<FixedOffset>,
-// }
-
-// impl JobInitData for FileEncryptorJobInit {
-// 	type Job = FileEncryptorJob;
-// }
-
-// #[async_trait::async_trait]
-// impl StatefulJob for FileEncryptorJob {
-// 	type Init = FileEncryptorJobInit;
-// 	type Data = ();
-// 	type Step = FileData;
-
-// 	const NAME: &'static str = "file_encryptor";
-
-// 	fn new() -> Self {
-// 		Self {}
-// 	}
-
-// 	async fn init(&self, ctx: WorkerContext, state: &mut JobState<Self>) -> Result<(), JobError> {
-// 		let Library { db, .. } = &*ctx.library;
-
-// 		state.steps = get_many_files_datas(
-// 			db,
-// 			get_location_path_from_location_id(db, state.init.location_id).await?,
-// 			&state.init.file_path_ids,
-// 		)
-// 		.await?
-// 		.into();
-
-// 		ctx.progress(vec![JobReportUpdate::TaskCount(state.steps.len())]);
-
-// 		Ok(())
-// 	}
-
-// 	async fn execute_step(
-// 		&self,
-// 		ctx: WorkerContext,
-// 		state: &mut JobState<Self>,
-// 	) -> Result<(), JobError> {
-// 		let step = &state.steps[0];
-
-// 		let Library { key_manager, .. } = &*ctx.library;
-
-// 		if !step.file_path.is_dir {
-// 			// handle overwriting checks, and making sure there's enough available space
-
-// 			let user_key = key_manager
-// 				.access_keymount(state.init.key_uuid)
-// 				.await?
-// 				.hashed_key;
-
-// 			let user_key_details = key_manager.access_keystore(state.init.key_uuid).await?;
-
-// 			let output_path = {
-// 				let mut path = step.full_path.clone();
-// 				let extension = path.extension().map_or_else(
-// 					|| Ok("bytes".to_string()),
-// 					|extension| {
-// 						Ok::<String, JobError>(format!(
-// 							"{}{BYTES_EXT}",
-// 							extension.to_str().ok_or(FileSystemJobsError::FilePath(
-// 								NonUtf8PathError(step.full_path.clone().into_boxed_path()).into()
-// 							))?
-// 						))
-// 					},
-// 				)?;
-
-// 				path.set_extension(extension);
-// 				path
-// 			};
-
-// 			let _guard = ctx
-// 				.library
-// 				.location_manager()
-// 				.temporary_ignore_events_for_path(
-// 					state.init.location_id,
-// 					ctx.library.clone(),
-// 					&output_path,
-// 				)
-// 				.await
-// 				.map_or_else(
-// 					|e| {
-// 						error!(
-// 							"Failed to make location manager ignore the path {}; Error: {e:#?}",
-// 							output_path.display()
-// 						);
-// 						None
-// 					},
-// 					Some,
-// 				);
-
-// 			let mut reader = File::open(&step.full_path)
-// 				.await
-// 				.map_err(|e| FileIOError::from((&step.full_path, e)))?;
-// 			let mut writer = File::create(&output_path)
-// 				.await
-// 				.map_err(|e| FileIOError::from((output_path, e)))?;
-
-// 			let master_key = Key::generate();
-
-// 			let mut header = FileHeader::new(
-// 				LATEST_FILE_HEADER,
-// 				state.init.algorithm,
-// 				vec![
-// 					Keyslot::new(
-// 						LATEST_KEYSLOT,
-// 						state.init.algorithm,
-// 						user_key_details.hashing_algorithm,
-// 						user_key_details.content_salt,
-// 						user_key,
-// 						master_key.clone(),
-// 					)
-// 					.await?,
-// 				],
-// 			)?;
-
-// 			if state.init.metadata || state.init.preview_media {
-// 				// if any are requested, we can make the query as it'll be used at least once
-// 				if let Some(ref object) = step.file_path.object {
-// 					if state.init.metadata {
-// 						let metadata = Metadata {
-// 							file_path_id: step.file_path.id,
-// 							name: step.file_path.materialized_path.clone(),
-// 							hidden: object.hidden,
-// 							favorite: object.favorite,
-// 							important: object.important,
-// 							note: object.note.clone(),
-// 							date_created: object.date_created,
-// 						};
-
-// 						header
-// 							.add_metadata(
-// 								LATEST_METADATA,
-// 								state.init.algorithm,
-// 								master_key.clone(),
-// 								&metadata,
-// 							)
-// 							.await?;
-// 					}
-
-// 					// if state.init.preview_media
-// 					// 	&& (object.has_thumbnail
-// 					// 		|| object.has_video_preview || object.has_thumbstrip)
-
-// 					// may not be the best - preview media (thumbnail) isn't guaranteed to be webp
-// 					let thumbnail_path = ctx
-// 						.library
-// 						.config()
-// 						.data_directory()
-// 						.join("thumbnails")
-// 						.join(
-// 							step.file_path
-// 								.cas_id
-// 								.as_ref()
-// 								.ok_or(JobError::MissingCasId)?,
-// 						)
-// 						.with_extension("wepb");
-
-// 					match fs::read(&thumbnail_path).await {
-// 						Ok(thumbnail_bytes) => {
-// 							header
-// 								.add_preview_media(
-// 									LATEST_PREVIEW_MEDIA,
-// 									state.init.algorithm,
-// 									master_key.clone(),
-// 									&thumbnail_bytes,
-// 								)
-// 								.await?;
-// 						}
-// 						Err(e) if e.kind() == io::ErrorKind::NotFound => {
-// 							// If the file just doesn't exist, then we don't care
-// 						}
-// 						Err(e) => {
-// 							return Err(FileIOError::from((thumbnail_path, e)).into());
-// 						}
-// 					}
-// 				} else {
-// 					// should use container encryption if it's a directory
-// 					warn!("skipping metadata/preview media inclusion, no associated object found")
-// 				}
-// 			}
-
-// 			header.write(&mut writer).await?;
-
-// 			let encryptor = Encryptor::new(master_key, header.nonce, header.algorithm)?;
-
-// 			encryptor
-// 				.encrypt_streams(&mut reader, &mut writer, &header.generate_aad())
-// 				.await?;
-// 		} else {
-// 			warn!(
-// 				"encryption is skipping {}/{} as it isn't a file",
-// 				step.file_path.materialized_path, step.file_path.name
-// 			)
-// 		}
-
-// 		ctx.progress(vec![JobReportUpdate::CompletedTaskCount(
-// 			state.step_number + 1,
-// 		)]);
-
-// 		Ok(())
-// 	}
-
-// 	async fn finalize(&self, ctx: WorkerContext, state: &mut JobState<Self>) -> JobResult {
-// 		invalidate_query!(ctx.library, "search.paths");
-
-// 		// mark job as successful
-// 		Ok(Some(serde_json::to_value(&state.init)?))
-// 	}
-// }
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobRunMetadata,
+		JobStepOutput, StatefulJob, WorkerContext,
+	},
+	library::Library,
+	location::{
+		file_path_helper::{
+			file_path_with_object, filter_existing_file_path_params, IsolatedFilePathData,
+		},
+		light_scan_location, location_with_indexer_rules,
+	},
+	object::media::thumbnail::get_thumbnail_path,
+	prisma::{file_path, location, object},
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use sd_crypto::{
+	crypto::Encryptor,
+	header::{file::FileHeader, keyslot::Keyslot},
+	primitives::{LATEST_FILE_HEADER, LATEST_KEYSLOT, LATEST_METADATA, LATEST_PREVIEW_MEDIA},
+	types::{Algorithm, HashingAlgorithm, Key, Salt},
+	Protected,
+};
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma_sync;
+use sd_sync::OperationFactory;
+
+use std::{
+	hash::Hash,
+	io,
+	path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, FixedOffset};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use thiserror::Error;
+use tokio::{
+	fs::{self, File},
+	task::{spawn_blocking, JoinError},
+};
+use tracing::error;
+use uuid::Uuid;
+
+use super::{
+	conflict::{partial_path_of, settle_conflict, ConflictPolicy, ConflictResolution, Settlement},
+	error::FileSystemJobsError,
+	get_location_path_from_location_id, get_many_files_datas, BYTES_EXT,
+};
+
+#[derive(Error, Debug)]
+pub enum FileEncryptionError {
+	#[error(transparent)]
+	Crypto(#[from] sd_crypto::Error),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("Failed to join Tokio spawn blocking: {0}")]
+	JoinTask(#[from] JoinError),
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct FileEncryptorJobInit {
+	pub location_id: location::id::Type,
+	pub file_path_ids: Vec<file_path::id::Type>,
+	pub algorithm: Algorithm,
+	pub hashing_algorithm: HashingAlgorithm,
+	/// The key manager key whose value is the password, so the password itself never ends up
+	/// in the job's state
+	pub key_uuid: Uuid,
+	/// Embed the object's name, flags and note in the encrypted header
+	pub metadata: bool,
+	/// Embed the file's thumbnail in the encrypted header, if it has one
+	pub preview_media: bool,
+	#[serde(default)]
+	#[specta(optional)]
+	pub conflict_policy: ConflictPolicy,
+}
+
+/// Object details embedded in an encrypted file's header, so they aren't lost when the
+/// encrypted file leaves the library
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EncryptedFileMetadata {
+	pub name: String,
+	pub hidden: bool,
+	pub favorite: bool,
+	pub important: bool,
+	pub note: Option<String>,
+	pub date_created: Option<DateTime<FixedOffset>>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileEncryptorJobData {
+	location_path: PathBuf,
+	/// Directories that receive encrypted files, indexed once the job finishes
+	target_directories: Vec<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct FileEncryptorJobStep {
+	source_path: PathBuf,
+	target_path: PathBuf,
+	metadata: Option<EncryptedFileMetadata>,
+	/// Used to find the thumbnail embedded as preview media
+	cas_id: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct FileEncryptorJobRunMetadata {
+	encrypted_files: u32,
+	target_paths: Vec<PathBuf>,
+	/// The client's decision for every remaining conflict, once it made one
+	#[serde(default)]
+	conflict_decision: Option<ConflictResolution>,
+}
+
+impl JobRunMetadata for FileEncryptorJobRunMetadata {
+	fn update(&mut self, new_data: Self) {
+		self.encrypted_files += new_data.encrypted_files;
+		self.target_paths.extend(new_data.target_paths);
+		if new_data.conflict_decision.is_some() {
+			self.conflict_decision = new_data.conflict_decision;
+		}
+	}
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for FileEncryptorJobInit {
+	type Data = FileEncryptorJobData;
+	type Step = FileEncryptorJobStep;
+	type RunMetadata = FileEncryptorJobRunMetadata;
+
+	const NAME: &'static str = "file_encryptor";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		// Failing early if the key manager is locked or the key is gone
+		key_password(&ctx.library, init.key_uuid).await?;
+
+		let location_path = get_location_path_from_location_id(db, init.location_id).await?;
+
+		let mut steps = vec![];
+		let mut errors = vec![];
+		let mut target_directories = vec![];
+
+		for file_data in get_many_files_datas(db, &location_path, &init.file_path_ids).await? {
+			if maybe_missing(file_data.file_path.is_dir, "file_path.is_dir")? {
+				errors.push(format!(
+					"Skipping encrypting {} as it's a directory",
+					file_data.full_path.display()
+				));
+				continue;
+			}
+
+			let target_directory = file_data
+				.full_path
+				.parent()
+				.map(Path::to_path_buf)
+				.unwrap_or_else(|| location_path.clone());
+
+			let file_name = file_data
+				.full_path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+				.to_string();
+
+			let target_path = target_directory.join(format!("{file_name}.{BYTES_EXT}"));
+
+			let object = file_data.file_path.object.as_ref();
+
+			let metadata = init.metadata.then(|| EncryptedFileMetadata {
+				name: file_name,
+				hidden: object.and_then(|o| o.hidden).unwrap_or_default(),
+				favorite: object.and_then(|o| o.favorite).unwrap_or_default(),
+				important: object.and_then(|o| o.important).unwrap_or_default(),
+				note: object.and_then(|o| o.note.clone()),
+				date_created: object.and_then(|o| o.date_created),
+			});
+
+			if !target_directories.contains(&target_directory) {
+				target_directories.push(target_directory);
+			}
+
+			steps.push(FileEncryptorJobStep {
+				source_path: file_data.full_path,
+				target_path,
+				metadata,
+				cas_id: init
+					.preview_media
+					.then_some(file_data.file_path.cas_id)
+					.flatten(),
+			});
+		}
+
+		*data = Some(FileEncryptorJobData {
+			location_path,
+			target_directories,
+		});
+
+		Ok((Default::default(), steps, JobRunErrors(errors)).into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep {
+			step:
+				FileEncryptorJobStep {
+					source_path,
+					target_path,
+					metadata,
+					cas_id,
+				},
+			..
+		}: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		run_metadata: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let init = self;
+
+		let Settlement {
+			target,
+			decided_for_all,
+			errors,
+		} = settle_conflict(
+			ctx,
+			init.conflict_policy,
+			run_metadata.conflict_decision,
+			source_path,
+			target_path,
+		)
+		.await?;
+
+		let mut new_run_metadata = FileEncryptorJobRunMetadata {
+			conflict_decision: decided_for_all,
+			..Default::default()
+		};
+
+		let Some(target_path) = target else {
+			return Ok((new_run_metadata, JobRunErrors(errors)).into());
+		};
+
+		ctx.progress_msg(format!(
+			"Encrypting {}",
+			source_path
+				.file_name()
+				.unwrap_or_default()
+				.to_string_lossy()
+		));
+
+		let preview_media = match cas_id {
+			Some(cas_id) => {
+				let thumbnail_path = get_thumbnail_path(&ctx.node, cas_id);
+				match fs::read(&thumbnail_path).await {
+					Ok(thumbnail) => Some(thumbnail),
+					// Not every file has a thumbnail, and that's fine
+					Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+					Err(e) => return Err(FileIOError::from((thumbnail_path, e)).into()),
+				}
+			}
+			None => None,
+		};
+
+		// If we were interrupted while writing, the same partial path is written again on resume
+		let partial_path = partial_path_of(&target_path);
+		if let Err(e) = encrypt_file(
+			source_path,
+			&partial_path,
+			init.algorithm,
+			init.hashing_algorithm,
+			key_password(&ctx.library, init.key_uuid).await?,
+			metadata.as_ref(),
+			preview_media.as_deref(),
+		)
+		.await
+		{
+			remove_partial_output(&partial_path).await;
+
+			// A single file failing to be encrypted shouldn't stop the others
+			return Ok((
+				new_run_metadata,
+				JobRunErrors(vec![format!(
+					"Failed to encrypt {}: {e}",
+					source_path.display()
+				)]),
+			)
+				.into());
+		}
+
+		fs::rename(&partial_path, &target_path)
+			.await
+			.map_err(|e| FileIOError::from((&partial_path, e)))?;
+
+		new_run_metadata.encrypted_files = 1;
+		new_run_metadata.target_paths.push(target_path);
+
+		Ok(new_run_metadata.into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		data: &Option<Self::Data>,
+		run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		let data = data
+			.as_ref()
+			.expect("critical error: missing data on job state");
+		let Library { db, sync, .. } = &*ctx.library;
+
+		if let Some(location) = db
+			.location()
+			.find_unique(location::id::equals(init.location_id))
+			.include(location_with_indexer_rules::include())
+			.exec()
+			.await?
+		{
+			// Indexing the encrypted files right away, instead of waiting for the watcher or a full scan
+			for directory_path in &data.target_directories {
+				if let Err(e) = light_scan_location(
+					ctx.node.clone(),
+					ctx.library.clone(),
+					location.clone(),
+					directory_path,
+				)
+				.await
+				{
+					error!(
+						"Failed to index encrypted files at {}: {e:#?}",
+						directory_path.display()
+					);
+				}
+			}
+		}
+
+		let encrypted_kind = ObjectKind::Encrypted as i32;
+
+		for target_path in &run_metadata.target_paths {
+			let iso_file_path = IsolatedFilePathData::new(
+				init.location_id,
+				&data.location_path,
+				target_path,
+				false,
+			)
+			.map_err(FileSystemJobsError::from)?;
+
+			let Some(object) = db
+				.file_path()
+				.find_first(filter_existing_file_path_params(&iso_file_path))
+				.include(file_path_with_object::include())
+				.exec()
+				.await?
+				.and_then(|file_path| file_path.object)
+			else {
+				continue;
+			};
+
+			if object.kind != Some(encrypted_kind) {
+				sync.write_op(
+					db,
+					sync.shared_update(
+						prisma_sync::object::SyncId {
+							pub_id: object.pub_id,
+						},
+						object::kind::NAME,
+						json!(encrypted_kind),
+					),
+					db.object().update(
+						object::id::equals(object.id),
+						vec![object::kind::set(Some(encrypted_kind))],
+					),
+				)
+				.await?;
+			}
+		}
+
+		invalidate_query!(ctx.library, "search.paths");
+
+		Ok(Some(json!({
+			"init": init,
+			"encrypted_files": run_metadata.encrypted_files,
+		})))
+	}
+}
+
+/// Encrypts `source_path` into `target_path`, behind a single password keyslot.
+///
+/// `metadata` and `preview_media` are encrypted with the same master key as the contents and
+/// stored in the header, so they can be read without decrypting the whole file.
+pub async fn encrypt_file(
+	source_path: impl AsRef<Path>,
+	target_path: impl AsRef<Path>,
+	algorithm: Algorithm,
+	hashing_algorithm: HashingAlgorithm,
+	password: Protected<Vec<u8>>,
+	metadata: Option<&EncryptedFileMetadata>,
+	preview_media: Option<&[u8]>,
+) -> Result<(), FileEncryptionError> {
+	let (source_path, target_path) = (source_path.as_ref(), target_path.as_ref());
+
+	let content_salt = Salt::generate();
+
+	// Password hashing is slow by design, so it shouldn't block the async runtime
+	let hashed_key =
+		spawn_blocking(move || hashing_algorithm.hash(password, content_salt, None)).await??;
+
+	let master_key = Key::generate();
+
+	let mut header = FileHeader::new(
+		LATEST_FILE_HEADER,
+		algorithm,
+		vec![
+			Keyslot::new(
+				LATEST_KEYSLOT,
+				algorithm,
+				hashing_algorithm,
+				content_salt,
+				hashed_key,
+				master_key.clone(),
+			)
+			.await?,
+		],
+	)?;
+
+	if let Some(metadata) = metadata {
+		header
+			.add_metadata(LATEST_METADATA, algorithm, master_key.clone(), metadata)
+			.await?;
+	}
+
+	if let Some(preview_media) = preview_media {
+		header
+			.add_preview_media(
+				LATEST_PREVIEW_MEDIA,
+				algorithm,
+				master_key.clone(),
+				preview_media,
+			)
+			.await?;
+	}
+
+	let mut reader = File::open(source_path)
+		.await
+		.map_err(|e| FileIOError::from((source_path, e)))?;
+	let mut writer = File::create(target_path)
+		.await
+		.map_err(|e| FileIOError::from((target_path, e)))?;
+
+	header.write(&mut writer).await?;
+
+	Encryptor::new(master_key, header.nonce, header.algorithm)?
+		.encrypt_streams(&mut reader, &mut writer, &header.generate_aad())
+		.await?;
+
+	Ok(())
+}
+
+/// The password held by a key of the library's key manager, which has to be unlocked
+pub(super) async fn key_password(
+	library: &Library,
+	key_uuid: Uuid,
+) -> Result<Protected<Vec<u8>>, sd_crypto::Error> {
+	library
+		.key_manager
+		.get_key(key_uuid)
+		.await
+		.map(|key| Protected::new(key.expose().as_bytes().to_vec()))
+}
+
+/// Removes what was written of a file that failed to be encrypted or decrypted
+pub(super) async fn remove_partial_output(path: impl AsRef<Path>) {
+	let path = path.as_ref();
+
+	match fs::remove_file(path).await {
+		Ok(()) => {}
+		Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+		Err(e) => error!("{:#?}", FileIOError::from((path, e))),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::object::fs::decrypt::decrypt_file;
+
+	use sd_crypto::types::Params;
+
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn encrypt_and_decrypt_round_trip() {
+		let dir = tempdir().unwrap();
+		let source_path = dir.path().join("secrets.txt");
+		let encrypted_path = dir.path().join("secrets.txt.bytes");
+		let decrypted_path = dir.path().join("decrypted.txt");
+
+		let contents = "the cake is a lie".repeat(100_000);
+		fs::write(&source_path, &contents).await.unwrap();
+
+		let metadata = EncryptedFileMetadata {
+			name: "secrets.txt".to_string(),
+			hidden: false,
+			favorite: true,
+			important: false,
+			note: Some("don't tell anyone".to_string()),
+			date_created: None,
+		};
+
+		encrypt_file(
+			&source_path,
+			&encrypted_path,
+			Algorithm::XChaCha20Poly1305,
+			HashingAlgorithm::Argon2id(Params::Standard),
+			Protected::new(b"password".to_vec()),
+			Some(&metadata),
+			Some(b"not really a thumbnail"),
+		)
+		.await
+		.unwrap();
+
+		let encrypted = fs::read(&encrypted_path).await.unwrap();
+		assert_ne!(encrypted, contents.as_bytes());
+
+		let mut reader = File::open(&encrypted_path).await.unwrap();
+		let (header, _) = FileHeader::from_reader(&mut reader).await.unwrap();
+
+		let decrypted_metadata: EncryptedFileMetadata = header
+			.decrypt_metadata(Protected::new(b"password".to_vec()))
+			.await
+			.unwrap();
+		assert_eq!(decrypted_metadata.note, metadata.note);
+		assert!(decrypted_metadata.favorite);
+
+		assert_eq!(
+			header
+				.decrypt_preview_media(Protected::new(b"password".to_vec()))
+				.await
+				.unwrap()
+				.expose(),
+			b"not really a thumbnail"
+		);
+
+		assert!(decrypt_file(
+			&encrypted_path,
+			&decrypted_path,
+			Protected::new(b"wrong password".to_vec()),
+		)
+		.await
+		.is_err());
+		assert!(!decrypted_path.exists());
+
+		decrypt_file(
+			&encrypted_path,
+			&decrypted_path,
+			Protected::new(b"password".to_vec()),
+		)
+		.await
+		.unwrap();
+
+		assert_eq!(fs::read_to_string(&decrypted_path).await.unwrap(), contents);
+	}
+}
@@ -26,6 +26,16 @@ pub enum FileSystemJobsError {
 	FilePath(#[from] FilePathError),
 	#[error("action would overwrite another file: {}", .0.display())]
 	WouldOverwrite(Box<Path>),
+	#[error("action would replace a file with itself: {}", .0.display())]
+	WouldReplaceItself(Box<Path>),
 	#[error("missing-field: {0}")]
 	MissingField(#[from] MissingFieldError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error("copy doesn't match its source: {}", .0.display())]
+	CopyMismatch(Box<Path>),
+	#[error("no trash available for: {}", .0.display())]
+	TrashUnavailable(Box<Path>),
+	#[error("file_path isn't in the trash by itself, but inside a trashed directory: <id='{0}'>")]
+	NotTrashedItself(file_path::id::Type),
 }
@@ -0,0 +1,864 @@
+use crate::{
+	invalidate_query,
+	job::{Job, JobManagerError, StatefulJob},
+	library::Library,
+	location::file_path_helper::{FilePathError, IsolatedFilePathData},
+	prisma::{file_operation, file_path, location, PrismaClient, SortOrder},
+	util::error::FileIOError,
+	Node,
+};
+
+use std::{
+	collections::BTreeMap,
+	path::{Path, PathBuf},
+	sync::Arc,
+};
+
+use chrono::{DateTime, Utc};
+use prisma_client_rust::QueryError;
+use rspc::ErrorCode;
+use serde::{Deserialize, Serialize};
+use specta::Type;
+use thiserror::Error;
+use tokio::fs;
+use tracing::{error, warn};
+
+use super::{
+	conflict::ConflictPolicy,
+	copy::FileCopierJobInit,
+	cut::FileCutterJobInit,
+	delete::FileDeleterJobInit,
+	error::FileSystemJobsError,
+	get_location_path_from_location_id,
+	trash::{restore_trashed, trashed_file_path},
+};
+
+/// How many operations are kept in the journal, older ones can't be undone anymore
+const JOURNAL_SIZE: i64 = 100;
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+	#[error("there's no operation to undo")]
+	NothingToUndo,
+	#[error("there's no operation to redo")]
+	NothingToRedo,
+	#[error("{} changed since the operation, leaving it alone", .0.display())]
+	Diverged(Box<Path>),
+	#[error("{} isn't indexed yet", .0.display())]
+	NotIndexed(Box<Path>),
+	#[error("the operation is half done, {0}")]
+	Unfinished(String),
+	#[error("database error: {0}")]
+	Database(#[from] QueryError),
+	#[error("failed to encode or decode a journaled operation: {0}")]
+	Serialization(#[from] serde_json::Error),
+	#[error(transparent)]
+	FilePath(#[from] FilePathError),
+	#[error(transparent)]
+	FileIO(#[from] FileIOError),
+	#[error(transparent)]
+	FileSystemJobs(#[from] FileSystemJobsError),
+	#[error(transparent)]
+	JobManager(#[from] JobManagerError),
+}
+
+impl From<JournalError> for rspc::Error {
+	fn from(err: JournalError) -> Self {
+		match err {
+			JournalError::NothingToUndo | JournalError::NothingToRedo => {
+				rspc::Error::with_cause(ErrorCode::BadRequest, err.to_string(), err)
+			}
+			JournalError::Diverged(_)
+			| JournalError::NotIndexed(_)
+			| JournalError::Unfinished(_)
+			| JournalError::FileSystemJobs(FileSystemJobsError::WouldOverwrite(_)) => {
+				rspc::Error::with_cause(ErrorCode::Conflict, err.to_string(), err)
+			}
+			_ => rspc::Error::with_cause(ErrorCode::InternalServerError, err.to_string(), err),
+		}
+	}
+}
+
+/// What was at a path right after an operation, files only count as untouched while their size
+/// and modification time stay the same
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct EntryState {
+	pub is_dir: bool,
+	pub size: u64,
+	pub modified: Option<DateTime<Utc>>,
+}
+
+impl EntryState {
+	async fn of(path: impl AsRef<Path>) -> Option<Self> {
+		let metadata = fs::symlink_metadata(path).await.ok()?;
+
+		Some(Self {
+			is_dir: metadata.is_dir(),
+			size: metadata.len(),
+			modified: metadata.modified().ok().map(Into::into),
+		})
+	}
+
+	fn matches(&self, other: &Self) -> bool {
+		// Directories change with whatever happens inside them
+		self.is_dir == other.is_dir
+			&& (self.is_dir || (self.size == other.size && self.modified == other.modified))
+	}
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+	pub file_path_pub_id: Vec<u8>,
+	/// Where the item was before the operation
+	pub before: PathBuf,
+	/// Where the item, or its copy, is after the operation
+	pub after: PathBuf,
+	/// What was at `after` once the operation completed, filled in when journaling
+	#[serde(default)]
+	pub state: Option<EntryState>,
+}
+
+impl JournalEntry {
+	pub fn new(file_path_pub_id: Vec<u8>, before: PathBuf, after: PathBuf) -> Self {
+		Self {
+			file_path_pub_id,
+			before,
+			after,
+			state: None,
+		}
+	}
+}
+
+/// What undoes a journaled operation, redoing it is doing the operation again
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub enum InverseAction {
+	/// Copies and duplicates are undone by moving the copies to the trash
+	TrashCopies {
+		source_location_id: location::id::Type,
+		target_location_id: location::id::Type,
+		target_file_name_suffix: Option<String>,
+		entries: Vec<JournalEntry>,
+	},
+	/// Cuts are undone by cutting the items back where they came from
+	CutBack {
+		source_location_id: location::id::Type,
+		target_location_id: location::id::Type,
+		entries: Vec<JournalEntry>,
+	},
+	/// Renames are undone by renaming the items back
+	RenameBack {
+		location_id: location::id::Type,
+		entries: Vec<JournalEntry>,
+	},
+	/// Deletions to the trash are undone by restoring the items, permanent deletions aren't
+	/// journaled
+	RestoreFromTrash {
+		location_id: location::id::Type,
+		entries: Vec<JournalEntry>,
+	},
+}
+
+impl InverseAction {
+	pub fn entries(&self) -> &[JournalEntry] {
+		match self {
+			Self::TrashCopies { entries, .. }
+			| Self::CutBack { entries, .. }
+			| Self::RenameBack { entries, .. }
+			| Self::RestoreFromTrash { entries, .. } => entries,
+		}
+	}
+
+	fn entries_mut(&mut self) -> &mut Vec<JournalEntry> {
+		match self {
+			Self::TrashCopies { entries, .. }
+			| Self::CutBack { entries, .. }
+			| Self::RenameBack { entries, .. }
+			| Self::RestoreFromTrash { entries, .. } => entries,
+		}
+	}
+
+	/// Whether every item is where undoing, or redoing, the operation puts it
+	async fn replayed(&self, undoing: bool) -> bool {
+		// Copies never leave their source
+		let keeps_before = matches!(self, Self::TrashCopies { .. });
+
+		for entry in self.entries() {
+			let before = fs::symlink_metadata(&entry.before).await.is_ok();
+			let after = fs::symlink_metadata(&entry.after).await.is_ok();
+
+			if after == undoing || (!keeps_before && before != undoing) {
+				return false;
+			}
+		}
+
+		true
+	}
+
+	fn encode(&self) -> Result<(String, Vec<u8>), serde_json::Error> {
+		Ok((
+			serde_json::to_string(self)?,
+			self.entries()
+				.iter()
+				.flat_map(|entry| entry.file_path_pub_id.iter().copied())
+				.collect(),
+		))
+	}
+}
+
+/// The journaled operation a job is undoing or redoing
+#[derive(Serialize, Deserialize, Hash, Type, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JournalReplay {
+	Undo(file_operation::id::Type),
+	Redo(file_operation::id::Type),
+}
+
+impl JournalReplay {
+	fn operation_id(self) -> file_operation::id::Type {
+		match self {
+			Self::Undo(id) | Self::Redo(id) => id,
+		}
+	}
+
+	fn undoing(self) -> bool {
+		matches!(self, Self::Undo(_))
+	}
+
+	/// Recorded on the operation while its jobs run, it stays there if one of them fails
+	fn unfinished(self) -> &'static str {
+		match self {
+			Self::Undo(_) => "undoing it hasn't finished",
+			Self::Redo(_) => "redoing it hasn't finished",
+		}
+	}
+}
+
+/// Records a completed operation in the journal. Jobs spawned to undo or redo a journaled
+/// operation pass which one they're replaying instead, see [`journal_replay`].
+pub async fn journal(
+	library: &Library,
+	replay: Option<JournalReplay>,
+	mut inverse: InverseAction,
+) -> Result<(), JournalError> {
+	let Library { db, .. } = library;
+
+	for entry in inverse.entries_mut() {
+		entry.state = EntryState::of(&entry.after).await;
+	}
+
+	if let Some(replay) = replay {
+		return journal_replay(db, replay, inverse).await;
+	}
+
+	if inverse.entries().is_empty() {
+		return Ok(());
+	}
+
+	let (encoded, pub_ids) = inverse.encode()?;
+
+	// Whatever was undone can't be redone on top of a new operation
+	db._batch((
+		db.file_operation()
+			.delete_many(vec![file_operation::date_undone::not(None)]),
+		db.file_operation().create(encoded, pub_ids, vec![]),
+	))
+	.await?;
+
+	if let Some(oldest_kept) = db
+		.file_operation()
+		.find_many(vec![])
+		.order_by(file_operation::id::order(SortOrder::Desc))
+		.skip(JOURNAL_SIZE - 1)
+		.take(1)
+		.select(file_operation::select!({ id }))
+		.exec()
+		.await?
+		.first()
+	{
+		db.file_operation()
+			.delete_many(vec![file_operation::id::lt(oldest_kept.id)])
+			.exec()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Records what one of the jobs replaying an operation did. Those undoing it leave it as is, while
+/// those redoing it update the entries of the items they moved, as each job only knows about its
+/// own. The operation is only marked undone, or redone, once all of its items got there.
+async fn journal_replay(
+	db: &PrismaClient,
+	replay: JournalReplay,
+	mut inverse: InverseAction,
+) -> Result<(), JournalError> {
+	let Some(operation) = db
+		.file_operation()
+		.find_unique(file_operation::id::equals(replay.operation_id()))
+		.exec()
+		.await?
+	else {
+		// Dropped from the journal in the meantime
+		return Ok(());
+	};
+	let mut journaled = serde_json::from_str::<InverseAction>(&operation.inverse)?;
+
+	let mut params = vec![];
+
+	if !replay.undoing() {
+		let entries = journaled.entries_mut();
+		for entry in inverse.entries_mut().drain(..) {
+			match entries
+				.iter_mut()
+				.find(|journaled| journaled.file_path_pub_id == entry.file_path_pub_id)
+			{
+				Some(journaled) => *journaled = entry,
+				None => entries.push(entry),
+			}
+		}
+
+		let (encoded, pub_ids) = journaled.encode()?;
+		params.extend([
+			file_operation::inverse::set(encoded),
+			file_operation::file_path_pub_ids::set(pub_ids),
+		]);
+	}
+
+	if journaled.replayed(replay.undoing()).await {
+		params.extend(replayed(replay));
+	}
+
+	if params.is_empty() {
+		return Ok(());
+	}
+
+	db.file_operation()
+		.update(file_operation::id::equals(operation.id), params)
+		.exec()
+		.await?;
+
+	Ok(())
+}
+
+/// Marks an operation undone or redone, clearing whatever an earlier attempt left
+fn replayed(replay: JournalReplay) -> [file_operation::SetParam; 2] {
+	[
+		file_operation::date_undone::set(replay.undoing().then(|| Utc::now().into())),
+		file_operation::replay_failure::set(None),
+	]
+}
+
+/// Undoes the latest operation that isn't undone yet
+pub async fn undo(node: &Arc<Node>, library: &Arc<Library>) -> Result<(), JournalError> {
+	let Library { db, .. } = &**library;
+
+	let operation = db
+		.file_operation()
+		.find_first(vec![file_operation::date_undone::equals(None)])
+		.order_by(file_operation::id::order(SortOrder::Desc))
+		.exec()
+		.await?
+		.ok_or(JournalError::NothingToUndo)?;
+	if let Some(failure) = operation.replay_failure {
+		return Err(JournalError::Unfinished(failure));
+	}
+	let inverse = serde_json::from_str::<InverseAction>(&operation.inverse)?;
+
+	for entry in inverse.entries() {
+		// What the operation left behind must be untouched...
+		match (EntryState::of(&entry.after).await, &entry.state) {
+			(Some(current), Some(journaled)) if current.matches(journaled) => {}
+			_ => return Err(JournalError::Diverged(entry.after.clone().into())),
+		}
+
+		// ...and the place the items came from still free, copies never left theirs
+		if !matches!(inverse, InverseAction::TrashCopies { .. })
+			&& fs::symlink_metadata(&entry.before).await.is_ok()
+		{
+			return Err(JournalError::Diverged(entry.before.clone().into()));
+		}
+	}
+
+	apply_inverse(node, library, JournalReplay::Undo(operation.id), inverse).await?;
+
+	invalidate_query!(library, "search.paths");
+	invalidate_query!(library, "search.objects");
+
+	Ok(())
+}
+
+/// Redoes the latest undone operation
+pub async fn redo(node: &Arc<Node>, library: &Arc<Library>) -> Result<(), JournalError> {
+	let Library { db, .. } = &**library;
+
+	// Operations are undone newest first, so the oldest undone one was the last to be undone
+	let operation = db
+		.file_operation()
+		.find_first(vec![file_operation::date_undone::not(None)])
+		.order_by(file_operation::id::order(SortOrder::Asc))
+		.exec()
+		.await?
+		.ok_or(JournalError::NothingToRedo)?;
+	if let Some(failure) = operation.replay_failure {
+		return Err(JournalError::Unfinished(failure));
+	}
+	let inverse = serde_json::from_str::<InverseAction>(&operation.inverse)?;
+
+	for entry in inverse.entries() {
+		// Copies only need their source, moved items must be just as the operation left them
+		let current = EntryState::of(&entry.before).await;
+		let untouched = match (&inverse, &current, &entry.state) {
+			(InverseAction::TrashCopies { .. }, Some(_), _) => true,
+			(_, Some(current), Some(journaled)) => current.matches(journaled),
+			_ => false,
+		};
+		if !untouched {
+			return Err(JournalError::Diverged(entry.before.clone().into()));
+		}
+
+		// Trashing again picks a new name in the trash anyway
+		if !matches!(inverse, InverseAction::RestoreFromTrash { .. })
+			&& fs::symlink_metadata(&entry.after).await.is_ok()
+		{
+			return Err(JournalError::Diverged(entry.after.clone().into()));
+		}
+	}
+
+	apply_operation(node, library, JournalReplay::Redo(operation.id), inverse).await?;
+
+	invalidate_query!(library, "search.paths");
+	invalidate_query!(library, "search.objects");
+
+	Ok(())
+}
+
+async fn set_replay_failure(
+	db: &PrismaClient,
+	replay: JournalReplay,
+	failure: Option<String>,
+) -> Result<(), QueryError> {
+	db.file_operation()
+		.update(
+			file_operation::id::equals(replay.operation_id()),
+			vec![file_operation::replay_failure::set(failure)],
+		)
+		.exec()
+		.await
+		.map(|_| ())
+}
+
+/// Runs the jobs replaying an operation one after the other. The failure recorded beforehand is
+/// cleared by the last of them to journal, once all the items got where they should, so it stays
+/// if any of them fails.
+async fn spawn_replay<SJob: StatefulJob>(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	replay: JournalReplay,
+	inits: impl IntoIterator<Item = SJob>,
+) -> Result<(), JournalError> {
+	let mut inits = inits.into_iter();
+	let Some(first) = inits.next() else {
+		return Ok(());
+	};
+	let job = inits.fold(Job::new(first), |job, init| job.queue_next(init));
+
+	set_replay_failure(&library.db, replay, Some(replay.unfinished().to_string())).await?;
+
+	if let Err(e) = job.spawn(node, library).await {
+		set_replay_failure(&library.db, replay, None).await?;
+
+		return Err(e.into());
+	}
+
+	Ok(())
+}
+
+/// Renames every item of an operation back, or again, putting the ones already renamed back where
+/// they were if one of them fails
+async fn rename_entries(entries: &[JournalEntry], undoing: bool) -> Result<(), JournalError> {
+	let paths = |entry: &JournalEntry| {
+		if undoing {
+			(entry.after.clone(), entry.before.clone())
+		} else {
+			(entry.before.clone(), entry.after.clone())
+		}
+	};
+
+	for (renamed, entry) in entries.iter().enumerate() {
+		let (from, to) = paths(entry);
+
+		if let Err(e) = fs::rename(&from, &to).await {
+			for entry in entries[..renamed].iter().rev() {
+				let (from, to) = paths(entry);
+				if let Err(e) = fs::rename(&to, &from).await {
+					error!(
+						"Failed to rename {} back to {}: {e:#?}",
+						to.display(),
+						from.display()
+					);
+				}
+			}
+
+			return Err(FileIOError::from((from, e)).into());
+		}
+	}
+
+	Ok(())
+}
+
+async fn apply_inverse(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	replay: JournalReplay,
+	inverse: InverseAction,
+) -> Result<(), JournalError> {
+	let Library { db, .. } = &**library;
+
+	match inverse {
+		InverseAction::TrashCopies {
+			target_location_id,
+			entries,
+			..
+		} => {
+			let init = FileDeleterJobInit {
+				location_id: target_location_id,
+				file_path_ids: file_path_ids_at(
+					db,
+					target_location_id,
+					entries.iter().map(|entry| &entry.after),
+				)
+				.await?,
+				to_trash: true,
+				journal_replay: Some(replay),
+			};
+
+			spawn_replay(node, library, replay, [init]).await?;
+		}
+
+		InverseAction::CutBack {
+			source_location_id,
+			target_location_id,
+			entries,
+		} => {
+			let source_location_path =
+				get_location_path_from_location_id(db, source_location_id).await?;
+
+			// A cut moves everything into a single directory, while the items may have come
+			// from many
+			let mut inits = vec![];
+			for (directory, entries) in group_by_parent(&entries, |entry| &entry.before) {
+				inits.push(FileCutterJobInit {
+					source_location_id: target_location_id,
+					target_location_id: source_location_id,
+					sources_file_path_ids: file_path_ids_at(
+						db,
+						target_location_id,
+						entries.iter().map(|entry| &entry.after),
+					)
+					.await?,
+					target_location_relative_directory_path: relative_directory(
+						&source_location_path,
+						directory,
+					)?,
+					conflict_policy: ConflictPolicy::Skip,
+					journal_replay: Some(replay),
+				});
+			}
+
+			spawn_replay(node, library, replay, inits).await?;
+		}
+
+		InverseAction::RenameBack { entries, .. } => {
+			rename_entries(&entries, true).await?;
+
+			db.file_operation()
+				.update(
+					file_operation::id::equals(replay.operation_id()),
+					replayed(replay).into(),
+				)
+				.exec()
+				.await?;
+		}
+
+		InverseAction::RestoreFromTrash { entries, .. } => {
+			let trashed = db
+				.file_path()
+				.find_many(vec![file_path::trash_path::in_vec(
+					entries
+						.iter()
+						.map(|entry| entry.after.to_string_lossy().to_string())
+						.collect(),
+				)])
+				.select(trashed_file_path::select())
+				.exec()
+				.await?;
+
+			let mut failed = entries.len().saturating_sub(trashed.len());
+			for trashed in trashed {
+				if let Err(e) = restore_trashed(library, trashed).await {
+					warn!("Failed to restore from the trash: {e:#?}");
+					failed += 1;
+				}
+			}
+
+			if failed > 0 {
+				let failure = format!("{failed} items couldn't be restored from the trash");
+				set_replay_failure(db, replay, Some(failure.clone())).await?;
+
+				return Err(JournalError::Unfinished(failure));
+			}
+
+			db.file_operation()
+				.update(
+					file_operation::id::equals(replay.operation_id()),
+					replayed(replay).into(),
+				)
+				.exec()
+				.await?;
+		}
+	}
+
+	Ok(())
+}
+
+async fn apply_operation(
+	node: &Arc<Node>,
+	library: &Arc<Library>,
+	replay: JournalReplay,
+	inverse: InverseAction,
+) -> Result<(), JournalError> {
+	let Library { db, .. } = &**library;
+
+	match inverse {
+		InverseAction::TrashCopies {
+			source_location_id,
+			target_location_id,
+			target_file_name_suffix,
+			entries,
+		} => {
+			let target_location_path =
+				get_location_path_from_location_id(db, target_location_id).await?;
+
+			let mut inits = vec![];
+			for (directory, entries) in group_by_parent(&entries, |entry| &entry.after) {
+				inits.push(FileCopierJobInit {
+					source_location_id,
+					target_location_id,
+					sources_file_path_ids: file_path_ids_at(
+						db,
+						source_location_id,
+						entries.iter().map(|entry| &entry.before),
+					)
+					.await?,
+					target_location_relative_directory_path: relative_directory(
+						&target_location_path,
+						directory,
+					)?,
+					target_file_name_suffix: target_file_name_suffix.clone(),
+					conflict_policy: ConflictPolicy::Skip,
+					verify: false,
+					journal_replay: Some(replay),
+				});
+			}
+
+			spawn_replay(node, library, replay, inits).await?;
+		}
+
+		InverseAction::CutBack {
+			source_location_id,
+			target_location_id,
+			entries,
+		} => {
+			let target_location_path =
+				get_location_path_from_location_id(db, target_location_id).await?;
+
+			let mut inits = vec![];
+			for (directory, entries) in group_by_parent(&entries, |entry| &entry.after) {
+				inits.push(FileCutterJobInit {
+					source_location_id,
+					target_location_id,
+					sources_file_path_ids: file_path_ids_at(
+						db,
+						source_location_id,
+						entries.iter().map(|entry| &entry.before),
+					)
+					.await?,
+					target_location_relative_directory_path: relative_directory(
+						&target_location_path,
+						directory,
+					)?,
+					conflict_policy: ConflictPolicy::Skip,
+					journal_replay: Some(replay),
+				});
+			}
+
+			spawn_replay(node, library, replay, inits).await?;
+		}
+
+		InverseAction::RenameBack {
+			location_id,
+			entries,
+		} => {
+			rename_entries(&entries, false).await?;
+
+			journal(
+				library,
+				Some(replay),
+				InverseAction::RenameBack {
+					location_id,
+					entries,
+				},
+			)
+			.await?;
+		}
+
+		InverseAction::RestoreFromTrash {
+			location_id,
+			entries,
+		} => {
+			let init = FileDeleterJobInit {
+				location_id,
+				file_path_ids: file_path_ids_at(
+					db,
+					location_id,
+					entries.iter().map(|entry| &entry.before),
+				)
+				.await?,
+				to_trash: true,
+				journal_replay: Some(replay),
+			};
+
+			spawn_replay(node, library, replay, [init]).await?;
+		}
+	}
+
+	Ok(())
+}
+
+/// The ids of the file_paths at `paths`, jobs can only act on what was indexed
+async fn file_path_ids_at<'a>(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+	paths: impl IntoIterator<Item = &'a PathBuf>,
+) -> Result<Vec<file_path::id::Type>, JournalError> {
+	let location_path = get_location_path_from_location_id(db, location_id).await?;
+
+	let mut ids = vec![];
+	for path in paths {
+		let is_dir = fs::metadata(path)
+			.await
+			.map_err(|e| FileIOError::from((path, e)))?
+			.is_dir();
+
+		let iso_file_path = IsolatedFilePathData::new(location_id, &location_path, path, is_dir)?;
+
+		ids.push(
+			db.file_path()
+				.find_unique((&iso_file_path).into())
+				.select(file_path::select!({ id }))
+				.exec()
+				.await?
+				.ok_or_else(|| JournalError::NotIndexed(path.clone().into()))?
+				.id,
+		);
+	}
+
+	Ok(ids)
+}
+
+fn group_by_parent<'a>(
+	entries: &'a [JournalEntry],
+	path: impl Fn(&'a JournalEntry) -> &'a PathBuf,
+) -> BTreeMap<&'a Path, Vec<&'a JournalEntry>> {
+	let mut groups = BTreeMap::<_, Vec<_>>::new();
+	for entry in entries {
+		let path = path(entry);
+		groups
+			.entry(path.parent().unwrap_or(path))
+			.or_default()
+			.push(entry);
+	}
+
+	groups
+}
+
+fn relative_directory(location_path: &Path, directory: &Path) -> Result<PathBuf, JournalError> {
+	directory
+		.strip_prefix(location_path)
+		.map(Path::to_path_buf)
+		.map_err(|_| JournalError::Diverged(directory.into()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn directories_match_whatever_their_contents() {
+		let file = EntryState {
+			is_dir: false,
+			size: 10,
+			modified: Some(Utc::now()),
+		};
+
+		assert!(file.matches(&file.clone()));
+		assert!(!file.matches(&EntryState {
+			size: 11,
+			..file.clone()
+		}));
+		assert!(!file.matches(&EntryState {
+			modified: None,
+			..file.clone()
+		}));
+
+		let directory = EntryState {
+			is_dir: true,
+			size: 4096,
+			modified: None,
+		};
+		assert!(directory.matches(&EntryState {
+			size: 8192,
+			modified: Some(Utc::now()),
+			..directory.clone()
+		}));
+		assert!(!directory.matches(&file));
+	}
+
+	#[test]
+	fn entries_are_grouped_by_their_parent_directory() {
+		let entry = |before: &str, after: &str| {
+			JournalEntry::new(vec![], PathBuf::from(before), PathBuf::from(after))
+		};
+		let entries = vec![
+			entry("/a/x.txt", "/target/x.txt"),
+			entry("/b/y.txt", "/target/y.txt"),
+			entry("/a/z", "/target/z"),
+		];
+
+		let groups = group_by_parent(&entries, |entry| &entry.before);
+		assert_eq!(groups.len(), 2);
+		assert_eq!(groups[Path::new("/a")].len(), 2);
+		assert_eq!(groups[Path::new("/b")].len(), 1);
+
+		assert_eq!(group_by_parent(&entries, |entry| &entry.after).len(), 1);
+	}
+
+	#[tokio::test]
+	async fn failed_renames_are_rolled_back() {
+		let dir = tempfile::tempdir().unwrap();
+		let entry = |before: &str, after: &str| {
+			JournalEntry::new(vec![], dir.path().join(before), dir.path().join(after))
+		};
+		fs::write(dir.path().join("a.txt"), b"a").await.unwrap();
+		fs::write(dir.path().join("b.txt"), b"b").await.unwrap();
+
+		let entries = vec![
+			entry("a.txt", "renamed.txt"),
+			entry("b.txt", "missing/renamed.txt"),
+		];
+		assert!(rename_entries(&entries, false).await.is_err());
+
+		assert!(fs::metadata(dir.path().join("a.txt")).await.is_ok());
+		assert!(fs::metadata(dir.path().join("b.txt")).await.is_ok());
+		assert!(fs::metadata(dir.path().join("renamed.txt")).await.is_err());
+	}
+}
@@ -4,28 +4,44 @@ use crate::{
 		LocationError,
 	},
 	prisma::{file_path, location, PrismaClient},
-	util::db::{maybe_missing, MissingFieldError},
+	util::{
+		db::{maybe_missing, MissingFieldError},
+		error::FileIOError,
+	},
 };
 
-use std::path::{Path, PathBuf};
+use std::{
+	collections::HashSet,
+	io,
+	path::{Path, PathBuf},
+};
 
 use serde::{Deserialize, Serialize};
+use tokio::fs;
 
 pub mod create;
+pub mod dedup;
 pub mod delete;
 pub mod erase;
+pub mod journal;
 
+pub mod archive;
+pub mod conflict;
+pub mod convert;
 pub mod copy;
 pub mod cut;
+pub mod transfer;
+pub mod trash;
 
-// pub mod decrypt;
-// pub mod encrypt;
+pub mod decrypt;
+pub mod encrypt;
 
 pub mod error;
 
 use error::FileSystemJobsError;
 
-// pub const BYTES_EXT: &str = ".bytes";
+/// Extension appended to files encrypted by the [`encrypt`] job
+pub const BYTES_EXT: &str = "bytes";
 
 #[derive(Serialize, Deserialize, Debug, Clone, Eq, PartialEq)]
 pub enum ObjectType {
@@ -137,6 +153,46 @@ pub async fn fetch_source_and_target_location_paths(
 	}
 }
 
+/// Picks the first path in `directory` named `{stem}.{extension}` or `{stem} ({n}).{extension}`
+/// that doesn't exist on disk and wasn't already `taken` by a previous call, so jobs producing
+/// new files never overwrite existing ones
+pub async fn find_available_path(
+	directory: impl AsRef<Path>,
+	stem: &str,
+	extension: Option<&str>,
+	taken: &mut HashSet<PathBuf>,
+) -> Result<PathBuf, FileIOError> {
+	let directory = directory.as_ref();
+	let file_name = |suffix: Option<u32>| {
+		let stem = match suffix {
+			Some(n) => format!("{stem} ({n})"),
+			None => stem.to_string(),
+		};
+
+		match extension {
+			Some(extension) if !extension.is_empty() => format!("{stem}.{extension}"),
+			_ => stem,
+		}
+	};
+
+	let mut path = directory.join(file_name(None));
+	let mut suffix = 1;
+	loop {
+		match fs::metadata(&path).await {
+			Ok(_) => {}
+			Err(e) if e.kind() == io::ErrorKind::NotFound => {
+				if taken.insert(path.clone()) {
+					return Ok(path);
+				}
+			}
+			Err(e) => return Err(FileIOError::from((path, e))),
+		}
+
+		path = directory.join(file_name(Some(suffix)));
+		suffix += 1;
+	}
+}
+
 fn construct_target_filename(
 	source_file_data: &FileData,
 	target_file_name_suffix: &Option<String>,
@@ -0,0 +1,423 @@
+use crate::{object::validation::hash::file_checksum, util::error::FileIOError};
+
+use std::{
+	fs::Metadata,
+	io,
+	path::{Path, PathBuf},
+};
+
+use filetime::FileTime;
+use tokio::{
+	fs::{self, File, OpenOptions},
+	io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt, SeekFrom},
+	task::spawn_blocking,
+};
+#[cfg(unix)]
+use tracing::warn;
+
+use super::error::FileSystemJobsError;
+
+/// Files are copied in chunks of this size, one job step each, so a job that gets shut down in
+/// the middle of a large file resumes from its last complete chunk
+pub const COPY_CHUNK_SIZE: u64 = 64 * 1024 * 1024;
+
+const PARTIAL_EXTENSION: &str = "sdpart";
+
+/// A copy is written next to its target under this name until it's complete and verified
+pub fn partial_path(target: impl AsRef<Path>) -> PathBuf {
+	let target = target.as_ref();
+
+	let mut file_name = target.file_name().unwrap_or_default().to_os_string();
+	file_name.push(".");
+	file_name.push(PARTIAL_EXTENSION);
+
+	target.with_file_name(file_name)
+}
+
+/// Renames can't cross filesystems, they fail with `EXDEV` on unix and with
+/// `ERROR_NOT_SAME_DEVICE` on Windows
+pub fn is_cross_device_error(e: &io::Error) -> bool {
+	#[cfg(windows)]
+	const CROSS_DEVICE: i32 = 17;
+	#[cfg(not(windows))]
+	const CROSS_DEVICE: i32 = 18;
+
+	e.raw_os_error() == Some(CROSS_DEVICE)
+}
+
+/// Copies the chunk of `source` starting at `offset` to the same offset of `partial`, returning
+/// how many bytes were copied. Anything past `offset` in `partial` was written by an interrupted
+/// step, so it's discarded first.
+pub async fn copy_chunk(
+	source: impl AsRef<Path>,
+	partial: impl AsRef<Path>,
+	offset: u64,
+) -> Result<u64, FileIOError> {
+	let (source, partial) = (source.as_ref(), partial.as_ref());
+
+	let mut source_file = File::open(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+	source_file
+		.seek(SeekFrom::Start(offset))
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	let mut partial_file = OpenOptions::new()
+		.create(true)
+		.write(true)
+		.open(partial)
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+	partial_file
+		.set_len(offset)
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+	partial_file
+		.seek(SeekFrom::Start(offset))
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+
+	let copied = tokio::io::copy(&mut source_file.take(COPY_CHUNK_SIZE), &mut partial_file)
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+
+	partial_file
+		.flush()
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+
+	Ok(copied)
+}
+
+/// Copies a whole file, sharing its data with `source` through a reflink where the filesystem
+/// supports it. Otherwise the data is copied by the kernel, with `copy_file_range` on Linux.
+pub async fn copy_file(
+	source: impl AsRef<Path>,
+	target: impl AsRef<Path>,
+) -> Result<(), FileIOError> {
+	let (source, target) = (source.as_ref(), target.as_ref());
+	let (source_path, target_path) = (source.to_path_buf(), target.to_path_buf());
+
+	spawn_blocking(move || reflink_copy::reflink_or_copy(source_path, target_path))
+		.await
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+		.and_then(|res| res)
+		.map(|_| ())
+		.map_err(|e| FileIOError::from((target, e)))
+}
+
+/// Compares a copy with its source, by size and then by BLAKE3 checksum, returning the checksum
+/// both share or `None` if they differ
+pub async fn verify_copy(
+	source: impl AsRef<Path>,
+	copy: impl AsRef<Path>,
+) -> Result<Option<String>, FileIOError> {
+	let (source, copy) = (source.as_ref(), copy.as_ref());
+
+	let source_len = fs::metadata(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?
+		.len();
+	let copy_len = fs::metadata(copy)
+		.await
+		.map_err(|e| FileIOError::from((copy, e)))?
+		.len();
+	if source_len != copy_len {
+		return Ok(None);
+	}
+
+	let source_checksum = file_checksum(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+	let copy_checksum = file_checksum(copy)
+		.await
+		.map_err(|e| FileIOError::from((copy, e)))?;
+
+	Ok((source_checksum == copy_checksum).then_some(source_checksum))
+}
+
+/// Sets the extended attributes, permissions and access and modification times of `source` on
+/// `target`. Extended attributes the target's filesystem doesn't take are left behind.
+pub async fn preserve_metadata(
+	source: impl AsRef<Path>,
+	source_metadata: &Metadata,
+	target: impl AsRef<Path>,
+) -> Result<(), FileIOError> {
+	let (source, target) = (source.as_ref(), target.as_ref());
+
+	#[cfg(unix)]
+	{
+		let (source_path, target_path) = (source.to_path_buf(), target.to_path_buf());
+
+		// Set first, as a read only target doesn't take them anymore
+		if let Err(e) = spawn_blocking(move || preserve_xattrs(&source_path, &target_path)).await {
+			warn!(
+				"Failed to preserve extended attributes of {}: {e:#?}",
+				target.display()
+			);
+		}
+	}
+	#[cfg(not(unix))]
+	let _ = source;
+
+	fs::set_permissions(target, source_metadata.permissions())
+		.await
+		.map_err(|e| FileIOError::from((target, e)))?;
+
+	let accessed = FileTime::from_last_access_time(source_metadata);
+	let modified = FileTime::from_last_modification_time(source_metadata);
+	let target_path = target.to_path_buf();
+
+	spawn_blocking(move || filetime::set_file_times(&target_path, accessed, modified))
+		.await
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+		.and_then(|res| res)
+		.map_err(|e| FileIOError::from((target, e)))
+}
+
+#[cfg(unix)]
+fn preserve_xattrs(source: &Path, target: &Path) {
+	if !xattr::SUPPORTED_PLATFORM {
+		return;
+	}
+
+	let names = match xattr::list(source) {
+		Ok(names) => names,
+		Err(e) => {
+			warn!(
+				"Failed to list extended attributes of {}: {e:#?}",
+				source.display()
+			);
+			return;
+		}
+	};
+
+	for name in names {
+		match xattr::get(source, &name) {
+			Ok(Some(value)) => {
+				// Some namespaces can only be written with privileges we may not have
+				if let Err(e) = xattr::set(target, &name, &value) {
+					warn!(
+						"Failed to set extended attribute {name:?} on {}: {e:#?}",
+						target.display()
+					);
+				}
+			}
+			Ok(None) => {}
+			Err(e) => warn!(
+				"Failed to read extended attribute {name:?} of {}: {e:#?}",
+				source.display()
+			),
+		}
+	}
+}
+
+/// Recreates the symlink `source` at `target`, pointing to the same place, then removes it.
+/// The link is never followed, whatever it points to is left untouched.
+pub async fn move_symlink(
+	source: impl AsRef<Path>,
+	target: impl AsRef<Path>,
+) -> Result<(), FileIOError> {
+	let (source, target) = (source.as_ref(), target.as_ref());
+
+	let link = fs::read_link(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	#[cfg(unix)]
+	{
+		fs::symlink(&link, target)
+			.await
+			.map_err(|e| FileIOError::from((target, e)))?;
+
+		fs::remove_file(source)
+			.await
+			.map_err(|e| FileIOError::from((source, e)))
+	}
+
+	// Windows tells links to directories apart from links to files
+	#[cfg(windows)]
+	{
+		if fs::metadata(source)
+			.await
+			.map_or(false, |metadata| metadata.is_dir())
+		{
+			fs::symlink_dir(&link, target)
+				.await
+				.map_err(|e| FileIOError::from((target, e)))?;
+
+			fs::remove_dir(source)
+				.await
+				.map_err(|e| FileIOError::from((source, e)))
+		} else {
+			fs::symlink_file(&link, target)
+				.await
+				.map_err(|e| FileIOError::from((target, e)))?;
+
+			fs::remove_file(source)
+				.await
+				.map_err(|e| FileIOError::from((source, e)))
+		}
+	}
+}
+
+/// Makes sure the complete copy at `partial` matches `source`, unless `verify` is off, carries the
+/// source's metadata over and moves the copy to `target`. Returns the checksum both share when
+/// verified. A copy that doesn't match is removed, the source is never touched here.
+pub async fn finish_copy(
+	source: impl AsRef<Path>,
+	partial: impl AsRef<Path>,
+	target: impl AsRef<Path>,
+	verify: bool,
+) -> Result<Option<String>, FileSystemJobsError> {
+	let (source, partial, target) = (source.as_ref(), partial.as_ref(), target.as_ref());
+
+	let source_metadata = fs::metadata(source)
+		.await
+		.map_err(|e| FileIOError::from((source, e)))?;
+
+	// The copy must be on disk before anyone gets rid of the source
+	let partial_file = OpenOptions::new()
+		.write(true)
+		.open(partial)
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+	partial_file
+		.sync_all()
+		.await
+		.map_err(|e| FileIOError::from((partial, e)))?;
+	drop(partial_file);
+
+	let checksum = if verify {
+		let Some(checksum) = verify_copy(source, partial).await? else {
+			fs::remove_file(partial)
+				.await
+				.map_err(|e| FileIOError::from((partial, e)))?;
+
+			return Err(FileSystemJobsError::CopyMismatch(target.into()));
+		};
+
+		Some(checksum)
+	} else {
+		None
+	};
+
+	// Verifying reads the copy, so its access time is only set afterwards
+	preserve_metadata(source, &source_metadata, partial).await?;
+
+	fs::rename(partial, target)
+		.await
+		.map_err(|e| FileIOError::from((target, e)))?;
+
+	Ok(checksum)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn interrupted_chunks_are_rewritten() {
+		let dir = tempdir().unwrap();
+		let source = dir.path().join("source.bin");
+		let target = dir.path().join("target.bin");
+		let partial = partial_path(&target);
+		assert_eq!(partial, dir.path().join("target.bin.sdpart"));
+
+		let contents = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+		fs::write(&source, &contents).await.unwrap();
+
+		// Leftovers of a step that was interrupted after writing garbage past the offset
+		fs::write(&partial, [&contents[..400], &[0u8; 300][..]].concat())
+			.await
+			.unwrap();
+
+		assert_eq!(copy_chunk(&source, &partial, 400).await.unwrap(), 600);
+		finish_copy(&source, &partial, &target, true).await.unwrap();
+
+		assert_eq!(fs::read(&target).await.unwrap(), contents);
+		assert!(fs::metadata(&partial).await.is_err());
+		assert_eq!(
+			FileTime::from_last_modification_time(&fs::metadata(&target).await.unwrap()),
+			FileTime::from_last_modification_time(&fs::metadata(&source).await.unwrap())
+		);
+	}
+
+	#[tokio::test]
+	async fn verified_copies_keep_their_metadata() {
+		let dir = tempdir().unwrap();
+		let source = dir.path().join("source.txt");
+		let target = dir.path().join("target.txt");
+
+		fs::write(&source, b"spacedrive").await.unwrap();
+		filetime::set_file_mtime(&source, FileTime::from_unix_time(1_000_000_000, 0)).unwrap();
+
+		copy_file(&source, &target).await.unwrap();
+		let checksum = verify_copy(&source, &target).await.unwrap();
+		assert_eq!(checksum, Some(file_checksum(&source).await.unwrap()));
+
+		let source_metadata = fs::metadata(&source).await.unwrap();
+		preserve_metadata(&source, &source_metadata, &target)
+			.await
+			.unwrap();
+		assert_eq!(
+			FileTime::from_last_modification_time(&fs::metadata(&target).await.unwrap()),
+			FileTime::from_unix_time(1_000_000_000, 0)
+		);
+
+		fs::write(&target, b"spacedrivf").await.unwrap();
+		assert_eq!(verify_copy(&source, &target).await.unwrap(), None);
+	}
+
+	#[tokio::test]
+	async fn mismatching_copies_are_discarded() {
+		let dir = tempdir().unwrap();
+		let source = dir.path().join("source.txt");
+		let target = dir.path().join("target.txt");
+		let partial = partial_path(&target);
+
+		fs::write(&source, b"spacedrive").await.unwrap();
+		fs::write(&partial, b"spacedrivf").await.unwrap();
+
+		assert!(matches!(
+			finish_copy(&source, &partial, &target, true).await,
+			Err(FileSystemJobsError::CopyMismatch(_))
+		));
+		assert!(fs::metadata(&partial).await.is_err());
+		assert!(fs::metadata(&target).await.is_err());
+		assert!(fs::metadata(&source).await.is_ok());
+	}
+
+	#[cfg(unix)]
+	#[tokio::test]
+	async fn symlinked_directories_are_moved_as_links() {
+		let dir = tempdir().unwrap();
+		let real = dir.path().join("real");
+		let source = dir.path().join("source");
+		let target = dir.path().join("target");
+
+		fs::create_dir(&real).await.unwrap();
+		fs::write(real.join("file.txt"), b"spacedrive")
+			.await
+			.unwrap();
+		fs::symlink(&real, &source).await.unwrap();
+
+		move_symlink(&source, &target).await.unwrap();
+
+		assert!(fs::symlink_metadata(&source).await.is_err());
+		assert!(fs::symlink_metadata(&target)
+			.await
+			.unwrap()
+			.file_type()
+			.is_symlink());
+		assert_eq!(fs::read_link(&target).await.unwrap(), real);
+		// The directory it points to wasn't moved along with it
+		assert_eq!(
+			fs::read(real.join("file.txt")).await.unwrap(),
+			b"spacedrive"
+		);
+	}
+}
@@ -0,0 +1,631 @@
+use crate::{
+	invalidate_query,
+	job::{
+		CurrentStep, JobError, JobInitOutput, JobResult, JobRunErrors, JobStepOutput, StatefulJob,
+		WorkerContext,
+	},
+	library::Library,
+	location::{file_path_helper::IsolatedFilePathData, LocationError},
+	prisma::{file_path, location, PrismaClient},
+	sync,
+	util::{db::maybe_missing, error::FileIOError},
+};
+
+use sd_prisma::prisma_sync;
+use sd_sync::{CRDTOperation, OperationFactory};
+
+use std::{
+	hash::Hash,
+	io,
+	path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use tokio::{fs, io::AsyncWriteExt};
+use tracing::warn;
+
+use super::{error::FileSystemJobsError, get_location_path_from_location_id};
+
+/// Only unix systems have a trash we can move files to, deleting them is always permanent elsewhere
+pub const TRASH_AVAILABLE: bool = cfg!(unix);
+
+const FILES_DIR: &str = "files";
+const INFO_DIR: &str = "info";
+const INFO_EXTENSION: &str = "trashinfo";
+
+file_path::select!(trashed_file_path {
+	id
+	pub_id
+	is_dir
+	materialized_path
+	name
+	extension
+	trashed_at
+	trash_path
+	trash_location_id
+});
+
+/// A trash directory as laid out by the freedesktop.org Trash specification, with the trashed
+/// items in `files/` and a `.trashinfo` record for each of them in `info/`.
+///
+/// It's used on macOS as well, as Finder's `~/.Trash` records where items came from in a way only
+/// Finder reads. Items trashed there don't show up in Finder, they're put back from Spacedrive.
+#[cfg_attr(not(unix), allow(dead_code))]
+struct TrashDir {
+	root: PathBuf,
+	/// Trash directories at the top of a volume record paths relative to it, the home trash
+	/// records absolute paths
+	topdir: Option<PathBuf>,
+}
+
+/// Moves `path` to the trash of the volume holding it, returning where it ended up
+pub async fn move_to_trash(path: impl AsRef<Path>) -> Result<PathBuf, FileSystemJobsError> {
+	let path = path.as_ref();
+
+	let trash = trash_dir_for(path).await?;
+	let (trash_path, info_path) = reserve_name(&trash, path).await?;
+
+	if let Err(e) = fs::rename(path, &trash_path).await {
+		// The record would otherwise point at nothing
+		fs::remove_file(&info_path)
+			.await
+			.map_err(|e| FileIOError::from((&info_path, e)))?;
+
+		return Err(FileIOError::from((path, e)).into());
+	}
+
+	Ok(trash_path)
+}
+
+/// Puts a trashed item back at `original_path`, which must not be taken by anything else
+pub async fn restore_from_trash(
+	trash_path: impl AsRef<Path>,
+	original_path: impl AsRef<Path>,
+) -> Result<(), FileSystemJobsError> {
+	let (trash_path, original_path) = (trash_path.as_ref(), original_path.as_ref());
+
+	if fs::symlink_metadata(original_path).await.is_ok() {
+		return Err(FileSystemJobsError::WouldOverwrite(original_path.into()));
+	}
+
+	if let Some(parent) = original_path.parent() {
+		fs::create_dir_all(parent)
+			.await
+			.map_err(|e| FileIOError::from((parent, e)))?;
+	}
+
+	fs::rename(trash_path, original_path)
+		.await
+		.map_err(|e| FileIOError::from((trash_path, e)))?;
+
+	remove_if_exists(&info_path_of(trash_path), false)
+		.await
+		.map_err(Into::into)
+}
+
+/// Puts a trashed item back in its location, along with the rows of everything inside it. The rows
+/// go back before the file does, so the watcher finds them in place.
+pub async fn restore_trashed(
+	library: &Library,
+	trashed: trashed_file_path::Data,
+) -> Result<(), FileSystemJobsError> {
+	let Library { db, sync, .. } = library;
+
+	let trash_path = trashed
+		.trash_path
+		.as_ref()
+		.ok_or(FileSystemJobsError::NotTrashedItself(trashed.id))?;
+	let location_id = maybe_missing(trashed.trash_location_id, "file_path.trash_location_id")?;
+
+	let original_path = get_location_path_from_location_id(db, location_id)
+		.await?
+		.join(IsolatedFilePathData::from_db_data(
+			location_id,
+			maybe_missing(trashed.is_dir, "file_path.is_dir")?,
+			maybe_missing(
+				trashed.materialized_path.as_deref(),
+				"file_path.materialized_path",
+			)?
+			.into(),
+			maybe_missing(trashed.name.as_deref(), "file_path.name")?.into(),
+			trashed.extension.as_deref().unwrap_or_default().into(),
+		));
+
+	if fs::symlink_metadata(&original_path).await.is_ok() {
+		return Err(FileSystemJobsError::WouldOverwrite(original_path.into()));
+	}
+
+	let location_pub_id = location_pub_id(db, location_id).await?;
+
+	let (mut restored_ids, mut restored_pub_ids) = (vec![trashed.id], vec![trashed.pub_id.clone()]);
+	if let Some(descendants) = trashed_descendants_params(&trashed) {
+		for file_path in db
+			.file_path()
+			.find_many(descendants)
+			.select(file_path::select!({ id pub_id }))
+			.exec()
+			.await?
+		{
+			restored_ids.push(file_path.id);
+			restored_pub_ids.push(file_path.pub_id);
+		}
+	}
+
+	sync.write_ops(
+		db,
+		(
+			location_ops(sync, &restored_pub_ids, Some(&location_pub_id)),
+			(
+				db.file_path().update_many(
+					vec![file_path::id::in_vec(restored_ids.clone())],
+					vec![
+						file_path::location_id::set(Some(location_id)),
+						file_path::trashed_at::set(None),
+						file_path::trash_location_id::set(None),
+					],
+				),
+				db.file_path().update(
+					file_path::id::equals(trashed.id),
+					vec![file_path::trash_path::set(None)],
+				),
+			),
+		),
+	)
+	.await?;
+
+	if let Err(e) = restore_from_trash(trash_path, &original_path).await {
+		sync.write_ops(
+			db,
+			(
+				location_ops(sync, &restored_pub_ids, None),
+				(
+					db.file_path().update_many(
+						vec![file_path::id::in_vec(restored_ids)],
+						vec![
+							file_path::location_id::set(None),
+							file_path::trashed_at::set(trashed.trashed_at),
+							file_path::trash_location_id::set(Some(location_id)),
+						],
+					),
+					db.file_path().update(
+						file_path::id::equals(trashed.id),
+						vec![file_path::trash_path::set(Some(trash_path.clone()))],
+					),
+				),
+			),
+		)
+		.await?;
+
+		return Err(e);
+	}
+
+	Ok(())
+}
+
+/// The pub id of a location, which is how the rows moving in and out of it are synced
+pub(super) async fn location_pub_id(
+	db: &PrismaClient,
+	location_id: location::id::Type,
+) -> Result<Vec<u8>, FileSystemJobsError> {
+	db.location()
+		.find_unique(location::id::equals(location_id))
+		.select(location::select!({ pub_id }))
+		.exec()
+		.await?
+		.map(|location| location.pub_id)
+		.ok_or(FileSystemJobsError::Location(LocationError::IdNotFound(
+			location_id,
+		)))
+}
+
+/// Sync operations moving rows back into a location, or out of any while they're in the trash.
+/// Other instances only learn that the rows left their location, the trash itself isn't shared.
+pub(super) fn location_ops(
+	sync: &sync::Manager,
+	pub_ids: &[Vec<u8>],
+	location_pub_id: Option<&Vec<u8>>,
+) -> Vec<CRDTOperation> {
+	pub_ids
+		.iter()
+		.map(|pub_id| {
+			sync.shared_update(
+				prisma_sync::file_path::SyncId {
+					pub_id: pub_id.clone(),
+				},
+				file_path::location::NAME,
+				json!(location_pub_id.map(|pub_id| prisma_sync::location::SyncId {
+					pub_id: pub_id.clone()
+				})),
+			)
+		})
+		.collect()
+}
+
+/// Permanently removes a trashed item along with its record
+pub async fn remove_from_trash(
+	trash_path: impl AsRef<Path>,
+	is_dir: bool,
+) -> Result<(), FileIOError> {
+	let trash_path = trash_path.as_ref();
+
+	remove_if_exists(trash_path, is_dir).await?;
+	remove_if_exists(&info_path_of(trash_path), false).await
+}
+
+/// The `.trashinfo` record of the item at `trash_path`
+pub fn info_path_of(trash_path: impl AsRef<Path>) -> PathBuf {
+	let trash_path = trash_path.as_ref();
+
+	let mut info_name = trash_path.file_name().unwrap_or_default().to_os_string();
+	info_name.push(".");
+	info_name.push(INFO_EXTENSION);
+
+	trash_path
+		.parent()
+		.and_then(Path::parent)
+		.unwrap_or(trash_path)
+		.join(INFO_DIR)
+		.join(info_name)
+}
+
+/// Where clause for the rows of everything that was trashed along with a directory
+pub fn trashed_descendants_params(
+	trashed: &trashed_file_path::Data,
+) -> Option<Vec<file_path::WhereParam>> {
+	let location_id = trashed.trash_location_id?;
+	let trashed_at = trashed.trashed_at?;
+
+	let children_path = IsolatedFilePathData::from_db_data(
+		location_id,
+		trashed.is_dir?,
+		trashed.materialized_path.as_deref()?.into(),
+		trashed.name.as_deref()?.into(),
+		trashed.extension.as_deref().unwrap_or_default().into(),
+	)
+	.materialized_path_for_children()?;
+
+	Some(vec![
+		file_path::location_id::equals(None),
+		file_path::trash_location_id::equals(Some(location_id)),
+		file_path::trashed_at::equals(Some(trashed_at)),
+		file_path::materialized_path::starts_with(children_path),
+	])
+}
+
+async fn remove_if_exists(path: &Path, is_dir: bool) -> Result<(), FileIOError> {
+	match if is_dir {
+		fs::remove_dir_all(path).await
+	} else {
+		fs::remove_file(path).await
+	} {
+		Ok(()) => Ok(()),
+		Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+		Err(e) => Err(FileIOError::from((path, e))),
+	}
+}
+
+/// The home trash, at `$XDG_DATA_HOME/Trash` or `~/.local/share/Trash` (on macOS too), if it's
+/// on the same volume as `path`, the one at the top of its volume otherwise
+#[cfg(unix)]
+async fn trash_dir_for(path: &Path) -> Result<TrashDir, FileSystemJobsError> {
+	use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+	let device = fs::symlink_metadata(path)
+		.await
+		.map_err(|e| FileIOError::from((path, e)))?
+		.dev();
+
+	let home_trash = std::env::var_os("XDG_DATA_HOME")
+		.filter(|data_home| !data_home.is_empty())
+		.map(PathBuf::from)
+		.or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+		.map(|data_home| data_home.join("Trash"))
+		.ok_or(FileSystemJobsError::TrashUnavailable(path.into()))?;
+
+	if device_of_nearest_ancestor(&home_trash).await == Some(device) {
+		create_trash_subdirs(&home_trash).await?;
+
+		return Ok(TrashDir {
+			root: home_trash,
+			topdir: None,
+		});
+	}
+
+	// The top directory of the volume is the highest ancestor still on the same device
+	let mut topdir = path.parent().unwrap_or(path).to_path_buf();
+	while let Some(parent) = topdir.parent() {
+		match fs::metadata(parent).await {
+			Ok(metadata) if metadata.dev() == device => topdir = parent.to_path_buf(),
+			_ => break,
+		}
+	}
+
+	const STICKY_BIT: u32 = 0o1000;
+
+	// SAFETY: getuid can't fail
+	let uid = unsafe { libc::getuid() };
+
+	// An administrator may provide a shared `.Trash` with the sticky bit set, it must not be
+	// a symlink
+	let shared_trash = topdir.join(".Trash");
+	let root = match fs::symlink_metadata(&shared_trash).await {
+		Ok(metadata) if metadata.is_dir() && metadata.permissions().mode() & STICKY_BIT != 0 => {
+			shared_trash.join(uid.to_string())
+		}
+		_ => topdir.join(format!(".Trash-{uid}")),
+	};
+
+	fs::DirBuilder::new()
+		.recursive(true)
+		.mode(0o700)
+		.create(&root)
+		.await
+		.map_err(|e| FileIOError::from((&root, e)))?;
+
+	create_trash_subdirs(&root).await?;
+
+	Ok(TrashDir {
+		root,
+		topdir: Some(topdir),
+	})
+}
+
+#[cfg(not(unix))]
+async fn trash_dir_for(path: &Path) -> Result<TrashDir, FileSystemJobsError> {
+	Err(FileSystemJobsError::TrashUnavailable(path.into()))
+}
+
+#[cfg(unix)]
+async fn device_of_nearest_ancestor(path: &Path) -> Option<u64> {
+	use std::os::unix::fs::MetadataExt;
+
+	for ancestor in path.ancestors() {
+		if let Ok(metadata) = fs::metadata(ancestor).await {
+			return Some(metadata.dev());
+		}
+	}
+
+	None
+}
+
+#[cfg(unix)]
+async fn create_trash_subdirs(root: &Path) -> Result<(), FileIOError> {
+	for dir in [root.join(FILES_DIR), root.join(INFO_DIR)] {
+		fs::DirBuilder::new()
+			.recursive(true)
+			.mode(0o700)
+			.create(&dir)
+			.await
+			.map_err(|e| FileIOError::from((dir, e)))?;
+	}
+
+	Ok(())
+}
+
+/// Claims a name in the trash for `original_path` by atomically creating its `.trashinfo` record,
+/// returning where the item and its record go
+async fn reserve_name(
+	trash: &TrashDir,
+	original_path: &Path,
+) -> Result<(PathBuf, PathBuf), FileIOError> {
+	let file_name = original_path.file_name().unwrap_or_default();
+	let stem = Path::new(file_name).file_stem().unwrap_or(file_name);
+	let extension = Path::new(file_name).extension();
+
+	let recorded_path = trash
+		.topdir
+		.as_ref()
+		.and_then(|topdir| original_path.strip_prefix(topdir).ok())
+		.unwrap_or(original_path);
+	let record = trash_info(recorded_path, Local::now());
+
+	for attempt in 1.. {
+		// Names already taken are numbered like `photo.2.jpg`
+		let mut name = file_name.to_os_string();
+		if attempt > 1 {
+			name = stem.to_os_string();
+			name.push(format!(".{attempt}"));
+			if let Some(extension) = extension {
+				name.push(".");
+				name.push(extension);
+			}
+		}
+
+		let trash_path = trash.root.join(FILES_DIR).join(&name);
+		if fs::symlink_metadata(&trash_path).await.is_ok() {
+			continue;
+		}
+
+		name.push(".");
+		name.push(INFO_EXTENSION);
+		let info_path = trash.root.join(INFO_DIR).join(name);
+
+		match fs::OpenOptions::new()
+			.write(true)
+			.create_new(true)
+			.open(&info_path)
+			.await
+		{
+			Ok(mut info_file) => {
+				info_file
+					.write_all(record.as_bytes())
+					.await
+					.map_err(|e| FileIOError::from((&info_path, e)))?;
+				info_file
+					.flush()
+					.await
+					.map_err(|e| FileIOError::from((&info_path, e)))?;
+
+				return Ok((trash_path, info_path));
+			}
+			Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+			Err(e) => return Err(FileIOError::from((info_path, e))),
+		}
+	}
+
+	unreachable!("the trash can't have taken every name")
+}
+
+fn trash_info(recorded_path: &Path, deleted_at: DateTime<Local>) -> String {
+	format!(
+		"[Trash Info]\nPath={}\nDeletionDate={}\n",
+		escape_path(recorded_path),
+		deleted_at.format("%Y-%m-%dT%H:%M:%S")
+	)
+}
+
+/// Paths in `.trashinfo` records are escaped like URLs
+fn escape_path(path: &Path) -> String {
+	#[cfg(unix)]
+	let bytes = std::os::unix::ffi::OsStrExt::as_bytes(path.as_os_str()).to_vec();
+	#[cfg(not(unix))]
+	let bytes = path.to_string_lossy().into_owned().into_bytes();
+
+	bytes
+		.into_iter()
+		.map(|byte| match byte {
+			b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+				(byte as char).to_string()
+			}
+			_ => format!("%{byte:02X}"),
+		})
+		.collect()
+}
+
+#[derive(Serialize, Deserialize, Hash, Type, Debug)]
+pub struct EmptyTrashJobInit {
+	/// Only empties what was trashed from this location, the whole trash of the library otherwise
+	#[specta(optional)]
+	#[serde(default)]
+	pub location_id: Option<location::id::Type>,
+}
+
+#[async_trait::async_trait]
+impl StatefulJob for EmptyTrashJobInit {
+	type Data = ();
+	type Step = trashed_file_path::Data;
+	type RunMetadata = ();
+
+	const NAME: &'static str = "empty_trash";
+
+	async fn init(
+		&self,
+		ctx: &WorkerContext,
+		data: &mut Option<Self::Data>,
+	) -> Result<JobInitOutput<Self::RunMetadata, Self::Step>, JobError> {
+		let init = self;
+		let Library { db, .. } = &*ctx.library;
+
+		let steps = db
+			.file_path()
+			.find_many(sd_utils::chain_optional_iter(
+				[file_path::trash_path::not(None)],
+				[init
+					.location_id
+					.map(Some)
+					.map(file_path::trash_location_id::equals)],
+			))
+			.select(trashed_file_path::select())
+			.exec()
+			.await?;
+
+		// Must fill in the data, otherwise the job will not run
+		*data = Some(());
+
+		Ok(steps.into())
+	}
+
+	async fn execute_step(
+		&self,
+		ctx: &WorkerContext,
+		CurrentStep { step, .. }: CurrentStep<'_, Self::Step>,
+		_: &Self::Data,
+		_: &Self::RunMetadata,
+	) -> Result<JobStepOutput<Self::Step, Self::RunMetadata>, JobError> {
+		let Library { db, sync, .. } = &*ctx.library;
+
+		let trash_path = maybe_missing(&step.trash_path, "file_path.trash_path")?;
+		let is_dir = maybe_missing(step.is_dir, "file_path.is_dir")?;
+
+		if let Err(e) = remove_from_trash(trash_path, is_dir).await {
+			warn!("Failed to remove {} from the trash: {e:#?}", trash_path);
+
+			return Ok(JobRunErrors(vec![e.to_string()]).into());
+		}
+
+		let mut removed_pub_ids = vec![step.pub_id.clone()];
+		if let Some(descendants) = trashed_descendants_params(step) {
+			removed_pub_ids.extend(
+				db.file_path()
+					.find_many(descendants)
+					.select(file_path::select!({ pub_id }))
+					.exec()
+					.await?
+					.into_iter()
+					.map(|file_path| file_path.pub_id),
+			);
+		}
+
+		sync.write_ops(
+			db,
+			(
+				removed_pub_ids
+					.iter()
+					.map(|pub_id| {
+						sync.shared_delete(prisma_sync::file_path::SyncId {
+							pub_id: pub_id.clone(),
+						})
+					})
+					.collect(),
+				db.file_path()
+					.delete_many(vec![file_path::pub_id::in_vec(removed_pub_ids)]),
+			),
+		)
+		.await?;
+
+		Ok(().into())
+	}
+
+	async fn finalize(
+		&self,
+		ctx: &WorkerContext,
+		_data: &Option<Self::Data>,
+		_run_metadata: &Self::RunMetadata,
+	) -> JobResult {
+		let init = self;
+		invalidate_query!(ctx.library, "search.paths");
+		invalidate_query!(ctx.library, "search.objects");
+
+		ctx.library.orphan_remover.invoke().await;
+
+		Ok(Some(json!({ "init": init })))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use chrono::TimeZone;
+
+	#[test]
+	fn trash_info_records_escaped_paths() {
+		let deleted_at = Local.with_ymd_and_hms(2023, 10, 18, 9, 5, 0).unwrap();
+
+		assert_eq!(
+			trash_info(Path::new("/home/user/My Files/100%.txt"), deleted_at),
+			"[Trash Info]\nPath=/home/user/My%20Files/100%25.txt\nDeletionDate=2023-10-18T09:05:00\n"
+		);
+	}
+
+	#[test]
+	fn info_records_sit_next_to_the_files_dir() {
+		assert_eq!(
+			info_path_of("/mnt/disk/.Trash-1000/files/photo.2.jpg"),
+			Path::new("/mnt/disk/.Trash-1000/info/photo.2.jpg.trashinfo")
+		);
+	}
+}
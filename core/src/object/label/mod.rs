@@ -0,0 +1,188 @@
+use crate::{
+	library::Library,
+	prisma::{label, label_on_object, object},
+};
+
+use sd_prisma::prisma_sync;
+use sd_sync::*;
+
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use specta::Type;
+use strum_macros::{AsRefStr, EnumString};
+use uuid::Uuid;
+
+/// Namespace of the `pub_id`s derived from label names
+const LABEL_NAMESPACE: Uuid = Uuid::from_u128(0x8c5f_0a47_3b1e_4d2a_9f6e_2c71_d0b8_a5e3);
+
+/// What assigned a label to an object.
+///
+/// Unlike tags, labels are never created by hand.
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum LabelSource {
+	/// Read from the file's EXIF data
+	Exif,
+	/// Applied by a rule, like the indexer rules
+	Rule,
+	/// Carried over when importing from another application
+	Import,
+}
+
+/// Labels are identified by their name, so the same label created on different
+/// instances ends up as a single label once synced.
+pub fn label_pub_id(name: &str) -> Vec<u8> {
+	Uuid::new_v5(&LABEL_NAMESPACE, name.as_bytes())
+		.as_bytes()
+		.to_vec()
+}
+
+/// Assigns labels to objects, creating the labels that don't exist yet.
+///
+/// Objects that already have one of the labels keep the source it was first assigned with.
+pub async fn assign_labels(
+	Library { db, sync, .. }: &Library,
+	object_ids: Vec<object::id::Type>,
+	names: impl IntoIterator<Item = String>,
+	source: LabelSource,
+) -> prisma_client_rust::Result<()> {
+	let names = names.into_iter().collect::<BTreeSet<_>>();
+
+	let mut labels = db
+		.label()
+		.find_many(vec![label::pub_id::in_vec(
+			names.iter().map(|name| label_pub_id(name)).collect(),
+		)])
+		.select(label::select!({ id pub_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|label| (label.id, label.pub_id))
+		.collect::<Vec<_>>();
+
+	let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+	let (sync_ops, db_creates): (Vec<_>, Vec<_>) = names
+		.into_iter()
+		.map(|name| (label_pub_id(&name), name))
+		.filter(|(pub_id, _)| !labels.iter().any(|(_, existing)| existing == pub_id))
+		.map(|(pub_id, name)| {
+			(
+				sync.shared_create(
+					prisma_sync::label::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					[
+						(label::name::NAME, json!(&name)),
+						(label::date_created::NAME, json!(&date_created.to_rfc3339())),
+					],
+				),
+				db.label().create(
+					pub_id,
+					vec![
+						label::name::set(Some(name)),
+						label::date_created::set(date_created),
+					],
+				),
+			)
+		})
+		.unzip();
+
+	if !db_creates.is_empty() {
+		labels.extend(
+			sync.write_ops(db, (sync_ops.into_iter().flatten().collect(), db_creates))
+				.await?
+				.into_iter()
+				.map(|label| (label.id, label.pub_id)),
+		);
+	}
+
+	let label_ids = labels.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+
+	let objects = db
+		.object()
+		.find_many(vec![object::id::in_vec(object_ids)])
+		.select(object::select!({
+			id
+			pub_id
+			labels(vec![label_on_object::label_id::in_vec(label_ids)]): select {
+				label_id
+			}
+		}))
+		.exec()
+		.await?;
+
+	let (sync_ops, db_creates) = objects
+		.iter()
+		.flat_map(|object| {
+			labels
+				.iter()
+				.filter(|(id, _)| !object.labels.iter().any(|rel| rel.label_id == *id))
+				.map(move |label| (object, label))
+		})
+		.fold(
+			(vec![], vec![]),
+			|(mut sync_ops, mut db_creates), (object, (label_id, pub_id))| {
+				db_creates.push(label_on_object::CreateUnchecked {
+					label_id: *label_id,
+					object_id: object.id,
+					_params: vec![
+						label_on_object::source::set(Some(source.as_ref().to_string())),
+						label_on_object::date_created::set(date_created),
+					],
+				});
+
+				sync_ops.extend(sync.relation_create(
+					prisma_sync::label_on_object::SyncId {
+						label: prisma_sync::label::SyncId {
+							pub_id: pub_id.clone(),
+						},
+						object: prisma_sync::object::SyncId {
+							pub_id: object.pub_id.clone(),
+						},
+					},
+					[
+						(label_on_object::source::NAME, json!(source.as_ref())),
+						(
+							label_on_object::date_created::NAME,
+							json!(&date_created.to_rfc3339()),
+						),
+					],
+				));
+
+				(sync_ops, db_creates)
+			},
+		);
+
+	if !db_creates.is_empty() {
+		sync.write_ops(db, (sync_ops, db.label_on_object().create_many(db_creates)))
+			.await?;
+	}
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::str::FromStr;
+
+	#[test]
+	fn label_pub_id_is_derived_from_name() {
+		assert_eq!(label_pub_id("Dog"), label_pub_id("Dog"));
+		assert_ne!(label_pub_id("Dog"), label_pub_id("dog"));
+		assert_eq!(label_pub_id("Dog").len(), 16);
+	}
+
+	#[test]
+	fn label_source_round_trips_as_column() {
+		for source in [LabelSource::Exif, LabelSource::Rule, LabelSource::Import] {
+			assert_eq!(LabelSource::from_str(source.as_ref()).unwrap(), source);
+		}
+	}
+}
@@ -5,19 +5,34 @@ use crate::{
 	util::error::FileIOError,
 };
 
-use sd_file_ext::extensions::{Extension, ImageExtension, ALL_IMAGE_EXTENSIONS};
-use sd_media_metadata::ImageMetadata;
+use sd_file_ext::extensions::{
+	AudioExtension, Extension, ImageExtension, ALL_AUDIO_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
+};
+use sd_images::{dhash, format_image};
+use sd_media_metadata::{AudioMetadata, ImageMetadata};
 
-use std::{collections::HashSet, path::Path};
+use std::{
+	collections::{HashMap, HashSet},
+	future::Future,
+	path::{Path, PathBuf},
+	str::FromStr,
+};
 
-use futures::future::join_all;
+use futures::{future::join_all, join};
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use tokio::task::spawn_blocking;
-use tracing::error;
+use tracing::{debug, error};
 
-use super::media_data_image_to_query;
+use super::{media_data_audio_to_query, media_data_image_to_query};
+
+#[cfg(feature = "ffmpeg")]
+use super::media_data_video_to_query;
+#[cfg(feature = "ffmpeg")]
+use sd_file_ext::extensions::{VideoExtension, ALL_VIDEO_EXTENSIONS};
+#[cfg(feature = "ffmpeg")]
+use sd_media_metadata::VideoMetadata;
 
 #[derive(Error, Debug)]
 pub enum MediaDataError {
@@ -28,6 +43,8 @@ pub enum MediaDataError {
 	FileIO(#[from] FileIOError),
 	#[error(transparent)]
 	MediaData(#[from] sd_media_metadata::Error),
+	#[error(transparent)]
+	Image(#[from] sd_images::Error),
 	#[error("failed to join tokio task: {0}")]
 	TokioJoinHandle(#[from] tokio::task::JoinError),
 }
@@ -38,13 +55,30 @@ pub struct MediaDataExtractorMetadata {
 	pub skipped: u32,
 }
 
-pub(super) static FILTERED_IMAGE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
-	ALL_IMAGE_EXTENSIONS
+pub(super) static FILTERED_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	let extensions = ALL_IMAGE_EXTENSIONS
 		.iter()
 		.cloned()
 		.filter(can_extract_media_data_for_image)
 		.map(Extension::Image)
-		.collect()
+		.chain(
+			ALL_AUDIO_EXTENSIONS
+				.iter()
+				.cloned()
+				.filter(can_extract_media_data_for_audio)
+				.map(Extension::Audio),
+		);
+
+	#[cfg(feature = "ffmpeg")]
+	let extensions = extensions.chain(
+		ALL_VIDEO_EXTENSIONS
+			.iter()
+			.cloned()
+			.filter(can_extract_media_data_for_video)
+			.map(Extension::Video),
+	);
+
+	extensions.collect()
 });
 
 pub const fn can_extract_media_data_for_image(image_extension: &ImageExtension) -> bool {
@@ -55,6 +89,21 @@ pub const fn can_extract_media_data_for_image(image_extension: &ImageExtension)
 	)
 }
 
+pub const fn can_extract_media_data_for_audio(audio_extension: &AudioExtension) -> bool {
+	use AudioExtension::*;
+	matches!(
+		audio_extension,
+		Mp3 | Mp2 | M4a | Wav | Aiff | Aif | Flac | Ogg | Oga | Opus | Aac | Wv
+	)
+}
+
+#[cfg(feature = "ffmpeg")]
+pub const fn can_extract_media_data_for_video(video_extension: &VideoExtension) -> bool {
+	use VideoExtension::*;
+	// Same as the thumbnailer, FFmpeg can't be sure about the format of these
+	!matches!(video_extension, Mpg | Swf | M2v | Hevc | M2ts | Mts | Ts)
+}
+
 pub async fn extract_media_data(path: impl AsRef<Path>) -> Result<ImageMetadata, MediaDataError> {
 	let path = path.as_ref().to_path_buf();
 
@@ -64,20 +113,224 @@ pub async fn extract_media_data(path: impl AsRef<Path>) -> Result<ImageMetadata,
 		.map_err(Into::into)
 }
 
+pub async fn extract_audio_media_data(
+	path: impl AsRef<Path>,
+) -> Result<AudioMetadata, MediaDataError> {
+	let path = path.as_ref().to_path_buf();
+
+	spawn_blocking(|| AudioMetadata::from_path(path))
+		.await?
+		.map_err(Into::into)
+}
+
+#[cfg(feature = "ffmpeg")]
+pub async fn extract_video_media_data(
+	path: impl AsRef<Path>,
+) -> Result<VideoMetadata, MediaDataError> {
+	let path = path.as_ref().to_path_buf();
+
+	spawn_blocking(|| VideoMetadata::from_path(path))
+		.await?
+		.map_err(Into::into)
+}
+
+pub async fn extract_perceptual_hash(path: impl AsRef<Path>) -> Result<u64, MediaDataError> {
+	let path = path.as_ref().to_path_buf();
+
+	spawn_blocking(move || format_image(path).map(|image| dhash(&image)))
+		.await?
+		.map_err(Into::into)
+}
+
+/// Images that couldn't be hashed are stored with an empty hash, so they aren't tried again
+fn hash_to_bytes(hash: Option<u64>) -> Vec<u8> {
+	hash.map(|hash| hash.to_be_bytes().to_vec())
+		.unwrap_or_default()
+}
+
+fn is_audio(file_path: &file_path_for_media_processor::Data) -> bool {
+	file_path
+		.extension
+		.as_ref()
+		.and_then(|extension| AudioExtension::from_str(&extension.to_lowercase()).ok())
+		.map_or(false, |extension| {
+			can_extract_media_data_for_audio(&extension)
+		})
+}
+
+#[cfg(feature = "ffmpeg")]
+fn is_video(file_path: &file_path_for_media_processor::Data) -> bool {
+	file_path
+		.extension
+		.as_ref()
+		.and_then(|extension| VideoExtension::from_str(&extension.to_lowercase()).ok())
+		.map_or(false, |extension| {
+			can_extract_media_data_for_video(&extension)
+		})
+}
+
 pub async fn process(
 	files_paths: impl IntoIterator<Item = &file_path_for_media_processor::Data>,
 	location_id: location::id::Type,
 	location_path: impl AsRef<Path>,
 	db: &PrismaClient,
 ) -> Result<(MediaDataExtractorMetadata, JobRunErrors), MediaDataError> {
+	let location_path = location_path.as_ref();
+
+	let (audio_files_paths, image_files_paths): (Vec<_>, Vec<_>) = files_paths
+		.into_iter()
+		.partition(|file_path| is_audio(file_path));
+
+	#[cfg(feature = "ffmpeg")]
+	let (video_files_paths, image_files_paths): (Vec<_>, Vec<_>) = image_files_paths
+		.into_iter()
+		.partition(|file_path| is_video(file_path));
+
+	let (mut run_metadata, mut errors) =
+		process_images(image_files_paths, location_id, location_path, db).await?;
+
+	#[cfg_attr(not(feature = "ffmpeg"), allow(unused_mut))]
+	let mut other_results = vec![
+		process_with_extractor(
+			audio_files_paths,
+			location_id,
+			location_path,
+			db,
+			media_data::audio_data::not(None),
+			|path| extract_audio_media_data(path),
+			media_data_audio_to_query,
+		)
+		.await?,
+	];
+
+	#[cfg(feature = "ffmpeg")]
+	other_results.push(
+		process_with_extractor(
+			video_files_paths,
+			location_id,
+			location_path,
+			db,
+			media_data::video_data::not(None),
+			|path| extract_video_media_data(path),
+			media_data_video_to_query,
+		)
+		.await?,
+	);
+
+	for (other_run_metadata, other_errors) in other_results {
+		run_metadata.extracted += other_run_metadata.extracted;
+		run_metadata.skipped += other_run_metadata.skipped;
+		errors.0.extend(other_errors.0);
+	}
+
+	Ok((run_metadata, errors))
+}
+
+/// Extracts media data with `extract` for the files that don't have any yet, `already_extracted`
+/// must match the media data rows that `extract` already filled in
+async fn process_with_extractor<T, Fut>(
+	files_paths: Vec<&file_path_for_media_processor::Data>,
+	location_id: location::id::Type,
+	location_path: &Path,
+	db: &PrismaClient,
+	already_extracted: media_data::WhereParam,
+	extract: impl Fn(PathBuf) -> Fut,
+	to_query: fn(
+		T,
+		media_data::object_id::Type,
+	) -> Result<media_data::CreateUnchecked, MediaDataError>,
+) -> Result<(MediaDataExtractorMetadata, JobRunErrors), MediaDataError>
+where
+	Fut: Future<Output = Result<T, MediaDataError>>,
+{
 	let mut run_metadata = MediaDataExtractorMetadata::default();
-	let files_paths = files_paths.into_iter().collect::<Vec<_>>();
 	if files_paths.is_empty() {
 		return Ok((run_metadata, JobRunErrors::default()));
 	}
 
-	let location_path = location_path.as_ref();
+	let objects_already_with_media_data = db
+		.media_data()
+		.find_many(vec![
+			media_data::object_id::in_vec(
+				files_paths
+					.iter()
+					.filter_map(|file_path| file_path.object_id)
+					.collect(),
+			),
+			already_extracted,
+		])
+		.select(media_data::select!({ object_id }))
+		.exec()
+		.await?
+		.into_iter()
+		.map(|media_data| media_data.object_id)
+		.collect::<HashSet<_>>();
+
+	run_metadata.skipped = objects_already_with_media_data.len() as u32;
 
+	let extract = &extract;
+	let maybe_media_data = join_all(
+		files_paths
+			.into_iter()
+			.filter_map(|file_path| {
+				file_path.object_id.and_then(|object_id| {
+					(!objects_already_with_media_data.contains(&object_id))
+						.then_some((file_path, object_id))
+				})
+			})
+			.filter_map(|(file_path, object_id)| {
+				IsolatedFilePathData::try_from((location_id, file_path))
+					.map_err(|e| error!("{e:#?}"))
+					.ok()
+					.map(|iso_file_path| (location_path.join(iso_file_path), object_id))
+			})
+			.map(|(path, object_id)| async move { (extract(path.clone()).await, path, object_id) }),
+	)
+	.await;
+
+	let mut errors = vec![];
+	let media_datas = maybe_media_data
+		.into_iter()
+		.filter_map(
+			|(maybe_media_data, path, object_id)| match maybe_media_data {
+				Ok(media_data) => to_query(media_data, object_id)
+					.map_err(|e| error!("{e:#?}"))
+					.ok(),
+				Err(e) => {
+					errors.push(format!(
+						"Couldn't process file: \"{}\"; Error: {e}",
+						path.display()
+					));
+					None
+				}
+			},
+		)
+		.collect::<Vec<_>>();
+
+	run_metadata.extracted = db
+		.media_data()
+		.create_many(media_datas)
+		.skip_duplicates()
+		.exec()
+		.await? as u32;
+	run_metadata.skipped += errors.len() as u32;
+
+	Ok((run_metadata, errors.into()))
+}
+
+async fn process_images(
+	files_paths: Vec<&file_path_for_media_processor::Data>,
+	location_id: location::id::Type,
+	location_path: &Path,
+	db: &PrismaClient,
+) -> Result<(MediaDataExtractorMetadata, JobRunErrors), MediaDataError> {
+	let mut run_metadata = MediaDataExtractorMetadata::default();
+	if files_paths.is_empty() {
+		return Ok((run_metadata, JobRunErrors::default()));
+	}
+
+	// Media data extracted before perceptual hashes existed only needs its hash,
+	// an empty one was already attempted
 	let objects_already_with_media_data = db
 		.media_data()
 		.find_many(vec![media_data::object_id::in_vec(
@@ -86,62 +339,103 @@ pub async fn process(
 				.filter_map(|file_path| file_path.object_id)
 				.collect(),
 		)])
-		.select(media_data::select!({ object_id }))
+		.select(media_data::select!({ object_id perceptual_hash }))
 		.exec()
-		.await?;
+		.await?
+		.into_iter()
+		.map(|media_data| (media_data.object_id, media_data.perceptual_hash.is_some()))
+		.collect::<HashMap<_, _>>();
 
-	if files_paths.len() == objects_already_with_media_data.len() {
+	if files_paths.len() == objects_already_with_media_data.len()
+		&& objects_already_with_media_data
+			.values()
+			.all(|has_hash| *has_hash)
+	{
 		// All files already have media data, skipping
 		run_metadata.skipped = files_paths.len() as u32;
 		return Ok((run_metadata, JobRunErrors::default()));
 	}
 
-	let objects_already_with_media_data = objects_already_with_media_data
-		.into_iter()
-		.map(|media_data| media_data.object_id)
-		.collect::<HashSet<_>>();
+	let (media_datas, hashes_to_update, errors) = {
+		let maybe_media_data =
+			join_all(
+				files_paths
+					.into_iter()
+					.filter_map(|file_path| {
+						file_path.object_id.and_then(|object_id| {
+							match objects_already_with_media_data.get(&object_id) {
+								Some(true) => {
+									run_metadata.skipped += 1;
+									None
+								}
+								Some(false) => Some((file_path, object_id, true)),
+								None => Some((file_path, object_id, false)),
+							}
+						})
+					})
+					.filter_map(|(file_path, object_id, hash_only)| {
+						IsolatedFilePathData::try_from((location_id, file_path))
+							.map_err(|e| error!("{e:#?}"))
+							.ok()
+							.map(|iso_file_path| {
+								(location_path.join(iso_file_path), object_id, hash_only)
+							})
+					})
+					.map(|(path, object_id, hash_only)| async move {
+						let (maybe_media_data, maybe_hash) = if hash_only {
+							(None, extract_perceptual_hash(&path).await)
+						} else {
+							let (maybe_media_data, maybe_hash) =
+								join!(extract_media_data(&path), extract_perceptual_hash(&path));
+							(Some(maybe_media_data), maybe_hash)
+						};
 
-	run_metadata.skipped = objects_already_with_media_data.len() as u32;
+						// Not every format we read metadata from can be decoded, so this isn't an error
+						let hash = maybe_hash
+							.map_err(|e| {
+								debug!("Couldn't hash image \"{}\": {e}", path.display());
+							})
+							.ok();
 
-	let (media_datas, errors) = {
-		let maybe_media_data = join_all(
-			files_paths
-				.into_iter()
-				.filter_map(|file_path| {
-					file_path.object_id.and_then(|object_id| {
-						(!objects_already_with_media_data.contains(&object_id))
-							.then_some((file_path, object_id))
-					})
-				})
-				.filter_map(|(file_path, object_id)| {
-					IsolatedFilePathData::try_from((location_id, file_path))
-						.map_err(|e| error!("{e:#?}"))
-						.ok()
-						.map(|iso_file_path| (location_path.join(iso_file_path), object_id))
-				})
-				.map(|(path, object_id)| async move {
-					(extract_media_data(&path).await, path, object_id)
-				}),
-		)
-		.await;
+						(maybe_media_data, hash, path, object_id)
+					}),
+			)
+			.await;
 
 		let total_media_data = maybe_media_data.len();
 
 		maybe_media_data.into_iter().fold(
 			// In the good case, all media data were extracted
-			(Vec::with_capacity(total_media_data), Vec::new()),
-			|(mut media_datas, mut errors), (maybe_media_data, path, object_id)| {
-				match maybe_media_data {
-					Ok(media_data) => media_datas.push((media_data, object_id)),
-					Err(MediaDataError::MediaData(sd_media_metadata::Error::NoExifDataOnPath(
-						_,
-					))) => {
-						// No exif data on path, skipping
+			(Vec::with_capacity(total_media_data), Vec::new(), Vec::new()),
+			|(mut media_datas, mut hashes_to_update, mut errors),
+			 (maybe_media_data, hash, path, object_id)| {
+				match (maybe_media_data, hash) {
+					(None, Some(hash)) => hashes_to_update.push((Some(hash), object_id)),
+					(None, None) => {
+						run_metadata.skipped += 1;
+						hashes_to_update.push((None, object_id));
+					}
+					(Some(Ok(media_data)), hash) => {
+						media_datas.push((Some(media_data), hash, object_id))
+					}
+					(
+						Some(Err(MediaDataError::MediaData(
+							sd_media_metadata::Error::NoExifDataOnPath(_),
+						))),
+						Some(hash),
+					) => media_datas.push((None, Some(hash), object_id)),
+					(
+						Some(Err(MediaDataError::MediaData(
+							sd_media_metadata::Error::NoExifDataOnPath(_),
+						))),
+						None,
+					) => {
+						// No exif data on path and the image can't be hashed, skipping
 						run_metadata.skipped += 1;
 					}
-					Err(e) => errors.push((e, path)),
+					(Some(Err(e)), _) => errors.push((e, path)),
 				}
-				(media_datas, errors)
+				(media_datas, hashes_to_update, errors)
 			},
 		)
 	};
@@ -151,10 +445,22 @@ pub async fn process(
 		.create_many(
 			media_datas
 				.into_iter()
-				.filter_map(|(media_data, object_id)| {
-					media_data_image_to_query(media_data, object_id)
-						.map_err(|e| error!("{e:#?}"))
-						.ok()
+				.filter_map(|(media_data, hash, object_id)| {
+					let mut query = match media_data {
+						Some(media_data) => media_data_image_to_query(media_data, object_id)
+							.map_err(|e| error!("{e:#?}"))
+							.ok()?,
+						None => media_data::CreateUnchecked {
+							object_id,
+							_params: vec![],
+						},
+					};
+
+					query
+						._params
+						.push(media_data::perceptual_hash::set(Some(hash_to_bytes(hash))));
+
+					Some(query)
 				})
 				.collect(),
 		)
@@ -162,7 +468,25 @@ pub async fn process(
 		.exec()
 		.await?;
 
-	run_metadata.extracted = created as u32;
+	let hashed = hashes_to_update
+		.iter()
+		.filter(|(hash, _)| hash.is_some())
+		.count();
+
+	db._batch(
+		hashes_to_update
+			.into_iter()
+			.map(|(hash, object_id)| {
+				db.media_data().update(
+					media_data::object_id::equals(object_id),
+					vec![media_data::perceptual_hash::set(Some(hash_to_bytes(hash)))],
+				)
+			})
+			.collect::<Vec<_>>(),
+	)
+	.await?;
+
+	run_metadata.extracted = created as u32 + hashed as u32;
 	run_metadata.skipped += errors.len() as u32;
 
 	Ok((
@@ -235,6 +235,18 @@ async fn get_files_for_thumbnailer(
 	.into_iter()
 	.map(|file_path| (file_path, ThumbnailerEntryKind::Image));
 
+	// audio files get their thumbnail from their cover art
+	let audio_thumb_files = get_all_children_files_by_extensions(
+		db,
+		parent_iso_file_path,
+		&thumbnail::FILTERED_AUDIO_EXTENSIONS,
+	)
+	.await?
+	.into_iter()
+	.map(|file_path| (file_path, ThumbnailerEntryKind::Audio));
+
+	let thumb_files = image_thumb_files.chain(audio_thumb_files);
+
 	#[cfg(feature = "ffmpeg")]
 	let all_files = {
 		// query database for all video files in this location that need thumbnails
@@ -245,14 +257,14 @@ async fn get_files_for_thumbnailer(
 		)
 		.await?;
 
-		image_thumb_files.chain(
+		thumb_files.chain(
 			video_files
 				.into_iter()
 				.map(|file_path| (file_path, ThumbnailerEntryKind::Video)),
 		)
 	};
 	#[cfg(not(feature = "ffmpeg"))]
-	let all_files = { image_thumb_files };
+	let all_files = { thumb_files };
 
 	Ok(all_files)
 }
@@ -264,7 +276,7 @@ async fn get_files_for_media_data_extraction(
 	get_all_children_files_by_extensions(
 		db,
 		parent_iso_file_path,
-		&media_data_extractor::FILTERED_IMAGE_EXTENSIONS,
+		&media_data_extractor::FILTERED_EXTENSIONS,
 	)
 	.await
 	.map(|file_paths| file_paths.into_iter())
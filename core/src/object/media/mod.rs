@@ -3,7 +3,7 @@ pub mod media_processor;
 pub mod thumbnail;
 
 pub use media_processor::MediaProcessorJobInit;
-use sd_media_metadata::ImageMetadata;
+use sd_media_metadata::{AudioMetadata, ImageMetadata, VideoMetadata};
 use sd_prisma::prisma::media_data::*;
 
 use self::media_data_extractor::MediaDataError;
@@ -27,6 +27,50 @@ pub fn media_data_image_to_query(
 	})
 }
 
+pub fn media_data_audio_to_query(
+	mda: AudioMetadata,
+	object_id: object_id::Type,
+) -> Result<CreateUnchecked, MediaDataError> {
+	Ok(CreateUnchecked {
+		object_id,
+		_params: vec![audio_data::set(Some(
+			serde_json::to_vec(&mda).map_err(sd_media_metadata::Error::from)?,
+		))],
+	})
+}
+
+pub fn media_data_audio_from_prisma_data(
+	data: sd_prisma::prisma::media_data::Data,
+) -> Option<AudioMetadata> {
+	from_slice_option_to_option(data.audio_data)
+}
+
+pub fn media_data_video_to_query(
+	mdv: VideoMetadata,
+	object_id: object_id::Type,
+) -> Result<CreateUnchecked, MediaDataError> {
+	Ok(CreateUnchecked {
+		object_id,
+		_params: vec![
+			duration::set(mdv.duration),
+			fps::set(mdv.fps),
+			streams::set(i32::try_from(mdv.streams.len()).ok()),
+			codecs::set(Some(mdv.codecs()).filter(|codecs| !codecs.is_empty())),
+			width::set(mdv.width.and_then(|width| i32::try_from(width).ok())),
+			height::set(mdv.height.and_then(|height| i32::try_from(height).ok())),
+			video_data::set(Some(
+				serde_json::to_vec(&mdv).map_err(sd_media_metadata::Error::from)?,
+			)),
+		],
+	})
+}
+
+pub fn media_data_video_from_prisma_data(
+	data: sd_prisma::prisma::media_data::Data,
+) -> Option<VideoMetadata> {
+	from_slice_option_to_option(data.video_data)
+}
+
 pub fn media_data_image_from_prisma_data(
 	data: sd_prisma::prisma::media_data::Data,
 ) -> Result<ImageMetadata, MediaDataError> {
@@ -8,9 +8,11 @@ use crate::{
 	Node,
 };
 
-use sd_file_ext::extensions::{Extension, ImageExtension, ALL_IMAGE_EXTENSIONS};
+use sd_file_ext::extensions::{
+	AudioExtension, Extension, ImageExtension, ALL_AUDIO_EXTENSIONS, ALL_IMAGE_EXTENSIONS,
+};
 use sd_images::format_image;
-use sd_media_metadata::image::Orientation;
+use sd_media_metadata::{image::Orientation, CoverArt};
 
 #[cfg(feature = "ffmpeg")]
 use sd_file_ext::extensions::{VideoExtension, ALL_VIDEO_EXTENSIONS};
@@ -75,6 +77,15 @@ pub(super) static FILTERED_IMAGE_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(||
 		.collect()
 });
 
+pub(super) static FILTERED_AUDIO_EXTENSIONS: Lazy<Vec<Extension>> = Lazy::new(|| {
+	ALL_AUDIO_EXTENSIONS
+		.iter()
+		.cloned()
+		.filter(can_generate_thumbnail_for_audio)
+		.map(Extension::Audio)
+		.collect()
+});
+
 #[derive(Error, Debug)]
 pub enum ThumbnailerError {
 	// Internal errors
@@ -88,6 +99,8 @@ pub enum ThumbnailerError {
 	Encoding,
 	#[error("error while converting the image: {0}")]
 	SdImages(#[from] sd_images::Error),
+	#[error("error while reading the cover art: {0}")]
+	MediaMetadata(#[from] sd_media_metadata::Error),
 }
 
 /// This is the target pixel count for all thumbnails to be resized to, and it is eventually downscaled
@@ -109,6 +122,7 @@ fn calculate_factor(w: f32, h: f32) -> (u32, u32) {
 #[derive(Debug, Serialize, Deserialize, Clone, Copy)]
 pub enum ThumbnailerEntryKind {
 	Image,
+	Audio,
 	#[cfg(feature = "ffmpeg")]
 	Video,
 }
@@ -119,6 +133,54 @@ pub struct ThumbnailerMetadata {
 	pub skipped: u32,
 }
 
+/// Resizes an image to the thumbnail size and encodes it as WebP
+fn encode_thumbnail(
+	img: DynamicImage,
+	orientation: Option<Orientation>,
+) -> Result<Vec<u8>, ThumbnailerError> {
+	let (w, h) = img.dimensions();
+	let (w_scale, h_scale) = calculate_factor(w as f32, h as f32);
+
+	// Optionally, resize the existing photo and convert back into DynamicImage
+	let mut img = DynamicImage::ImageRgba8(imageops::resize(
+		&img,
+		w_scale,
+		h_scale,
+		imageops::FilterType::Triangle,
+	));
+
+	// this corrects the rotation/flip of the image based on the *available* exif data
+	// not all images have exif data, so we don't error
+	if let Some(orientation) = orientation {
+		img = orientation.correct_thumbnail(img);
+	}
+
+	// Create the WebP encoder for the above image
+	let Ok(encoder) = Encoder::from_image(&img) else {
+		return Err(ThumbnailerError::Encoding);
+	};
+
+	// Type WebPMemory is !Send, which makes the Future in this function !Send,
+	// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
+	// which implies on a unwanted clone...
+	Ok(encoder.encode(TARGET_QUALITY).deref().to_owned())
+}
+
+async fn write_thumbnail(output_path: &Path, webp: &[u8]) -> Result<(), ThumbnailerError> {
+	if let Some(shard_dir) = output_path.parent() {
+		fs::create_dir_all(shard_dir)
+			.await
+			.map_err(|e| FileIOError::from((shard_dir, e)))?;
+	} else {
+		return Err(ThumbnailerError::Encoding);
+	}
+
+	fs::write(output_path, webp)
+		.await
+		.map_err(|e| FileIOError::from((output_path, e)))
+		.map_err(Into::into)
+}
+
 pub async fn generate_image_thumbnail<P: AsRef<Path>>(
 	file_path: P,
 	output_path: P,
@@ -128,48 +190,38 @@ pub async fn generate_image_thumbnail<P: AsRef<Path>>(
 	let webp = tokio::task::block_in_place(move || -> Result<_, ThumbnailerError> {
 		let img = format_image(&file_path).map_err(|_| ThumbnailerError::Encoding)?;
 
-		let (w, h) = img.dimensions();
-		let (w_scale, h_scale) = calculate_factor(w as f32, h as f32);
-
-		// Optionally, resize the existing photo and convert back into DynamicImage
-		let mut img = DynamicImage::ImageRgba8(imageops::resize(
-			&img,
-			w_scale,
-			h_scale,
-			imageops::FilterType::Triangle,
-		));
-
-		// this corrects the rotation/flip of the image based on the *available* exif data
-		// not all images have exif data, so we don't error
-		if let Some(orientation) = Orientation::from_path(file_path) {
-			img = orientation.correct_thumbnail(img);
-		}
+		encode_thumbnail(img, Orientation::from_path(file_path))
+	})?;
+
+	write_thumbnail(output_path.as_ref(), &webp).await
+}
 
-		// Create the WebP encoder for the above image
-		let Ok(encoder) = Encoder::from_image(&img) else {
-			return Err(ThumbnailerError::Encoding);
+/// Generates a thumbnail from the cover art embedded in an audio file.
+///
+/// Returns `false` if the file has no cover art.
+pub async fn generate_audio_thumbnail<P: AsRef<Path>>(
+	file_path: P,
+	output_path: P,
+) -> Result<bool, ThumbnailerError> {
+	let file_path = file_path.as_ref().to_path_buf();
+
+	let maybe_webp = tokio::task::block_in_place(move || -> Result<_, ThumbnailerError> {
+		let Some(CoverArt(data)) = CoverArt::from_path(file_path)? else {
+			return Ok(None);
 		};
 
-		// Type WebPMemory is !Send, which makes the Future in this function !Send,
-		// this make us `deref` to have a `&[u8]` and then `to_owned` to make a Vec<u8>
-		// which implies on a unwanted clone...
-		Ok(encoder.encode(TARGET_QUALITY).deref().to_owned())
-	})?;
+		let img = image::load_from_memory(&data).map_err(|_| ThumbnailerError::Encoding)?;
 
-	let output_path = output_path.as_ref();
+		encode_thumbnail(img, None).map(Some)
+	})?;
 
-	if let Some(shard_dir) = output_path.parent() {
-		fs::create_dir_all(shard_dir)
-			.await
-			.map_err(|e| FileIOError::from((shard_dir, e)))?;
-	} else {
-		return Err(ThumbnailerError::Encoding);
-	}
+	let Some(webp) = maybe_webp else {
+		return Ok(false);
+	};
 
-	fs::write(output_path, &webp)
+	write_thumbnail(output_path.as_ref(), &webp)
 		.await
-		.map_err(|e| FileIOError::from((output_path, e)))
-		.map_err(Into::into)
+		.map(|()| true)
 }
 
 #[cfg(feature = "ffmpeg")]
@@ -200,6 +252,16 @@ pub const fn can_generate_thumbnail_for_image(image_extension: &ImageExtension)
 	)
 }
 
+/// Audio files whose tags can hold cover art
+pub const fn can_generate_thumbnail_for_audio(audio_extension: &AudioExtension) -> bool {
+	use AudioExtension::*;
+
+	matches!(
+		audio_extension,
+		Mp3 | M4a | Flac | Ogg | Oga | Opus | Wav | Aiff | Aif | Wv
+	)
+}
+
 pub(super) async fn process(
 	entries: impl IntoIterator<Item = (&file_path_for_media_processor::Data, ThumbnailerEntryKind)>,
 	location_id: location::id::Type,
@@ -383,6 +445,32 @@ async fn process_single_thumbnail(
 				return;
 			}
 		}
+		ThumbnailerEntryKind::Audio => {
+			match generate_audio_thumbnail(&input_path, &output_path).await {
+				Ok(true) => {}
+				Ok(false) => {
+					trace!(
+						"No cover art to generate a thumbnail from for \"{}\"",
+						input_path.display()
+					);
+					run_metadata.skipped += 1;
+
+					return;
+				}
+				Err(e) => {
+					error!(
+						"Error generating thumb for audio \"{}\": {e:#?}",
+						input_path.display()
+					);
+					errors.push(format!(
+						"Had an error generating thumbnail for \"{}\"",
+						input_path.display()
+					));
+
+					return;
+				}
+			}
+		}
 		#[cfg(feature = "ffmpeg")]
 		ThumbnailerEntryKind::Video => {
 			if let Err(e) = generate_video_thumbnail(&input_path, &output_path).await {
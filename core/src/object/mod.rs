@@ -3,11 +3,15 @@ use crate::prisma::{file_path, object};
 use serde::{Deserialize, Serialize};
 use specta::Type;
 
+pub mod album;
 pub mod cas;
+pub mod content;
 pub mod file_identifier;
 pub mod fs;
+pub mod label;
 pub mod media;
 pub mod orphan_remover;
+pub mod space;
 pub mod tag;
 pub mod thumbnail_remover;
 pub mod validation;
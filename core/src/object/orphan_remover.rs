@@ -1,4 +1,4 @@
-use crate::prisma::{object, tag_on_object, PrismaClient};
+use crate::prisma::{label_on_object, object, object_in_album, tag_on_object, PrismaClient};
 
 use std::{sync::Arc, time::Duration};
 
@@ -58,7 +58,11 @@ impl OrphanRemoverActor {
 		loop {
 			let Ok(objects_ids) = db
 				.object()
-				.find_many(vec![object::file_paths::none(vec![])])
+				// Objects in spaces may be links or notes, which never have file paths
+				.find_many(vec![
+					object::file_paths::none(vec![]),
+					object::spaces::none(vec![]),
+				])
 				.take(512)
 				.select(object::select!({ id }))
 				.exec()
@@ -84,6 +88,14 @@ impl OrphanRemoverActor {
 				._batch((
 					db.tag_on_object()
 						.delete_many(vec![tag_on_object::object_id::in_vec(objects_ids.clone())]),
+					db.label_on_object()
+						.delete_many(vec![label_on_object::object_id::in_vec(
+							objects_ids.clone(),
+						)]),
+					db.object_in_album()
+						.delete_many(vec![object_in_album::object_id::in_vec(
+							objects_ids.clone(),
+						)]),
 					db.object()
 						.delete_many(vec![object::id::in_vec(objects_ids)]),
 				))
@@ -94,3 +106,65 @@ impl OrphanRemoverActor {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::{prisma::space, util::db::new_test_db};
+
+	use tempfile::tempdir;
+	use uuid::Uuid;
+
+	#[tokio::test]
+	async fn objects_in_spaces_are_kept() {
+		let dir = tempdir().unwrap();
+		let db = new_test_db(dir.path()).await;
+
+		let new_pub_id = || Uuid::new_v4().as_bytes().to_vec();
+
+		let space = db
+			.space()
+			.create(new_pub_id(), vec![])
+			.exec()
+			.await
+			.unwrap();
+		// Neither in a space nor with any file paths
+		db.object()
+			.create(new_pub_id(), vec![])
+			.exec()
+			.await
+			.unwrap();
+		let in_space = db
+			.object()
+			.create(new_pub_id(), vec![])
+			.exec()
+			.await
+			.unwrap();
+
+		db.object_in_space()
+			.create(
+				space::id::equals(space.id),
+				object::id::equals(in_space.id),
+				vec![],
+			)
+			.exec()
+			.await
+			.unwrap();
+
+		OrphanRemoverActor::process_clean_up(&db).await;
+
+		let remaining = db
+			.object()
+			.find_many(vec![])
+			.select(object::select!({ id }))
+			.exec()
+			.await
+			.unwrap()
+			.into_iter()
+			.map(|object| object.id)
+			.collect::<Vec<_>>();
+
+		assert_eq!(remaining, vec![in_space.id]);
+	}
+}
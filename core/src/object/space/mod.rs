@@ -0,0 +1,244 @@
+use crate::{
+	library::Library,
+	prisma::{object, object_in_space, space, PrismaClient},
+	sync,
+};
+
+use sd_file_ext::kind::ObjectKind;
+use sd_prisma::prisma_sync;
+use sd_sync::*;
+
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use specta::Type;
+use uuid::Uuid;
+
+#[derive(Type, Deserialize, Clone)]
+pub struct SpaceCreateArgs {
+	pub name: String,
+	#[specta(optional)]
+	pub description: Option<String>,
+}
+
+impl SpaceCreateArgs {
+	pub async fn exec(
+		self,
+		Library { db, sync, .. }: &Library,
+	) -> prisma_client_rust::Result<space::Data> {
+		let pub_id = Uuid::new_v4().as_bytes().to_vec();
+		let date_created: DateTime<FixedOffset> = Utc::now().into();
+
+		sync.write_ops(
+			db,
+			(
+				sync.shared_create(
+					prisma_sync::space::SyncId {
+						pub_id: pub_id.clone(),
+					},
+					[
+						(space::name::NAME, json!(&self.name)),
+						(space::description::NAME, json!(&self.description)),
+						(space::date_created::NAME, json!(&date_created.to_rfc3339())),
+					],
+				),
+				db.space().create(
+					pub_id,
+					vec![
+						space::name::set(Some(self.name)),
+						space::description::set(self.description),
+						space::date_created::set(Some(date_created)),
+					],
+				),
+			),
+		)
+		.await
+	}
+}
+
+/// Where an item is placed within a space
+#[derive(Serialize, Deserialize, Type, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SpaceLayout {
+	pub x: i32,
+	pub y: i32,
+	/// `None` for the item's default size
+	pub width: Option<i32>,
+	pub height: Option<i32>,
+}
+
+impl SpaceLayout {
+	pub fn from_data(data: &object_in_space::Data) -> Self {
+		Self {
+			x: data.x.unwrap_or_default(),
+			y: data.y.unwrap_or_default(),
+			width: data.width,
+			height: data.height,
+		}
+	}
+
+	pub fn to_params(self) -> Vec<object_in_space::SetParam> {
+		vec![
+			object_in_space::x::set(Some(self.x)),
+			object_in_space::y::set(Some(self.y)),
+			object_in_space::width::set(self.width),
+			object_in_space::height::set(self.height),
+		]
+	}
+
+	pub fn to_sync_values(self) -> [(&'static str, Value); 4] {
+		[
+			(object_in_space::x::NAME, json!(self.x)),
+			(object_in_space::y::NAME, json!(self.y)),
+			(object_in_space::width::NAME, json!(self.width)),
+			(object_in_space::height::NAME, json!(self.height)),
+		]
+	}
+}
+
+/// Content of a space that isn't a file.
+///
+/// These are stored as objects without any file paths, which only live for as long as they are in a space.
+#[derive(Deserialize, Type, Debug, Clone)]
+#[serde(tag = "type", content = "value")]
+pub enum SpaceContent {
+	/// An external link, which can open web pages, apps or other spaces
+	Link(String),
+	/// A plain text note
+	Note(String),
+}
+
+impl SpaceContent {
+	fn kind(&self) -> ObjectKind {
+		match self {
+			Self::Link(_) => ObjectKind::Link,
+			Self::Note(_) => ObjectKind::Text,
+		}
+	}
+
+	/// Creates the object holding this content right within a space.
+	///
+	/// Both are written in a single batch, otherwise the orphan remover could delete the object
+	/// before it makes it into the space.
+	pub async fn create_in_space(
+		self,
+		db: &PrismaClient,
+		sync: &sync::Manager,
+		space_pub_id: Vec<u8>,
+		layout: SpaceLayout,
+	) -> prisma_client_rust::Result<object::Data> {
+		let pub_id = Uuid::new_v4().as_bytes().to_vec();
+		let kind = self.kind() as i32;
+		let date_created: DateTime<FixedOffset> = Utc::now().into();
+		let (Self::Link(note) | Self::Note(note)) = self;
+
+		let sync_ops = sync
+			.shared_create(
+				prisma_sync::object::SyncId {
+					pub_id: pub_id.clone(),
+				},
+				[
+					(object::kind::NAME, json!(kind)),
+					(object::note::NAME, json!(&note)),
+					(
+						object::date_created::NAME,
+						json!(&date_created.to_rfc3339()),
+					),
+				],
+			)
+			.into_iter()
+			.chain(sync.relation_create(
+				object_in_space_sync_id(&space_pub_id, pub_id.clone()),
+				layout.to_sync_values(),
+			))
+			.collect();
+
+		let (object, _) = sync
+			.write_ops(
+				db,
+				(
+					sync_ops,
+					(
+						db.object().create(
+							pub_id.clone(),
+							vec![
+								object::kind::set(Some(kind)),
+								object::note::set(Some(note)),
+								object::date_created::set(Some(date_created)),
+							],
+						),
+						db.object_in_space().create(
+							space::pub_id::equals(space_pub_id),
+							object::pub_id::equals(pub_id),
+							layout.to_params(),
+						),
+					),
+				),
+			)
+			.await?;
+
+		Ok(object)
+	}
+}
+
+pub fn object_in_space_sync_id(
+	space_pub_id: &[u8],
+	object_pub_id: Vec<u8>,
+) -> prisma_sync::object_in_space::SyncId {
+	prisma_sync::object_in_space::SyncId {
+		space: prisma_sync::space::SyncId {
+			pub_id: space_pub_id.to_vec(),
+		},
+		object: prisma_sync::object::SyncId {
+			pub_id: object_pub_id,
+		},
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::util::db::new_test_db;
+
+	use tempfile::tempdir;
+
+	#[tokio::test]
+	async fn content_is_created_within_the_space() {
+		let dir = tempdir().unwrap();
+		let db = new_test_db(dir.path()).await;
+		let sync = sync::Manager::new(&db, Uuid::new_v4(), &Default::default()).manager;
+
+		let space_pub_id = Uuid::new_v4().as_bytes().to_vec();
+		let space = db
+			.space()
+			.create(space_pub_id.clone(), vec![])
+			.exec()
+			.await
+			.unwrap();
+
+		let layout = SpaceLayout {
+			x: 10,
+			y: 20,
+			width: Some(300),
+			height: None,
+		};
+
+		let object = SpaceContent::Note("hello".to_string())
+			.create_in_space(&db, &sync, space_pub_id, layout)
+			.await
+			.unwrap();
+
+		assert_eq!(object.kind, Some(ObjectKind::Text as i32));
+		assert_eq!(object.note.as_deref(), Some("hello"));
+
+		let rel = db
+			.object_in_space()
+			.find_unique(object_in_space::space_id_object_id(space.id, object.id))
+			.exec()
+			.await
+			.unwrap()
+			.unwrap();
+
+		assert_eq!(SpaceLayout::from_data(&rel), layout);
+	}
+}
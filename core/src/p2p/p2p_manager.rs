@@ -10,20 +10,22 @@ use std::{
 };
 
 use sd_p2p::{
-	spaceblock::{BlockSize, Range, SpaceblockRequest, Transfer},
-	spacetunnel::{RemoteIdentity, Tunnel},
+	spaceblock::{BlockSize, Range, SpaceblockError, SpaceblockRequest, Transfer},
+	spacetime::UnicastStream,
+	spacetunnel::{RemoteIdentity, Tunnel, TunnelError},
 	Event, Manager, ManagerError, ManagerStream, MetadataManager, PeerId,
 };
 use sd_prisma::prisma::file_path;
 use serde::Serialize;
 use specta::Type;
+use thiserror::Error;
 use tokio::{
 	fs::File,
 	io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
 	sync::{broadcast, oneshot, Mutex},
 	time::sleep,
 };
-use tracing::{debug, error, info};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 use crate::{
@@ -42,6 +44,21 @@ use super::{
 /// The amount of time to wait for a Spacedrop request to be accepted or rejected before it's automatically rejected
 const SPACEDROP_TIMEOUT: Duration = Duration::from_secs(60);
 
+/// How many times a file request is attempted, each attempt resuming from where the last one got to
+const FILE_REQUEST_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Error)]
+pub enum RequestFileError {
+	#[error("failed to open a stream to peer '{0}'")]
+	Stream(PeerId),
+	#[error("io error: {0}")]
+	Io(#[from] std::io::Error),
+	#[error("failed to establish a tunnel: {0}")]
+	Tunnel(#[from] TunnelError),
+	#[error("error receiving file: {0}")]
+	Spaceblock(#[from] SpaceblockError),
+}
+
 /// TODO: P2P event for the frontend
 #[derive(Debug, Clone, Type, Serialize)]
 #[serde(tag = "type")]
@@ -279,11 +296,13 @@ impl P2PManager {
 
 														let f = File::create(file_path).await.unwrap();
 
-														Transfer::new(&req, |percent| {
+														match Transfer::new(&req, |percent| {
 															events.send(P2PEvent::SpacedropProgress { id, percent }).ok();
-														}, &cancelled).receive(&mut stream, f).await;
-
-														info!("spacedrop({id}): complete");
+														}, &cancelled).receive(&mut stream, f).await {
+															Ok(()) => info!("spacedrop({id}): complete"),
+															Err(SpaceblockError::Cancelled) => info!("spacedrop({id}): cancelled"),
+															Err(e) => error!("spacedrop({id}): error receiving file: {e}"),
+														}
 													}
 													Ok(None) => {
 														info!("spacedrop({id}): rejected");
@@ -309,13 +328,33 @@ impl P2PManager {
 											.await;
 									}
 									Header::Sync(library_id) => {
-										let mut tunnel = Tunnel::responder(stream).await.unwrap();
+										let Some(library) =
+											node.libraries.get_library(&library_id).await
+										else {
+											error!(
+												"Peer '{}' requested sync for unknown library '{library_id}'",
+												event.peer_id
+											);
+											return;
+										};
 
-										let msg =
-											SyncMessage::from_stream(&mut tunnel).await.unwrap();
+										let Some(mut tunnel) =
+											Self::accept_tunnel(&node, &library, stream).await
+										else {
+											return;
+										};
 
-										let library =
-											node.libraries.get_library(&library_id).await.unwrap();
+										let msg = match SyncMessage::from_stream(&mut tunnel).await
+										{
+											Ok(msg) => msg,
+											Err(e) => {
+												error!(
+													"Failed to read sync message from peer '{}': {e:?}",
+													event.peer_id
+												);
+												return;
+											}
+										};
 
 										match msg {
 											SyncMessage::NewOperations => {
@@ -329,16 +368,32 @@ impl P2PManager {
 										range,
 									} => {
 										if !node.files_over_p2p_flag.load(Ordering::Relaxed) {
-											panic!("Files over P2P is disabled!");
+											error!(
+												"Peer '{}' requested a file but files over P2P is disabled",
+												event.peer_id
+											);
+											return;
 										}
 
-										// TODO: Tunnel and authentication
 										// TODO: Use BufReader
 
-										let library =
-											node.libraries.get_library(&library_id).await.unwrap();
+										let Some(library) =
+											node.libraries.get_library(&library_id).await
+										else {
+											error!(
+												"Peer '{}' requested a file from unknown library '{library_id}'",
+												event.peer_id
+											);
+											return;
+										};
+
+										let Some(mut tunnel) =
+											Self::accept_tunnel(&node, &library, stream).await
+										else {
+											return;
+										};
 
-										let file_path = library
+										let file_path = match library
 											.db
 											.file_path()
 											.find_unique(file_path::pub_id::equals(
@@ -347,34 +402,72 @@ impl P2PManager {
 											.select(file_path_to_handle_p2p_serve_file::select())
 											.exec()
 											.await
-											.unwrap()
-											.unwrap();
-
-										let location = file_path.location.as_ref().unwrap();
-										let location_path = location.path.as_ref().unwrap();
-										let path = Path::new(location_path).join(
-											IsolatedFilePathData::try_from((
-												location.id,
-												&file_path,
-											))
-											.unwrap(),
-										);
+										{
+											Ok(Some(file_path)) => file_path,
+											Ok(None) => {
+												error!("File path '{file_path_id}' requested over P2P not found");
+												return;
+											}
+											Err(e) => {
+												error!("Failed to fetch file path '{file_path_id}' requested over P2P: {e:#?}");
+												return;
+											}
+										};
 
-										debug!("Serving path '{:?}' over P2P", path);
+										let Some((location, location_path)) = file_path
+											.location
+											.as_ref()
+											.and_then(|l| l.path.as_ref().map(|path| (l, path)))
+										else {
+											error!("File path '{file_path_id}' requested over P2P has no location path");
+											return;
+										};
+
+										let path = match IsolatedFilePathData::try_from((
+											location.id,
+											&file_path,
+										)) {
+											Ok(iso_file_path) => {
+												Path::new(location_path).join(iso_file_path)
+											}
+											Err(e) => {
+												error!("Failed to build path of file path '{file_path_id}' requested over P2P: {e:#?}");
+												return;
+											}
+										};
 
-										let file = File::open(&path).await.unwrap();
+										debug!("Serving path '{:?}' over P2P", path);
 
-										let metadata = file.metadata().await.unwrap();
+										let (file, metadata) = match File::open(&path).await {
+											Ok(file) => match file.metadata().await {
+												Ok(metadata) => (file, metadata),
+												Err(e) => {
+													error!("Failed to read metadata of '{}' requested over P2P: {e:#?}", path.display());
+													return;
+												}
+											},
+											Err(e) => {
+												error!("Failed to open '{}' requested over P2P: {e:#?}", path.display());
+												return;
+											}
+										};
 										let block_size = BlockSize::from_size(metadata.len());
 
-										stream.write_all(&block_size.to_bytes()).await.unwrap();
-										stream
-											.write_all(&metadata.len().to_le_bytes())
-											.await
-											.unwrap();
+										if let Err(e) =
+											tunnel.write_all(&block_size.to_bytes()).await
+										{
+											error!("Failed to send block size of file path '{file_path_id}' over P2P: {e:#?}");
+											return;
+										}
+										if let Err(e) =
+											tunnel.write_all(&metadata.len().to_le_bytes()).await
+										{
+											error!("Failed to send size of file path '{file_path_id}' over P2P: {e:#?}");
+											return;
+										}
 
 										let file = BufReader::new(file);
-										Transfer::new(
+										if let Err(e) = Transfer::new(
 											&SpaceblockRequest {
 												// TODO: Removing need for this field in this case
 												name: "todo".to_string(),
@@ -390,8 +483,13 @@ impl P2PManager {
 											},
 											&Arc::new(AtomicBool::new(false)),
 										)
-										.send(&mut stream, file)
-										.await;
+										.send(&mut tunnel, file)
+										.await
+										{
+											error!(
+												"Error serving file path '{file_path_id}' over P2P: {e}"
+											);
+										}
 									}
 									Header::Connected(identities) => {
 										Self::resync_handler(
@@ -515,7 +613,14 @@ impl P2PManager {
 				};
 
 				// Remember, originator creates a new stream internally so the handler for this doesn't have to do anything.
-				super::sync::originator(library_id, &library.sync, &node.nlm, &node.p2p).await;
+				super::sync::originator(
+					library_id,
+					&library.sync,
+					&library.identity,
+					&node.nlm,
+					&node.p2p,
+				)
+				.await;
 			}
 		}
 	}
@@ -586,7 +691,7 @@ impl P2PManager {
 		let i = Instant::now();
 
 		let file = BufReader::new(file);
-		Transfer::new(
+		match Transfer::new(
 			&match header {
 				Header::Spacedrop(req) => req,
 				_ => unreachable!(),
@@ -600,64 +705,131 @@ impl P2PManager {
 			&cancelled,
 		)
 		.send(&mut stream, file)
-		.await;
-
-		debug!(
-			"Finished Spacedrop to peer '{peer_id}' after '{:?}",
-			i.elapsed()
-		);
+		.await
+		{
+			Ok(()) => debug!(
+				"Finished Spacedrop to peer '{peer_id}' after '{:?}",
+				i.elapsed()
+			),
+			Err(SpaceblockError::Cancelled) => {
+				debug!("Spacedrop to peer '{peer_id}' was cancelled")
+			}
+			Err(e) => {
+				error!("Error sending Spacedrop to peer '{peer_id}': {e}");
+				return Err(());
+			}
+		}
 
 		Ok(Some(id))
 	}
 
 	// DO NOT USE THIS WITHOUT `node.files_over_p2p_flag == true`
-	// TODO: Error handling
+	/// Requests a range of a file from another instance of the library.
+	///
+	/// The size of the file reported by the peer is sent to `size` before any of the file is written to `output`.
+	/// If the connection drops the request is retried, resuming from the last block written to `output`.
+	#[allow(clippy::too_many_arguments)]
 	pub async fn request_file(
 		&self,
 		peer_id: PeerId,
+		remote_identity: &RemoteIdentity,
 		library: &Library,
 		file_path_id: Uuid,
 		range: Range,
-		output: impl AsyncWrite + Unpin,
-	) {
-		let mut stream = self.manager.stream(peer_id).await.unwrap(); // TODO: handle providing incorrect peer id
-
-		// TODO: Tunnel for encryption + authentication
-
-		stream
-			.write_all(
-				&Header::File {
-					library_id: library.id,
-					file_path_id,
-					range: range.clone(),
+		size: oneshot::Sender<u64>,
+		mut output: impl AsyncWrite + Unpin,
+	) -> Result<(), RequestFileError> {
+		let mut range = range;
+		let mut size_tx = Some(size);
+		let mut attempt = 1;
+
+		loop {
+			let mut stream = self
+				.manager
+				.stream(peer_id)
+				.await
+				.map_err(|_| RequestFileError::Stream(peer_id))?; // TODO: handle providing incorrect peer id
+
+			stream
+				.write_all(
+					&Header::File {
+						library_id: library.id,
+						file_path_id,
+						range: range.clone(),
+					}
+					.to_bytes(),
+				)
+				.await?;
+
+			let mut tunnel = Tunnel::initiator(stream, &library.identity, remote_identity).await?;
+
+			let block_size = BlockSize::from_stream(&mut tunnel).await?;
+			let size = tunnel.read_u64_le().await?;
+			if let Some(size_tx) = size_tx.take() {
+				// The receiver being gone means the HTTP request was dropped, so there is no one to send the file to
+				if size_tx.send(size).is_err() {
+					return Ok(());
 				}
-				.to_bytes(),
-			)
-			.await
-			.unwrap();
-
-		let block_size = BlockSize::from_stream(&mut stream).await.unwrap();
-		let size = stream.read_u64_le().await.unwrap();
+			}
 
-		Transfer::new(
-			&SpaceblockRequest {
+			let req = SpaceblockRequest {
 				// TODO: Removing need for this field in this case
 				name: "todo".to_string(),
-				// TODO: Maybe removing need for `size` from this side
 				size,
 				block_size,
 				range,
-			},
-			|percent| {
-				debug!(
-					"P2P receiving file path '{}' - progress {}%",
-					file_path_id, percent
-				);
-			},
-			&Arc::new(AtomicBool::new(false)),
-		)
-		.receive(&mut stream, output)
-		.await;
+			};
+			let transfer = Transfer::new(
+				&req,
+				|percent| {
+					debug!(
+						"P2P receiving file path '{}' - progress {}%",
+						file_path_id, percent
+					);
+				},
+				&Arc::new(AtomicBool::new(false)),
+			);
+
+			match transfer.receive(&mut tunnel, &mut output).await {
+				Ok(()) => return Ok(()),
+				// The offset is only advanced for verified blocks, so a corrupted one is simply requested again.
+				// Failing to write to `output` is not retried, as that's not on the peer's side.
+				Err(e @ (SpaceblockError::Io(_) | SpaceblockError::ChecksumMismatch { .. }))
+					if attempt < FILE_REQUEST_ATTEMPTS =>
+				{
+					warn!(
+						"File request for '{file_path_id}' from peer '{peer_id}' failed at offset '{}', resuming: {e}",
+						transfer.offset()
+					);
+
+					range = req.resume_from(transfer.offset()).range;
+					attempt += 1;
+				}
+				Err(e) => return Err(e.into()),
+			}
+		}
+	}
+
+	/// Accepts a tunnel from one of the library's instances, any other peer is turned away
+	async fn accept_tunnel(
+		node: &Node,
+		library: &Library,
+		stream: UnicastStream,
+	) -> Option<Tunnel> {
+		let instances = node
+			.nlm
+			.state()
+			.await
+			.remove(&library.id)
+			.map(|data| data.instances)
+			.unwrap_or_default();
+
+		Tunnel::responder(stream, &library.identity, |identity| {
+			instances.contains_key(identity)
+		})
+		.await
+		.map_err(|e| error!("Rejected tunnel for library '{}': {e}", library.id))
+		.ok()
 	}
 
 	pub async fn shutdown(&self) {
@@ -36,6 +36,10 @@ pub enum HeaderError {
 	SpacedropRequest(#[from] SpacedropRequestError),
 	#[error("error reading sync request: {0}")]
 	SyncRequest(decode::Error),
+	#[error("error reading file request: {0}")]
+	FileRequest(decode::Error),
+	#[error("error reading file request range: {0}")]
+	FileRange(std::io::Error),
 }
 
 impl Header {
@@ -57,17 +61,15 @@ impl Header {
 					.map_err(HeaderError::SyncRequest)?,
 			)),
 			4 => Ok(Self::File {
-				library_id: decode::uuid(stream).await.unwrap(),
-				file_path_id: decode::uuid(stream).await.unwrap(),
-				range: match stream.read_u8().await.unwrap() {
-					0 => Range::Full,
-					1 => {
-						let start = stream.read_u64_le().await.unwrap();
-						let end = stream.read_u64_le().await.unwrap();
-						Range::Partial(start..end)
-					}
-					_ => todo!(),
-				},
+				library_id: decode::uuid(stream)
+					.await
+					.map_err(HeaderError::FileRequest)?,
+				file_path_id: decode::uuid(stream)
+					.await
+					.map_err(HeaderError::FileRequest)?,
+				range: Range::from_stream(stream)
+					.await
+					.map_err(HeaderError::FileRange)?,
 			}),
 			// TODO: Error handling
 			255 => Ok(Self::Connected({
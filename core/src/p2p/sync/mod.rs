@@ -3,7 +3,7 @@ use std::{collections::HashMap, sync::Arc};
 use itertools::{Either, Itertools};
 use sd_p2p::{
 	proto::{decode, encode},
-	spacetunnel::{RemoteIdentity, Tunnel},
+	spacetunnel::{Identity, RemoteIdentity, Tunnel},
 	DiscoveredPeer, PeerId,
 };
 use sd_sync::CRDTOperation;
@@ -289,6 +289,7 @@ mod originator {
 	pub async fn run(
 		library_id: Uuid,
 		sync: &Arc<sync::Manager>,
+		identity: &Arc<Identity>,
 		nlm: &NetworkedLibraries,
 		p2p: &Arc<super::P2PManager>,
 	) {
@@ -296,12 +297,14 @@ mod originator {
 		let library = libraries.get(&library_id).unwrap();
 
 		// TODO: Deduplicate any duplicate peer ids -> This is an edge case but still
-		for instance in library.instances.values() {
+		for (remote_identity, instance) in &library.instances {
 			let InstanceState::Connected(peer_id) = *instance else {
 				continue;
 			};
 
 			let sync = sync.clone();
+			let identity = identity.clone();
+			let remote_identity = remote_identity.clone();
 			let p2p = p2p.clone();
 
 			tokio::spawn(async move {
@@ -316,7 +319,12 @@ mod originator {
 					.await
 					.unwrap();
 
-				let mut tunnel = Tunnel::initiator(stream).await.unwrap();
+				let Ok(mut tunnel) = Tunnel::initiator(stream, &identity, &remote_identity)
+					.await
+					.map_err(|e| error!("Failed to open sync tunnel to peer '{peer_id:?}': {e}"))
+				else {
+					return;
+				};
 
 				tunnel
 					.write_all(&SyncMessage::NewOperations.to_bytes())
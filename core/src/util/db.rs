@@ -57,6 +57,16 @@ pub async fn load_and_migrate(db_url: &str) -> Result<PrismaClient, MigrationErr
 	Ok(client)
 }
 
+/// A freshly migrated library database in `dir`, for tests
+#[cfg(test)]
+pub(crate) async fn new_test_db(dir: &std::path::Path) -> std::sync::Arc<PrismaClient> {
+	std::sync::Arc::new(
+		load_and_migrate(&format!("file:{}", dir.join("library.db").display()))
+			.await
+			.expect("failed to create test database"),
+	)
+}
+
 pub fn inode_from_db(db_inode: &[u8]) -> u64 {
 	u64::from_le_bytes(db_inode.try_into().expect("corrupted inode in database"))
 }
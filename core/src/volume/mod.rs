@@ -3,12 +3,20 @@
 use serde::{Deserialize, Serialize};
 use serde_with::{serde_as, DisplayFromStr};
 use specta::Type;
-use std::{fmt::Display, path::PathBuf, sync::OnceLock};
+use std::{
+	fmt::Display,
+	path::{Path, PathBuf},
+	sync::OnceLock,
+};
 use sysinfo::{DiskExt, System, SystemExt};
 use thiserror::Error;
 use tokio::sync::Mutex;
 use tracing::error;
 
+mod tracker;
+
+pub use tracker::*;
+
 fn sys_guard() -> &'static Mutex<System> {
 	static SYS: OnceLock<Mutex<System>> = OnceLock::new();
 	SYS.get_or_init(|| Mutex::new(System::new_all()))
@@ -36,6 +44,8 @@ impl Display for DiskType {
 #[derive(Serialize, Deserialize, Debug, Clone, Type)]
 pub struct Volume {
 	pub name: String,
+	/// UUID of the filesystem, or its serial number on Windows
+	pub uuid: Option<String>,
 	pub mount_points: Vec<PathBuf>,
 	#[specta(type = String)]
 	#[serde_as(as = "DisplayFromStr")]
@@ -48,6 +58,38 @@ pub struct Volume {
 	pub is_root_filesystem: bool,
 }
 
+impl Volume {
+	/// Identifies the volume across mounts, falling back to its name and mount point when the
+	/// filesystem has no UUID
+	pub fn identifier(&self) -> String {
+		self.uuid.clone().unwrap_or_else(|| {
+			format!(
+				"{}:{}",
+				self.name,
+				self.mount_points
+					.first()
+					.map(|mount_point| mount_point.to_string_lossy())
+					.unwrap_or_default()
+			)
+		})
+	}
+}
+
+/// Finds the volume whose mount point is the closest parent of `path`
+pub fn volume_of(path: &Path, volumes: &[Volume]) -> Option<(usize, PathBuf)> {
+	volumes
+		.iter()
+		.enumerate()
+		.flat_map(|(idx, volume)| {
+			volume
+				.mount_points
+				.iter()
+				.filter(|mount_point| path.starts_with(mount_point))
+				.map(move |mount_point| (idx, mount_point.clone()))
+		})
+		.max_by_key(|(_, mount_point)| mount_point.components().count())
+}
+
 #[derive(Error, Debug)]
 pub enum VolumeError {
 	#[error("Database error: {0}")]
@@ -64,11 +106,13 @@ impl From<VolumeError> for rspc::Error {
 
 #[cfg(target_os = "linux")]
 pub async fn get_volumes() -> Vec<Volume> {
-	use std::{collections::HashMap, path::Path};
+	use std::collections::HashMap;
 
 	let mut sys = sys_guard().lock().await;
 	sys.refresh_disks_list();
 
+	let uuids = filesystem_uuids().await;
+
 	let mut volumes: Vec<Volume> = Vec::new();
 	let mut path_to_volume_index = HashMap::new();
 	for disk in sys.disks() {
@@ -81,6 +125,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 		let available_capacity = disk.available_space();
 		let is_root_filesystem = mount_point.is_absolute() && mount_point.parent().is_none();
 
+		let mut uuid = None;
 		let mut disk_path: PathBuf = PathBuf::from(disk_name);
 		if file_system.as_ref().map(|fs| fs == "ZFS").unwrap_or(false) {
 			// Use a custom path for ZFS disks to avoid conflicts with normal disks paths
@@ -104,6 +149,8 @@ pub async fn get_volumes() -> Vec<Volume> {
 				Ok(real_path) => real_path,
 			};
 
+			uuid = uuids.get(&real_path).cloned();
+
 			// Check if disk is a symlink to another disk
 			if real_path != disk_path {
 				// Disk is a symlink to another disk, assign it to the same volume
@@ -161,6 +208,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 
 		volumes.push(Volume {
 			name,
+			uuid,
 			disk_type: if disk.is_removable() {
 				DiskType::Removable
 			} else {
@@ -181,6 +229,87 @@ pub async fn get_volumes() -> Vec<Volume> {
 	volumes
 }
 
+/// Maps device paths, like `/dev/sda1`, to the UUID of the filesystem they hold
+#[cfg(target_os = "linux")]
+async fn filesystem_uuids() -> std::collections::HashMap<PathBuf, String> {
+	let mut uuids = std::collections::HashMap::new();
+
+	let mut entries = match tokio::fs::read_dir("/dev/disk/by-uuid").await {
+		Ok(entries) => entries,
+		Err(e) => {
+			error!("Failed to read filesystem UUIDs: {e:#?}");
+			return uuids;
+		}
+	};
+
+	while let Ok(Some(entry)) = entries.next_entry().await {
+		if let Ok(device) = tokio::fs::canonicalize(entry.path()).await {
+			uuids.insert(device, entry.file_name().to_string_lossy().to_string());
+		}
+	}
+
+	uuids
+}
+
+#[cfg(target_os = "macos")]
+#[derive(Deserialize)]
+struct DiskUtilInfo {
+	#[serde(rename = "VolumeUUID")]
+	volume_uuid: Option<String>,
+}
+
+#[cfg(target_os = "macos")]
+async fn volume_uuid(mount_point: &Path) -> Option<String> {
+	let output = tokio::process::Command::new("diskutil")
+		.args(["info", "-plist"])
+		.arg(mount_point)
+		.output()
+		.await
+		.map_err(|err| error!("Failed to execute diskutil: {err:#?}"))
+		.ok()?;
+
+	if !output.status.success() {
+		error!("Command diskutil return error");
+		return None;
+	}
+
+	plist::from_bytes::<DiskUtilInfo>(&output.stdout)
+		.map_err(|err| error!("Failed to parse diskutil output: {err:#?}"))
+		.ok()?
+		.volume_uuid
+}
+
+#[cfg(windows)]
+async fn volume_uuid(mount_point: &Path) -> Option<String> {
+	let mut caption = mount_point.to_str()?.to_string();
+
+	// Remove path separator from Disk letter
+	caption.pop();
+
+	let output = tokio::process::Command::new("cmd")
+		.args([
+			"/C",
+			&format!("wmic logicaldisk where Caption='{caption}' get VolumeSerialNumber"),
+		])
+		.output()
+		.await
+		.map_err(|err| error!("Failed to execute wmic: {err:#?}"))
+		.ok()?;
+
+	if !output.status.success() {
+		error!("Command wmic return error");
+		return None;
+	}
+
+	String::from_utf8(output.stdout)
+		.ok()?
+		.split("\r\r\n")
+		.nth(1)
+		.map(str::trim)
+		.filter(|serial| !serial.is_empty())
+		.map(ToString::to_string)
+}
+
 #[cfg(target_os = "macos")]
 #[derive(Deserialize)]
 #[serde(rename_all = "kebab-case")]
@@ -275,6 +404,11 @@ pub async fn get_volumes() -> Vec<Volume> {
 			}
 		}
 
+		#[cfg(any(target_os = "macos", windows))]
+		let uuid = volume_uuid(&mount_point).await;
+		#[cfg(not(any(target_os = "macos", windows)))]
+		let uuid = None;
+
 		#[allow(unused_mut)] // mut is used in windows
 		let mut total_capacity = disk.total_space();
 		let available_capacity = disk.available_space();
@@ -331,6 +465,7 @@ pub async fn get_volumes() -> Vec<Volume> {
 
 		Some(Volume {
 			name,
+			uuid,
 			disk_type: if disk.is_removable() {
 				DiskType::Removable
 			} else {
@@ -353,44 +488,51 @@ pub async fn get_volumes() -> Vec<Volume> {
 	.collect::<Vec<Volume>>()
 }
 
-// pub async fn save_volume(library: &Library) -> Result<(), VolumeError> {
-// 	// enter all volumes associate with this client add to db
-// 	for volume in get_volumes() {
-// 		let params = vec![
-// 			disk_type::set(volume.disk_type.map(|t| t.to_string())),
-// 			filesystem::set(volume.file_system.clone()),
-// 			total_bytes_capacity::set(volume.total_capacity.to_string()),
-// 			total_bytes_available::set(volume.available_capacity.to_string()),
-// 		];
-
-// 		library
-// 			.db
-// 			.volume()
-// 			.upsert(
-// 				node_id_mount_point_name(
-// 					library.node_local_id,
-// 					volume.mount_point,
-// 					volume.name,
-// 				),
-// 				volume::create(
-// 					library.node_local_id,
-// 					volume.name,
-// 					volume.mount_point,
-// 					params.clone(),
-// 				),
-// 				params,
-// 			)
-// 			.exec()
-// 			.await?;
-// 	}
-// 	// cleanup: remove all unmodified volumes associate with this client
-
-// 	Ok(())
-// }
-
-// #[test]
-// fn test_get_volumes() {
-//   let volumes = get_volumes()?;
-//   dbg!(&volumes);
-//   assert!(volumes.len() > 0);
-// }
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn volume(name: &str, mount_points: &[&str]) -> Volume {
+		Volume {
+			name: name.to_string(),
+			uuid: None,
+			mount_points: mount_points.iter().map(PathBuf::from).collect(),
+			total_capacity: 0,
+			available_capacity: 0,
+			disk_type: DiskType::SSD,
+			file_system: None,
+			is_root_filesystem: false,
+		}
+	}
+
+	#[test]
+	fn paths_belong_to_their_closest_mount_point() {
+		let volumes = [
+			volume("root", &["/"]),
+			volume("data", &["/mnt/data", "/srv"]),
+		];
+
+		assert_eq!(
+			volume_of(Path::new("/home/user/Photos"), &volumes),
+			Some((0, PathBuf::from("/")))
+		);
+		assert_eq!(
+			volume_of(Path::new("/mnt/data/Photos"), &volumes),
+			Some((1, PathBuf::from("/mnt/data")))
+		);
+		assert_eq!(
+			volume_of(Path::new("/srv"), &volumes),
+			Some((1, PathBuf::from("/srv")))
+		);
+		assert_eq!(volume_of(Path::new("relative"), &volumes), None);
+	}
+
+	#[test]
+	fn identifier_prefers_the_filesystem_uuid() {
+		let mut data = volume("/dev/sdb1", &["/mnt/data"]);
+		assert_eq!(data.identifier(), "/dev/sdb1:/mnt/data");
+
+		data.uuid = Some("5E3C-1F2A".to_string());
+		assert_eq!(data.identifier(), "5E3C-1F2A");
+	}
+}
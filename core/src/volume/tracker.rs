@@ -0,0 +1,279 @@
+use crate::{
+	api::CoreEvent,
+	invalidate_query,
+	library::Library,
+	prisma::{instance, location, volume},
+	Node,
+};
+
+use std::{
+	collections::HashMap,
+	path::Path,
+	sync::{Arc, Weak},
+	time::Duration,
+};
+
+use chrono::Utc;
+use serde::Serialize;
+use specta::Type;
+use tokio::time::{interval, MissedTickBehavior};
+use tracing::{error, info};
+use uuid::Uuid;
+
+use super::{get_volumes, volume_of, VolumeError};
+
+const VOLUME_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+volume::select!(tracked_volume {
+	id
+	identifier
+	name
+	mount_point
+	total_bytes_capacity
+	total_bytes_available
+	disk_type
+	filesystem
+	is_system
+	is_mounted
+	date_modified
+});
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Type)]
+pub enum VolumeEventKind {
+	Mounted,
+	Unmounted,
+}
+
+#[derive(Debug, Clone, Serialize, Type)]
+pub struct VolumeEvent {
+	pub library_id: Uuid,
+	pub volume_id: volume::id::Type,
+	pub name: String,
+	pub mount_point: String,
+	pub kind: VolumeEventKind,
+}
+
+/// Records the volumes of this instance in the library, marks the ones that went away as
+/// unmounted and binds every location of this instance to the volume holding it.
+///
+/// Returns whether any volume was mounted or unmounted since the last call.
+pub async fn save_volumes(library: &Library) -> Result<bool, VolumeError> {
+	let Library { db, .. } = library;
+	let instance_id = library.config().instance_id;
+
+	let volumes = get_volumes().await;
+
+	let mut saved = db
+		.volume()
+		.find_many(vec![volume::instance_id::equals(instance_id)])
+		.select(tracked_volume::select())
+		.exec()
+		.await?
+		.into_iter()
+		.map(|volume| (volume.identifier.clone(), volume))
+		.collect::<HashMap<_, _>>();
+
+	let mut events = vec![];
+	let mut mounted_ids = Vec::with_capacity(volumes.len());
+
+	for volume in &volumes {
+		let identifier = volume.identifier();
+		let mount_point = volume
+			.mount_points
+			.first()
+			.map(|mount_point| mount_point.to_string_lossy().to_string())
+			.unwrap_or_default();
+		let total_capacity = volume.total_capacity.to_string();
+		let available_capacity = volume.available_capacity.to_string();
+		let disk_type = volume.disk_type.to_string();
+
+		let previous = saved.remove(&identifier);
+
+		if let Some(previous) = &previous {
+			mounted_ids.push(previous.id);
+
+			if previous.is_mounted
+				&& previous.name == volume.name
+				&& previous.mount_point == mount_point
+				&& previous.total_bytes_capacity == total_capacity
+				&& previous.total_bytes_available == available_capacity
+				&& previous.disk_type.as_ref() == Some(&disk_type)
+				&& previous.filesystem == volume.file_system
+				&& previous.is_system == volume.is_root_filesystem
+			{
+				continue;
+			}
+		}
+
+		use volume::*;
+		let params = vec![
+			name::set(volume.name.clone()),
+			mount_point::set(mount_point.clone()),
+			total_bytes_capacity::set(total_capacity),
+			total_bytes_available::set(available_capacity),
+			disk_type::set(Some(disk_type)),
+			filesystem::set(volume.file_system.clone()),
+			is_system::set(volume.is_root_filesystem),
+			is_mounted::set(true),
+			date_modified::set(Utc::now().into()),
+		];
+
+		let saved_volume = db
+			.volume()
+			.upsert(
+				instance_id_identifier(instance_id, identifier.clone()),
+				create(
+					identifier,
+					volume.name.clone(),
+					mount_point.clone(),
+					instance::id::equals(instance_id),
+					params.clone(),
+				),
+				params,
+			)
+			.select(volume::select!({ id }))
+			.exec()
+			.await?;
+
+		if previous.is_none() {
+			mounted_ids.push(saved_volume.id);
+		}
+
+		if !previous.map_or(false, |previous| previous.is_mounted) {
+			events.push(VolumeEvent {
+				library_id: library.id,
+				volume_id: saved_volume.id,
+				name: volume.name.clone(),
+				mount_point,
+				kind: VolumeEventKind::Mounted,
+			});
+		}
+	}
+
+	// Whatever is left wasn't found on this instance anymore
+	for volume in saved.into_values().filter(|volume| volume.is_mounted) {
+		db.volume()
+			.update(
+				volume::id::equals(volume.id),
+				vec![
+					volume::is_mounted::set(false),
+					volume::date_modified::set(Utc::now().into()),
+				],
+			)
+			.exec()
+			.await?;
+
+		events.push(VolumeEvent {
+			library_id: library.id,
+			volume_id: volume.id,
+			name: volume.name,
+			mount_point: volume.mount_point,
+			kind: VolumeEventKind::Unmounted,
+		});
+	}
+
+	bind_locations(library, &volumes, &mounted_ids).await?;
+
+	let changed = !events.is_empty();
+	for event in events {
+		info!(
+			"Volume {:?} <id='{}', mount_point='{}'>",
+			event.kind, event.volume_id, event.mount_point
+		);
+		library.emit(CoreEvent::Volume(event));
+	}
+
+	Ok(changed)
+}
+
+/// Links each location of this instance to the volume holding it and mirrors that volume's
+/// capacity. Locations stay bound to their volume while it's unmounted, as their path would
+/// otherwise resolve to whichever volume holds the now empty mount point.
+async fn bind_locations(
+	library: &Library,
+	volumes: &[super::Volume],
+	mounted_ids: &[volume::id::Type],
+) -> Result<(), VolumeError> {
+	let Library { db, .. } = library;
+
+	let locations = db
+		.location()
+		.find_many(vec![location::instance_id::equals(Some(
+			library.config().instance_id,
+		))])
+		.select(location::select!({ id path volume_id total_capacity available_capacity }))
+		.exec()
+		.await?;
+
+	for location in locations {
+		if location
+			.volume_id
+			.map_or(false, |volume_id| !mounted_ids.contains(&volume_id))
+		{
+			continue;
+		}
+
+		let Some((idx, _)) = location
+			.path
+			.as_ref()
+			.and_then(|path| volume_of(Path::new(path), volumes))
+		else {
+			continue;
+		};
+
+		let volume_id = mounted_ids[idx];
+		let total_capacity = volumes[idx].total_capacity.to_be_bytes().to_vec();
+		let available_capacity = volumes[idx].available_capacity.to_be_bytes().to_vec();
+
+		if location.volume_id == Some(volume_id)
+			&& location.total_capacity.as_ref() == Some(&total_capacity)
+			&& location.available_capacity.as_ref() == Some(&available_capacity)
+		{
+			continue;
+		}
+
+		// Volumes are local to each instance, so neither the link nor the capacities are synced
+		db.location()
+			.update(
+				location::id::equals(location.id),
+				vec![
+					location::volume::connect(volume::id::equals(volume_id)),
+					location::total_capacity::set(Some(total_capacity)),
+					location::available_capacity::set(Some(available_capacity)),
+				],
+			)
+			.exec()
+			.await?;
+	}
+
+	Ok(())
+}
+
+/// Keeps the volumes of a library up to date for as long as the library is loaded
+pub(crate) fn spawn_volume_tracker(node: Arc<Node>, library: Weak<Library>) {
+	tokio::spawn(async move {
+		let mut check_interval = interval(VOLUME_CHECK_INTERVAL);
+		check_interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+		loop {
+			check_interval.tick().await;
+
+			let Some(library) = library.upgrade() else {
+				break;
+			};
+
+			match save_volumes(&library).await {
+				Ok(true) => {
+					invalidate_query!(library, "volumes.tracked");
+					invalidate_query!(library, "locations.list");
+					invalidate_query!(node; node, "volumes.list");
+				}
+				Ok(false) => {}
+				Err(e) => error!(
+					"Failed to save volumes of library <id='{}'>: {e:#?}",
+					library.id
+				),
+			}
+		}
+	});
+}
@@ -121,10 +121,19 @@ pub struct KeyManager {
 }
 impl KeyManager {
 	/// Initialize the Key Manager with `StoredKeys` retrieved from the database.
+	///
+	/// This uses the OS keyring, if one is available.
 	pub async fn new(stored_keys: Vec<StoredKey>) -> Result<Self> {
-		let keyring = KeyringInterface::new()
-			.map(|k| Arc::new(Mutex::new(k)))
-			.ok();
+		Self::with_keyring(stored_keys, KeyringInterface::new().ok()).await
+	}
+
+	/// Initialize the Key Manager with `StoredKeys` retrieved from the database, and the keyring
+	/// that should hold the secret key (`None` if there isn't one).
+	pub async fn with_keyring(
+		stored_keys: Vec<StoredKey>,
+		keyring: Option<KeyringInterface>,
+	) -> Result<Self> {
+		let keyring = keyring.map(|k| Arc::new(Mutex::new(k)));
 
 		let keymanager = Self {
 			root_key: Mutex::new(None),
@@ -295,17 +304,15 @@ impl KeyManager {
 		)
 		.await?;
 
-		// attempt to insert into the OS keyring
+		// attempt to insert into the keyring
 		// can ignore false here as we want to silently error
-		if let Ok(keyring) = KeyringInterface::new() {
-			let identifier = Identifier {
-				application: APP_IDENTIFIER,
-				library_uuid: &library_uuid.to_string(),
-				usage: SECRET_KEY_IDENTIFIER,
-			};
-
-			keyring.insert(identifier, secret_key.into()).ok();
-		}
+		self.keyring_insert(
+			library_uuid,
+			SECRET_KEY_IDENTIFIER.to_string(),
+			secret_key.into(),
+		)
+		.await
+		.ok();
 
 		let verification_key = StoredKey {
 			uuid: Uuid::new_v4(),
@@ -1060,3 +1067,170 @@ impl KeyManager {
 		Ok(())
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use crate::{
+		keys::keyring::{memory::MemoryKeyring, KeyringInterface},
+		types::Params,
+	};
+
+	const ALGORITHM: Algorithm = Algorithm::XChaCha20Poly1305;
+	const HASHING_ALGORITHM: HashingAlgorithm = HashingAlgorithm::Argon2id(Params::Standard);
+
+	/// Creates an unlocked key manager backed by an in-memory keyring
+	async fn setup(library_uuid: Uuid, password: &str) -> KeyManager {
+		let key_manager = KeyManager::with_keyring(
+			vec![],
+			Some(KeyringInterface::with_keyring(MemoryKeyring::default())),
+		)
+		.await
+		.unwrap();
+
+		let verification_key = key_manager
+			.onboarding(
+				OnboardingConfig {
+					password: Protected::new(password.to_string()),
+					algorithm: ALGORITHM,
+					hashing_algorithm: HASHING_ALGORITHM,
+				},
+				library_uuid,
+			)
+			.await
+			.unwrap();
+
+		key_manager
+			.populate_keystore(vec![verification_key])
+			.await
+			.unwrap();
+
+		key_manager
+	}
+
+	#[tokio::test]
+	async fn setup_and_unlock() {
+		let library_uuid = Uuid::new_v4();
+		let key_manager = setup(library_uuid, "password").await;
+		assert!(key_manager.is_unlocked().await);
+
+		key_manager.clear_root_key().await.unwrap();
+		assert!(!key_manager.is_unlocked().await);
+
+		// the secret key is retrieved from the keyring
+		assert!(key_manager
+			.unlock(
+				Protected::new("wrong password".to_string()),
+				None,
+				library_uuid,
+				|| {}
+			)
+			.await
+			.is_err());
+		assert!(!key_manager.is_unlocked().await);
+		assert!(!key_manager.is_unlocking().await.unwrap());
+
+		key_manager
+			.unlock(
+				Protected::new("password".to_string()),
+				None,
+				library_uuid,
+				|| {},
+			)
+			.await
+			.unwrap();
+		assert!(key_manager.is_unlocked().await);
+	}
+
+	#[tokio::test]
+	async fn add_mount_and_unmount() {
+		let key_manager = setup(Uuid::new_v4(), "password").await;
+
+		let uuid = key_manager
+			.add_to_keystore(
+				Protected::new("file key".to_string()),
+				ALGORITHM,
+				HASHING_ALGORITHM,
+				false,
+				false,
+				None,
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(key_manager.dump_keystore().len(), 1);
+		assert!(key_manager.get_mounted_uuids().is_empty());
+
+		key_manager.mount(uuid).await.unwrap();
+		assert_eq!(key_manager.get_mounted_uuids(), vec![uuid]);
+		assert_eq!(key_manager.enumerate_hashed_keys().len(), 1);
+		assert_eq!(
+			key_manager.get_key(uuid).await.unwrap().expose(),
+			"file key"
+		);
+
+		key_manager.set_default(uuid).await.unwrap();
+		assert_eq!(key_manager.get_default().await.unwrap(), uuid);
+
+		key_manager.unmount(uuid).unwrap();
+		assert!(key_manager.get_mounted_uuids().is_empty());
+		assert!(key_manager.unmount(uuid).is_err());
+
+		assert!(key_manager.set_default(Uuid::new_v4()).await.is_err());
+	}
+
+	#[tokio::test]
+	async fn backup_and_restore() {
+		let library_uuid = Uuid::new_v4();
+		let key_manager = setup(library_uuid, "password").await;
+
+		let uuid = key_manager
+			.add_to_keystore(
+				Protected::new("file key".to_string()),
+				ALGORITHM,
+				HASHING_ALGORITHM,
+				false,
+				false,
+				None,
+			)
+			.await
+			.unwrap();
+
+		let mut backup = key_manager.dump_keystore();
+		backup.push(key_manager.get_verification_key().await.unwrap());
+
+		let secret_key = key_manager
+			.keyring_retrieve(library_uuid, SECRET_KEY_IDENTIFIER.to_string())
+			.await
+			.unwrap();
+
+		// a different library, with a different master password and secret key
+		let other_key_manager = setup(Uuid::new_v4(), "other password").await;
+
+		assert!(other_key_manager
+			.import_keystore_backup(
+				Protected::new("wrong password".to_string()),
+				SecretKeyString(secret_key.clone()),
+				&backup,
+			)
+			.await
+			.is_err());
+
+		let restored = other_key_manager
+			.import_keystore_backup(
+				Protected::new("password".to_string()),
+				SecretKeyString(secret_key),
+				&backup,
+			)
+			.await
+			.unwrap();
+
+		assert_eq!(restored.len(), 1);
+		assert_eq!(restored[0].uuid, uuid);
+		assert_eq!(
+			other_key_manager.get_key(uuid).await.unwrap().expose(),
+			"file key"
+		);
+	}
+}
@@ -0,0 +1,57 @@
+//! This is an in-memory keyring, for when an OS keyring isn't available (and for testing).
+//!
+//! Nothing is persisted, so everything stored within it is lost once it's dropped.
+
+use std::{
+	collections::HashMap,
+	sync::{Mutex, PoisonError},
+};
+
+use super::{Identifier, Keyring};
+use crate::{types::SecretKeyString, Error, Protected, Result};
+
+#[derive(Default)]
+pub struct MemoryKeyring {
+	items: Mutex<HashMap<String, Protected<Vec<u8>>>>,
+}
+
+impl MemoryKeyring {
+	fn item_name(identifier: Identifier) -> String {
+		format!(
+			"{} - {} - {}",
+			identifier.application, identifier.library_uuid, identifier.usage
+		)
+	}
+}
+
+impl Keyring for MemoryKeyring {
+	fn insert(&self, identifier: Identifier, value: SecretKeyString) -> Result<()> {
+		self.items
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.insert(
+				Self::item_name(identifier),
+				Protected::new(value.expose().as_bytes().to_vec()),
+			);
+
+		Ok(())
+	}
+
+	fn retrieve(&self, identifier: Identifier) -> Result<Protected<Vec<u8>>> {
+		self.items
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.get(&Self::item_name(identifier))
+			.cloned()
+			.ok_or(Error::KeyringError)
+	}
+
+	fn delete(&self, identifier: Identifier) -> Result<()> {
+		self.items
+			.lock()
+			.unwrap_or_else(PoisonError::into_inner)
+			.remove(&Self::item_name(identifier))
+			.map(|_| ())
+			.ok_or(Error::KeyringError)
+	}
+}
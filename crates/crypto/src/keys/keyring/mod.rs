@@ -6,6 +6,8 @@ pub mod linux;
 #[cfg(any(target_os = "macos", target_os = "ios"))]
 pub mod apple;
 
+pub mod memory;
+
 /// This identifier is platform-agnostic and is used for identifying keys within OS keyrings
 #[derive(Clone, Copy)]
 pub struct Identifier<'a> {
@@ -66,6 +68,13 @@ impl KeyringInterface {
 		Ok(Self { keyring })
 	}
 
+	/// This allows using a keyring other than the OS one, such as [`memory::MemoryKeyring`].
+	pub fn with_keyring(keyring: impl Keyring + Send + 'static) -> Self {
+		Self {
+			keyring: Box::new(keyring),
+		}
+	}
+
 	pub fn insert(&self, identifier: Identifier, value: SecretKeyString) -> Result<()> {
 		self.keyring.insert(identifier, value)
 	}
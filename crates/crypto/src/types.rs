@@ -26,7 +26,7 @@ pub enum Nonce {
 /// These parameters define the password-hashing level.
 ///
 /// The greater the parameter, the longer the password will take to hash.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[cfg_attr(
 	feature = "serde",
 	derive(serde::Serialize),
@@ -40,7 +40,7 @@ pub enum Params {
 }
 
 /// This defines all available password hashing algorithms.
-#[derive(Clone, Copy, PartialEq, Eq)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 #[cfg_attr(
 	feature = "serde",
 	derive(serde::Serialize),
@@ -121,7 +121,7 @@ impl Deref for Nonce {
 }
 
 /// These are all possible algorithms that can be used for encryption and decryption
-#[derive(Clone, Copy, Eq, PartialEq, Hash)]
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug)]
 #[cfg_attr(
 	feature = "serde",
 	derive(serde::Serialize),
@@ -12,9 +12,11 @@ mod movie_decoder;
 mod thumbnailer;
 mod utils;
 mod video_frame;
+mod video_info;
 
 pub use error::ThumbnailerError;
 pub use thumbnailer::{Thumbnailer, ThumbnailerBuilder};
+pub use video_info::{ChapterInfo, StreamInfo, StreamKind, VideoInfo};
 
 /// Helper function to generate a thumbnail file from a video file with reasonable defaults
 pub async fn to_thumbnail(
@@ -46,6 +48,12 @@ pub async fn to_webp_bytes(
 		.await
 }
 
+/// Helper function to read the streams, chapters and duration of a video file without decoding it,
+/// this is blocking so async callers should run it in a separate thread
+pub fn probe(video_file_path: impl AsRef<Path>) -> Result<VideoInfo, ThumbnailerError> {
+	MovieDecoder::new(video_file_path, false)?.probe()
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
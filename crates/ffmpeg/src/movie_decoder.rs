@@ -2,19 +2,21 @@ use crate::{
 	error::{FfmpegError, ThumbnailerError},
 	utils::from_path,
 	video_frame::{FfmpegFrame, FrameSource, VideoFrame},
+	video_info::{ChapterInfo, StreamInfo, StreamKind, VideoInfo},
 };
 
 use ffmpeg_sys_next::{
 	av_buffersink_get_frame, av_buffersrc_write_frame, av_dict_get, av_display_rotation_get,
 	av_frame_alloc, av_frame_free, av_packet_alloc, av_packet_free, av_packet_unref, av_read_frame,
 	av_seek_frame, av_stream_get_side_data, avcodec_alloc_context3, avcodec_find_decoder,
-	avcodec_flush_buffers, avcodec_free_context, avcodec_open2, avcodec_parameters_to_context,
-	avcodec_receive_frame, avcodec_send_packet, avfilter_get_by_name, avfilter_graph_alloc,
-	avfilter_graph_config, avfilter_graph_create_filter, avfilter_graph_free, avfilter_link,
-	avformat_close_input, avformat_find_stream_info, avformat_open_input, AVCodec, AVCodecContext,
-	AVCodecID, AVFilterContext, AVFilterGraph, AVFormatContext, AVFrame, AVMediaType, AVPacket,
+	avcodec_flush_buffers, avcodec_free_context, avcodec_get_name, avcodec_open2,
+	avcodec_parameters_to_context, avcodec_receive_frame, avcodec_send_packet,
+	avfilter_get_by_name, avfilter_graph_alloc, avfilter_graph_config,
+	avfilter_graph_create_filter, avfilter_graph_free, avfilter_link, avformat_close_input,
+	avformat_find_stream_info, avformat_open_input, AVCodec, AVCodecContext, AVCodecID,
+	AVDictionary, AVFilterContext, AVFilterGraph, AVFormatContext, AVFrame, AVMediaType, AVPacket,
 	AVPacketSideDataType, AVRational, AVStream, AVERROR, AVERROR_EOF, AVPROBE_SCORE_MAX,
-	AV_DICT_IGNORE_SUFFIX, AV_TIME_BASE, EAGAIN,
+	AV_DICT_IGNORE_SUFFIX, AV_DISPOSITION_ATTACHED_PIC, AV_TIME_BASE, EAGAIN,
 };
 use std::{
 	ffi::{CStr, CString},
@@ -266,6 +268,73 @@ impl MovieDecoder {
 		Duration::from_secs(unsafe { (*self.format_context).duration as u64 / AV_TIME_BASE as u64 })
 	}
 
+	/// Reads the duration, streams and chapters of the container, along with the dimensions and
+	/// frame rate of the video stream picked by this decoder
+	pub(crate) fn probe(&self) -> Result<VideoInfo, ThumbnailerError> {
+		let format_context = unsafe { &*self.format_context };
+		let video_stream = unsafe { &*self.video_stream };
+		let video_codec_params = unsafe { &*video_stream.codecpar };
+
+		let mut streams = vec![];
+		for stream_idx in 0..(format_context.nb_streams.try_into()?) {
+			let stream = unsafe { &**format_context.streams.offset(stream_idx) };
+
+			// Cover art is stored as a single frame video stream, it isn't a track of its own
+			if stream.disposition & AV_DISPOSITION_ATTACHED_PIC as i32 != 0 {
+				continue;
+			}
+
+			let codec_params = unsafe { &*stream.codecpar };
+			streams.push(StreamInfo {
+				kind: match codec_params.codec_type {
+					AVMediaType::AVMEDIA_TYPE_VIDEO => StreamKind::Video,
+					AVMediaType::AVMEDIA_TYPE_AUDIO => StreamKind::Audio,
+					AVMediaType::AVMEDIA_TYPE_SUBTITLE => StreamKind::Subtitle,
+					AVMediaType::AVMEDIA_TYPE_ATTACHMENT => StreamKind::Attachment,
+					AVMediaType::AVMEDIA_TYPE_DATA => StreamKind::Data,
+					_ => StreamKind::Unknown,
+				},
+				// `avcodec_get_name` never returns null, unknown codecs get a placeholder name
+				codec: unsafe { CStr::from_ptr(avcodec_get_name(codec_params.codec_id)) }
+					.to_string_lossy()
+					.into_owned(),
+				language: dict_value(stream.metadata, "language"),
+			});
+		}
+
+		let mut chapters = vec![];
+		for chapter_idx in 0..(format_context.nb_chapters.try_into()?) {
+			let chapter = unsafe { &**format_context.chapters.offset(chapter_idx) };
+			chapters.push(ChapterInfo {
+				title: dict_value(chapter.metadata, "title"),
+				start: to_duration(chapter.start, chapter.time_base),
+				end: to_duration(chapter.end, chapter.time_base),
+			});
+		}
+
+		// Some containers only know the duration of their streams
+		let duration = match to_duration(
+			format_context.duration,
+			AVRational {
+				num: 1,
+				den: AV_TIME_BASE as i32,
+			},
+		) {
+			Duration::ZERO => to_duration(video_stream.duration, video_stream.time_base),
+			duration => duration,
+		};
+
+		Ok(VideoInfo {
+			duration,
+			width: video_codec_params.width.try_into()?,
+			height: video_codec_params.height.try_into()?,
+			fps: frame_rate(video_stream.avg_frame_rate)
+				.or_else(|| frame_rate(video_stream.r_frame_rate)),
+			streams,
+			chapters,
+		})
+	}
+
 	fn initialize_video(&mut self, prefer_embedded_metadata: bool) -> Result<(), ThumbnailerError> {
 		self.find_preferred_video_stream(prefer_embedded_metadata)?;
 
@@ -689,6 +758,34 @@ impl Drop for MovieDecoder {
 	}
 }
 
+fn dict_value(dict: *mut AVDictionary, key: &str) -> Option<String> {
+	let key = CString::new(key).ok()?;
+	let entry = unsafe { av_dict_get(dict, key.as_ptr(), std::ptr::null(), 0) };
+
+	// WARNING: NEVER use CString with foreign raw pointer (causes double-free)
+	(!entry.is_null()).then(|| {
+		unsafe { CStr::from_ptr((*entry).value) }
+			.to_string_lossy()
+			.into_owned()
+	})
+}
+
+/// Converts a timestamp in `time_base` units, unknown (`AV_NOPTS_VALUE`) timestamps become zero
+fn to_duration(timestamp: i64, time_base: AVRational) -> Duration {
+	if timestamp <= 0 || time_base.num <= 0 || time_base.den <= 0 {
+		return Duration::ZERO;
+	}
+
+	let micros =
+		i128::from(timestamp) * i128::from(time_base.num) * 1_000_000 / i128::from(time_base.den);
+
+	Duration::from_micros(u64::try_from(micros).unwrap_or(u64::MAX))
+}
+
+fn frame_rate(rate: AVRational) -> Option<f64> {
+	(rate.num > 0 && rate.den > 0).then(|| f64::from(rate.num) / f64::from(rate.den))
+}
+
 fn check_error(return_code: i32, error_message: &str) -> Result<(), ThumbnailerError> {
 	if return_code < 0 {
 		Err(ThumbnailerError::FfmpegWithReason(
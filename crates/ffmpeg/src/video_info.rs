@@ -0,0 +1,42 @@
+use std::time::Duration;
+
+/// What a stream in a container carries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamKind {
+	Video,
+	Audio,
+	Subtitle,
+	Attachment,
+	Data,
+	Unknown,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+	pub kind: StreamKind,
+	/// Short codec name as reported by `FFmpeg`, e.g. "h264" or "aac"
+	pub codec: String,
+	/// Language tag of the stream, usually an ISO 639-2 code like "eng"
+	pub language: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChapterInfo {
+	pub title: Option<String>,
+	pub start: Duration,
+	pub end: Duration,
+}
+
+/// Container and stream information of a video file, read without decoding any frames
+#[derive(Debug, Clone, PartialEq)]
+pub struct VideoInfo {
+	pub duration: Duration,
+	/// Dimensions of the main video stream
+	pub width: u32,
+	pub height: u32,
+	/// Average frame rate of the main video stream, if the container reports one
+	pub fps: Option<f64>,
+	/// Every stream in the container, except embedded cover art
+	pub streams: Vec<StreamInfo>,
+	pub chapters: Vec<ChapterInfo>,
+}
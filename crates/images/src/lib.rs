@@ -29,11 +29,13 @@ mod generic;
 	any(not(any(target_os = "linux", target_os = "windows")), heif_images)
 ))]
 mod heif;
+mod perceptual_hash;
 mod svg;
 
 pub use error::{Error, Result};
 pub use formatter::format_image;
 pub use image::DynamicImage;
+pub use perceptual_hash::{dhash, hamming_distance};
 use std::{fs, io::Read, path::Path};
 
 pub trait ImageHandler {
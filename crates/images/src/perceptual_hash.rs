@@ -0,0 +1,51 @@
+use image::{imageops::FilterType, DynamicImage};
+
+/// The width of the grayscale image that is hashed, one more than the bits in each row
+const HASH_WIDTH: u32 = 9;
+const HASH_HEIGHT: u32 = 8;
+
+/// Computes the difference hash (dHash) of an image.
+///
+/// Each bit records whether a pixel is darker than its right neighbour, in a 9x8 grayscale copy
+/// of the image. Resized or re-encoded copies of a photo end up with the same or a very close hash.
+#[must_use]
+pub fn dhash(image: &DynamicImage) -> u64 {
+	let small = image
+		.resize_exact(HASH_WIDTH, HASH_HEIGHT, FilterType::Triangle)
+		.to_luma8();
+
+	(0..HASH_HEIGHT)
+		.flat_map(|y| (0..HASH_WIDTH - 1).map(move |x| (x, y)))
+		.fold(0, |hash, (x, y)| {
+			let darker = small.get_pixel(x, y).0[0] < small.get_pixel(x + 1, y).0[0];
+			(hash << 1) | u64::from(darker)
+		})
+}
+
+/// The number of bits that differ between two hashes, `0` meaning the images look the same.
+#[must_use]
+pub const fn hamming_distance(a: u64, b: u64) -> u32 {
+	(a ^ b).count_ones()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::{GrayImage, Luma};
+
+	fn gradient(width: u32, height: u32, invert: bool) -> DynamicImage {
+		DynamicImage::ImageLuma8(GrayImage::from_fn(width, height, |x, y| {
+			let value =
+				u8::try_from((x * 7 + y * 3) * 255 / (width * 7 + height * 3)).unwrap_or(u8::MAX);
+			Luma([if invert { u8::MAX - value } else { value }])
+		}))
+	}
+
+	#[test]
+	fn test_dhash() {
+		let original = dhash(&gradient(640, 480, false));
+
+		assert!(hamming_distance(original, dhash(&gradient(320, 240, false))) <= 4);
+		assert!(hamming_distance(original, dhash(&gradient(640, 480, true))) > 32);
+	}
+}
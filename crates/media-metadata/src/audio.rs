@@ -1,19 +1,261 @@
 use std::path::Path;
 
+use lofty::{
+	iff::{
+		aiff::{AiffCompressionType, AiffFile},
+		wav::{WavFile, WavFormat},
+	},
+	mp4::{Mp4Codec, Mp4File},
+	mpeg::{Layer, MpegFile},
+	Accessor, AudioFile, FileType, ParseOptions, Picture, PictureType, Probe, Tag, TaggedFile,
+	TaggedFileExt,
+};
+
 use crate::Result;
 
 #[derive(
 	Default, Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type,
 )]
 pub struct AudioMetadata {
-	duration: Option<i32>, // can't use `Duration` due to bigint
-	audio_codec: Option<String>,
+	pub title: Option<String>,
+	pub artist: Option<String>,
+	pub album: Option<String>,
+	pub track: Option<u32>,
+	pub year: Option<u32>,
+	pub genre: Option<String>,
+	/// In milliseconds
+	pub duration: Option<i32>, // can't use `Duration` due to bigint
+	/// In kbps
+	pub bitrate: Option<u32>,
+	/// The codec the audio is encoded with, e.g. "aac" or "pcm", rather than its container
+	pub audio_codec: Option<String>,
+	/// Whether the file has embedded cover art, see [`CoverArt::from_path`]
+	pub has_cover_art: bool,
+}
+
+/// An image embedded in an audio file's tags, in whichever format it was stored as
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct CoverArt(pub Vec<u8>);
+
+/// Picks the front cover, falling back to whichever picture comes first
+fn cover_art(file: &TaggedFile) -> Option<&Picture> {
+	let pictures = file.tags().iter().flat_map(Tag::pictures);
+
+	pictures
+		.clone()
+		.find(|picture| picture.pic_type() == PictureType::CoverFront)
+		.or_else(|| pictures.clone().next())
+}
+
+/// Reads a file along with its codec.
+///
+/// Containers like MP4, WAV and AIFF can hold audio in several codecs, which is only exposed by
+/// their own properties, so those are read as their concrete type before being made a [`TaggedFile`].
+fn read_with_codec(path: impl AsRef<Path>) -> Result<(TaggedFile, Option<String>)> {
+	let probe = Probe::open(path)?;
+	let file_type = probe.file_type();
+	let mut reader = probe.into_inner();
+	let options = ParseOptions::new();
+
+	let codec = |codec: &str| Some(codec.to_string());
+
+	Ok(match file_type {
+		Some(FileType::Mp4) => {
+			let file = Mp4File::read_from(&mut reader, options)?;
+			let codec = match file.properties().codec() {
+				Mp4Codec::AAC => codec("aac"),
+				Mp4Codec::ALAC => codec("alac"),
+				Mp4Codec::MP3 => codec("mp3"),
+				Mp4Codec::FLAC => codec("flac"),
+				_ => None,
+			};
+			(file.into(), codec)
+		}
+		Some(FileType::Wav) => {
+			let file = WavFile::read_from(&mut reader, options)?;
+			let codec = match file.properties().format() {
+				WavFormat::PCM => codec("pcm"),
+				WavFormat::IEEE_FLOAT => codec("pcm_float"),
+				WavFormat::Other(_) => None,
+			};
+			(file.into(), codec)
+		}
+		Some(FileType::Aiff) => {
+			let file = AiffFile::read_from(&mut reader, options)?;
+			let codec = match file.properties().compression_type() {
+				// Only AIFC files are compressed
+				None | Some(AiffCompressionType::None | AiffCompressionType::sowt) => codec("pcm"),
+				Some(
+					AiffCompressionType::fl32
+					| AiffCompressionType::fl64
+					| AiffCompressionType::FL32,
+				) => codec("pcm_float"),
+				Some(AiffCompressionType::alaw | AiffCompressionType::ALAW) => codec("alaw"),
+				Some(AiffCompressionType::ulaw | AiffCompressionType::ULAW) => codec("ulaw"),
+				Some(other) => Some(other.compression_name().to_lowercase()),
+			};
+			(file.into(), codec)
+		}
+		Some(FileType::Mpeg) => {
+			let file = MpegFile::read_from(&mut reader, options)?;
+			let codec = match file.properties().layer() {
+				Layer::Layer1 => codec("mp1"),
+				Layer::Layer2 => codec("mp2"),
+				Layer::Layer3 => codec("mp3"),
+			};
+			(file.into(), codec)
+		}
+		// The other formats only ever hold a single codec
+		file_type => {
+			let codec = match file_type {
+				Some(FileType::Aac) => codec("aac"),
+				Some(FileType::Ape) => codec("ape"),
+				Some(FileType::Flac) => codec("flac"),
+				Some(FileType::Mpc) => codec("musepack"),
+				Some(FileType::Opus) => codec("opus"),
+				Some(FileType::Vorbis) => codec("vorbis"),
+				Some(FileType::Speex) => codec("speex"),
+				Some(FileType::WavPack) => codec("wavpack"),
+				_ => None,
+			};
+
+			let mut probe = Probe::new(reader);
+			if let Some(file_type) = file_type {
+				probe.set_file_type(file_type);
+			}
+
+			(probe.read()?, codec)
+		}
+	})
 }
 
 impl AudioMetadata {
-	#[allow(clippy::missing_errors_doc)]
-	#[allow(clippy::missing_panics_doc)]
-	pub fn from_path(_path: impl AsRef<Path>) -> Result<Self> {
-		todo!()
+	/// Reads the tags (`ID3v2`, FLAC/Vorbis comments, MP4 atoms, etc) and audio properties of a file.
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		let (file, audio_codec) = read_with_codec(path)?;
+		let properties = file.properties();
+
+		// The primary tag is the one native to the format, other tags only fill in the gaps
+		let tags = file
+			.primary_tag()
+			.into_iter()
+			.chain(file.tags().iter())
+			.collect::<Vec<_>>();
+
+		macro_rules! first {
+			($accessor:ident) => {
+				tags.iter()
+					.find_map(|tag| tag.$accessor().map(|value| value.to_string()))
+			};
+			($accessor:ident as number) => {
+				tags.iter().find_map(|tag| tag.$accessor())
+			};
+		}
+
+		Ok(Self {
+			title: first!(title),
+			artist: first!(artist),
+			album: first!(album),
+			track: first!(track as number),
+			year: first!(year as number),
+			genre: first!(genre),
+			duration: i32::try_from(properties.duration().as_millis())
+				.ok()
+				.filter(|duration| *duration > 0),
+			bitrate: properties
+				.audio_bitrate()
+				.or_else(|| properties.overall_bitrate()),
+			audio_codec,
+			has_cover_art: cover_art(&file).is_some(),
+		})
+	}
+}
+
+impl CoverArt {
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Option<Self>> {
+		let file = lofty::read_from_path(path)?;
+
+		Ok(cover_art(&file).map(|picture| Self(picture.data().to_vec())))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use std::fs;
+
+	use lofty::{MimeType, TagExt, TagType};
+	use tempfile::tempdir;
+
+	const COVER: &[u8] = b"not really a png";
+
+	/// A second of 8kHz mono 8-bit silence
+	fn write_wav(path: &Path) {
+		let data_len = 8_000_u32;
+
+		let mut wav = vec![];
+		wav.extend(b"RIFF");
+		wav.extend((36 + data_len).to_le_bytes());
+		wav.extend(b"WAVE");
+		wav.extend(b"fmt ");
+		wav.extend(16_u32.to_le_bytes());
+		wav.extend(1_u16.to_le_bytes()); // PCM
+		wav.extend(1_u16.to_le_bytes()); // Channels
+		wav.extend(8_000_u32.to_le_bytes()); // Sample rate
+		wav.extend(8_000_u32.to_le_bytes()); // Bytes per second
+		wav.extend(1_u16.to_le_bytes()); // Block align
+		wav.extend(8_u16.to_le_bytes()); // Bits per sample
+		wav.extend(b"data");
+		wav.extend(data_len.to_le_bytes());
+		wav.resize(wav.len() + data_len as usize, 0x80);
+
+		fs::write(path, wav).unwrap();
+	}
+
+	#[test]
+	fn tags_and_cover_art_are_read() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("tagged.wav");
+		write_wav(&path);
+
+		let mut tag = Tag::new(TagType::Id3v2);
+		tag.set_title("Title".to_string());
+		tag.set_artist("Artist".to_string());
+		tag.push_picture(Picture::new_unchecked(
+			PictureType::CoverFront,
+			MimeType::Png,
+			None,
+			COVER.to_vec(),
+		));
+		tag.save_to_path(&path).unwrap();
+
+		let metadata = AudioMetadata::from_path(&path).unwrap();
+
+		assert_eq!(metadata.title.as_deref(), Some("Title"));
+		assert_eq!(metadata.artist.as_deref(), Some("Artist"));
+		assert_eq!(metadata.duration, Some(1_000));
+		assert_eq!(metadata.audio_codec.as_deref(), Some("pcm"));
+		assert!(metadata.has_cover_art);
+
+		assert_eq!(
+			CoverArt::from_path(&path).unwrap(),
+			Some(CoverArt(COVER.to_vec()))
+		);
+	}
+
+	#[test]
+	fn untagged_files_have_no_cover_art() {
+		let dir = tempdir().unwrap();
+		let path = dir.path().join("untagged.wav");
+		write_wav(&path);
+
+		let metadata = AudioMetadata::from_path(&path).unwrap();
+
+		assert_eq!(metadata.title, None);
+		assert_eq!(metadata.duration, Some(1_000));
+		assert!(!metadata.has_cover_art);
+
+		assert_eq!(CoverArt::from_path(&path).unwrap(), None);
 	}
 }
@@ -9,6 +9,11 @@ pub enum Error {
 	Io(std::io::Error, Box<Path>),
 	#[error("error from the exif crate: {0}")]
 	Exif(#[from] exif::Error),
+	#[error("error while reading audio tags: {0}")]
+	Audio(#[from] lofty::error::LoftyError),
+	#[cfg(feature = "ffmpeg")]
+	#[error("error while probing video: {0}")]
+	Video(#[from] sd_ffmpeg::ThumbnailerError),
 	#[error("there was an error while parsing time with chrono: {0}")]
 	Chrono(#[from] chrono::ParseError),
 	#[error("there was an error while converting between types")]
@@ -26,10 +26,10 @@ mod error;
 pub mod image;
 pub mod video;
 
-pub use audio::AudioMetadata;
+pub use audio::{AudioMetadata, CoverArt};
 pub use error::{Error, Result};
 pub use image::ImageMetadata;
-pub use video::VideoMetadata;
+pub use video::{VideoChapter, VideoMetadata, VideoStream, VideoStreamKind};
 
 #[derive(Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
 #[serde(tag = "type")]
@@ -1,20 +1,150 @@
+#[cfg(feature = "ffmpeg")]
 use std::path::Path;
 
+#[cfg(feature = "ffmpeg")]
 use crate::Result;
 
-#[derive(
-	Default, Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type,
-)]
+#[derive(Default, Clone, PartialEq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
 pub struct VideoMetadata {
-	duration: Option<i32>, // bigint
-	video_codec: Option<String>,
-	audio_codec: Option<String>,
+	/// In milliseconds
+	pub duration: Option<i32>, // can't use `Duration` due to bigint
+	pub width: Option<u32>,
+	pub height: Option<u32>,
+	pub fps: Option<f64>,
+	/// Codec of the first video stream
+	pub video_codec: Option<String>,
+	/// Codec of the first audio stream
+	pub audio_codec: Option<String>,
+	pub streams: Vec<VideoStream>,
+	pub chapters: Vec<VideoChapter>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
+pub enum VideoStreamKind {
+	Video,
+	Audio,
+	Subtitle,
+	Attachment,
+	Data,
+	Unknown,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct VideoStream {
+	pub kind: VideoStreamKind,
+	pub codec: String,
+	/// Usually an ISO 639-2 code, like "eng"
+	pub language: Option<String>,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug, serde::Serialize, serde::Deserialize, specta::Type)]
+pub struct VideoChapter {
+	pub title: Option<String>,
+	/// In milliseconds
+	pub start: i32,
+	/// In milliseconds
+	pub end: i32,
 }
 
 impl VideoMetadata {
-	#[allow(clippy::missing_errors_doc)]
-	#[allow(clippy::missing_panics_doc)]
-	pub fn from_path(_path: impl AsRef<Path>) -> Result<Self> {
-		todo!()
+	/// Probes the container of a video with `FFmpeg`, no frames are decoded.
+	#[cfg(feature = "ffmpeg")]
+	pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+		let info = sd_ffmpeg::probe(path)?;
+
+		let first_codec = |kind| {
+			info.streams
+				.iter()
+				.find(|stream| stream.kind == kind)
+				.map(|stream| stream.codec.clone())
+		};
+
+		Ok(Self {
+			duration: millis(info.duration).filter(|duration| *duration > 0),
+			width: (info.width > 0).then_some(info.width),
+			height: (info.height > 0).then_some(info.height),
+			fps: info.fps,
+			video_codec: first_codec(sd_ffmpeg::StreamKind::Video),
+			audio_codec: first_codec(sd_ffmpeg::StreamKind::Audio),
+			streams: info
+				.streams
+				.iter()
+				.map(|stream| VideoStream {
+					kind: stream.kind.into(),
+					codec: stream.codec.clone(),
+					language: stream.language.clone(),
+				})
+				.collect(),
+			chapters: info
+				.chapters
+				.iter()
+				.map(|chapter| VideoChapter {
+					title: chapter.title.clone(),
+					start: millis(chapter.start).unwrap_or(i32::MAX),
+					end: millis(chapter.end).unwrap_or(i32::MAX),
+				})
+				.collect(),
+		})
+	}
+
+	/// The distinct codecs of all streams, in stream order, e.g. "h264,aac"
+	#[must_use]
+	pub fn codecs(&self) -> String {
+		let mut codecs = Vec::<&str>::with_capacity(self.streams.len());
+		for stream in &self.streams {
+			if !codecs.contains(&stream.codec.as_str()) {
+				codecs.push(&stream.codec);
+			}
+		}
+
+		codecs.join(",")
+	}
+}
+
+#[cfg(feature = "ffmpeg")]
+fn millis(duration: std::time::Duration) -> Option<i32> {
+	i32::try_from(duration.as_millis()).ok()
+}
+
+#[cfg(feature = "ffmpeg")]
+impl From<sd_ffmpeg::StreamKind> for VideoStreamKind {
+	fn from(kind: sd_ffmpeg::StreamKind) -> Self {
+		use sd_ffmpeg::StreamKind;
+
+		match kind {
+			StreamKind::Video => Self::Video,
+			StreamKind::Audio => Self::Audio,
+			StreamKind::Subtitle => Self::Subtitle,
+			StreamKind::Attachment => Self::Attachment,
+			StreamKind::Data => Self::Data,
+			StreamKind::Unknown => Self::Unknown,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn codecs_are_deduplicated_in_order() {
+		let stream = |kind, codec: &str| VideoStream {
+			kind,
+			codec: codec.to_string(),
+			language: None,
+		};
+
+		let metadata = VideoMetadata {
+			streams: vec![
+				stream(VideoStreamKind::Video, "h264"),
+				stream(VideoStreamKind::Audio, "aac"),
+				stream(VideoStreamKind::Audio, "aac"),
+				stream(VideoStreamKind::Subtitle, "subrip"),
+			],
+			..Default::default()
+		};
+
+		assert_eq!(metadata.codecs(), "h264,aac,subrip");
+		assert_eq!(VideoMetadata::default().codecs(), "");
 	}
 }
@@ -1,49 +1,112 @@
 use tokio::io::AsyncReadExt;
 
-/// TODO
+use super::SpaceblockError;
+
+/// The length of the BLAKE3 checksum sent alongside each block
+pub const BLOCK_CHECKSUM_LEN: usize = blake3::OUT_LEN;
+
+/// A chunk of a file being transferred.
+///
+/// On the wire each block carries a BLAKE3 checksum of its data, which is verified by the receiver.
 #[derive(Debug, PartialEq, Eq)]
 pub struct Block<'a> {
-	// TODO: File content, checksum, source location so it can be resent!
+	/// Offset of the start of the block within the file
 	pub offset: u64,
 	pub size: u64,
 	pub data: &'a [u8],
-	// TODO: Checksum?
 }
 
 impl<'a> Block<'a> {
 	pub fn to_bytes(&self) -> Vec<u8> {
-		let mut buf = Vec::new();
+		let mut buf = Vec::with_capacity(16 + BLOCK_CHECKSUM_LEN + self.data.len());
 		buf.extend_from_slice(&self.offset.to_le_bytes());
 		buf.extend_from_slice(&self.size.to_le_bytes());
+		buf.extend_from_slice(blake3::hash(self.data).as_bytes());
 		buf.extend_from_slice(self.data);
 		buf
 	}
 
+	/// Decode a block, reading its data into `data_buf`.
+	///
+	/// Blocks larger than `data_buf` or whose data doesn't match their checksum are rejected.
 	pub async fn from_stream(
 		stream: &mut (impl AsyncReadExt + Unpin),
-		data_buf: &mut [u8],
-	) -> Result<Block<'a>, ()> {
-		let mut offset = [0; 8];
-		stream.read_exact(&mut offset).await.map_err(|_| ())?; // TODO: Error handling
-		let offset = u64::from_le_bytes(offset);
+		data_buf: &'a mut [u8],
+	) -> Result<Block<'a>, SpaceblockError> {
+		let offset = stream.read_u64_le().await?;
+		let size = stream.read_u64_le().await?;
 
-		let mut size = [0; 8];
-		stream.read_exact(&mut size).await.map_err(|_| ())?; // TODO: Error handling
-		let size = u64::from_le_bytes(size);
+		let mut checksum = [0; BLOCK_CHECKSUM_LEN];
+		stream.read_exact(&mut checksum).await?;
 
-		// TODO: Ensure `size` is `block_size` or smaller else buffer overflow
+		let data = usize::try_from(size)
+			.ok()
+			.and_then(|size| data_buf.get_mut(..size))
+			.ok_or(SpaceblockError::InvalidBlockSize { offset, size })?;
+		stream.read_exact(data).await?;
 
-		stream
-			.read_exact(&mut data_buf[..size as usize])
-			.await
-			.map_err(|_| ())?; // TODO: Error handling
+		if blake3::hash(data) != checksum {
+			return Err(SpaceblockError::ChecksumMismatch { offset });
+		}
 
-		Ok(Self {
-			offset,
-			size,
-			data: &[], // TODO: This is super cringe. Data should be decoded here but lifetimes and extra allocations become a major concern.
-		})
+		Ok(Self { offset, size, data })
 	}
 }
 
-// TODO: Unit test `Block`
+#[cfg(test)]
+mod tests {
+	use std::io::Cursor;
+
+	use super::*;
+
+	#[tokio::test]
+	async fn test_block() {
+		let block = Block {
+			offset: 420,
+			size: 10,
+			data: b"Spacedrive".as_ref(),
+		};
+
+		let mut data_buf = [0u8; 64];
+		let block2 = Block::from_stream(&mut Cursor::new(block.to_bytes()), &mut data_buf)
+			.await
+			.unwrap();
+		assert_eq!(block, block2);
+	}
+
+	#[tokio::test]
+	async fn test_block_checksum_mismatch() {
+		let block = Block {
+			offset: 420,
+			size: 10,
+			data: b"Spacedrive".as_ref(),
+		};
+
+		let mut bytes = block.to_bytes();
+		*bytes.last_mut().unwrap() ^= 1;
+
+		let result = Block::from_stream(&mut Cursor::new(bytes), &mut [0u8; 64]).await;
+		assert!(matches!(
+			result,
+			Err(SpaceblockError::ChecksumMismatch { offset: 420 })
+		));
+	}
+
+	#[tokio::test]
+	async fn test_block_too_large() {
+		let block = Block {
+			offset: 0,
+			size: 10,
+			data: b"Spacedrive".as_ref(),
+		};
+
+		let result = Block::from_stream(&mut Cursor::new(block.to_bytes()), &mut [0u8; 4]).await;
+		assert!(matches!(
+			result,
+			Err(SpaceblockError::InvalidBlockSize {
+				offset: 0,
+				size: 10
+			})
+		));
+	}
+}
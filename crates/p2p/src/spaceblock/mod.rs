@@ -4,11 +4,12 @@
 #![allow(unused)] // TODO: This module is still in heavy development!
 
 use std::{
+	io::SeekFrom,
 	marker::PhantomData,
 	path::{Path, PathBuf},
 	string::FromUtf8Error,
 	sync::{
-		atomic::{AtomicBool, Ordering},
+		atomic::{AtomicBool, AtomicU64, Ordering},
 		Arc,
 	},
 };
@@ -16,7 +17,10 @@ use std::{
 use thiserror::Error;
 use tokio::{
 	fs::File,
-	io::{AsyncBufRead, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader},
+	io::{
+		AsyncBufRead, AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt, AsyncWrite, AsyncWriteExt,
+		BufReader,
+	},
 };
 use tracing::debug;
 
@@ -33,6 +37,32 @@ pub use block::*;
 pub use block_size::*;
 pub use sb_request::*;
 
+#[derive(Debug, Error)]
+pub enum SpaceblockError {
+	#[error("io error: {0}")]
+	Io(#[from] std::io::Error),
+	/// Reading or writing the local file failed, as opposed to the stream with the peer
+	#[error("file io error: {0}")]
+	File(std::io::Error),
+	#[error("invalid message discriminator '{0}'")]
+	InvalidMessage(u8),
+	#[error("invalid acknowledgement '{0}'")]
+	InvalidAck(u8),
+	#[error("range '{range:?}' is invalid for a file of size '{size}'")]
+	InvalidRange {
+		range: std::ops::Range<u64>,
+		size: u64,
+	},
+	#[error("block at offset '{offset}' has an invalid size of '{size}'")]
+	InvalidBlockSize { offset: u64, size: u64 },
+	#[error("expected block at offset '{expected}' but got '{got}'")]
+	UnexpectedOffset { expected: u64, got: u64 },
+	#[error("checksum mismatch for block at offset '{offset}'")]
+	ChecksumMismatch { offset: u64 },
+	#[error("transfer was cancelled")]
+	Cancelled,
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum Msg<'a> {
 	Block(Block<'a>),
@@ -40,19 +70,14 @@ pub enum Msg<'a> {
 }
 
 impl<'a> Msg<'a> {
-	pub async fn from_stream<'b>(
+	pub async fn from_stream(
 		stream: &mut (impl AsyncReadExt + Unpin),
-		data_buf: &'b mut [u8],
-	) -> Result<Msg<'a>, ()> {
-		let discriminator = stream.read_u8().await.unwrap(); // TODO: Error handling
-		match discriminator {
-			0 => {
-				Ok(Msg::Block(
-					Block::from_stream(stream, data_buf).await.unwrap(),
-				)) // TODO: Error handling
-			}
+		data_buf: &'a mut [u8],
+	) -> Result<Msg<'a>, SpaceblockError> {
+		match stream.read_u8().await? {
+			0 => Ok(Msg::Block(Block::from_stream(stream, data_buf).await?)),
 			1 => Ok(Msg::Cancelled),
-			_ => panic!("Invalid message type"),
+			d => Err(SpaceblockError::InvalidMessage(d)),
 		}
 	}
 
@@ -69,133 +94,178 @@ impl<'a> Msg<'a> {
 	}
 }
 
-/// TODO
+/// Sends or receives the bytes of [`SpaceblockRequest::range`] over a stream.
+///
+/// Each block is acknowledged by the receiver before the next one is sent.
 pub struct Transfer<'a, F> {
 	req: &'a SpaceblockRequest,
 	on_progress: F,
 	cancelled: &'a AtomicBool,
+	offset: AtomicU64,
 }
 
 impl<'a, F> Transfer<'a, F>
 where
 	F: Fn(u8) + 'a,
 {
-	// TODO: Handle `req.range` correctly in this code
-
 	pub fn new(req: &'a SpaceblockRequest, on_progress: F, cancelled: &'a AtomicBool) -> Self {
 		Self {
 			req,
 			on_progress,
 			cancelled,
+			offset: AtomicU64::new(match &req.range {
+				Range::Full => 0,
+				Range::Partial(range) => range.start,
+			}),
 		}
 	}
 
+	/// The offset within the file up to which bytes have been transferred.
+	///
+	/// If a transfer fails, [`SpaceblockRequest::resume_from`] this offset to pick up where it left off.
+	pub fn offset(&self) -> u64 {
+		self.offset.load(Ordering::Relaxed)
+	}
+
+	fn progress(&self, range: &std::ops::Range<u64>, offset: u64) {
+		let percent = if range.is_empty() {
+			100
+		} else {
+			// SAFETY: Percent must be between 0 and 100
+			((offset - range.start) as f64 / (range.end - range.start) as f64 * 100.0) as u8
+		};
+
+		(self.on_progress)(percent);
+	}
+
 	pub async fn send(
 		&self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
-		mut file: (impl AsyncBufRead + Unpin),
-	) {
+		mut file: (impl AsyncBufRead + AsyncSeek + Unpin),
+	) -> Result<(), SpaceblockError> {
+		let range = self.req.range.bounds(self.req.size)?;
+		file.seek(SeekFrom::Start(range.start))
+			.await
+			.map_err(SpaceblockError::File)?;
+
 		// We manually implement what is basically a `BufReader` so we have more control
 		let mut buf = vec![0u8; self.req.block_size.size() as usize];
-		let mut offset: u64 = 0;
+		let mut offset = range.start;
 
-		loop {
+		while offset < range.end {
 			if self.cancelled.load(Ordering::Relaxed) {
-				stream.write_all(&Msg::Cancelled.to_bytes()).await.unwrap(); // TODO: Error handling
-				stream.flush().await.unwrap(); // TODO: Error handling
-				return;
+				stream.write_all(&Msg::Cancelled.to_bytes()).await?;
+				stream.flush().await?;
+				return Err(SpaceblockError::Cancelled);
 			}
 
-			let read = file.read(&mut buf[..]).await.unwrap(); // TODO: Error handling
-			offset += read as u64;
-			(self.on_progress)(((offset as f64 / self.req.size as f64) * 100.0) as u8); // SAFETY: Percent must be between 0 and 100
-
-			if read == 0 {
-				if offset != self.req.size {
-					panic!("U dun goofed"); // TODO: Error handling
-				}
+			let size = (range.end - offset).min(buf.len() as u64);
+			let data = &mut buf[..size as usize];
+			// The file being shorter than expected is an error, we never send a truncated file
+			file.read_exact(data).await.map_err(SpaceblockError::File)?;
 
-				return;
-			}
-
-			let block = Block {
-				offset,
-				size: read as u64,
-				data: &buf[..read],
-			};
+			let block = Block { offset, size, data };
 			debug!(
 				"Sending block at offset {} of size {}",
 				block.offset, block.size
 			);
-			stream
-				.write_all(&Msg::Block(block).to_bytes())
-				.await
-				.unwrap(); // TODO: Error handling
-			stream.flush().await.unwrap(); // TODO: Error handling
+			stream.write_all(&Msg::Block(block).to_bytes()).await?;
+			stream.flush().await?;
+
+			offset += size;
+			self.offset.store(offset, Ordering::Relaxed);
+			self.progress(&range, offset);
 
-			match stream.read_u8().await.unwrap() {
+			match stream.read_u8().await? {
 				// Continue sending
 				0 => {}
 				// Cancelled by user
 				1 => {
 					debug!("Receiver cancelled Spacedrop transfer!");
-					return;
+					return Err(SpaceblockError::Cancelled);
 				}
 				// Transfer complete
-				2 => {
-					return;
-				}
-				_ => todo!(),
+				2 => return Ok(()),
+				ack => return Err(SpaceblockError::InvalidAck(ack)),
 			}
 		}
+
+		Ok(())
 	}
 
 	pub async fn receive(
 		&self,
 		stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
 		mut file: (impl AsyncWrite + Unpin),
-	) {
+	) -> Result<(), SpaceblockError> {
+		let range = self.req.range.bounds(self.req.size)?;
+
 		// We manually implement what is basically a `BufReader` so we have more control
 		let mut data_buf = vec![0u8; self.req.block_size.size() as usize];
-		let mut offset: u64 = 0;
+		let mut offset = range.start;
+
+		// Nothing will be sent for an empty range
+		if range.is_empty() {
+			self.progress(&range, offset);
+			return Ok(());
+		}
 
-		// TODO: Prevent loop being a DOS vector
 		loop {
 			// TODO: Timeout if nothing is being received
-			let msg = Msg::from_stream(stream, &mut data_buf).await.unwrap(); // TODO: Error handling
-			match msg {
+			match Msg::from_stream(stream, &mut data_buf).await? {
 				Msg::Block(block) => {
-					offset += block.size;
-					(self.on_progress)(((offset as f64 / self.req.size as f64) * 100.0) as u8); // SAFETY: Percent must be between 0 and 100
+					if block.offset != offset {
+						return Err(SpaceblockError::UnexpectedOffset {
+							expected: offset,
+							got: block.offset,
+						});
+					}
+
+					// An empty block would let the sender keep us looping forever
+					if block.size == 0 || block.size > range.end - offset {
+						return Err(SpaceblockError::InvalidBlockSize {
+							offset: block.offset,
+							size: block.size,
+						});
+					}
 
 					debug!(
 						"Received block at offset {} of size {}",
 						block.offset, block.size
 					);
-					file.write_all(&data_buf[..block.size as usize])
+					file.write_all(block.data)
 						.await
-						.unwrap(); // TODO: Error handling
+						.map_err(SpaceblockError::File)?;
+
+					offset += block.size;
+					self.offset.store(offset, Ordering::Relaxed);
+					self.progress(&range, offset);
 
-					// TODO: Should this be `read == 0`
-					if offset == self.req.size {
+					if offset == range.end {
 						break;
 					}
 
-					stream
-						.write_u8(self.cancelled.load(Ordering::Relaxed) as u8)
-						.await
-						.unwrap();
-					stream.flush().await.unwrap(); // TODO: Error handling
+					if self.cancelled.load(Ordering::Relaxed) {
+						stream.write_u8(1).await?;
+						stream.flush().await?;
+						return Err(SpaceblockError::Cancelled);
+					}
+
+					stream.write_u8(0).await?;
+					stream.flush().await?;
 				}
 				Msg::Cancelled => {
 					debug!("Sender cancelled Spacedrop transfer!");
-					return;
+					return Err(SpaceblockError::Cancelled);
 				}
 			}
 		}
 
-		stream.write_u8(2).await.unwrap();
-		stream.flush().await.unwrap(); // TODO: Error handling
+		file.flush().await.map_err(SpaceblockError::File)?;
+		stream.write_u8(2).await?;
+		stream.flush().await?;
+
+		Ok(())
 	}
 }
 
@@ -258,7 +328,8 @@ mod tests {
 				tx.send(()).unwrap();
 				Transfer::new(&req, |_| {}, &Default::default())
 					.send(&mut client, file)
-					.await;
+					.await
+					.unwrap();
 			}
 		});
 
@@ -267,7 +338,8 @@ mod tests {
 		let mut result = Vec::new();
 		Transfer::new(&req, |_| {}, &Default::default())
 			.receive(&mut server, &mut result)
-			.await;
+			.await
+			.unwrap();
 		assert_eq!(result, data);
 	}
 
@@ -297,7 +369,8 @@ mod tests {
 				tx.send(()).unwrap();
 				Transfer::new(&req, |_| {}, &Default::default())
 					.send(&mut client, file)
-					.await;
+					.await
+					.unwrap();
 			}
 		});
 
@@ -306,7 +379,120 @@ mod tests {
 		let mut result = Vec::new();
 		Transfer::new(&req, |_| {}, &Default::default())
 			.receive(&mut server, &mut result)
+			.await
+			.unwrap();
+		assert_eq!(result, data);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_partial_range() {
+		let (mut client, mut server) = tokio::io::duplex(64);
+
+		// This is sent out of band of Spaceblock
+		let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+		let req = SpaceblockRequest {
+			name: "Demo".to_string(),
+			size: data.len() as u64,
+			block_size: BlockSize::dangerously_new(64),
+			range: Range::Partial(100..900),
+		};
+
+		let (tx, rx) = oneshot::channel();
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				tx.send(()).unwrap();
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+					.unwrap();
+			}
+		});
+
+		rx.await.unwrap();
+
+		let mut result = Vec::new();
+		let transfer = Transfer::new(&req, |_| {}, &Default::default());
+		transfer.receive(&mut server, &mut result).await.unwrap();
+		assert_eq!(result, data[100..900]);
+		assert_eq!(transfer.offset(), 900);
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_invalid_range() {
+		let req = SpaceblockRequest {
+			name: "Demo".to_string(),
+			size: 10,
+			block_size: BlockSize::dangerously_new(64),
+			range: Range::Partial(5..11),
+		};
+
+		let (mut client, _server) = tokio::io::duplex(64);
+		let file = BufReader::new(Cursor::new(b"Spacedrive".to_vec()));
+		let result = Transfer::new(&req, |_| {}, &Default::default())
+			.send(&mut client, file)
 			.await;
+		assert!(matches!(result, Err(SpaceblockError::InvalidRange { .. })));
+	}
+
+	#[tokio::test]
+	async fn test_spaceblock_resume() {
+		// This is sent out of band of Spaceblock
+		let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+		let req = SpaceblockRequest {
+			name: "Demo".to_string(),
+			size: data.len() as u64,
+			block_size: BlockSize::dangerously_new(64),
+			range: Range::Full,
+		};
+
+		// The sender only gets through part of the file before the connection drops
+		let (mut client, mut server) = tokio::io::duplex(64);
+		tokio::spawn({
+			let req = SpaceblockRequest {
+				range: Range::Partial(0..400),
+				..req.clone()
+			};
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+					.unwrap();
+			}
+		});
+
+		let mut result = Vec::new();
+		let transfer = Transfer::new(&req, |_| {}, &Default::default());
+		assert!(transfer.receive(&mut server, &mut result).await.is_err());
+		let offset = transfer.offset();
+		assert_eq!(offset, 400);
+		assert_eq!(result, data[..400]);
+
+		// So we pick up where we left off
+		let req = req.resume_from(offset);
+		assert_eq!(req.range, Range::Partial(offset..data.len() as u64));
+
+		let (mut client, mut server) = tokio::io::duplex(64);
+		tokio::spawn({
+			let req = req.clone();
+			let data = data.clone();
+			async move {
+				let file = BufReader::new(Cursor::new(data));
+				Transfer::new(&req, |_| {}, &Default::default())
+					.send(&mut client, file)
+					.await
+					.unwrap();
+			}
+		});
+
+		Transfer::new(&req, |_| {}, &Default::default())
+			.receive(&mut server, &mut result)
+			.await
+			.unwrap();
 		assert_eq!(result, data);
 	}
 
@@ -323,14 +509,16 @@ mod tests {
 		};
 		let msg = Msg::Block(block);
 		let bytes = msg.to_bytes();
-		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut [0u8; 64])
+		let mut data_buf = [0u8; 64];
+		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut data_buf)
 			.await
 			.unwrap();
 		assert_eq!(msg, msg2);
 
 		let msg = Msg::Cancelled;
 		let bytes = msg.to_bytes();
-		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut [0u8; 64])
+		let mut data_buf = [0u8; 64];
+		let msg2 = Msg::from_stream(&mut Cursor::new(bytes), &mut data_buf)
 			.await
 			.unwrap();
 		assert_eq!(msg, msg2);
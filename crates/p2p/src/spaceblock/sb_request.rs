@@ -1,9 +1,11 @@
+use std::io::{self, ErrorKind};
+
 use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt};
 
 use crate::proto::{decode, encode};
 
-use super::BlockSize;
+use super::{BlockSize, SpaceblockError};
 
 /// TODO
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -15,16 +17,39 @@ pub enum Range {
 }
 
 impl Range {
-	// TODO: Per field and proper error handling
-	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> std::io::Result<Self> {
-		match stream.read_u8().await.unwrap() {
+	pub async fn from_stream(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<Self> {
+		match stream.read_u8().await? {
 			0 => Ok(Self::Full),
 			1 => {
-				let start = stream.read_u64_le().await.unwrap();
-				let end = stream.read_u64_le().await.unwrap();
+				let start = stream.read_u64_le().await?;
+				let end = stream.read_u64_le().await?;
+				if start > end {
+					return Err(io::Error::new(
+						ErrorKind::InvalidData,
+						format!("range start '{start}' is after its end '{end}'"),
+					));
+				}
+
 				Ok(Self::Partial(start..end))
 			}
-			_ => todo!(),
+			d => Err(io::Error::new(
+				ErrorKind::InvalidData,
+				format!("invalid range discriminator '{d}'"),
+			)),
+		}
+	}
+
+	/// The bytes of a file of `size` bytes this range covers
+	pub fn bounds(&self, size: u64) -> Result<std::ops::Range<u64>, SpaceblockError> {
+		match self {
+			Self::Full => Ok(0..size),
+			Self::Partial(range) if range.start <= range.end && range.end <= size => {
+				Ok(range.clone())
+			}
+			Self::Partial(range) => Err(SpaceblockError::InvalidRange {
+				range: range.clone(),
+				size,
+			}),
 		}
 	}
 
@@ -59,6 +84,8 @@ pub enum SpacedropRequestError {
 	Name(decode::Error),
 	#[error("SpacedropRequestError::Size({0})")]
 	Size(std::io::Error),
+	#[error("SpacedropRequestError::Range({0})")]
+	Range(std::io::Error),
 }
 
 impl SpaceblockRequest {
@@ -79,10 +106,27 @@ impl SpaceblockRequest {
 			name,
 			size,
 			block_size,
-			range: Range::from_stream(stream).await.unwrap(),
+			range: Range::from_stream(stream)
+				.await
+				.map_err(SpacedropRequestError::Range)?,
 		})
 	}
 
+	/// The request for what is left of this one once `offset` bytes of the file have been received.
+	///
+	/// This is used to resume a transfer after the connection was dropped.
+	pub fn resume_from(&self, offset: u64) -> Self {
+		let end = match &self.range {
+			Range::Full => self.size,
+			Range::Partial(range) => range.end,
+		};
+
+		Self {
+			range: Range::Partial(offset.min(end)..end),
+			..self.clone()
+		}
+	}
+
 	pub fn to_bytes(&self) -> Vec<u8> {
 		let Self {
 			name,
@@ -1,6 +1,6 @@
 use std::hash::{Hash, Hasher};
 
-use ed25519_dalek::{VerifyingKey, SECRET_KEY_LENGTH};
+use ed25519_dalek::{Signature, Signer, VerifyingKey, SECRET_KEY_LENGTH};
 use rand_core::OsRng;
 use serde::Serialize;
 use specta::Type;
@@ -50,6 +50,10 @@ impl Identity {
 	pub fn to_remote_identity(&self) -> RemoteIdentity {
 		RemoteIdentity(self.0.verifying_key())
 	}
+
+	pub(crate) fn sign(&self, message: &[u8]) -> Signature {
+		self.0.sign(message)
+	}
 }
 #[derive(Clone, PartialEq, Eq)]
 pub struct RemoteIdentity(ed25519_dalek::VerifyingKey);
@@ -99,4 +103,8 @@ impl RemoteIdentity {
 	pub fn verifying_key(&self) -> VerifyingKey {
 		self.0
 	}
+
+	pub(crate) fn verify(&self, message: &[u8], signature: &Signature) -> bool {
+		self.0.verify_strict(message, signature).is_ok()
+	}
 }
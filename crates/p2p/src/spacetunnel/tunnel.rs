@@ -1,77 +1,607 @@
 use std::{
-	io,
+	fmt, io,
 	pin::Pin,
-	task::{Context, Poll},
+	task::{ready, Context, Poll},
 };
 
+use chacha20poly1305::{
+	aead::{Aead, KeyInit},
+	ChaCha20Poly1305, Key, Nonce,
+};
+use ed25519_dalek::{Signature, SIGNATURE_LENGTH};
+use rand_core::OsRng;
+use thiserror::Error;
 use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, ReadBuf};
+use x25519_dalek::{EphemeralSecret, PublicKey};
 
 use crate::spacetime::UnicastStream;
 
-#[derive(Debug)]
-pub struct Tunnel {
-	stream: UnicastStream,
+use super::{Identity, IdentityErr, RemoteIdentity};
+
+/// Sent by the initiator so the responder knows the stream is actually a tunnel
+const DISCRIMINATOR: u8 = b'T';
+/// Mixed into the handshake transcript, so it can't be confused with any other protocol
+const PROTOCOL_NAME: &[u8] = b"spacetunnel/1";
+const INITIATOR_KEY_CONTEXT: &str = "spacetunnel 2023-10-07 initiator to responder key";
+const RESPONDER_KEY_CONTEXT: &str = "spacetunnel 2023-10-07 responder to initiator key";
+
+const PUBLIC_KEY_LEN: usize = 32;
+/// The largest amount of plaintext sealed into a single frame
+const MAX_FRAME_LEN: usize = 64 * 1024;
+/// Poly1305 authentication tag appended to every frame
+const TAG_LEN: usize = 16;
+/// Every frame is prefixed by the length of its ciphertext as a big endian `u32`
+const LEN_PREFIX_LEN: usize = 4;
+
+#[derive(Debug, Error)]
+pub enum TunnelError {
+	#[error("error reading or writing the tunnel's stream: {0}")]
+	Io(#[from] io::Error),
+	#[error("invalid discriminator. Is this stream actually a tunnel?")]
+	InvalidDiscriminator,
+	#[error("invalid remote identity: {0}")]
+	InvalidIdentity(#[from] IdentityErr),
+	#[error("the key exchange didn't produce a usable shared secret")]
+	WeakKeyExchange,
+	#[error("the remote peer's signature doesn't match its identity")]
+	InvalidSignature,
+	#[error("the remote peer identified as '{0:?}' instead of the one we were expecting")]
+	UnexpectedIdentity(RemoteIdentity),
+	#[error("the remote peer identified as '{0:?}' which we don't know")]
+	UnknownIdentity(RemoteIdentity),
+}
+
+/// An encrypted and authenticated stream between two [`Identity`]s.
+///
+/// Both peers exchange ephemeral X25519 keys and sign the handshake transcript with their
+/// [`Identity`], then every write is sealed into a ChaCha20-Poly1305 frame with a key per direction.
+pub struct Tunnel<S = UnicastStream> {
+	stream: S,
+	remote_identity: RemoteIdentity,
+	sender: FrameCipher,
+	receiver: FrameCipher,
+	/// Sealed frame that hasn't made it to the stream yet
+	write_buf: Vec<u8>,
+	write_pos: usize,
+	/// Bytes read from the stream that don't make up a whole frame yet
+	read_buf: Vec<u8>,
+	/// Opened frame that hasn't been read out yet
+	plaintext: Vec<u8>,
+	plaintext_pos: usize,
+}
+
+impl<S: fmt::Debug> fmt::Debug for Tunnel<S> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_struct("Tunnel")
+			.field("stream", &self.stream)
+			.field("remote_identity", &self.remote_identity)
+			.finish()
+	}
 }
 
-impl Tunnel {
-	// TODO: Proper errors
-	pub async fn initiator(mut stream: UnicastStream) -> Result<Self, &'static str> {
+impl<S: AsyncRead + AsyncWrite + Unpin> Tunnel<S> {
+	/// Opens a tunnel to the peer holding `remote_identity`, failing if anyone else answers.
+	pub async fn initiator(
+		mut stream: S,
+		identity: &Identity,
+		remote_identity: &RemoteIdentity,
+	) -> Result<Self, TunnelError> {
+		let secret = EphemeralSecret::random_from_rng(OsRng);
+		let ephemeral = PublicKey::from(&secret);
+
+		stream.write_all(&[DISCRIMINATOR]).await?;
+		stream.write_all(ephemeral.as_bytes()).await?;
+		stream.flush().await?;
+
+		let remote_ephemeral = read_public_key(&mut stream).await?;
+		let responder = read_identity(&mut stream).await?;
+		if responder != *remote_identity {
+			return Err(TunnelError::UnexpectedIdentity(responder));
+		}
+
+		let transcript = transcript(&ephemeral, &remote_ephemeral);
+		verify_signature(
+			&mut stream,
+			&responder,
+			&signed_payload(b"responder", &transcript, &[&responder]),
+		)
+		.await?;
+
+		let initiator = identity.to_remote_identity();
+		stream.write_all(&initiator.to_bytes()).await?;
+		stream
+			.write_all(
+				&identity
+					.sign(&signed_payload(
+						b"initiator",
+						&transcript,
+						&[&responder, &initiator],
+					))
+					.to_bytes(),
+			)
+			.await?;
+		stream.flush().await?;
+
+		let (initiator_key, responder_key) = derive_keys(secret, &remote_ephemeral, &transcript)?;
+
+		Ok(Self::new(stream, responder, initiator_key, responder_key))
+	}
+
+	/// Accepts a tunnel from a peer, as long as `is_known` recognises its identity.
+	pub async fn responder(
+		mut stream: S,
+		identity: &Identity,
+		is_known: impl FnOnce(&RemoteIdentity) -> bool,
+	) -> Result<Self, TunnelError> {
+		if stream.read_u8().await? != DISCRIMINATOR {
+			return Err(TunnelError::InvalidDiscriminator);
+		}
+
+		let remote_ephemeral = read_public_key(&mut stream).await?;
+
+		let secret = EphemeralSecret::random_from_rng(OsRng);
+		let ephemeral = PublicKey::from(&secret);
+		let transcript = transcript(&remote_ephemeral, &ephemeral);
+
+		let responder = identity.to_remote_identity();
+		stream.write_all(ephemeral.as_bytes()).await?;
+		stream.write_all(&responder.to_bytes()).await?;
 		stream
-			.write_all(&[b'T'])
-			.await
-			.map_err(|_| "Error writing discriminator")?;
+			.write_all(
+				&identity
+					.sign(&signed_payload(b"responder", &transcript, &[&responder]))
+					.to_bytes(),
+			)
+			.await?;
+		stream.flush().await?;
+
+		let initiator = read_identity(&mut stream).await?;
+		verify_signature(
+			&mut stream,
+			&initiator,
+			&signed_payload(b"initiator", &transcript, &[&responder, &initiator]),
+		)
+		.await?;
 
-		// TODO: Do pairing + authentication
+		if !is_known(&initiator) {
+			return Err(TunnelError::UnknownIdentity(initiator));
+		}
+
+		let (initiator_key, responder_key) = derive_keys(secret, &remote_ephemeral, &transcript)?;
+
+		Ok(Self::new(stream, initiator, responder_key, initiator_key))
+	}
 
-		Ok(Self { stream })
+	fn new(
+		stream: S,
+		remote_identity: RemoteIdentity,
+		sender_key: [u8; 32],
+		receiver_key: [u8; 32],
+	) -> Self {
+		Self {
+			stream,
+			remote_identity,
+			sender: FrameCipher::new(sender_key),
+			receiver: FrameCipher::new(receiver_key),
+			write_buf: Vec::new(),
+			write_pos: 0,
+			read_buf: Vec::new(),
+			plaintext: Vec::new(),
+			plaintext_pos: 0,
+		}
+	}
+
+	/// The identity of the peer on the other end, which was verified during the handshake
+	pub fn remote_identity(&self) -> &RemoteIdentity {
+		&self.remote_identity
 	}
 
-	// TODO: Proper errors
-	pub async fn responder(mut stream: UnicastStream) -> Result<Self, &'static str> {
-		let discriminator = stream
-			.read_u8()
-			.await
-			.map_err(|_| "Error reading discriminator. Is this stream actually a tunnel?")?;
-		if discriminator != b'T' {
-			return Err("Invalid discriminator. Is this stream actually a tunnel?");
+	/// Writes out the sealed frame that's waiting in `write_buf`, if there is one
+	fn poll_write_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		while self.write_pos < self.write_buf.len() {
+			let n = ready!(
+				Pin::new(&mut self.stream).poll_write(cx, &self.write_buf[self.write_pos..])
+			)?;
+			if n == 0 {
+				return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+			}
+
+			self.write_pos += n;
 		}
 
-		// TODO: Do pairing + authentication
+		self.write_buf.clear();
+		self.write_pos = 0;
 
-		Ok(Self { stream })
+		Poll::Ready(Ok(()))
+	}
+
+	/// Length of the next frame's ciphertext, if we have read its prefix yet
+	fn next_frame_len(&self) -> io::Result<Option<usize>> {
+		let Some(prefix) = self.read_buf.get(..LEN_PREFIX_LEN) else {
+			return Ok(None);
+		};
+
+		let mut len = [0u8; LEN_PREFIX_LEN];
+		len.copy_from_slice(prefix);
+		let len = u32::from_be_bytes(len) as usize;
+
+		if !(TAG_LEN..=MAX_FRAME_LEN + TAG_LEN).contains(&len) {
+			return Err(invalid_data("tunnel frame has an invalid length"));
+		}
+
+		Ok(Some(len))
 	}
 }
 
-impl AsyncRead for Tunnel {
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for Tunnel<S> {
 	fn poll_read(
 		self: Pin<&mut Self>,
 		cx: &mut Context<'_>,
 		buf: &mut ReadBuf<'_>,
 	) -> Poll<io::Result<()>> {
-		// TODO: Do decryption
+		let this = self.get_mut();
+
+		loop {
+			if this.plaintext_pos < this.plaintext.len() {
+				let n = buf
+					.remaining()
+					.min(this.plaintext.len() - this.plaintext_pos);
+				buf.put_slice(&this.plaintext[this.plaintext_pos..this.plaintext_pos + n]);
+				this.plaintext_pos += n;
+
+				return Poll::Ready(Ok(()));
+			}
 
-		Pin::new(&mut self.get_mut().stream).poll_read(cx, buf)
+			if let Some(frame_len) = this.next_frame_len()? {
+				if this.read_buf.len() >= LEN_PREFIX_LEN + frame_len {
+					let frame = this
+						.read_buf
+						.drain(..LEN_PREFIX_LEN + frame_len)
+						.skip(LEN_PREFIX_LEN)
+						.collect::<Vec<_>>();
+
+					this.plaintext = this.receiver.open(&frame)?;
+					this.plaintext_pos = 0;
+
+					continue;
+				}
+			}
+
+			let mut chunk = [0u8; 8 * 1024];
+			let mut chunk = ReadBuf::new(&mut chunk);
+			ready!(Pin::new(&mut this.stream).poll_read(cx, &mut chunk))?;
+
+			if chunk.filled().is_empty() {
+				// The stream can only end in between frames
+				return Poll::Ready(if this.read_buf.is_empty() {
+					Ok(())
+				} else {
+					Err(io::ErrorKind::UnexpectedEof.into())
+				});
+			}
+
+			this.read_buf.extend_from_slice(chunk.filled());
+		}
 	}
 }
 
-impl AsyncWrite for Tunnel {
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncWrite for Tunnel<S> {
 	fn poll_write(
 		self: Pin<&mut Self>,
 		cx: &mut Context<'_>,
 		buf: &[u8],
 	) -> Poll<io::Result<usize>> {
-		// TODO: Do encryption
+		let this = self.get_mut();
 
-		Pin::new(&mut self.get_mut().stream).poll_write(cx, buf)
+		// A new frame is only sealed once the previous one made it to the stream
+		ready!(this.poll_write_pending(cx))?;
+
+		if buf.is_empty() {
+			return Poll::Ready(Ok(0));
+		}
+
+		let plaintext = &buf[..buf.len().min(MAX_FRAME_LEN)];
+		let ciphertext = this.sender.seal(plaintext)?;
+
+		this.write_buf
+			.extend_from_slice(&(ciphertext.len() as u32).to_be_bytes());
+		this.write_buf.extend_from_slice(&ciphertext);
+
+		// The frame is buffered, so the plaintext counts as written even if the stream isn't ready for it yet
+		if let Poll::Ready(Err(e)) = this.poll_write_pending(cx) {
+			return Poll::Ready(Err(e));
+		}
+
+		Poll::Ready(Ok(plaintext.len()))
 	}
 
 	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_flush(cx)
+		let this = self.get_mut();
+
+		ready!(this.poll_write_pending(cx))?;
+		Pin::new(&mut this.stream).poll_flush(cx)
 	}
 
 	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-		Pin::new(&mut self.get_mut().stream).poll_shutdown(cx)
+		let this = self.get_mut();
+
+		ready!(this.poll_write_pending(cx))?;
+		Pin::new(&mut this.stream).poll_shutdown(cx)
 	}
 }
 
-// TODO: Unit tests
+/// One direction of a tunnel, every frame being sealed with the next nonce of a counter
+struct FrameCipher {
+	cipher: ChaCha20Poly1305,
+	counter: u64,
+}
+
+impl FrameCipher {
+	fn new(key: [u8; 32]) -> Self {
+		Self {
+			cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+			counter: 0,
+		}
+	}
+
+	fn next_nonce(&mut self) -> io::Result<Nonce> {
+		let mut nonce = Nonce::default();
+		nonce[4..].copy_from_slice(&self.counter.to_le_bytes());
+
+		// Reusing a nonce would break the encryption, so the tunnel must be reopened instead
+		self.counter = self
+			.counter
+			.checked_add(1)
+			.ok_or_else(|| io::Error::new(io::ErrorKind::Other, "tunnel nonces exhausted"))?;
+
+		Ok(nonce)
+	}
+
+	fn seal(&mut self, plaintext: &[u8]) -> io::Result<Vec<u8>> {
+		let nonce = self.next_nonce()?;
+
+		self.cipher
+			.encrypt(&nonce, plaintext)
+			.map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to seal tunnel frame"))
+	}
+
+	fn open(&mut self, ciphertext: &[u8]) -> io::Result<Vec<u8>> {
+		let nonce = self.next_nonce()?;
+
+		self.cipher
+			.decrypt(&nonce, ciphertext)
+			.map_err(|_| invalid_data("failed to authenticate tunnel frame"))
+	}
+}
+
+fn invalid_data(msg: &'static str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+async fn read_public_key(stream: &mut (impl AsyncRead + Unpin)) -> io::Result<PublicKey> {
+	let mut bytes = [0u8; PUBLIC_KEY_LEN];
+	stream.read_exact(&mut bytes).await?;
+
+	Ok(PublicKey::from(bytes))
+}
+
+async fn read_identity(
+	stream: &mut (impl AsyncRead + Unpin),
+) -> Result<RemoteIdentity, TunnelError> {
+	let mut bytes = [0u8; PUBLIC_KEY_LEN];
+	stream.read_exact(&mut bytes).await?;
+
+	Ok(RemoteIdentity::from_bytes(&bytes)?)
+}
+
+async fn verify_signature(
+	stream: &mut (impl AsyncRead + Unpin),
+	identity: &RemoteIdentity,
+	payload: &[u8],
+) -> Result<(), TunnelError> {
+	let mut bytes = [0u8; SIGNATURE_LENGTH];
+	stream.read_exact(&mut bytes).await?;
+
+	if identity.verify(payload, &Signature::from_bytes(&bytes)) {
+		Ok(())
+	} else {
+		Err(TunnelError::InvalidSignature)
+	}
+}
+
+/// Hash of everything both peers agreed on before authenticating each other
+fn transcript(initiator_ephemeral: &PublicKey, responder_ephemeral: &PublicKey) -> [u8; 32] {
+	blake3::Hasher::new()
+		.update(PROTOCOL_NAME)
+		.update(initiator_ephemeral.as_bytes())
+		.update(responder_ephemeral.as_bytes())
+		.finalize()
+		.into()
+}
+
+/// What each peer signs to prove it holds its identity.
+///
+/// The role stops a signature being replayed by the other side, and the identities stop it
+/// being presented to anyone other than the peer it was meant for.
+fn signed_payload(role: &[u8], transcript: &[u8; 32], identities: &[&RemoteIdentity]) -> Vec<u8> {
+	let mut payload = [role, transcript.as_slice()].concat();
+	for identity in identities {
+		payload.extend_from_slice(&identity.to_bytes());
+	}
+
+	payload
+}
+
+/// Derives the keys of both directions from the key exchange, bound to the handshake transcript.
+///
+/// Returns `(initiator_key, responder_key)`, each one being used by that peer to send.
+fn derive_keys(
+	secret: EphemeralSecret,
+	remote_ephemeral: &PublicKey,
+	transcript: &[u8; 32],
+) -> Result<([u8; 32], [u8; 32]), TunnelError> {
+	let shared_secret = secret.diffie_hellman(remote_ephemeral);
+
+	// A low order public key would let the remote peer force a shared secret everyone knows
+	if !shared_secret.was_contributory() {
+		return Err(TunnelError::WeakKeyExchange);
+	}
+
+	let key_material = [shared_secret.as_bytes().as_slice(), transcript.as_slice()].concat();
+
+	Ok((
+		blake3::derive_key(INITIATOR_KEY_CONTEXT, &key_material),
+		blake3::derive_key(RESPONDER_KEY_CONTEXT, &key_material),
+	))
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::io::{duplex, DuplexStream};
+
+	use super::*;
+
+	async fn open_tunnels(
+		initiator_identity: &Identity,
+		responder_identity: &Identity,
+		expected_responder: &RemoteIdentity,
+		known_initiator: &RemoteIdentity,
+	) -> (
+		Result<Tunnel<DuplexStream>, TunnelError>,
+		Result<Tunnel<DuplexStream>, TunnelError>,
+	) {
+		// A small buffer, so the handshake has to interleave its reads and writes
+		let (initiator_stream, responder_stream) = duplex(64);
+
+		tokio::join!(
+			Tunnel::initiator(initiator_stream, initiator_identity, expected_responder),
+			Tunnel::responder(responder_stream, responder_identity, |identity| {
+				identity == known_initiator
+			}),
+		)
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_round_trip() {
+		let (initiator_identity, responder_identity) = (Identity::new(), Identity::new());
+
+		let (initiator, responder) = open_tunnels(
+			&initiator_identity,
+			&responder_identity,
+			&responder_identity.to_remote_identity(),
+			&initiator_identity.to_remote_identity(),
+		)
+		.await;
+		let (mut initiator, mut responder) = (initiator.unwrap(), responder.unwrap());
+
+		assert_eq!(
+			initiator.remote_identity(),
+			&responder_identity.to_remote_identity()
+		);
+		assert_eq!(
+			responder.remote_identity(),
+			&initiator_identity.to_remote_identity()
+		);
+
+		// Spans multiple frames, which are larger than the underlying buffer
+		let data = (0..MAX_FRAME_LEN * 3 + 42)
+			.map(|i| i as u8)
+			.collect::<Vec<_>>();
+
+		let ((), received) = tokio::join!(
+			async {
+				initiator.write_all(&data).await.unwrap();
+				initiator.flush().await.unwrap();
+			},
+			async {
+				let mut received = vec![0u8; data.len()];
+				responder.read_exact(&mut received).await.unwrap();
+				received
+			},
+		);
+		assert_eq!(received, data);
+
+		responder.write_all(b"Spacedrive").await.unwrap();
+		responder.flush().await.unwrap();
+
+		let mut received = [0u8; 10];
+		initiator.read_exact(&mut received).await.unwrap();
+		assert_eq!(&received, b"Spacedrive");
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_rejects_unknown_initiator() {
+		let (initiator_identity, responder_identity) = (Identity::new(), Identity::new());
+
+		let (_, responder) = open_tunnels(
+			&initiator_identity,
+			&responder_identity,
+			&responder_identity.to_remote_identity(),
+			&Identity::new().to_remote_identity(),
+		)
+		.await;
+
+		assert!(matches!(
+			responder,
+			Err(TunnelError::UnknownIdentity(identity))
+				if identity == initiator_identity.to_remote_identity()
+		));
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_rejects_unexpected_responder() {
+		let (initiator_identity, responder_identity) = (Identity::new(), Identity::new());
+
+		let (initiator, responder) = open_tunnels(
+			&initiator_identity,
+			&responder_identity,
+			&Identity::new().to_remote_identity(),
+			&initiator_identity.to_remote_identity(),
+		)
+		.await;
+
+		assert!(matches!(
+			initiator,
+			Err(TunnelError::UnexpectedIdentity(identity))
+				if identity == responder_identity.to_remote_identity()
+		));
+		// The initiator hung up before authenticating itself
+		assert!(responder.is_err());
+	}
+
+	#[tokio::test]
+	async fn test_tunnel_frames_are_sealed() {
+		let key = [42u8; 32];
+		let remote_identity = Identity::new().to_remote_identity();
+
+		let (stream, mut wire) = duplex(1024);
+		let mut sender = Tunnel::new(stream, remote_identity.clone(), key, [0u8; 32]);
+		sender.write_all(b"Spacedrive").await.unwrap();
+		sender.flush().await.unwrap();
+
+		let mut frame = vec![0u8; LEN_PREFIX_LEN + 10 + TAG_LEN];
+		wire.read_exact(&mut frame).await.unwrap();
+		assert!(!frame.windows(10).any(|window| window == b"Spacedrive"));
+
+		// An untouched frame opens fine
+		let (stream, mut wire) = duplex(1024);
+		let mut receiver = Tunnel::new(stream, remote_identity.clone(), [0u8; 32], key);
+		wire.write_all(&frame).await.unwrap();
+
+		let mut received = [0u8; 10];
+		receiver.read_exact(&mut received).await.unwrap();
+		assert_eq!(&received, b"Spacedrive");
+
+		// But a single flipped bit is caught
+		frame[LEN_PREFIX_LEN] ^= 1;
+
+		let (stream, mut wire) = duplex(1024);
+		let mut receiver = Tunnel::new(stream, remote_identity, [0u8; 32], key);
+		wire.write_all(&frame).await.unwrap();
+
+		assert_eq!(
+			receiver.read(&mut received).await.unwrap_err().kind(),
+			io::ErrorKind::InvalidData
+		);
+	}
+}
@@ -149,7 +149,7 @@ pub fn r#enum(models: Vec<ModelWithSyncType>) -> TokenStream {
 							panic!("item and group not found!");
 					};
 
-					let id = prisma::tag_on_object::#compound_id(item.id, group.id);
+					let id = prisma::#model_name_snake::#compound_id(item.id, group.id);
 
 					match data {
 						sd_sync::RelationOperationData::Create => {
@@ -1,33 +1,56 @@
-import { useLibraryMutation, useZodForm } from '@sd/client';
-import { CheckBox, Dialog, Tooltip, useDialog, UseDialogProps } from '@sd/ui';
+import { useBridgeQuery, useLibraryMutation, useZodForm } from '@sd/client';
+import { CheckBox, Dialog, Tooltip, useDialog, UseDialogProps, z } from '@sd/ui';
 
 interface Props extends UseDialogProps {
 	locationId: number;
 	pathIds: number[];
 }
 
+const schema = z.object({
+	permanently: z.boolean()
+});
+
 export default (props: Props) => {
 	const deleteFile = useLibraryMutation('files.deleteFiles');
+	// Nodes without a trash can only delete files forever
+	const nodeState = useBridgeQuery(['nodeState']);
+	const trashAvailable = nodeState.data?.trash_available ?? false;
 
-	const form = useZodForm();
+	const form = useZodForm({
+		schema,
+		defaultValues: {
+			permanently: false
+		}
+	});
 
 	return (
 		<Dialog
 			form={form}
-			onSubmit={form.handleSubmit(() =>
+			onSubmit={form.handleSubmit((data) =>
 				deleteFile.mutateAsync({
 					location_id: props.locationId,
-					file_path_ids: props.pathIds
+					file_path_ids: props.pathIds,
+					to_trash: trashAvailable && !data.permanently
 				})
 			)}
 			dialog={useDialog(props)}
 			title="Delete a file"
-			description="Warning: This will delete your file forever, we don't have a trash can yet..."
+			description={
+				trashAvailable
+					? 'The file will be moved to the trash, unless you delete it forever.'
+					: 'The file will be deleted forever, this device has no trash.'
+			}
 			loading={deleteFile.isLoading}
 			ctaLabel="Delete"
 			ctaDanger
 			className="w-[200px]"
 		>
+			{trashAvailable && (
+				<div className="flex items-center pt-2">
+					<CheckBox className="!mt-0" {...form.register('permanently')} />
+					<p className="text-sm text-ink-dull">Delete forever</p>
+				</div>
+			)}
 			<Tooltip label="Coming soon">
 				<div className="flex items-center pt-2 opacity-50">
 					<CheckBox disabled className="!mt-0" />
@@ -280,6 +280,7 @@ export const RenameLocationTextBox = (props: Omit<Props, 'renameHandler'>) => {
 				generate_preview_media: null,
 				sync_preview_media: null,
 				hidden: null,
+				index_content: null,
 				indexer_rules_ids: []
 			});
 		} catch (e) {
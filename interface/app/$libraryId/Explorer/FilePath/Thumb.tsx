@@ -21,7 +21,7 @@ import { usePlatform } from '~/util/Platform';
 
 import { useExplorerContext } from '../Context';
 import { getExplorerStore } from '../store';
-import { useExplorerItemData } from '../util';
+import { isArchiveMember, useExplorerItemData } from '../util';
 import LayeredFileIcon from './LayeredFileIcon';
 import classes from './Thumb.module.scss';
 
@@ -58,6 +58,12 @@ export const FileThumb = memo((props: ThumbProps) => {
 	const { parent } = useExplorerContext();
 	const { library } = useLibraryContext();
 
+	// Archive members have no thumbnails, so we show images straight from the archive
+	const archiveMemberPath =
+		props.data.type === 'NonIndexedPath' && isArchiveMember(props.data.item)
+			? props.data.item.path
+			: null;
+
 	const [src, setSrc] = useState<string>();
 	const [loaded, setLoaded] = useState<boolean>(false);
 	const [thumbType, setThumbType] = useState(ThumbType.Icon);
@@ -87,14 +93,14 @@ export const FileThumb = memo((props: ThumbProps) => {
 		setSrc(undefined);
 		setLoaded(false);
 
-		if (props.loadOriginal) {
+		if (props.loadOriginal || (archiveMemberPath && itemData.kind === 'Image')) {
 			setThumbType(ThumbType.Original);
 		} else if (itemData.hasLocalThumbnail) {
 			setThumbType(ThumbType.Thumbnail);
 		} else {
 			setThumbType(ThumbType.Icon);
 		}
-	}, [props.loadOriginal, itemData]);
+	}, [props.loadOriginal, itemData, archiveMemberPath]);
 
 	useEffect(() => {
 		const locationId =
@@ -109,6 +115,11 @@ export const FileThumb = memo((props: ThumbProps) => {
 					(itemData.extension !== 'pdf' || pdfViewerEnabled())
 				) {
 					setSrc(platform.getFileUrl(library.uuid, locationId, filePath.id));
+				} else if (
+					archiveMemberPath &&
+					(itemData.extension !== 'pdf' || pdfViewerEnabled())
+				) {
+					setSrc(platform.getArchiveMemberUrl(library.uuid, archiveMemberPath));
 				} else {
 					setThumbType(ThumbType.Thumbnail);
 				}
@@ -133,7 +144,17 @@ export const FileThumb = memo((props: ThumbProps) => {
 				);
 				break;
 		}
-	}, [props.data.item, filePath, isDark, library.uuid, itemData, platform, thumbType, parent]);
+	}, [
+		props.data.item,
+		filePath,
+		isDark,
+		library.uuid,
+		itemData,
+		platform,
+		thumbType,
+		parent,
+		archiveMemberPath
+	]);
 
 	return (
 		<div
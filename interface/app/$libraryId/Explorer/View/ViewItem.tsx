@@ -15,7 +15,7 @@ import { usePlatform } from '~/util/Platform';
 
 import { useExplorerContext } from '../Context';
 import { getQuickPreviewStore } from '../QuickPreview/store';
-import { uniqueId } from '../util';
+import { isBrowsableArchive, uniqueId } from '../util';
 import { useExplorerViewContext } from '../ViewContext';
 
 export const useViewItemDoubleClick = () => {
@@ -130,7 +130,11 @@ export const useViewItemDoubleClick = () => {
 				const [non_indexed] = items.non_indexed;
 				if (non_indexed) {
 					navigate({
-						search: createSearchParams({ path: non_indexed.path }).toString()
+						search: createSearchParams({
+							path: isBrowsableArchive(non_indexed)
+								? `${non_indexed.path}!/`
+								: non_indexed.path
+						}).toString()
 					});
 					return;
 				}
@@ -1,5 +1,5 @@
 import { useMemo } from 'react';
-import { getExplorerItemData, type ExplorerItem } from '@sd/client';
+import { getExplorerItemData, type ExplorerItem, type NonIndexedPathItem } from '@sd/client';
 import { ExplorerParamsSchema } from '~/app/route-schemas';
 import { useZodSearchParams } from '~/hooks';
 
@@ -28,6 +28,20 @@ export function useExplorerItemData(explorerItem: ExplorerItem) {
 	}, [explorerItem, newThumbnail]);
 }
 
+// Keep in sync with `ArchiveFormat::from_file_name` and `ARCHIVE_PATH_SEPARATOR` in core
+const ARCHIVE_EXTENSIONS = /\.(zip|tar|tar\.gz|tgz|tar\.zst|tzst)$/i;
+const ARCHIVE_MEMBER_PATH = /\.(zip|tar|tar\.gz|tgz|tar\.zst|tzst)!\//i;
+
+/** Whether a non indexed path lives inside an archive, see `getArchiveMemberUrl` */
+export const isArchiveMember = (item: NonIndexedPathItem) => ARCHIVE_MEMBER_PATH.test(item.path);
+
+/**
+ * Whether a non indexed path is an archive that can be browsed like a directory,
+ * archives nested inside of other archives can't be browsed without extracting them
+ */
+export const isBrowsableArchive = (item: NonIndexedPathItem) =>
+	!item.is_dir && !isArchiveMember(item) && ARCHIVE_EXTENSIONS.test(item.path);
+
 export const pubIdToString = (pub_id: number[]) =>
 	pub_id.map((b) => b.toString(16).padStart(2, '0')).join('');
 
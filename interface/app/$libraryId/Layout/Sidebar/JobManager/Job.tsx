@@ -1,10 +1,13 @@
 import {
+	Archive,
 	Copy,
 	Fingerprint,
 	Folder,
 	Icon,
 	Image,
 	Info,
+	Lock,
+	LockOpen,
 	Scissors,
 	Trash
 } from '@phosphor-icons/react';
@@ -29,6 +32,11 @@ const JobIcon: Record<string, Icon> = {
 	file_copier: Copy,
 	file_deleter: Trash,
 	file_cutter: Scissors,
+	file_archiver: Archive,
+	file_extractor: Archive,
+	file_converter: Image,
+	file_encryptor: Lock,
+	file_decryptor: LockOpen,
 	object_validator: Fingerprint
 };
 
@@ -5,7 +5,7 @@ import Skeleton from 'react-loading-skeleton';
 import 'react-loading-skeleton/dist/skeleton.css';
 
 import { useEffect, useState } from 'react';
-import { byteSize, Statistics, useLibraryContext, useLibraryQuery } from '@sd/client';
+import { byteSize, LibraryStatistics, useLibraryContext, useLibraryQuery } from '@sd/client';
 import { Tooltip } from '@sd/ui';
 import { useCounter } from '~/hooks';
 
@@ -16,13 +16,13 @@ interface StatItemProps {
 	info?: string;
 }
 
-const StatItemNames: Partial<Record<keyof Statistics, string>> = {
+const StatItemNames: Partial<Record<keyof LibraryStatistics, string>> = {
 	total_bytes_capacity: 'Total capacity',
 	preview_media_bytes: 'Preview media',
 	library_db_size: 'Index size',
 	total_bytes_free: 'Free space'
 };
-const StatDescriptions: Partial<Record<keyof Statistics, string>> = {
+const StatDescriptions: Partial<Record<keyof LibraryStatistics, string>> = {
 	total_bytes_capacity:
 		'The total capacity of all nodes connected to the library. May show incorrect values during alpha.',
 	preview_media_bytes: 'The total size of all preview media files, such as thumbnails.',
@@ -31,8 +31,6 @@ const StatDescriptions: Partial<Record<keyof Statistics, string>> = {
 };
 
 const EMPTY_STATISTICS = {
-	id: 0,
-	date_captured: '',
 	total_bytes_capacity: '0',
 	preview_media_bytes: '0',
 	library_db_size: '0',
@@ -125,10 +123,10 @@ export default () => {
 					return (
 						<StatItem
 							key={`${library.uuid} ${key}`}
-							title={StatItemNames[key as keyof Statistics]!}
+							title={StatItemNames[key as keyof LibraryStatistics]!}
 							bytes={BigInt(value)}
 							isLoading={stats.isLoading}
-							info={StatDescriptions[key as keyof Statistics]}
+							info={StatDescriptions[key as keyof LibraryStatistics]}
 						/>
 					);
 				})}
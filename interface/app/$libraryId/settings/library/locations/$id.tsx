@@ -31,6 +31,7 @@ const schema = z.object({
 	name: z.string().nullable(),
 	path: z.string().min(1).nullable(),
 	hidden: z.boolean().nullable(),
+	indexContent: z.boolean().nullable(),
 	indexerRulesIds: z.array(z.number()),
 	locationType: z.string(),
 	syncPreviewMedia: z.boolean().nullable(),
@@ -64,6 +65,7 @@ const EditLocationForm = () => {
 			name: locationData.data?.name ?? '',
 			path: locationData.data?.path ?? '',
 			hidden: locationData.data?.hidden ?? false,
+			indexContent: locationData.data?.index_content ?? false,
 			syncPreviewMedia: locationData.data?.sync_preview_media ?? false,
 			generatePreviewMedia: locationData.data?.generate_preview_media ?? false
 		}
@@ -85,6 +87,7 @@ const EditLocationForm = () => {
 			path: data.path,
 			name: data.name,
 			hidden: data.hidden,
+			index_content: data.indexContent,
 			indexer_rules_ids: data.indexerRulesIds,
 			sync_preview_media: data.syncPreviewMedia,
 			generate_preview_media: data.generatePreviewMedia
@@ -182,6 +185,15 @@ const EditLocationForm = () => {
 						</Label>
 						<SwitchField {...form.register('hidden')} size="sm" />
 					</ToggleSection>
+					<ToggleSection>
+						<Label className="grow">
+							Index the contents of text files{' '}
+							<Tooltip label="Allows files in this location to be found by what is written inside them. Extracted text is stored in the library database.">
+								<Info className="inline" />
+							</Tooltip>
+						</Label>
+						<SwitchField {...form.register('indexContent')} size="sm" />
+					</ToggleSection>
 				</div>
 				<Divider />
 				<Controller
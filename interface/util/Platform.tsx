@@ -8,6 +8,9 @@ export type Platform = {
 	platform: 'web' | 'tauri'; // This represents the specific platform implementation
 	getThumbnailUrlByThumbKey: (thumbKey: string[]) => string;
 	getFileUrl: (libraryId: string, locationLocalId: number, filePathId: number) => string;
+	// Serves a file from inside an archive, given its virtual path (eg. `/x/foo.zip!/docs/readme.md`).
+	// The archive must be indexed in one of the library's locations.
+	getArchiveMemberUrl: (libraryId: string, path: string) => string;
 	openLink: (url: string) => void;
 	// Tauri patches `window.confirm` to return `Promise` not `bool`
 	confirm(msg: string, cb: (result: boolean) => void): void;
@@ -3,6 +3,10 @@
 
 export type Procedures = {
     queries: 
+        { key: "albums.get", input: LibraryArgs<number>, result: Album | null } | 
+        { key: "albums.getForObject", input: LibraryArgs<number>, result: Album[] } | 
+        { key: "albums.getObjectIds", input: LibraryArgs<number>, result: number[] } | 
+        { key: "albums.list", input: LibraryArgs<AlbumListArgs>, result: AlbumWithCover[] } | 
         { key: "auth.me", input: never, result: { id: string; email: string } } | 
         { key: "backups.getAll", input: never, result: GetAll } | 
         { key: "buildInfo", input: never, result: BuildInfo } | 
@@ -11,11 +15,26 @@ export type Procedures = {
         { key: "files.getEphemeralMediaData", input: string, result: MediaMetadata | null } | 
         { key: "files.getMediaData", input: LibraryArgs<number>, result: MediaMetadata } | 
         { key: "files.getPath", input: LibraryArgs<number>, result: string | null } | 
+        { key: "files.pendingConflicts", input: LibraryArgs<null>, result: FileConflictEvent[] } | 
         { key: "invalidation.test-invalidate", input: never, result: number } | 
         { key: "jobs.isActive", input: LibraryArgs<null>, result: boolean } | 
         { key: "jobs.reports", input: LibraryArgs<null>, result: JobGroup[] } | 
+        { key: "jobs.schedules.list", input: LibraryArgs<null>, result: JobSchedule[] } | 
+        { key: "keys.getDefault", input: LibraryArgs<null>, result: string | null } | 
+        { key: "keys.getSecretKey", input: LibraryArgs<null>, result: string | null } | 
+        { key: "keys.isSetup", input: LibraryArgs<null>, result: boolean } | 
+        { key: "keys.isUnlocked", input: LibraryArgs<null>, result: boolean } | 
+        { key: "keys.isUnlocking", input: LibraryArgs<null>, result: boolean | null } | 
+        { key: "keys.list", input: LibraryArgs<null>, result: StoredKey[] } | 
+        { key: "keys.listMounted", input: LibraryArgs<null>, result: string[] } | 
+        { key: "labels.count", input: LibraryArgs<null>, result: { [key: number]: number } } | 
+        { key: "labels.get", input: LibraryArgs<number>, result: Label | null } | 
+        { key: "labels.getForObject", input: LibraryArgs<number>, result: LabelForObject[] } | 
+        { key: "labels.list", input: LibraryArgs<null>, result: Label[] } | 
         { key: "library.list", input: never, result: LibraryConfigWrapped[] } | 
-        { key: "library.statistics", input: LibraryArgs<null>, result: Statistics } | 
+        { key: "library.statistics", input: LibraryArgs<null>, result: LibraryStatistics } | 
+        { key: "library.statisticsBreakdown", input: LibraryArgs<number | null>, result: StatisticsBreakdown | null } | 
+        { key: "library.statisticsHistory", input: LibraryArgs<StatisticsHistoryArgs>, result: StatisticsSummary[] } | 
         { key: "locations.get", input: LibraryArgs<number>, result: Location | null } | 
         { key: "locations.getWithRules", input: LibraryArgs<number>, result: LocationWithIndexerRules | null } | 
         { key: "locations.indexer_rules.get", input: LibraryArgs<number>, result: IndexerRule } | 
@@ -29,31 +48,60 @@ export type Procedures = {
         { key: "notifications.get", input: never, result: Notification[] } | 
         { key: "p2p.nlmState", input: never, result: { [key: string]: LibraryData } } | 
         { key: "preferences.get", input: LibraryArgs<null>, result: LibraryPreferences } | 
+        { key: "search.content", input: LibraryArgs<ContentSearchArgs>, result: SearchData<ContentSearchItem> } | 
+        { key: "search.duplicates", input: LibraryArgs<null>, result: DuplicatesData } | 
         { key: "search.ephemeralPaths", input: LibraryArgs<EphemeralPathSearchArgs>, result: NonIndexedFileSystemEntries } | 
         { key: "search.objects", input: LibraryArgs<ObjectSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.objectsCount", input: LibraryArgs<{ filter?: ObjectFilterArgs }>, result: number } | 
         { key: "search.paths", input: LibraryArgs<FilePathSearchArgs>, result: SearchData<ExplorerItem> } | 
         { key: "search.pathsCount", input: LibraryArgs<{ filter?: FilePathFilterArgs }>, result: number } | 
+        { key: "search.saved.get", input: LibraryArgs<number>, result: SavedSearch | null } | 
+        { key: "search.saved.list", input: LibraryArgs<null>, result: SavedSearch[] } | 
+        { key: "search.saved.run", input: LibraryArgs<SavedSearchRunArgs>, result: SearchData<ExplorerItem> } | 
+        { key: "search.similarImageGroups", input: LibraryArgs<{ threshold?: number | null }>, result: ExplorerItem[][] } | 
+        { key: "search.similarImages", input: LibraryArgs<SimilarImagesArgs>, result: SimilarImage[] } | 
+        { key: "spaces.content", input: LibraryArgs<number>, result: SpaceItem[] } | 
+        { key: "spaces.get", input: LibraryArgs<number>, result: Space | null } | 
+        { key: "spaces.list", input: LibraryArgs<null>, result: Space[] } | 
         { key: "sync.messages", input: LibraryArgs<null>, result: CRDTOperation[] } | 
         { key: "tags.get", input: LibraryArgs<number>, result: Tag | null } | 
         { key: "tags.getForObject", input: LibraryArgs<number>, result: Tag[] } | 
         { key: "tags.getWithObjects", input: LibraryArgs<number[]>, result: { [key: number]: number[] } } | 
         { key: "tags.list", input: LibraryArgs<null>, result: Tag[] } | 
-        { key: "volumes.list", input: never, result: Volume[] },
+        { key: "volumes.list", input: never, result: Volume[] } | 
+        { key: "volumes.tracked", input: LibraryArgs<null>, result: TrackedVolume[] },
     mutations: 
+        { key: "albums.addObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.create", input: LibraryArgs<AlbumCreateArgs>, result: Album } | 
+        { key: "albums.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "albums.hide", input: LibraryArgs<AlbumHideArgs>, result: null } | 
+        { key: "albums.removeObjects", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
+        { key: "albums.rename", input: LibraryArgs<AlbumRenameArgs>, result: null } | 
+        { key: "albums.reorder", input: LibraryArgs<AlbumObjectsArgs>, result: null } | 
         { key: "auth.logout", input: never, result: null } | 
         { key: "backups.backup", input: LibraryArgs<null>, result: string } | 
         { key: "backups.delete", input: string, result: null } | 
         { key: "backups.restore", input: string, result: null } | 
+        { key: "files.archiveFiles", input: LibraryArgs<FileArchiverJobInit>, result: null } | 
+        { key: "files.convertImages", input: LibraryArgs<FileConverterJobInit>, result: null } | 
         { key: "files.copyFiles", input: LibraryArgs<FileCopierJobInit>, result: null } | 
         { key: "files.cutFiles", input: LibraryArgs<FileCutterJobInit>, result: null } | 
+        { key: "files.decryptFiles", input: LibraryArgs<FileDecryptorJobInit>, result: null } | 
+        { key: "files.deduplicateFiles", input: LibraryArgs<DeduplicatorJobInit>, result: null } | 
         { key: "files.deleteFiles", input: LibraryArgs<FileDeleterJobInit>, result: null } | 
         { key: "files.duplicateFiles", input: LibraryArgs<FileCopierJobInit>, result: null } | 
+        { key: "files.emptyTrash", input: LibraryArgs<EmptyTrashJobInit>, result: null } | 
+        { key: "files.encryptFiles", input: LibraryArgs<FileEncryptorJobInit>, result: null } | 
         { key: "files.eraseFiles", input: LibraryArgs<FileEraserJobInit>, result: null } | 
+        { key: "files.extractFiles", input: LibraryArgs<FileExtractorJobInit>, result: null } | 
+        { key: "files.redo", input: LibraryArgs<null>, result: null } | 
         { key: "files.removeAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "files.renameFile", input: LibraryArgs<RenameFileArgs>, result: null } | 
+        { key: "files.resolveConflict", input: LibraryArgs<ResolveConflictArgs>, result: null } | 
+        { key: "files.restoreFromTrash", input: LibraryArgs<number[]>, result: null } | 
         { key: "files.setFavorite", input: LibraryArgs<SetFavoriteArgs>, result: null } | 
         { key: "files.setNote", input: LibraryArgs<SetNoteArgs>, result: null } | 
+        { key: "files.undo", input: LibraryArgs<null>, result: null } | 
         { key: "files.updateAccessTime", input: LibraryArgs<number[]>, result: null } | 
         { key: "invalidation.test-invalidate-mutation", input: LibraryArgs<null>, result: null } | 
         { key: "jobs.cancel", input: LibraryArgs<string>, result: null } | 
@@ -64,6 +112,21 @@ export type Procedures = {
         { key: "jobs.objectValidator", input: LibraryArgs<ObjectValidatorArgs>, result: null } | 
         { key: "jobs.pause", input: LibraryArgs<string>, result: null } | 
         { key: "jobs.resume", input: LibraryArgs<string>, result: null } | 
+        { key: "jobs.schedules.create", input: LibraryArgs<JobScheduleCreateArgs>, result: JobSchedule } | 
+        { key: "jobs.schedules.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "jobs.schedules.update", input: LibraryArgs<JobScheduleUpdateArgs>, result: JobSchedule } | 
+        { key: "keys.add", input: LibraryArgs<KeyAddArgs>, result: string } | 
+        { key: "keys.backupKeystore", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.lock", input: LibraryArgs<null>, result: null } | 
+        { key: "keys.mount", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.restoreKeystore", input: LibraryArgs<RestoreBackupArgs>, result: number } | 
+        { key: "keys.setDefault", input: LibraryArgs<string>, result: null } | 
+        { key: "keys.setup", input: LibraryArgs<OnboardingConfig>, result: null } | 
+        { key: "keys.unlock", input: LibraryArgs<UnlockKeyManagerArgs>, result: null } | 
+        { key: "keys.unmount", input: LibraryArgs<string>, result: null } | 
+        { key: "labels.assign", input: LibraryArgs<LabelAssignArgs>, result: null } | 
+        { key: "labels.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "labels.unassign", input: LibraryArgs<LabelUnassignArgs>, result: null } | 
         { key: "library.create", input: CreateLibraryArgs, result: LibraryConfigWrapped } | 
         { key: "library.delete", input: string, result: null } | 
         { key: "library.edit", input: EditLibraryArgs, result: null } | 
@@ -85,6 +148,16 @@ export type Procedures = {
         { key: "p2p.pairingResponse", input: [number, PairingDecision], result: null } | 
         { key: "p2p.spacedrop", input: SpacedropArgs, result: null } | 
         { key: "preferences.update", input: LibraryArgs<LibraryPreferences>, result: null } | 
+        { key: "search.saved.create", input: LibraryArgs<SavedSearchCreateArgs>, result: SavedSearch } | 
+        { key: "search.saved.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "search.saved.update", input: LibraryArgs<SavedSearchUpdateArgs>, result: null } | 
+        { key: "spaces.addContent", input: LibraryArgs<SpaceAddContentArgs>, result: number } | 
+        { key: "spaces.addObjects", input: LibraryArgs<SpaceAddObjectsArgs>, result: null } | 
+        { key: "spaces.create", input: LibraryArgs<SpaceCreateArgs>, result: Space } | 
+        { key: "spaces.delete", input: LibraryArgs<number>, result: null } | 
+        { key: "spaces.moveItems", input: LibraryArgs<SpaceMoveItemsArgs>, result: null } | 
+        { key: "spaces.removeItems", input: LibraryArgs<SpaceRemoveItemsArgs>, result: null } | 
+        { key: "spaces.update", input: LibraryArgs<SpaceUpdateArgs>, result: null } | 
         { key: "tags.assign", input: LibraryArgs<TagAssignArgs>, result: null } | 
         { key: "tags.create", input: LibraryArgs<TagCreateArgs>, result: Tag } | 
         { key: "tags.delete", input: LibraryArgs<number>, result: null } | 
@@ -92,6 +165,7 @@ export type Procedures = {
         { key: "toggleFeatureFlag", input: BackendFeature, result: null },
     subscriptions: 
         { key: "auth.loginSession", input: never, result: Response } | 
+        { key: "files.conflicts", input: LibraryArgs<null>, result: FileConflictEvent } | 
         { key: "invalidation.listen", input: never, result: InvalidateOperationEvent[] } | 
         { key: "jobs.newThumbnail", input: LibraryArgs<null>, result: string[] } | 
         { key: "jobs.progress", input: LibraryArgs<null>, result: JobProgressEvent } | 
@@ -99,10 +173,32 @@ export type Procedures = {
         { key: "locations.quickRescan", input: LibraryArgs<LightScanArgs>, result: null } | 
         { key: "notifications.listen", input: never, result: Notification } | 
         { key: "p2p.events", input: never, result: P2PEvent } | 
-        { key: "sync.newMessage", input: LibraryArgs<null>, result: null }
+        { key: "sync.newMessage", input: LibraryArgs<null>, result: null } | 
+        { key: "volumes.events", input: LibraryArgs<null>, result: VolumeEvent }
 };
 
-export type AudioMetadata = { duration: number | null; audio_codec: string | null }
+export type Album = { id: number; pub_id: number[]; name: string | null; is_hidden: boolean | null; date_created: string | null; date_modified: string | null }
+
+export type AlbumCreateArgs = { name: string }
+
+export type AlbumHideArgs = { id: number; hidden: boolean }
+
+export type AlbumListArgs = { includeHidden?: boolean }
+
+export type AlbumObjectsArgs = { album_id: number; object_ids: number[] }
+
+export type AlbumRenameArgs = { id: number; name: string }
+
+export type AlbumWithCover = { album: Album; thumbnail_key: string[] | null }
+
+/**
+ * These are all possible algorithms that can be used for encryption and decryption
+ */
+export type Algorithm = "XChaCha20Poly1305" | "Aes256Gcm"
+
+export type ArchiveFormat = "Zip" | "Tar" | "TarGz" | "TarZst"
+
+export type AudioMetadata = { title: string | null; artist: string | null; album: string | null; track: number | null; year: number | null; genre: string | null; duration: number | null; bitrate: number | null; audio_codec: string | null; has_cover_art: boolean }
 
 /**
  * All of the feature flags provided by the core itself. The frontend has it's own set of feature flags!
@@ -130,18 +226,61 @@ export type ColorProfile = "Normal" | "Custom" | "HDRNoOriginal" | "HDRWithOrigi
 
 export type Composite = "Unknown" | "False" | "General" | "Live"
 
+export type ConflictDecision = { resolution: ConflictResolution; apply_to_all: boolean }
+
+export type ConflictPolicy = "Skip" | "Overwrite" | "KeepBoth" | "KeepNewer" | "KeepLarger" | "Ask"
+
+export type ConflictResolution = "Skip" | "Overwrite" | "KeepBoth"
+
+export type ConflictingItem = { path: string; is_dir: boolean; size: string; date_modified: string | null }
+
+export type ContentSearchArgs = { query: string; take?: number | null; filter?: FilePathFilterArgs }
+
+export type ContentSearchItem = { item: ExplorerItem; snippet: string; score: number }
+
+/**
+ * Formats that images can be converted to. Anything [`format_image`] can load
+ * (including HEIF and SVG) can be converted into one of these.
+ */
+export type ConvertImageFormat = "Png" | "Jpeg" | "Webp"
+
 export type CreateLibraryArgs = { name: LibraryName }
 
 export type CursorOrderItem<T> = { order: SortOrder; data: T }
 
+/**
+ * What to do with the extra copies of a file
+ */
+export type DeduplicateAction = "Delete" | "Hardlink" | { Move: { target_location_id: number; target_location_relative_directory_path: string } }
+
+export type DeduplicatorJobInit = { groups: DuplicateSelection[]; action: DeduplicateAction }
+
 export type Dimensions = { width: number; height: number }
 
 export type DiskType = "SSD" | "HDD" | "Removable"
 
 export type DoubleClickAction = "openFile" | "quickPreview"
 
+/**
+ * Copies of the same file, found in one or more locations
+ */
+export type DuplicateGroup = { object_id: number; verified: boolean; wasted_bytes: string; items: ExplorerItem[] }
+
+export type DuplicateSelection = { keep: number; duplicates: number[] }
+
+export type DuplicatesData = { groups: DuplicateGroup[]; total_wasted_bytes: string }
+
 export type EditLibraryArgs = { id: string; name: LibraryName | null; description: MaybeUndefined<string> }
 
+export type EmptyTrashJobInit = { location_id?: number | null }
+
+/**
+ * This should be used for passing an encrypted key around.
+ * 
+ * This is always `ENCRYPTED_KEY_LEN` (which is `KEY_LEM` + `AEAD_TAG_LEN`)
+ */
+export type EncryptedKey = number[]
+
 export type EphemeralPathOrder = { field: "name"; value: SortOrder } | { field: "sizeInBytes"; value: SortOrder } | { field: "dateCreated"; value: SortOrder } | { field: "dateModified"; value: SortOrder }
 
 export type EphemeralPathSearchArgs = { path: string; withHiddenFiles: boolean; order?: EphemeralPathOrder | null }
@@ -159,21 +298,35 @@ export type ExplorerLayout = "grid" | "list" | "media"
 
 export type ExplorerSettings<TOrder> = { layoutMode: ExplorerLayout | null; gridItemSize: number | null; mediaColumns: number | null; mediaAspectSquare: boolean | null; openOnDoubleClick: DoubleClickAction | null; showBytesInGridView: boolean | null; colVisibility: { [key: string]: boolean } | null; colSizes: { [key: string]: number } | null; order?: TOrder | null; showHiddenFiles?: boolean }
 
-export type FileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string; target_file_name_suffix: string | null }
+export type FileArchiverJobInit = { source_location_id: number; sources_file_path_ids: number[]; target_location_id: number; target_location_relative_directory_path: string; archive_name: string; format: ArchiveFormat }
+
+export type FileConflictEvent = { id: string; library_id: string; job_id: string; source: ConflictingItem; target: ConflictingItem }
+
+export type FileConverterJobInit = { source_location_id: number; sources_file_path_ids: number[]; format: ConvertImageFormat; resize?: ImageResize | null; quality?: number | null; target?: FileConverterTarget | null; conflict_policy?: ConflictPolicy }
+
+export type FileConverterTarget = { location_id: number; location_relative_directory_path: string }
 
-export type FileCutterJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string }
+export type FileCopierJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string; target_file_name_suffix: string | null; conflict_policy?: ConflictPolicy; verify?: boolean; journal_replay?: JournalReplay | null }
 
-export type FileDeleterJobInit = { location_id: number; file_path_ids: number[] }
+export type FileCutterJobInit = { source_location_id: number; target_location_id: number; sources_file_path_ids: number[]; target_location_relative_directory_path: string; conflict_policy?: ConflictPolicy; journal_replay?: JournalReplay | null }
+
+export type FileDecryptorJobInit = { location_id: number; file_path_ids: number[]; key_uuid: string; conflict_policy?: ConflictPolicy }
+
+export type FileDeleterJobInit = { location_id: number; file_path_ids: number[]; to_trash: boolean; journal_replay?: JournalReplay | null }
+
+export type FileEncryptorJobInit = { location_id: number; file_path_ids: number[]; algorithm: Algorithm; hashing_algorithm: HashingAlgorithm; key_uuid: string; metadata: boolean; preview_media: boolean; conflict_policy?: ConflictPolicy }
 
 export type FileEraserJobInit = { location_id: number; file_path_ids: number[]; passes: string }
 
-export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null }
+export type FileExtractorJobInit = { source_location_id: number; sources_file_path_ids: number[]; target_location_id: number; target_location_relative_directory_path: string; conflict_policy?: ConflictPolicy }
+
+export type FilePath = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; trashed_at: string | null; trash_path: string | null; trash_location_id: number | null }
 
 export type FilePathCursor = { isDir: boolean; variant: FilePathCursorVariant }
 
 export type FilePathCursorVariant = "none" | { name: CursorOrderItem<string> } | { sizeInBytes: SortOrder } | { dateCreated: CursorOrderItem<string> } | { dateModified: CursorOrderItem<string> } | { dateIndexed: CursorOrderItem<string> } | { object: FilePathObjectCursor }
 
-export type FilePathFilterArgs = { locationId?: number | null; search?: string | null; extension?: string | null; createdAt?: OptionalRange<string>; path?: string | null; object?: ObjectFilterArgs | null; hidden?: boolean | null }
+export type FilePathFilterArgs = { locationId?: number | null; search?: string | null; extension?: string | null; createdAt?: OptionalRange<string>; path?: string | null; object?: ObjectFilterArgs | null; hidden?: boolean | null; trashed?: boolean | null; query?: string | null }
 
 export type FilePathObjectCursor = { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
@@ -181,7 +334,7 @@ export type FilePathOrder = { field: "name"; value: SortOrder } | { field: "size
 
 export type FilePathSearchArgs = { take?: number | null; orderAndPagination?: OrderAndPagination<number, FilePathOrder, FilePathCursor> | null; filter?: FilePathFilterArgs; groupDirectories?: boolean }
 
-export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; object: Object | null }
+export type FilePathWithObject = { id: number; pub_id: number[]; is_dir: boolean | null; cas_id: string | null; integrity_checksum: string | null; location_id: number | null; materialized_path: string | null; name: string | null; extension: string | null; hidden: boolean | null; size_in_bytes: string | null; size_in_bytes_bytes: number[] | null; inode: number[] | null; object_id: number | null; key_id: number | null; date_created: string | null; date_modified: string | null; date_indexed: string | null; trashed_at: string | null; trash_path: string | null; trash_location_id: number | null; object: Object | null }
 
 export type Flash = { mode: FlashMode; fired: boolean | null; returned: boolean | null; red_eye_reduction: boolean | null }
 
@@ -197,6 +350,11 @@ export type GetAll = { backups: Backup[]; directory: string }
 
 export type GetArgs = { id: number }
 
+/**
+ * This defines all available password hashing algorithms.
+ */
+export type HashingAlgorithm = { name: "Argon2id"; params: Params } | { name: "BalloonBlake3"; params: Params }
+
 export type Header = { id: string; timestamp: string; library_id: string; library_name: string }
 
 export type IdentifyUniqueFilesArgs = { id: number; path: string }
@@ -205,6 +363,11 @@ export type ImageData = { device_make: string | null; device_model: string | nul
 
 export type ImageMetadata = { dimensions: Dimensions; date_taken: MediaTime; location: MediaLocation | null; camera_data: ImageData; artist: string | null; description: string | null; copyright: string | null; exif_version: string | null }
 
+/**
+ * Images larger than these dimensions are downscaled to fit them, keeping their aspect ratio
+ */
+export type ImageResize = { max_width: number; max_height: number }
+
 export type IndexerRule = { id: number; pub_id: number[]; name: string | null; default: boolean | null; rules_per_kind: number[] | null; date_created: string | null; date_modified: string | null }
 
 /**
@@ -229,8 +392,36 @@ export type JobProgressEvent = { id: string; library_id: string; task_count: num
 
 export type JobReport = { id: string; name: string; action: string | null; data: number[] | null; metadata: any | null; is_background: boolean; errors_text: string[]; created_at: string | null; started_at: string | null; completed_at: string | null; parent_id: string | null; status: JobStatus; task_count: number; completed_task_count: number; message: string; estimated_completion: string }
 
+export type JobSchedule = { id: number; kind: ScheduledJobKind; location_id: number | null; recurrence: Recurrence; enabled: boolean; catch_up: boolean; last_run: string | null; next_run: string }
+
+export type JobScheduleCreateArgs = { kind: ScheduledJobKind; location_id: number | null; recurrence: Recurrence; catch_up: boolean }
+
+export type JobScheduleUpdateArgs = { id: number; recurrence: Recurrence | null; enabled: boolean | null; catch_up: boolean | null }
+
 export type JobStatus = "Queued" | "Running" | "Completed" | "Canceled" | "Failed" | "Paused" | "CompletedWithErrors"
 
+/**
+ * The journaled operation a job is undoing or redoing
+ */
+export type JournalReplay = { Undo: number } | { Redo: number }
+
+export type KeyAddArgs = { algorithm: Algorithm; hashing_algorithm: HashingAlgorithm; key: string; library_sync: boolean; automount: boolean }
+
+export type Label = { id: number; pub_id: number[]; name: string | null; date_created: string; date_modified: string }
+
+export type LabelAssignArgs = { object_ids: number[]; labels: string[]; source: LabelSource }
+
+export type LabelForObject = { label: Label; source: LabelSource | null }
+
+/**
+ * What assigned a label to an object.
+ * 
+ * Unlike tags, labels are never created by hand.
+ */
+export type LabelSource = "exif" | "rule" | "import"
+
+export type LabelUnassignArgs = { object_ids: number[]; label_id: number }
+
 /**
  * Can wrap a query argument to require it to contain a `library_id` and provide helpers for working with libraries.
  */
@@ -249,9 +440,15 @@ export type LibraryName = string
 
 export type LibraryPreferences = { location?: { [key: string]: LocationSettings } }
 
+/**
+ * The statistics of a library as they are right now, their history is recorded by
+ * [`update_library_statistics`]
+ */
+export type LibraryStatistics = { total_object_count: number; library_db_size: string; total_bytes_used: string; total_bytes_capacity: string; total_unique_bytes: string; total_bytes_free: string; preview_media_bytes: string }
+
 export type LightScanArgs = { location_id: number; sub_path: string }
 
-export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null }
+export type Location = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number[] | null; available_capacity: number[] | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; index_content: boolean | null; date_created: string | null; instance_id: number | null; volume_id: number | null }
 
 /**
  * `LocationCreateArgs` is the argument received from the client using `rspc` to create a new location.
@@ -262,6 +459,8 @@ export type LocationCreateArgs = { path: string; dry_run: boolean; indexer_rules
 
 export type LocationSettings = { explorer: ExplorerSettings<FilePathOrder> }
 
+export type LocationStatistics = { location_id: number; name: string | null; storage: StorageStatistics }
+
 /**
  * `LocationUpdateArgs` is the argument received from the client using `rspc` to update a location.
  * It contains the id of the location to be updated, possible a name to change the current location's name
@@ -270,9 +469,9 @@ export type LocationSettings = { explorer: ExplorerSettings<FilePathOrder> }
  * It is important to note that only the indexer rule ids in this vector will be used from now on.
  * Old rules that aren't in this vector will be purged.
  */
-export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; indexer_rules_ids: number[]; path: string | null }
+export type LocationUpdateArgs = { id: number; name: string | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; index_content: boolean | null; indexer_rules_ids: number[]; path: string | null }
 
-export type LocationWithIndexerRules = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number | null; available_capacity: number | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; date_created: string | null; instance_id: number | null; indexer_rules: { indexer_rule: IndexerRule }[] }
+export type LocationWithIndexerRules = { id: number; pub_id: number[]; name: string | null; path: string | null; total_capacity: number[] | null; available_capacity: number[] | null; size_in_bytes: number[] | null; is_archived: boolean | null; generate_preview_media: boolean | null; sync_preview_media: boolean | null; hidden: boolean | null; index_content: boolean | null; date_created: string | null; instance_id: number | null; volume_id: number | null; indexer_rules: { indexer_rule: IndexerRule }[] }
 
 export type MaybeNot<T> = T | { not: T }
 
@@ -289,12 +488,19 @@ export type MediaMetadata = ({ type: "Image" } & ImageMetadata) | ({ type: "Vide
  */
 export type MediaTime = { Naive: string } | { Utc: string } | "Undefined"
 
-export type NodeState = ({ id: string; name: string; p2p_port: number | null; features: BackendFeature[]; p2p_email: string | null; p2p_img_url: string | null }) & { data_path: string }
+export type NodeState = ({ id: string; name: string; p2p_port: number | null; features: BackendFeature[]; p2p_email: string | null; p2p_img_url: string | null }) & { data_path: string; trash_available: boolean }
 
 export type NonIndexedFileSystemEntries = { entries: ExplorerItem[]; errors: Error[] }
 
 export type NonIndexedPathItem = { path: string; name: string; extension: string; kind: number; is_dir: boolean; date_created: string; date_modified: string; size_in_bytes_bytes: number[]; hidden: boolean }
 
+/**
+ * This should be used for providing a nonce to encrypt/decrypt functions.
+ * 
+ * You may also generate a nonce for a given algorithm with `Nonce::generate()`
+ */
+export type Nonce = { XChaCha20Poly1305: number[] } | { Aes256Gcm: number[] }
+
 /**
  * Represents a single notification.
  */
@@ -312,7 +518,7 @@ export type Object = { id: number; pub_id: number[]; kind: number | null; key_id
 
 export type ObjectCursor = "none" | { dateAccessed: CursorOrderItem<string> } | { kind: CursorOrderItem<number> }
 
-export type ObjectFilterArgs = { favorite?: boolean | null; hidden?: ObjectHiddenFilter; dateAccessed?: MaybeNot<string | null> | null; kind?: number[]; tags?: number[]; category?: Category | null }
+export type ObjectFilterArgs = { favorite?: boolean | null; hidden?: ObjectHiddenFilter; dateAccessed?: MaybeNot<string | null> | null; kind?: number[]; tags?: number[]; albums?: number[]; labels?: number[]; category?: Category | null; query?: string | null }
 
 export type ObjectHiddenFilter = "exclude" | "include"
 
@@ -324,6 +530,8 @@ export type ObjectValidatorArgs = { id: number; path: string }
 
 export type ObjectWithFilePaths = { id: number; pub_id: number[]; kind: number | null; key_id: number | null; hidden: boolean | null; favorite: boolean | null; important: boolean | null; note: string | null; date_created: string | null; date_accessed: string | null; file_paths: FilePath[] }
 
+export type OnboardingConfig = { password: string; algorithm: Algorithm; hashing_algorithm: HashingAlgorithm }
+
 /**
  * Represents the operating system which the remote peer is running.
  * This is not used internally and predominantly is designed to be used for display purposes by the embedding application.
@@ -345,12 +553,21 @@ export type PairingDecision = { decision: "accept"; libraryId: string } | { deci
 
 export type PairingStatus = { type: "EstablishingConnection" } | { type: "PairingRequested" } | { type: "LibraryAlreadyExists" } | { type: "PairingDecisionRequest" } | { type: "PairingInProgress"; data: { library_name: string; library_description: string | null } } | { type: "InitialSyncProgress"; data: number } | { type: "PairingComplete"; data: string } | { type: "PairingRejected" }
 
+/**
+ * These parameters define the password-hashing level.
+ *
+ * The greater the parameter, the longer the password will take to hash.
+ */
+export type Params = "Standard" | "Hardened" | "Paranoid"
+
 export type PeerId = string
 
 export type PeerMetadata = { name: string; operating_system: OperatingSystem | null; version: string | null; email: string | null; img_url: string | null }
 
 export type PlusCode = string
 
+export type Recurrence = { type: "Interval"; value: number } | { type: "Cron"; value: string }
+
 export type RelationOperation = { relation_item: any; relation_group: any; relation: string; data: RelationOperationData }
 
 export type RelationOperationData = "c" | { u: { field: string; value: any } } | "d"
@@ -365,12 +582,38 @@ export type RenameOne = { from_file_path_id: number; to: string }
 
 export type RescanArgs = { location_id: number; sub_path: string }
 
+export type ResolveConflictArgs = { id: string; decision: ConflictDecision }
+
 export type Response = { Start: { user_code: string; verification_url: string; verification_url_complete: string } } | "Complete" | "Error"
 
+export type RestoreBackupArgs = { password: string; secret_key: string; path: string }
+
 export type RuleKind = "AcceptFilesByGlob" | "RejectFilesByGlob" | "AcceptIfChildrenDirectoriesArePresent" | "RejectIfChildrenDirectoriesArePresent"
 
+/**
+ * This should be used for passing a salt around.
+ * 
+ * You may also generate a salt with `Salt::generate()`
+ */
+export type Salt = number[]
+
 export type SanitisedNodeConfig = { id: string; name: string; p2p_port: number | null; features: BackendFeature[]; p2p_email: string | null; p2p_img_url: string | null }
 
+export type SavedSearch = { id: number; pubId: number[]; name: string | null; icon: string | null; search: SavedSearchQuery | null; dateCreated: string | null; dateModified: string | null }
+
+export type SavedSearchCreateArgs = { name: string; icon?: string | null; search: SavedSearchQuery }
+
+/**
+ * What a saved search looks for and how its results are ordered
+ */
+export type SavedSearchQuery = { target: "paths"; filter: FilePathFilterArgs; order?: FilePathOrder | null } | { target: "objects"; filter: ObjectFilterArgs; order?: ObjectOrder | null }
+
+export type SavedSearchRunArgs = { id: number; take?: number | null; offset?: number | null }
+
+export type SavedSearchUpdateArgs = { id: number; name?: string | null; icon?: string | null; search?: SavedSearchQuery | null }
+
+export type ScheduledJobKind = "Rescan" | "ObjectValidator" | "RegenerateThumbnails" | "StatisticsSnapshot"
+
 export type SearchData<T> = { cursor: number[] | null; items: T[] }
 
 export type SetFavoriteArgs = { id: number; favorite: boolean }
@@ -381,13 +624,74 @@ export type SharedOperation = { record_id: any; model: string; data: SharedOpera
 
 export type SharedOperationData = "c" | { u: { field: string; value: any } } | "d"
 
+export type SimilarImage = { distance: number; item: ExplorerItem }
+
+export type SimilarImagesArgs = { objectId: number; threshold?: number | null }
+
 export type SingleInvalidateOperationEvent = { key: string; arg: any; result: any | null }
 
 export type SortOrder = "Asc" | "Desc"
 
+export type Space = { id: number; pub_id: number[]; name: string | null; description: string | null; date_created: string | null; date_modified: string | null }
+
+export type SpaceAddContentArgs = { space_id: number; content: SpaceContent; layout: SpaceLayout }
+
+export type SpaceAddObjectsArgs = { space_id: number; objects: SpaceObject[] }
+
+/**
+ * Content of a space that isn't a file.
+ * 
+ * These are stored as objects without any file paths, which only live for as long as they are in a space.
+ */
+export type SpaceContent = { type: "Link"; value: string } | { type: "Note"; value: string }
+
+export type SpaceCreateArgs = { name: string; description?: string | null }
+
+export type SpaceItem = { layout: SpaceLayout; item: ExplorerItem }
+
+/**
+ * Where an item is placed within a space
+ */
+export type SpaceLayout = { x: number; y: number; width: number | null; height: number | null }
+
+export type SpaceMoveItemsArgs = { space_id: number; objects: SpaceObject[] }
+
+export type SpaceObject = { object_id: number; layout: SpaceLayout }
+
+export type SpaceRemoveItemsArgs = { space_id: number; object_ids: number[] }
+
+export type SpaceUpdateArgs = { id: number; name?: string | null; description?: string | null }
+
 export type SpacedropArgs = { peer_id: PeerId; file_path: string[] }
 
-export type Statistics = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_bytes_used: string; total_bytes_capacity: string; total_unique_bytes: string; total_bytes_free: string; preview_media_bytes: string }
+export type StatisticsBreakdown = { kinds: { [key: number]: StorageStatistics }; locations: LocationStatistics[]; volumes: VolumeStatistics[] }
+
+export type StatisticsHistoryArgs = { from?: string | null; to?: string | null }
+
+export type StatisticsSummary = { id: number; date_captured: string; total_object_count: number; library_db_size: string; total_bytes_used: string; total_bytes_capacity: string; total_unique_bytes: string; total_bytes_free: string; preview_media_bytes: string }
+
+/**
+ * Size of a group of files, where `unique_bytes` counts each object only once and
+ * `duplicated_bytes` are taken by the extra copies
+ */
+export type StorageStatistics = { files: number; total_bytes: string; unique_bytes: string; duplicated_bytes: string }
+
+/**
+ * This is a stored key, and can be freely written to the database.
+ * 
+ * It contains no sensitive information that is not encrypted.
+ */
+export type StoredKey = { uuid: string; version: StoredKeyVersion; key_type: StoredKeyType; algorithm: Algorithm; hashing_algorithm: HashingAlgorithm; content_salt: Salt; master_key: EncryptedKey; master_key_nonce: Nonce; key_nonce: Nonce; key: number[]; salt: Salt; memory_only: boolean; automount: boolean }
+
+/**
+ * This denotes the type of key. `Root` keys can be used to unlock the key manager, and `User` keys are ordinary keys.
+ */
+export type StoredKeyType = "User" | "Root"
+
+/**
+ * This denotes the `StoredKey` version.
+ */
+export type StoredKeyVersion = "V1"
 
 export type Tag = { id: number; pub_id: number[]; name: string | null; color: string | null; redundancy_goal: number | null; date_created: string | null; date_modified: string | null }
 
@@ -397,6 +701,22 @@ export type TagCreateArgs = { name: string; color: string }
 
 export type TagUpdateArgs = { id: number; name: string | null; color: string | null }
 
-export type VideoMetadata = { duration: number | null; video_codec: string | null; audio_codec: string | null }
+export type TrackedVolume = { id: number; identifier: string; name: string; mount_point: string; total_bytes_capacity: string; total_bytes_available: string; disk_type: string | null; filesystem: string | null; is_system: boolean; is_mounted: boolean; date_modified: string }
+
+export type UnlockKeyManagerArgs = { password: string; secret_key?: string | null }
+
+export type VideoChapter = { title: string | null; start: number; end: number }
+
+export type VideoMetadata = { duration: number | null; width: number | null; height: number | null; fps: number | null; video_codec: string | null; audio_codec: string | null; streams: VideoStream[]; chapters: VideoChapter[] }
+
+export type VideoStream = { kind: VideoStreamKind; codec: string; language: string | null }
+
+export type VideoStreamKind = "Video" | "Audio" | "Subtitle" | "Attachment" | "Data" | "Unknown"
+
+export type Volume = { name: string; uuid: string | null; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean }
+
+export type VolumeEvent = { library_id: string; volume_id: number; name: string; mount_point: string; kind: VolumeEventKind }
+
+export type VolumeEventKind = "Mounted" | "Unmounted"
 
-export type Volume = { name: string; mount_points: string[]; total_capacity: string; available_capacity: string; disk_type: DiskType; file_system: string | null; is_root_filesystem: boolean }
+export type VolumeStatistics = { name: string; mount_point: string; total_capacity: string; available_capacity: string; storage: StorageStatistics }
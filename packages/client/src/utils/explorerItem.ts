@@ -1,7 +1,7 @@
 import { useMemo } from 'react';
 
 import type { ExplorerItem, FilePath, NonIndexedPathItem, Object } from '../core';
-import { byteSize } from '../lib';
+import { byteSize, bytesToNumber } from '../lib';
 import { ObjectKind } from './objectKind';
 
 export function getItemObject(data: ExplorerItem) {
@@ -66,7 +66,9 @@ export function getExplorerItemData(data?: null | ExplorerItem) {
 		if ('date_modified' in filePath) itemData.dateModified = filePath.date_modified;
 	} else if (location) {
 		if (location.total_capacity != null && location.available_capacity != null)
-			itemData.size = byteSize(location.total_capacity - location.available_capacity);
+			itemData.size = byteSize(
+				bytesToNumber(location.total_capacity) - bytesToNumber(location.available_capacity)
+			);
 
 		itemData.name = location.name;
 		itemData.fullName = location.name;
@@ -144,6 +144,46 @@ export function useJobInfo(job: JobReport, realtimeUpdate: JobProgressEvent | nu
 				} ${completedTaskCount} ${plural(completedTaskCount, 'file')}`,
 				textItems: [[{ text: job.status }]]
 			};
+		case 'file_archiver':
+			return {
+				...data,
+				name: `${
+					isQueued ? 'Archive' : isRunning ? 'Archiving' : 'Archived'
+				} ${completedTaskCount} ${plural(completedTaskCount, 'file')}`,
+				textItems: [[{ text: isRunning ? realtimeUpdate?.message ?? '' : job.status }]]
+			};
+		case 'file_extractor':
+			return {
+				...data,
+				name: `${
+					isQueued ? 'Extract' : isRunning ? 'Extracting' : 'Extracted'
+				} ${completedTaskCount} ${plural(completedTaskCount, 'archive')}`,
+				textItems: [[{ text: isRunning ? realtimeUpdate?.message ?? '' : job.status }]]
+			};
+		case 'file_converter':
+			return {
+				...data,
+				name: `${
+					isQueued ? 'Convert' : isRunning ? 'Converting' : 'Converted'
+				} ${completedTaskCount} ${plural(completedTaskCount, 'image')}`,
+				textItems: [[{ text: isRunning ? realtimeUpdate?.message ?? '' : job.status }]]
+			};
+		case 'file_encryptor':
+			return {
+				...data,
+				name: `${
+					isQueued ? 'Encrypt' : isRunning ? 'Encrypting' : 'Encrypted'
+				} ${completedTaskCount} ${plural(completedTaskCount, 'file')}`,
+				textItems: [[{ text: isRunning ? realtimeUpdate?.message ?? '' : job.status }]]
+			};
+		case 'file_decryptor':
+			return {
+				...data,
+				name: `${
+					isQueued ? 'Decrypt' : isRunning ? 'Decrypting' : 'Decrypted'
+				} ${completedTaskCount} ${plural(completedTaskCount, 'file')}`,
+				textItems: [[{ text: isRunning ? realtimeUpdate?.message ?? '' : job.status }]]
+			};
 		case 'object_validator':
 			return {
 				...data,